@@ -0,0 +1,32 @@
+#!/bin/bash
+# Replaces entries of tests/fixtures/swap_calldata.json with mainnet transactions.
+#
+# Usage: ETH_RPC_URL=https://... scripts/fetch_swap_fixtures.sh name=0x<tx_hash> [name=0x<tx_hash> ...]
+#
+# Each fixture keeps the transaction hash it was taken from, so it can be
+# checked on a block explorer. Requires curl and jq.
+
+set -euo pipefail
+
+FIXTURES="$(dirname "$0")/../tests/fixtures/swap_calldata.json"
+
+if [ -z "${ETH_RPC_URL:-}" ] || [ $# -eq 0 ]; then
+    echo "Usage: ETH_RPC_URL=https://... $0 name=0x<tx_hash> [...]" >&2
+    exit 1
+fi
+
+for pair in "$@"; do
+    name="${pair%%=*}"
+    hash="${pair#*=}"
+
+    tx=$(curl -sf -X POST "$ETH_RPC_URL" \
+        -H "Content-Type: application/json" \
+        -d "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"eth_getTransactionByHash\",\"params\":[\"$hash\"]}" \
+        | jq -e '.result')
+
+    jq --arg name "$name" --argjson tx "$tx" \
+        '.[$name] = { tx_hash: $tx.hash, from: $tx.from, to: $tx.to, value: $tx.value, input: $tx.input }' \
+        "$FIXTURES" > "$FIXTURES.tmp"
+    mv "$FIXTURES.tmp" "$FIXTURES"
+    echo "$name <- $hash"
+done
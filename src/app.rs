@@ -0,0 +1,539 @@
+use crate::{
+    config::Config,
+    handlers::*,
+    middleware::{
+        create_rate_limit_layer, extract_agent_address, require_mempool, require_tx_tracking, x402_middleware_layer,
+        RateLimiter, X402Middleware,
+    },
+    services::*,
+};
+use anyhow::Result;
+use axum::{
+    middleware as axum_middleware,
+    routing::{delete, get, post},
+    Router,
+};
+use std::sync::Arc;
+use tower_http::{
+    cors::CorsLayer,
+    trace::{DefaultMakeSpan, TraceLayer},
+};
+
+/// Services shared by the HTTP handlers
+pub struct AppServices {
+    pub cache: Arc<CacheService>,
+    pub ethereum: Arc<EthereumService>,
+    pub chains: Arc<ChainRegistry>,
+    pub analytics: Arc<Analytics>,
+    pub reputation: Arc<ReputationService>,
+    pub prices: Arc<PriceService>,
+    /// Base Sepolia, where x402 payments are verified
+    pub payment_chain: Arc<dyn ChainClient>,
+    /// `None` when the mempool WebSocket is not configured
+    pub mempool: Option<Arc<MempoolService>>,
+    pub tx_lifecycle: Option<Arc<TxLifecycleTracker>>,
+    pub watchlists: Arc<WatchlistService>,
+    pub mev_detector: Arc<MEVDetector>,
+    pub mev_feed: Arc<MevFeed>,
+    pub mev_history: Arc<MevHistoryService>,
+}
+
+/// Builds the full API router with payment middleware on every paid route
+pub async fn build_router(config: &Config, services: AppServices) -> Result<Router> {
+    // Paid requests are limited per payer, across every priced route
+    let payer_limiter = Arc::new(RateLimiter::new(config.rate_limit_per_second, config.rate_limit_burst));
+    
+    // Initialize x402 middleware for gas prediction, token prices and agent profiles ($0.01)
+    let x402_gas = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            "0.01".to_string(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Initialize x402 middleware for historical gas analytics ($0.05)
+    let x402_gas_history = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            "0.05".to_string(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Initialize x402 middleware for the gas prediction stream (credits are metered per message)
+    let x402_gas_stream = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            config.gas_stream_price.clone(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Initialize x402 middleware for mempool analytics ($0.05)
+    let x402_mempool = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            "0.05".to_string(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Initialize x402 middleware for MEV ($0.10)
+    let x402_mev = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            "0.10".to_string(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Initialize x402 middleware for the MEV opportunity stream (credits are metered per message)
+    let x402_mev_stream = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            config.mev_stream_price.clone(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Initialize x402 middleware for the watchlist match stream (credits are metered per message)
+    let x402_watchlist_stream = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            config.watchlist_stream_price.clone(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Initialize x402 middleware for MEV protection and bundle simulation ($0.05)
+    let x402_mev_protect = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            "0.05".to_string(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Initialize x402 middleware for historical MEV reports ($0.05)
+    let x402_mev_history = Arc::new(
+        X402Middleware::new(
+            config.facilitator_url.clone(),
+            services.payment_chain.clone(),
+            config.recipient_address,
+            config.usdc_address,
+            "0.05".to_string(),
+            services.reputation.clone(),
+            payer_limiter.clone(),
+        )
+        .await?,
+    );
+    
+    // Build application state
+    let app_state = AppState {
+        chains: services.chains.clone(),
+        analytics: services.analytics.clone(),
+        reputation: services.reputation.clone(),
+        prices: services.prices.clone(),
+    };
+    
+    let gas_stream_state = GasStreamState {
+        chains: services.chains.clone(),
+        analytics: services.analytics.clone(),
+        reputation: services.reputation.clone(),
+        message_price: config.gas_stream_message_price,
+    };
+    
+    let mev_state = MEVState {
+        ethereum: services.ethereum.clone(),
+        mempool: services.mempool.clone(),
+        mev_detector: services.mev_detector.clone(),
+        feed: services.mev_feed.clone(),
+        history: services.mev_history.clone(),
+        analytics: services.analytics.clone(),
+        reputation: services.reputation.clone(),
+    };
+    
+    let mev_stream_state = MevStreamState {
+        mempool: services.mempool.clone(),
+        feed: services.mev_feed.clone(),
+        analytics: services.analytics.clone(),
+        reputation: services.reputation.clone(),
+        message_price: config.mev_stream_message_price,
+    };
+    
+    let mempool_state = MempoolState {
+        mempool: services.mempool.clone(),
+        tracker: services.tx_lifecycle.clone(),
+        dexes: services.mev_detector.dexes().clone(),
+        analytics: services.analytics.clone(),
+        reputation: services.reputation.clone(),
+    };
+    
+    let watchlist_state = WatchlistState {
+        watchlists: services.watchlists.clone(),
+        analytics: services.analytics.clone(),
+        reputation: services.reputation.clone(),
+        message_price: config.watchlist_stream_message_price,
+    };
+    
+    let health_state = HealthState {
+        cache: services.cache.clone(),
+        ethereum: services.ethereum.clone(),
+        chains: services.chains.clone(),
+        payment_chain: services.payment_chain.clone(),
+        mempool: services.mempool.clone(),
+        analytics: services.analytics.clone(),
+    };
+    
+    // Build router
+    let app = Router::new()
+        // Public endpoints (no payment required)
+        .route("/health", get(health_check))
+        .with_state(health_state)
+        
+        .route("/stats", get(get_stats))
+        .with_state(services.analytics.clone())
+        
+        .route("/ws/dashboard", get(websocket_handler))
+        .with_state(services.analytics.clone())
+        
+        // Protected endpoints (payment required)
+        .route(
+            "/api/gas/prediction",
+            get(predict_gas)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/gas/estimate",
+            post(estimate_cost)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/gas/history",
+            get(get_gas_history)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas_history.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/gas/history/heatmap",
+            get(get_gas_heatmap)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas_history.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/prices",
+            get(get_prices)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/agents/:address",
+            get(get_agent_profile)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .with_state(app_state)
+        
+        .route(
+            "/ws/gas",
+            get(gas_stream_ws)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas_stream.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/gas/stream",
+            get(gas_stream_sse)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas_stream.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .with_state(gas_stream_state)
+        
+        // Answers 503 while the mempool stream is down or not configured
+        .route(
+            "/api/mev/opportunities",
+            get(get_mev_opportunities)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_mev.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                }))
+                .layer(axum_middleware::from_fn({
+                    let mempool = services.mempool.clone();
+                    move |req, next| {
+                        let mempool = mempool.clone();
+                        async move { require_mempool(mempool, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/mev/protect",
+            post(protect_transaction)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_mev_protect.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/mev/bundle/simulate",
+            post(simulate_bundle)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_mev_protect.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/mev/history",
+            get(get_mev_history)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_mev_history.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/mev/history/block/:number",
+            get(get_mev_block_report)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_mev_history.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .with_state(mev_state)
+        
+        .route(
+            "/ws/mev",
+            get(mev_stream_ws)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_mev_stream.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                }))
+                .layer(axum_middleware::from_fn({
+                    let mempool = services.mempool.clone();
+                    move |req, next| {
+                        let mempool = mempool.clone();
+                        async move { require_mempool(mempool, req, next).await }
+                    }
+                })),
+        )
+        .with_state(mev_stream_state)
+        
+        .route(
+            "/api/mempool/tx/:hash",
+            get(get_tx_status)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                }))
+                .layer(axum_middleware::from_fn({
+                    let tx_lifecycle = services.tx_lifecycle.clone();
+                    move |req, next| {
+                        let tx_lifecycle = tx_lifecycle.clone();
+                        async move { require_tx_tracking(tx_lifecycle, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/mempool/inclusion",
+            get(get_inclusion_stats)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                }))
+                .layer(axum_middleware::from_fn({
+                    let tx_lifecycle = services.tx_lifecycle.clone();
+                    move |req, next| {
+                        let tx_lifecycle = tx_lifecycle.clone();
+                        async move { require_tx_tracking(tx_lifecycle, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/mempool/summary",
+            get(get_mempool_summary)
+                .layer(axum_middleware::from_fn(extract_agent_address))
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_mempool.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                }))
+                .layer(axum_middleware::from_fn({
+                    let mempool = services.mempool.clone();
+                    move |req, next| {
+                        let mempool = mempool.clone();
+                        async move { require_mempool(mempool, req, next).await }
+                    }
+                })),
+        )
+        .with_state(mempool_state)
+        
+        .route(
+            "/api/watchlists",
+            get(list_watchlists)
+                .post(create_watchlist)
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/api/watchlists/:id",
+            delete(delete_watchlist)
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_gas.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .route(
+            "/ws/watchlists",
+            get(watchlist_ws)
+                .layer(axum_middleware::from_fn({
+                    let x402 = x402_watchlist_stream.clone();
+                    move |req, next| {
+                        let x402 = x402.clone();
+                        async move { x402_middleware_layer(x402, req, next).await }
+                    }
+                })),
+        )
+        .with_state(watchlist_state);
+    
+    // Global middleware
+    Ok(app
+        .layer(create_rate_limit_layer(
+            config.rate_limit_per_second,
+            config.rate_limit_burst,
+        ))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::default().include_headers(true)),
+        )
+        .layer(CorsLayer::permissive()))
+}
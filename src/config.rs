@@ -1,6 +1,13 @@
-use anyhow::{bail, Context, Result};
+use crate::contracts::ETH_USD_FEED;
+use crate::models::{ChainSpec, GasModel};
+use crate::services::{
+    arbitrage::DEFAULT_ARBITRAGE_POOLS, liquidation::DEFAULT_LENDING_MARKETS, price::DEFAULT_PRICE_FEEDS, DexRegistry,
+    Erc8004Deployment, LendingMarket, MempoolConfig, PoolConfig, PoolSpec, PriceFeed, ReputationConfig,
+};
+use anyhow::{anyhow, bail, Context, Result};
 use ethers::types::Address;
 use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub enum Environment {
@@ -9,26 +16,86 @@ pub enum Environment {
     Production,
 }
 
+/// An additional chain served by the gas endpoints
+#[derive(Debug, Clone)]
+pub struct ChainConfig {
+    pub spec: ChainSpec,
+    pub rpc_urls: Vec<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub environment: Environment,
     pub host: String,
     pub port: u16,
     
-    // Ethereum Mainnet (data source)
-    pub eth_rpc_url: String,
-    pub eth_rpc_fallback: Option<String>,
+    // Ethereum Mainnet (data source), in order of preference
+    pub eth_rpc_urls: Vec<String>,
+    
+    // Mempool WebSocket (optional - MEV endpoints answer 503 without it)
+    pub eth_ws_url: Option<String>,
+    pub mempool: MempoolConfig,
+    
+    // DEX routers, factories, pool init code hashes and fee tiers per chain
+    pub dex_registry: DexRegistry,
+    
+    // Pools watched for backrun arbitrage (venue:address)
+    pub arbitrage_pools: Vec<PoolSpec>,
+    
+    // Lending markets watched for liquidations (protocol:address), and the
+    // blocks of events scanned for borrowers at startup
+    pub lending_markets: Vec<LendingMarket>,
+    pub liquidation_lookback_blocks: u64,
+    
+    // RPC provider pools (health scoring, failover, hedging, quorum)
+    pub rpc_pool: PoolConfig,
+    
+    // L2s (enabled by setting their RPC URL)
+    pub l2_chains: Vec<ChainConfig>,
+    
+    // Gas prediction model used when a request does not pick one
+    pub gas_model: GasModel,
+    
+    // Chainlink ETH / USD feed (mainnet) for USD cost conversion
+    pub eth_usd_feed: Address,
+    
+    // Chainlink USD feeds (token:feed) for tokens not priced from Uniswap V3
+    pub price_feeds: Vec<PriceFeed>,
     
     // Base Sepolia (payment network)
-    pub base_sepolia_rpc_url: String,
+    pub base_sepolia_rpc_urls: Vec<String>,
     pub base_sepolia_chain_id: u64,
     pub usdc_address: Address,
     
+    // ERC-8004 registries per network (network:identity:reputation:[validation]:from_block)
+    // and the network whose registries score agents
+    pub erc8004_registries: Vec<Erc8004Deployment>,
+    pub erc8004_network: String,
+    
+    // Registration gating and the blend of on-chain and locally computed reputation
+    pub reputation: ReputationConfig,
+    
     // x402 Configuration
     pub facilitator_url: String,
     pub recipient_address: Address,
     pub seller_private_key: String,
     
+    // Gas prediction stream: minimum payment to connect, and price per message
+    pub gas_stream_price: String,
+    pub gas_stream_message_price: f64,
+    
+    // MEV opportunity stream: minimum payment to connect, and price per message
+    pub mev_stream_price: String,
+    pub mev_stream_message_price: f64,
+    
+    // Watchlist match stream: minimum payment to connect, and price per message
+    pub watchlist_stream_price: String,
+    pub watchlist_stream_message_price: f64,
+    
+    // Gas history: blocks kept in memory per chain, and days kept in the cache
+    pub gas_history_max_blocks: usize,
+    pub gas_history_retention_days: u64,
+    
     // Redis
     pub redis_url: String,
     
@@ -51,24 +118,104 @@ impl Config {
                 .parse()
                 .context("Invalid PORT")?,
                 
-            eth_rpc_url: std::env::var("ETH_RPC_URL")
-                .context("ETH_RPC_URL required")?,
-            eth_rpc_fallback: std::env::var("ETH_RPC_FALLBACK").ok(),
+            eth_rpc_urls: Self::parse_rpc_urls("ETH_RPC_URL")?
+                .into_iter()
+                .chain(std::env::var("ETH_RPC_FALLBACK").ok().as_deref().map(split_list).unwrap_or_default())
+                .collect(),
+            eth_ws_url: std::env::var("ETH_WS_URL").ok().filter(|url| !url.is_empty()),
+            mempool: Self::parse_mempool_config()?,
+            dex_registry: match std::env::var("DEX_REGISTRY") {
+                Ok(path) if !path.is_empty() => DexRegistry::load(&path)?,
+                _ => DexRegistry::mainnet(),
+            },
+            arbitrage_pools: split_list(
+                &std::env::var("ARBITRAGE_POOLS").unwrap_or_else(|_| DEFAULT_ARBITRAGE_POOLS.to_string()),
+            )
+            .iter()
+            .map(|pool| pool.parse().map_err(|e: String| anyhow!(e)))
+            .collect::<Result<_>>()
+            .context("Invalid ARBITRAGE_POOLS")?,
+            lending_markets: split_list(
+                &std::env::var("LENDING_MARKETS").unwrap_or_else(|_| DEFAULT_LENDING_MARKETS.to_string()),
+            )
+            .iter()
+            .map(|market| market.parse().map_err(|e: String| anyhow!(e)))
+            .collect::<Result<_>>()
+            .context("Invalid LENDING_MARKETS")?,
+            liquidation_lookback_blocks: std::env::var("LIQUIDATION_LOOKBACK_BLOCKS")
+                .unwrap_or_else(|_| "7200".to_string())
+                .parse()
+                .context("Invalid LIQUIDATION_LOOKBACK_BLOCKS")?,
+            rpc_pool: Self::parse_pool_config()?,
+            l2_chains: Self::parse_l2_chains(),
+            
+            gas_model: std::env::var("GAS_PREDICTION_MODEL")
+                .unwrap_or_else(|_| "ewma".to_string())
+                .parse()
+                .map_err(|e: String| anyhow!(e))
+                .context("Invalid GAS_PREDICTION_MODEL")?,
+            eth_usd_feed: Address::from_str(
+                &std::env::var("ETH_USD_FEED")
+                    .unwrap_or_else(|_| ETH_USD_FEED.to_string()),
+            )
+            .context("Invalid address for ETH_USD_FEED")?,
+            price_feeds: split_list(
+                &std::env::var("PRICE_FEEDS").unwrap_or_else(|_| DEFAULT_PRICE_FEEDS.to_string()),
+            )
+            .iter()
+            .map(|feed| feed.parse().map_err(|e: String| anyhow!(e)))
+            .collect::<Result<_>>()
+            .context("Invalid PRICE_FEEDS")?,
             
-            base_sepolia_rpc_url: std::env::var("BASE_SEPOLIA_RPC_URL")
-                .context("BASE_SEPOLIA_RPC_URL required")?,
+            base_sepolia_rpc_urls: Self::parse_rpc_urls("BASE_SEPOLIA_RPC_URL")?,
             base_sepolia_chain_id: std::env::var("BASE_SEPOLIA_CHAIN_ID")
                 .unwrap_or_else(|_| "84532".to_string())
                 .parse()
                 .context("Invalid BASE_SEPOLIA_CHAIN_ID")?,
             usdc_address: Self::parse_address("USDC_ADDRESS")?,
             
+            erc8004_registries: split_list(&std::env::var("ERC8004_REGISTRIES").unwrap_or_default())
+                .iter()
+                .map(|deployment| deployment.parse().map_err(|e: String| anyhow!(e)))
+                .collect::<Result<_>>()
+                .context("Invalid ERC8004_REGISTRIES")?,
+            erc8004_network: std::env::var("ERC8004_NETWORK")
+                .unwrap_or_else(|_| "base-sepolia".to_string()),
+            reputation: Self::parse_reputation_config()?,
+            
             facilitator_url: std::env::var("FACILITATOR_URL")
                 .context("FACILITATOR_URL required")?,
             recipient_address: Self::parse_address("RECIPIENT_ADDRESS")?,
             seller_private_key: std::env::var("SELLER_PRIVATE_KEY")
                 .context("SELLER_PRIVATE_KEY required")?,
                 
+            gas_stream_price: std::env::var("GAS_STREAM_PRICE")
+                .unwrap_or_else(|_| "0.10".to_string()),
+            gas_stream_message_price: std::env::var("GAS_STREAM_MESSAGE_PRICE")
+                .unwrap_or_else(|_| "0.001".to_string())
+                .parse()
+                .context("Invalid GAS_STREAM_MESSAGE_PRICE")?,
+            mev_stream_price: std::env::var("MEV_STREAM_PRICE")
+                .unwrap_or_else(|_| "1.00".to_string()),
+            mev_stream_message_price: std::env::var("MEV_STREAM_MESSAGE_PRICE")
+                .unwrap_or_else(|_| "0.01".to_string())
+                .parse()
+                .context("Invalid MEV_STREAM_MESSAGE_PRICE")?,
+            watchlist_stream_price: std::env::var("WATCHLIST_STREAM_PRICE")
+                .unwrap_or_else(|_| "1.00".to_string()),
+            watchlist_stream_message_price: std::env::var("WATCHLIST_STREAM_MESSAGE_PRICE")
+                .unwrap_or_else(|_| "0.01".to_string())
+                .parse()
+                .context("Invalid WATCHLIST_STREAM_MESSAGE_PRICE")?,
+            gas_history_max_blocks: std::env::var("GAS_HISTORY_MAX_BLOCKS")
+                .unwrap_or_else(|_| "50000".to_string())
+                .parse()
+                .context("Invalid GAS_HISTORY_MAX_BLOCKS")?,
+            gas_history_retention_days: std::env::var("GAS_HISTORY_RETENTION_DAYS")
+                .unwrap_or_else(|_| "90".to_string())
+                .parse()
+                .context("Invalid GAS_HISTORY_RETENTION_DAYS")?,
+                
             redis_url: std::env::var("REDIS_URL")
                 .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
                 
@@ -86,6 +233,13 @@ impl Config {
         Ok(config)
     }
     
+    /// The ERC-8004 registries on `erc8004_network`; `None` leaves reputation in mock mode
+    pub fn erc8004_deployment(&self) -> Option<&Erc8004Deployment> {
+        self.erc8004_registries
+            .iter()
+            .find(|deployment| deployment.network == self.erc8004_network)
+    }
+    
     fn parse_environment() -> Result<Environment> {
         let env = std::env::var("ENVIRONMENT")
             .unwrap_or_else(|_| "development".to_string());
@@ -98,6 +252,103 @@ impl Config {
         }
     }
     
+    fn parse_l2_chains() -> Vec<ChainConfig> {
+        [
+            (ChainSpec::base(), "BASE_RPC_URL"),
+            (ChainSpec::optimism(), "OPTIMISM_RPC_URL"),
+            (ChainSpec::arbitrum(), "ARBITRUM_RPC_URL"),
+        ]
+        .into_iter()
+        .filter_map(|(spec, var)| {
+            std::env::var(var)
+                .ok()
+                .map(|urls| ChainConfig { spec, rpc_urls: split_list(&urls) })
+        })
+        .collect()
+    }
+    
+    /// A required variable holding one or more comma-separated RPC URLs
+    fn parse_rpc_urls(var: &str) -> Result<Vec<String>> {
+        let urls = std::env::var(var).with_context(|| format!("{} required", var))?;
+        Ok(split_list(&urls))
+    }
+    
+    fn parse_pool_config() -> Result<PoolConfig> {
+        let hedge_delay_ms: u64 = std::env::var("RPC_HEDGE_DELAY_MS")
+            .unwrap_or_else(|_| "250".to_string())
+            .parse()
+            .context("Invalid RPC_HEDGE_DELAY_MS")?;
+        
+        Ok(PoolConfig {
+            hedge_delay: (hedge_delay_ms > 0).then(|| Duration::from_millis(hedge_delay_ms)),
+            failure_threshold: std::env::var("RPC_FAILURE_THRESHOLD")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse()
+                .context("Invalid RPC_FAILURE_THRESHOLD")?,
+            cooldown: Duration::from_secs(
+                std::env::var("RPC_COOLDOWN_SECS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .context("Invalid RPC_COOLDOWN_SECS")?,
+            ),
+            quorum: std::env::var("RPC_QUORUM")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .context("Invalid RPC_QUORUM")?,
+        })
+    }
+    
+    fn parse_mempool_config() -> Result<MempoolConfig> {
+        Ok(MempoolConfig {
+            mode: std::env::var("MEMPOOL_SUBSCRIPTION")
+                .unwrap_or_else(|_| "full".to_string())
+                .parse()
+                .map_err(|e: String| anyhow!(e))
+                .context("Invalid MEMPOOL_SUBSCRIPTION")?,
+            to_addresses: Self::parse_address_list("MEMPOOL_TO_ADDRESSES")?,
+            from_addresses: Self::parse_address_list("MEMPOOL_FROM_ADDRESSES")?,
+            capacity: std::env::var("MEMPOOL_CAPACITY")
+                .unwrap_or_else(|_| "5000".to_string())
+                .parse()
+                .context("Invalid MEMPOOL_CAPACITY")?,
+            ttl: Duration::from_secs(
+                std::env::var("MEMPOOL_TTL_SECS")
+                    .unwrap_or_else(|_| "300".to_string())
+                    .parse()
+                    .context("Invalid MEMPOOL_TTL_SECS")?,
+            ),
+            lookup_concurrency: std::env::var("MEMPOOL_LOOKUP_CONCURRENCY")
+                .unwrap_or_else(|_| "16".to_string())
+                .parse()
+                .context("Invalid MEMPOOL_LOOKUP_CONCURRENCY")?,
+        })
+    }
+    
+    fn parse_reputation_config() -> Result<ReputationConfig> {
+        Ok(ReputationConfig {
+            require_registration: std::env::var("ERC8004_REQUIRE_REGISTRATION")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("Invalid ERC8004_REQUIRE_REGISTRATION")?,
+            onchain_weight: std::env::var("REPUTATION_ONCHAIN_WEIGHT")
+                .unwrap_or_else(|_| "0.5".to_string())
+                .parse()
+                .context("Invalid REPUTATION_ONCHAIN_WEIGHT")?,
+        })
+    }
+    
+    /// Comma-separated addresses; unset means an empty list
+    fn parse_address_list(var: &str) -> Result<Vec<Address>> {
+        std::env::var(var)
+            .ok()
+            .as_deref()
+            .map(split_list)
+            .unwrap_or_default()
+            .iter()
+            .map(|addr| Address::from_str(addr).with_context(|| format!("Invalid address in {}", var)))
+            .collect()
+    }
+    
     fn parse_address(var: &str) -> Result<Address> {
         let addr_str = std::env::var(var)
             .with_context(|| format!("{} required", var))?;
@@ -107,13 +358,67 @@ impl Config {
     
     fn validate(&self) -> Result<()> {
         // Validate URLs
-        if !self.eth_rpc_url.starts_with("http") {
+        if self.eth_rpc_urls.is_empty() || self.eth_rpc_urls.iter().any(|url| !url.starts_with("http")) {
             bail!("ETH_RPC_URL must be HTTP(S) URL");
         }
+        if self.base_sepolia_rpc_urls.is_empty() || self.base_sepolia_rpc_urls.iter().any(|url| !url.starts_with("http")) {
+            bail!("BASE_SEPOLIA_RPC_URL must be HTTP(S) URL");
+        }
+        for chain in &self.l2_chains {
+            if chain.rpc_urls.is_empty() || chain.rpc_urls.iter().any(|url| !url.starts_with("http")) {
+                bail!("{} RPC URL must be HTTP(S) URL", chain.spec.name);
+            }
+        }
+        if let Some(ws_url) = &self.eth_ws_url {
+            if !ws_url.starts_with("ws") {
+                bail!("ETH_WS_URL must be WS(S) URL");
+            }
+        }
+        if self.mempool.capacity == 0 || self.mempool.lookup_concurrency == 0 {
+            bail!("MEMPOOL_CAPACITY and MEMPOOL_LOOKUP_CONCURRENCY must be at least 1");
+        }
+        for dex in self.dex_registry.deployments() {
+            if dex.factory.is_none() || dex.init_code_hash.is_none() {
+                bail!("DEX_REGISTRY: {} on {} needs a factory and an init code hash", dex.name, dex.chain);
+            }
+        }
+        let ethereum_dexes = self.dex_registry.on_chain(&ChainSpec::ethereum());
+        if let Some(pool) = self.arbitrage_pools.iter().find(|pool| ethereum_dexes.get(&pool.venue).is_none()) {
+            bail!("ARBITRAGE_POOLS: {} is not an Ethereum DEX in the registry", pool.venue);
+        }
+        if self.rpc_pool.quorum == 0 || self.rpc_pool.failure_threshold == 0 {
+            bail!("RPC_QUORUM and RPC_FAILURE_THRESHOLD must be at least 1");
+        }
         if !self.facilitator_url.starts_with("http") {
             bail!("FACILITATOR_URL must be HTTP(S) URL");
         }
         
+        if self.gas_stream_message_price <= 0.0 {
+            bail!("GAS_STREAM_MESSAGE_PRICE must be positive");
+        }
+        if self.mev_stream_message_price <= 0.0 {
+            bail!("MEV_STREAM_MESSAGE_PRICE must be positive");
+        }
+        if self.watchlist_stream_message_price <= 0.0 {
+            bail!("WATCHLIST_STREAM_MESSAGE_PRICE must be positive");
+        }
+        if !(0.0..=1.0).contains(&self.reputation.onchain_weight) {
+            bail!("REPUTATION_ONCHAIN_WEIGHT must be between 0 and 1");
+        }
+        if !self.erc8004_registries.is_empty() || self.reputation.require_registration {
+            if self.erc8004_deployment().is_none() {
+                bail!("ERC8004_REGISTRIES has no registries for ERC8004_NETWORK {}", self.erc8004_network);
+            }
+            let served = ["ethereum", "base-sepolia"].contains(&self.erc8004_network.as_str())
+                || self.l2_chains.iter().any(|chain| chain.spec.name == self.erc8004_network);
+            if !served {
+                bail!("ERC8004_NETWORK {} needs an RPC URL", self.erc8004_network);
+            }
+        }
+        if self.gas_history_retention_days == 0 {
+            bail!("GAS_HISTORY_RETENTION_DAYS must be at least 1");
+        }
+        
         // Validate private key format
         if !self.seller_private_key.starts_with("0x") {
             bail!("SELLER_PRIVATE_KEY must start with 0x");
@@ -128,3 +433,10 @@ impl Config {
     }
 }
 
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(String::from)
+        .collect()
+}
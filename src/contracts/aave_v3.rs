@@ -0,0 +1,41 @@
+use ethers::prelude::*;
+
+// Aave V3 Pool: reserve and user state, and the events that move positions.
+// The ReserveData and configuration map structs are all static fields, so
+// they are declared flat, which encodes the same.
+abigen!(
+    AaveV3Pool,
+    r#"[
+        function ADDRESSES_PROVIDER() view returns (address)
+        function getReservesList() view returns (address[])
+        function getReserveData(address asset) view returns (uint256 configuration, uint128 liquidityIndex, uint128 currentLiquidityRate, uint128 variableBorrowIndex, uint128 currentVariableBorrowRate, uint128 currentStableBorrowRate, uint40 lastUpdateTimestamp, uint16 id, address aTokenAddress, address stableDebtTokenAddress, address variableDebtTokenAddress, address interestRateStrategyAddress, uint128 accruedToTreasury, uint128 unbacked, uint128 isolationModeTotalDebt)
+        function getUserConfiguration(address user) view returns (uint256)
+        function getUserAccountData(address user) view returns (uint256 totalCollateralBase, uint256 totalDebtBase, uint256 availableBorrowsBase, uint256 currentLiquidationThreshold, uint256 ltv, uint256 healthFactor)
+        function liquidationCall(address collateralAsset, address debtAsset, address user, uint256 debtToCover, bool receiveAToken)
+        event Supply(address indexed reserve, address user, address indexed onBehalfOf, uint256 amount, uint16 indexed referralCode)
+        event Withdraw(address indexed reserve, address indexed user, address indexed to, uint256 amount)
+        event Borrow(address indexed reserve, address user, address indexed onBehalfOf, uint256 amount, uint8 interestRateMode, uint256 borrowRate, uint16 indexed referralCode)
+        event Repay(address indexed reserve, address indexed user, address indexed repayer, uint256 amount, bool useATokens)
+        event LiquidationCall(address indexed collateralAsset, address indexed debtAsset, address indexed user, uint256 debtToCover, uint256 liquidatedCollateralAmount, address liquidator, bool receiveAToken)
+    ]"#
+);
+
+// Registry of the market's contracts, named by the pool
+abigen!(
+    PoolAddressesProvider,
+    r#"[
+        function getPriceOracle() view returns (address)
+    ]"#
+);
+
+// Prices in the base currency (USD on mainnet), with Chainlink sources
+abigen!(
+    AaveOracle,
+    r#"[
+        function BASE_CURRENCY_UNIT() view returns (uint256)
+        function getAssetsPrices(address[] assets) view returns (uint256[])
+        function getSourceOfAsset(address asset) view returns (address)
+    ]"#
+);
+
+pub const AAVE_V3_POOL: &str = "0x87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2";
@@ -0,0 +1,15 @@
+use ethers::prelude::*;
+
+// Chainlink AggregatorV3Interface ABI; `aggregator` is only on proxies and
+// names the contract that receives the oracle's report transactions
+abigen!(
+    AggregatorV3,
+    r#"[
+        function aggregator() view returns (address)
+        function decimals() view returns (uint8)
+        function latestRoundData() view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    ]"#
+);
+
+// Chainlink ETH / USD feed on Ethereum mainnet
+pub const ETH_USD_FEED: &str = "0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419";
@@ -0,0 +1,28 @@
+use ethers::prelude::*;
+
+// Compound V3 (Comet) market: one borrowable base asset, several collaterals.
+// AssetInfo has only static fields, so it is declared flat.
+abigen!(
+    Comet,
+    r#"[
+        function baseToken() view returns (address)
+        function baseTokenPriceFeed() view returns (address)
+        function baseScale() view returns (uint256)
+        function numAssets() view returns (uint8)
+        function getAssetInfo(uint8 i) view returns (uint8 offset, address asset, address priceFeed, uint64 scale, uint64 borrowCollateralFactor, uint64 liquidateCollateralFactor, uint64 liquidationFactor, uint128 supplyCap)
+        function getPrice(address priceFeed) view returns (uint256)
+        function storeFrontPriceFactor() view returns (uint256)
+        function borrowBalanceOf(address account) view returns (uint256)
+        function collateralBalanceOf(address account, address asset) view returns (uint128)
+        function absorb(address absorber, address[] accounts)
+        function buyCollateral(address asset, uint256 minAmount, uint256 baseAmount, address recipient)
+        event Supply(address indexed from, address indexed dst, uint256 amount)
+        event Withdraw(address indexed src, address indexed to, uint256 amount)
+        event SupplyCollateral(address indexed from, address indexed dst, address indexed asset, uint256 amount)
+        event WithdrawCollateral(address indexed src, address indexed to, address indexed asset, uint256 amount)
+        event AbsorbDebt(address indexed absorber, address indexed borrower, uint256 basePaidOut, uint256 usdValue)
+    ]"#
+);
+
+pub const COMET_USDC: &str = "0xc3d688B66703497DAA19211EEdff47f25384cdc3";
+pub const COMET_WETH: &str = "0xA17581A9E3356d9A858b789D68B4d866e593aE94";
@@ -0,0 +1,25 @@
+use ethers::prelude::*;
+
+// ERC-20 calls used when simulating swaps and reading token metadata.
+// `decimals()` has the same selector as on Chainlink feeds, so their
+// `DecimalsCall` serves both.
+abigen!(
+    ERC20,
+    r#"[
+        function balanceOf(address owner) view returns (uint256)
+        function approve(address spender, uint256 amount) returns (bool)
+        function symbol() view returns (string)
+    ]"#
+);
+
+// Wrapped Ether on Ethereum mainnet
+pub const WETH: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+
+// Wrapped Ether predeploy on OP-stack chains (Base, Optimism)
+pub const OP_STACK_WETH: &str = "0x4200000000000000000000000000000000000006";
+
+// Wrapped Ether on Arbitrum One
+pub const ARBITRUM_WETH: &str = "0x82aF49447D8a07e3bd95BD0d56f35241523fBab1";
+
+// USD Coin on Ethereum mainnet
+pub const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
@@ -0,0 +1,16 @@
+use ethers::prelude::*;
+
+// ERC-8004 Identity Registry: an ERC-721 where each token is an agent and its
+// URI points at the agent's registration file
+abigen!(
+    IdentityRegistry,
+    r#"[
+        function register(string tokenURI) returns (uint256 agentId)
+        function tokenURI(uint256 agentId) view returns (string)
+        function ownerOf(uint256 agentId) view returns (address)
+        function balanceOf(address owner) view returns (uint256)
+        function getMetadata(uint256 agentId, string key) view returns (bytes)
+        event Transfer(address indexed from, address indexed to, uint256 indexed tokenId)
+        event Registered(uint256 indexed agentId, string tokenURI, address indexed owner)
+    ]"#
+);
@@ -0,0 +1,33 @@
+use ethers::prelude::*;
+
+// OP-stack GasPriceOracle predeploy ABI
+abigen!(
+    GasPriceOracle,
+    r#"[
+        function l1BaseFee() view returns (uint256)
+        function blobBaseFee() view returns (uint256)
+        function isEcotone() view returns (bool)
+        function getL1Fee(bytes data) view returns (uint256)
+    ]"#
+);
+
+// Arbitrum ArbGasInfo precompile ABI
+abigen!(
+    ArbGasInfo,
+    r#"[
+        function getL1BaseFeeEstimate() view returns (uint256)
+        function getPricesInWei() view returns (uint256, uint256, uint256, uint256, uint256, uint256)
+    ]"#
+);
+
+// Arbitrum NodeInterface virtual contract ABI
+abigen!(
+    NodeInterface,
+    r#"[
+        function gasEstimateComponents(address to, bool contractCreation, bytes data) payable returns (uint64 gasEstimate, uint64 gasEstimateForL1, uint256 baseFee, uint256 l1BaseFeeEstimate)
+    ]"#
+);
+
+pub const OP_GAS_PRICE_ORACLE: &str = "0x420000000000000000000000000000000000000F";
+pub const ARB_GAS_INFO: &str = "0x000000000000000000000000000000000000006C";
+pub const ARB_NODE_INTERFACE: &str = "0x00000000000000000000000000000000000000C8";
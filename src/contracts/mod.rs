@@ -1,4 +1,32 @@
-pub mod agent_registry;
+pub mod aave_v3;
+pub mod chainlink;
+pub mod compound_v3;
+pub mod erc20;
+pub mod identity_registry;
+pub mod l2;
+pub mod reputation_registry;
+pub mod swap_router02;
+pub mod uniswap_v2;
+pub mod uniswap_v3;
+pub mod universal_router;
+pub mod validation_registry;
 
-pub use agent_registry::*;
+pub use chainlink::*;
+pub use erc20::*;
+pub use l2::*;
+pub use uniswap_v2::*;
 
+// The Uniswap routers share function names, so only their call enums and
+// addresses are re-exported
+pub use swap_router02::{UniswapSwapRouter02Calls, UNISWAP_SWAP_ROUTER_02};
+pub use uniswap_v3::{UniswapV3SwapRouterCalls, UNISWAP_V3_ROUTER};
+pub use universal_router::{UniversalRouterCalls, UNIVERSAL_ROUTER};
+
+// The lending markets share event names (Supply, Withdraw), so only their
+// addresses are re-exported
+pub use aave_v3::AAVE_V3_POOL;
+pub use compound_v3::{COMET_USDC, COMET_WETH};
+
+// The ERC-8004 Reputation and Validation registries both have `getSummary`,
+// and the Identity registry's `balanceOf` clashes with ERC-20, so they are
+// used through their modules
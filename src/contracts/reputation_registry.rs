@@ -0,0 +1,11 @@
+use ethers::prelude::*;
+
+// ERC-8004 Reputation Registry: client feedback (0-100) about an agent,
+// optionally narrowed to some clients and tags (zero tags match all)
+abigen!(
+    ReputationRegistry,
+    r#"[
+        function getSummary(uint256 agentId, address[] clientAddresses, bytes32 tag1, bytes32 tag2) view returns (uint64 count, uint8 averageScore)
+        function getIdentityRegistry() view returns (address)
+    ]"#
+);
@@ -0,0 +1,24 @@
+use ethers::prelude::*;
+
+// Uniswap SwapRouter02: V3 swaps without a deadline (it moved to `multicall`)
+// plus V2 swaps
+abigen!(
+    UniswapSwapRouter02,
+    r#"[
+        struct Router02ExactInputSingleParams { address tokenIn; address tokenOut; uint24 fee; address recipient; uint256 amountIn; uint256 amountOutMinimum; uint160 sqrtPriceLimitX96; }
+        struct Router02ExactInputParams { bytes path; address recipient; uint256 amountIn; uint256 amountOutMinimum; }
+        struct Router02ExactOutputSingleParams { address tokenIn; address tokenOut; uint24 fee; address recipient; uint256 amountOut; uint256 amountInMaximum; uint160 sqrtPriceLimitX96; }
+        struct Router02ExactOutputParams { bytes path; address recipient; uint256 amountOut; uint256 amountInMaximum; }
+        function exactInputSingle(Router02ExactInputSingleParams params) payable returns (uint256 amountOut)
+        function exactInput(Router02ExactInputParams params) payable returns (uint256 amountOut)
+        function exactOutputSingle(Router02ExactOutputSingleParams params) payable returns (uint256 amountIn)
+        function exactOutput(Router02ExactOutputParams params) payable returns (uint256 amountIn)
+        function swapExactTokensForTokens(uint256 amountIn, uint256 amountOutMin, address[] path, address to) payable returns (uint256 amountOut)
+        function swapTokensForExactTokens(uint256 amountOut, uint256 amountInMax, address[] path, address to) payable returns (uint256 amountIn)
+        function multicall(bytes[] data) payable returns (bytes[] results)
+        function multicall(uint256 deadline, bytes[] data) payable returns (bytes[] results)
+        function multicall(bytes32 previousBlockhash, bytes[] data) payable returns (bytes[] results)
+    ]"#
+);
+
+pub const UNISWAP_SWAP_ROUTER_02: &str = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45";
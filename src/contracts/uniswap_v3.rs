@@ -0,0 +1,40 @@
+use ethers::prelude::*;
+
+// Uniswap V3 SwapRouter: every swap carries its own deadline
+abigen!(
+    UniswapV3SwapRouter,
+    r#"[
+        struct ExactInputSingleParams { address tokenIn; address tokenOut; uint24 fee; address recipient; uint256 deadline; uint256 amountIn; uint256 amountOutMinimum; uint160 sqrtPriceLimitX96; }
+        struct ExactInputParams { bytes path; address recipient; uint256 deadline; uint256 amountIn; uint256 amountOutMinimum; }
+        struct ExactOutputSingleParams { address tokenIn; address tokenOut; uint24 fee; address recipient; uint256 deadline; uint256 amountOut; uint256 amountInMaximum; uint160 sqrtPriceLimitX96; }
+        struct ExactOutputParams { bytes path; address recipient; uint256 deadline; uint256 amountOut; uint256 amountInMaximum; }
+        function exactInputSingle(ExactInputSingleParams params) payable returns (uint256 amountOut)
+        function exactInput(ExactInputParams params) payable returns (uint256 amountOut)
+        function exactOutputSingle(ExactOutputSingleParams params) payable returns (uint256 amountIn)
+        function exactOutput(ExactOutputParams params) payable returns (uint256 amountIn)
+        function multicall(bytes[] data) payable returns (bytes[] results)
+    ]"#
+);
+
+// Uniswap V3 pool price, in-range liquidity and tick oracle
+abigen!(
+    UniswapV3Pool,
+    r#"[
+        function fee() view returns (uint24)
+        function liquidity() view returns (uint128)
+        function observe(uint32[] secondsAgos) view returns (int56[], uint160[])
+        function slot0() view returns (uint160 sqrtPriceX96, int24 tick, uint16 observationIndex, uint16 observationCardinality, uint16 observationCardinalityNext, uint8 feeProtocol, bool unlocked)
+        event Swap(address indexed sender, address indexed recipient, int256 amount0, int256 amount1, uint160 sqrtPriceX96, uint128 liquidity, int24 tick)
+    ]"#
+);
+
+// Uniswap V3 factory, to find a token's pool for each fee tier
+abigen!(
+    UniswapV3Factory,
+    r#"[
+        function getPool(address tokenA, address tokenB, uint24 fee) view returns (address)
+    ]"#
+);
+
+pub const UNISWAP_V3_FACTORY: &str = "0x1F98431c8aD98523631AE4a59f267346ea31F984";
+pub const UNISWAP_V3_ROUTER: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
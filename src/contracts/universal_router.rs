@@ -0,0 +1,16 @@
+use ethers::prelude::*;
+
+// Uniswap Universal Router: a byte string of commands, one ABI-encoded input each
+abigen!(
+    UniversalRouter,
+    r#"[
+        function execute(bytes commands, bytes[] inputs) payable
+        function execute(bytes commands, bytes[] inputs, uint256 deadline) payable
+    ]"#,
+    methods {
+        execute(bytes,bytes[]) as execute;
+        execute(bytes,bytes[],uint256) as execute_with_deadline;
+    }
+);
+
+pub const UNIVERSAL_ROUTER: &str = "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD";
@@ -0,0 +1,12 @@
+use ethers::prelude::*;
+
+// ERC-8004 Validation Registry: validator responses (0-100) to an agent's
+// validation requests
+abigen!(
+    ValidationRegistry,
+    r#"[
+        function getSummary(uint256 agentId, address[] validatorAddresses, bytes32 tag) view returns (uint64 count, uint8 avgResponse)
+        function getAgentValidations(uint256 agentId) view returns (bytes32[])
+        function getValidationStatus(bytes32 requestHash) view returns (address validatorAddress, uint256 agentId, uint8 response, bytes32 tag, uint256 lastUpdate)
+    ]"#
+);
@@ -19,9 +19,15 @@ pub enum QGuardError {
     #[error("Invalid payment proof: {0}")]
     InvalidPaymentProof(String),
     
+    #[error("Payment already used: {0:?}")]
+    PaymentReplayed(ethers::types::H256),
+    
     #[error("Insufficient reputation: {current} < {required}")]
     InsufficientReputation { current: u64, required: u64 },
     
+    #[error("Agent not registered in the ERC-8004 Identity Registry: {0}")]
+    AgentNotRegistered(String),
+    
     #[error("Reputation error: {0}")]
     ReputationError(String),
     
@@ -37,9 +43,18 @@ pub enum QGuardError {
     #[error("Configuration error: {0}")]
     ConfigError(String),
     
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    
+    #[error("Not found: {0}")]
+    NotFound(String),
+    
     #[error("Rate limit exceeded")]
     RateLimitExceeded,
     
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    
     #[error("Internal server error: {0}")]
     InternalError(String),
     
@@ -98,15 +113,30 @@ impl IntoResponse for QGuardError {
             QGuardError::PaymentVerificationFailed(_) => {
                 (StatusCode::PAYMENT_REQUIRED, "PAYMENT_VERIFICATION_FAILED", None)
             }
+            QGuardError::PaymentReplayed(_) => {
+                (StatusCode::PAYMENT_REQUIRED, "PAYMENT_REPLAYED", None)
+            }
             QGuardError::InvalidPaymentProof(_) => {
                 (StatusCode::BAD_REQUEST, "INVALID_PAYMENT_PROOF", None)
             }
             QGuardError::InsufficientReputation { .. } => {
                 (StatusCode::FORBIDDEN, "INSUFFICIENT_REPUTATION", None)
             }
+            QGuardError::AgentNotRegistered(_) => {
+                (StatusCode::FORBIDDEN, "AGENT_NOT_REGISTERED", None)
+            }
+            QGuardError::InvalidRequest(_) => {
+                (StatusCode::BAD_REQUEST, "INVALID_REQUEST", None)
+            }
+            QGuardError::NotFound(_) => {
+                (StatusCode::NOT_FOUND, "NOT_FOUND", None)
+            }
             QGuardError::RateLimitExceeded => {
                 (StatusCode::TOO_MANY_REQUESTS, "RATE_LIMIT_EXCEEDED", None)
             }
+            QGuardError::ServiceUnavailable(_) => {
+                (StatusCode::SERVICE_UNAVAILABLE, "SERVICE_UNAVAILABLE", None)
+            }
             QGuardError::RpcError(_) | QGuardError::ContractError(_) => {
                 (StatusCode::BAD_GATEWAY, "UPSTREAM_ERROR", None)
             }
@@ -0,0 +1,55 @@
+use crate::{
+    error::QGuardError,
+    handlers::{billing::charge_agent, AppState},
+    middleware::x402::PaymentVerification,
+    models::{AgentProfile, ApiResponse},
+};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use ethers::types::Address;
+use uuid::Uuid;
+
+/// ERC-8004 identity, registration file, feedback and validation summaries
+/// of an agent address
+pub async fn get_agent_profile(
+    State(state): State<AppState>,
+    Path(address): Path<String>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<AgentProfile>>, QGuardError> {
+    let address: Address = address
+        .parse()
+        .map_err(|_| QGuardError::InvalidRequest(format!("Invalid agent address: {}", address)))?;
+    let registry = state
+        .reputation
+        .registry()
+        .ok_or_else(|| QGuardError::ServiceUnavailable("No ERC-8004 registries configured".to_string()))?;
+    let network = registry.deployment().network.clone();
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.01,
+        "/api/agents",
+    )
+    .await?;
+    
+    let profile = state
+        .reputation
+        .agent_profile(address)
+        .await
+        .map_err(|e| QGuardError::ServiceUnavailable(format!("ERC-8004 registries unavailable: {}", e)))?
+        .ok_or_else(|| QGuardError::NotFound(format!("Agent {:?} is not registered on {}", address, network)))?;
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: profile,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: network,
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
@@ -0,0 +1,77 @@
+use crate::{
+    error::QGuardError,
+    middleware::x402::PaymentVerification,
+    services::{Analytics, ReputationService},
+};
+use ethers::types::{Address, U256};
+
+/// Minimum reputation required to use any paid endpoint
+pub const MIN_REPUTATION: u64 = 100;
+
+/// Reputation-gated price for the agent that paid. ERC-8004 registration is
+/// checked on the payer by the payment middleware; `X-Agent-Address` can be
+/// spoofed, so it has no say in the price.
+pub async fn quote_price(
+    reputation: &ReputationService,
+    payer: Address,
+    base_price: f64,
+    endpoint: &str,
+) -> Result<f64, QGuardError> {
+    let score = reputation.get_reputation(payer).await
+        .map_err(|e| QGuardError::ReputationError(e.to_string()))?;
+    
+    tracing::info!(
+        "Agent {:?} with reputation {} accessing {}",
+        payer,
+        score,
+        endpoint
+    );
+    
+    // Deny access for reputation < 100
+    if score < MIN_REPUTATION {
+        return Err(QGuardError::InsufficientReputation {
+            current: score,
+            required: MIN_REPUTATION,
+        });
+    }
+    
+    // Calculate dynamic pricing based on reputation
+    Ok(reputation.calculate_price(base_price, score))
+}
+
+/// Applies reputation gating and pricing for a paid endpoint and records the
+/// payment. Returns the price charged in USD.
+pub async fn charge_agent(
+    reputation: &ReputationService,
+    analytics: &Analytics,
+    payment: &PaymentVerification,
+    base_price: f64,
+    endpoint: &str,
+) -> Result<f64, QGuardError> {
+    let actual_price = quote_price(reputation, payment.payer, base_price, endpoint).await?;
+    
+    analytics.record_payment(actual_price, endpoint, &format!("{:?}", payment.payer)).await;
+    
+    Ok(actual_price)
+}
+
+/// Number of messages a metered stream may send: the verified payment divided
+/// by the payer's (reputation-discounted) per-message price.
+pub async fn stream_credits(
+    reputation: &ReputationService,
+    analytics: &Analytics,
+    payment: &PaymentVerification,
+    base_message_price: f64,
+    endpoint: &str,
+) -> Result<u64, QGuardError> {
+    let message_price = quote_price(reputation, payment.payer, base_message_price, endpoint).await?;
+    
+    // USDC has 6 decimals
+    let paid_usd = U256::from_dec_str(&payment.amount)
+        .map(|amount| amount.as_u128() as f64 / 1e6)
+        .map_err(|e| QGuardError::InvalidPaymentProof(format!("Invalid payment amount: {}", e)))?;
+    
+    analytics.record_payment(paid_usd, endpoint, &format!("{:?}", payment.payer)).await;
+    
+    Ok((paid_usd / message_price).floor() as u64)
+}
@@ -1,65 +1,183 @@
 use crate::{
     error::QGuardError,
-    models::{ApiResponse, GasPrediction},
-    services::{Analytics, EthereumService, ReputationService},
+    handlers::billing::charge_agent,
+    middleware::x402::PaymentVerification,
+    models::{
+        ApiResponse, CostEstimate, GasLimitSource, GasModel, GasPrediction, L1DataFee, SpeedTier,
+        TierCost,
+    },
+    services::{Analytics, ChainRegistry, PriceService, ReputationService},
+};
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
 };
-use axum::{extract::State, Extension, Json};
 use chrono::Utc;
-use ethers::types::Address;
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, U256,
+};
+use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub ethereum: Arc<EthereumService>,
+    pub chains: Arc<ChainRegistry>,
     pub analytics: Arc<Analytics>,
     pub reputation: Arc<ReputationService>,
+    pub prices: Arc<PriceService>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GasPredictionQuery {
+    /// One of `ewma`, `eip1559`, `percentile`, `kalman`; defaults to the configured model
+    pub model: Option<String>,
+    /// Chain name (`ethereum`, `base`, `optimism`, `arbitrum`); defaults to `ethereum`
+    pub chain: Option<String>,
 }
 
 pub async fn predict_gas(
     State(state): State<AppState>,
-    agent: Option<Extension<Address>>,
+    Query(query): Query<GasPredictionQuery>,
+    Extension(payment): Extension<PaymentVerification>,
 ) -> Result<Json<ApiResponse<GasPrediction>>, QGuardError> {
-    // Check agent reputation if provided
-    let mut actual_price = 0.01;
+    let model = parse_model(query.model.as_deref())?;
+    let chain = state.chains.get(query.chain.as_deref())?;
     
-    if let Some(Extension(agent_addr)) = agent {
-        let reputation = state.reputation.get_reputation(agent_addr).await
-            .map_err(|e| QGuardError::ReputationError(e.to_string()))?;
-        
-        tracing::info!(
-            "Agent {:?} with reputation {} accessing gas prediction",
-            agent_addr,
-            reputation
-        );
-        
-        // Deny access for reputation < 100
-        if reputation < 100 {
-            return Err(QGuardError::InsufficientReputation {
-                current: reputation,
-                required: 100,
-            });
-        }
-        
-        // Calculate dynamic pricing based on reputation
-        actual_price = state.reputation.calculate_price(0.01, reputation);
-        
-        // Record analytics with agent info
-        state.analytics.record_payment(actual_price, "/api/gas/prediction", &format!("{:?}", agent_addr)).await;
-    } else {
-        // Record analytics for anonymous access
-        state.analytics.record_payment(actual_price, "/api/gas/prediction", "anonymous").await;
-    }
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.01,
+        "/api/gas/prediction",
+    )
+    .await?;
     
-    let prediction = state.ethereum.get_gas_prediction().await?;
+    let prediction = chain.get_gas_prediction(model).await?;
     
     Ok(Json(ApiResponse {
         success: true,
         data: prediction,
         timestamp: Utc::now(),
         cache_hit: false, // TODO: Track this properly
-        data_source: "ethereum-mainnet".to_string(),
+        data_source: chain.chain().data_source(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+
+/// Body for `POST /api/gas/estimate`: either `gas_limit` or `transaction`
+#[derive(Debug, Deserialize)]
+pub struct CostEstimateRequest {
+    pub gas_limit: Option<u64>,
+    pub transaction: Option<EstimateTransaction>,
+    pub model: Option<String>,
+    pub chain: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EstimateTransaction {
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    pub data: Option<Bytes>,
+    pub value: Option<U256>,
+}
+
+impl From<EstimateTransaction> for TransactionRequest {
+    fn from(tx: EstimateTransaction) -> Self {
+        TransactionRequest {
+            from: tx.from,
+            to: tx.to.map(Into::into),
+            data: tx.data,
+            value: tx.value,
+            ..Default::default()
+        }
+    }
+}
+
+pub async fn estimate_cost(
+    State(state): State<AppState>,
+    Extension(payment): Extension<PaymentVerification>,
+    Json(request): Json<CostEstimateRequest>,
+) -> Result<Json<ApiResponse<CostEstimate>>, QGuardError> {
+    let model = parse_model(request.model.as_deref())?;
+    let chain = state.chains.get(request.chain.as_deref())?;
+    
+    // The L1 data fee depends on the calldata, so it is only known for full transactions.
+    // Invalid input and reverting transactions are rejected before the agent is charged.
+    let (gas_limit, gas_limit_source, l1_data_fee) = match (request.gas_limit, request.transaction) {
+        (Some(gas_limit), None) => (gas_limit, GasLimitSource::Provided, None),
+        (None, Some(tx)) => {
+            let request: TransactionRequest = tx.into();
+            let mut tx: TypedTransaction = request.into();
+            let gas_limit = chain.estimate_gas(&tx).await?;
+            tx.set_gas(gas_limit);
+            
+            (gas_limit, GasLimitSource::Estimated, chain.l1_data_fee(&tx).await?)
+        }
+        _ => {
+            return Err(QGuardError::InvalidRequest(
+                "Provide exactly one of gas_limit or transaction".to_string(),
+            ))
+        }
+    };
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.01,
+        "/api/gas/estimate",
+    )
+    .await?;
+    
+    let prediction = chain.get_gas_prediction(model).await?;
+    let eth_usd_price = state.prices.eth_usd_price().await
+        .map_err(|e| QGuardError::InternalError(format!("ETH/USD price unavailable: {}", e)))?;
+    
+    // OP-stack charges the L1 data fee on top; Arbitrum already counts it in the gas limit
+    let extra_fee_wei = match l1_data_fee {
+        Some((fee, false)) => fee,
+        _ => U256::zero(),
+    };
+    
+    let tiers = SpeedTier::ALL
+        .iter()
+        .map(|tier| TierCost::new(&prediction, *tier, gas_limit, extra_fee_wei, eth_usd_price))
+        .collect();
+    
+    let l1_data_fee = l1_data_fee.map(|(fee, included_in_gas_limit)| {
+        let fee_eth = fee.as_u128() as f64 / 1e18;
+        L1DataFee {
+            fee_wei: fee.to_string(),
+            fee_eth,
+            fee_usd: fee_eth * eth_usd_price,
+            included_in_gas_limit,
+        }
+    });
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: CostEstimate {
+            chain: prediction.chain.clone(),
+            gas_limit,
+            gas_limit_source,
+            eth_usd_price,
+            block_number: prediction.block_number,
+            model: prediction.model,
+            l1_data_fee,
+            tiers,
+        },
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: chain.chain().data_source(),
         request_id: Uuid::new_v4().to_string(),
     }))
 }
 
+fn parse_model(model: Option<&str>) -> Result<Option<GasModel>, QGuardError> {
+    model
+        .map(str::parse::<GasModel>)
+        .transpose()
+        .map_err(QGuardError::InvalidRequest)
+}
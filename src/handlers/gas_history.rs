@@ -0,0 +1,194 @@
+use crate::{
+    error::QGuardError,
+    handlers::{billing::charge_agent, AppState},
+    middleware::x402::PaymentVerification,
+    models::{ApiResponse, GasHeatmap, GasHistory},
+    services::gas_history,
+};
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Most buckets a single history response may contain
+const MAX_BUCKETS: u64 = 2000;
+
+#[derive(Debug, Deserialize)]
+pub struct GasHistoryQuery {
+    pub chain: Option<String>,
+    /// Unix seconds or RFC 3339; defaults to 24 hours before `to`
+    pub from: Option<String>,
+    /// Unix seconds or RFC 3339; defaults to now
+    pub to: Option<String>,
+    /// Block range, inclusive; used instead of `from`/`to` when given
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    /// Bucket width such as `5m`, `1h` or `1d`; defaults to `1h`
+    pub bucket: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GasHeatmapQuery {
+    pub chain: Option<String>,
+    /// Days of history to aggregate; defaults to 7
+    pub days: Option<u64>,
+}
+
+pub async fn get_gas_history(
+    State(state): State<AppState>,
+    Query(query): Query<GasHistoryQuery>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<GasHistory>>, QGuardError> {
+    let chain = state.chains.get(query.chain.as_deref())?;
+    let store = state.chains.history();
+    let chain_name = chain.chain().name.clone();
+    let bucket_secs = parse_duration(query.bucket.as_deref().unwrap_or("1h"))?;
+
+    let (from, to, block_range) = match (query.from_block, query.to_block) {
+        (None, None) => {
+            let to = parse_time(query.to.as_deref())?.unwrap_or_else(|| Utc::now().timestamp() as u64);
+            let from = parse_time(query.from.as_deref())?.unwrap_or(to.saturating_sub(86400));
+            (from, to, None)
+        }
+        (Some(from_block), Some(to_block)) if from_block <= to_block => {
+            // Timestamps are estimated, so widen the window and filter on block numbers
+            let block_time = chain.chain().block_time_ms as f64 / 1000.0;
+            let estimate = |block| store.estimate_timestamp(&chain_name, block, block_time);
+            let (Some(from), Some(to)) = (estimate(from_block).await, estimate(to_block).await) else {
+                return Err(QGuardError::InvalidRequest(format!(
+                    "No gas history recorded for {} yet",
+                    chain_name
+                )));
+            };
+            (from.saturating_sub(3600), to.saturating_add(3600), Some(from_block..=to_block))
+        }
+        _ => {
+            return Err(QGuardError::InvalidRequest(
+                "from_block and to_block must both be set, with from_block <= to_block".to_string(),
+            ))
+        }
+    };
+
+    validate_range(from, to, store.retention_secs())?;
+    if (to - from) / bucket_secs > MAX_BUCKETS {
+        return Err(QGuardError::InvalidRequest(format!(
+            "Range needs more than {} buckets; use a wider bucket",
+            MAX_BUCKETS
+        )));
+    }
+
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.05,
+        "/api/gas/history",
+    )
+    .await?;
+
+    let mut records = store.records_between(&chain_name, from, to).await;
+    let (from, to) = match block_range {
+        Some(range) => {
+            records.retain(|r| range.contains(&r.number));
+            match (records.first(), records.last()) {
+                (Some(first), Some(last)) => (first.timestamp, last.timestamp + 1),
+                _ => (from, to),
+            }
+        }
+        None => (from, to),
+    };
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: gas_history::aggregate(&chain_name, &records, from, to, bucket_secs),
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: chain.chain().data_source(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+pub async fn get_gas_heatmap(
+    State(state): State<AppState>,
+    Query(query): Query<GasHeatmapQuery>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<GasHeatmap>>, QGuardError> {
+    let chain = state.chains.get(query.chain.as_deref())?;
+    let store = state.chains.history();
+    let chain_name = &chain.chain().name;
+
+    let to = Utc::now().timestamp() as u64;
+    let from = to.saturating_sub(query.days.unwrap_or(7).saturating_mul(86400));
+    validate_range(from, to, store.retention_secs())?;
+
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.05,
+        "/api/gas/history/heatmap",
+    )
+    .await?;
+
+    let records = store.records_between(chain_name, from, to).await;
+
+    Ok(Json(ApiResponse {
+        success: true,
+        data: gas_history::heatmap(chain_name, &records, from, to),
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: chain.chain().data_source(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+fn validate_range(from: u64, to: u64, retention_secs: u64) -> Result<(), QGuardError> {
+    if from >= to {
+        return Err(QGuardError::InvalidRequest("from must be before to".to_string()));
+    }
+    if to - from > retention_secs {
+        return Err(QGuardError::InvalidRequest(format!(
+            "Range exceeds the {} day history retention",
+            retention_secs / 86400
+        )));
+    }
+    Ok(())
+}
+
+/// Accepts unix seconds or an RFC 3339 timestamp
+fn parse_time(value: Option<&str>) -> Result<Option<u64>, QGuardError> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Some(seconds));
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| Some(time.timestamp().max(0) as u64))
+        .map_err(|_| QGuardError::InvalidRequest(format!("Invalid timestamp: {}", value)))
+}
+
+/// Parses durations such as `30s`, `5m`, `1h` or `1d` into seconds
+fn parse_duration(value: &str) -> Result<u64, QGuardError> {
+    let invalid = || QGuardError::InvalidRequest(format!("Invalid bucket: {} (e.g. 5m, 1h, 1d)", value));
+
+    let split = value.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let amount: u64 = value[..split].parse().map_err(|_| invalid())?;
+    let unit = match &value[split..] {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(invalid()),
+    };
+
+    match amount.checked_mul(unit).unwrap_or(0) {
+        0 => Err(invalid()),
+        seconds => Ok(seconds),
+    }
+}
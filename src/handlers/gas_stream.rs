@@ -0,0 +1,196 @@
+use crate::{
+    error::QGuardError,
+    handlers::billing::stream_credits,
+    middleware::x402::PaymentVerification,
+    models::{GasModel, GasPrediction, GasStreamMessage, GasUpdate},
+    services::{Analytics, ChainRegistry, ReputationService},
+};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Response,
+    },
+    Extension,
+};
+use futures::{SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::watch;
+
+#[derive(Clone)]
+pub struct GasStreamState {
+    pub chains: Arc<ChainRegistry>,
+    pub analytics: Arc<Analytics>,
+    pub reputation: Arc<ReputationService>,
+    /// Base price per delivered message, before reputation discounts
+    pub message_price: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GasStreamQuery {
+    pub model: Option<String>,
+    pub chain: Option<String>,
+}
+
+/// A subscription to one chain's predictions with a fixed number of message credits
+struct GasSubscription {
+    updates: watch::Receiver<Option<Arc<GasUpdate>>>,
+    model: GasModel,
+    credits: u64,
+    analytics: Arc<Analytics>,
+    endpoint: &'static str,
+}
+
+impl GasSubscription {
+    async fn open(
+        state: &GasStreamState,
+        query: &GasStreamQuery,
+        payment: &PaymentVerification,
+        endpoint: &'static str,
+    ) -> Result<Self, QGuardError> {
+        let follower = state.chains.follower(query.chain.as_deref())?;
+        let model = match query.model.as_deref() {
+            Some(model) => model.parse().map_err(QGuardError::InvalidRequest)?,
+            None => state.chains.get(query.chain.as_deref())?.default_model(),
+        };
+
+        let credits = stream_credits(
+            &state.reputation,
+            &state.analytics,
+            payment,
+            state.message_price,
+            endpoint,
+        )
+        .await?;
+
+        if credits == 0 {
+            return Err(QGuardError::PaymentVerificationFailed(format!(
+                "Payment does not cover a single message at {} USDC",
+                state.message_price
+            )));
+        }
+
+        tracing::info!(
+            "Gas stream opened on {} ({}, {} credits) via {}",
+            follower.chain_name(),
+            model,
+            credits,
+            endpoint
+        );
+
+        let mut updates = follower.subscribe();
+        // Deliver the current prediction straight away
+        updates.mark_changed();
+
+        Ok(Self {
+            updates,
+            model,
+            credits,
+            analytics: state.analytics.clone(),
+            endpoint,
+        })
+    }
+
+    /// Waits for the next head and spends one credit on it. Returns `None` once
+    /// the credits are spent or the follower is gone.
+    async fn next_message(&mut self) -> Option<GasStreamMessage> {
+        if self.credits == 0 {
+            return None;
+        }
+
+        let prediction = self.next_prediction().await?;
+        self.credits -= 1;
+        self.analytics.record_stream_message(self.endpoint).await;
+
+        Some(GasStreamMessage {
+            prediction,
+            credits_remaining: self.credits,
+        })
+    }
+
+    async fn next_prediction(&mut self) -> Option<GasPrediction> {
+        loop {
+            self.updates.changed().await.ok()?;
+
+            let update = self.updates.borrow_and_update().clone();
+            if let Some(prediction) = update.as_ref().and_then(|u| u.prediction(self.model)) {
+                return Some(prediction.clone());
+            }
+        }
+    }
+}
+
+pub async fn gas_stream_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<GasStreamState>,
+    Query(query): Query<GasStreamQuery>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Response, QGuardError> {
+    let subscription = GasSubscription::open(&state, &query, &payment, "/ws/gas").await?;
+
+    Ok(ws.on_upgrade(move |socket| handle_gas_socket(socket, subscription)))
+}
+
+async fn handle_gas_socket(socket: WebSocket, mut subscription: GasSubscription) {
+    let (mut sender, mut receiver) = socket.split();
+
+    loop {
+        tokio::select! {
+            message = subscription.next_message() => {
+                let Some(message) = message else { break };
+
+                let Ok(text) = serde_json::to_string(&message) else { continue };
+                if sender.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+            }
+
+            // Pings are answered by the WebSocket implementation
+            Some(Ok(msg)) = receiver.next() => {
+                if let Message::Close(_) = msg {
+                    return;
+                }
+            }
+        }
+    }
+
+    if subscription.credits == 0 {
+        let _ = sender
+            .send(Message::Text(r#"{"event":"credits_exhausted"}"#.to_string()))
+            .await;
+    }
+    let _ = sender.send(Message::Close(None)).await;
+
+    tracing::debug!("Gas stream WebSocket closed");
+}
+
+pub async fn gas_stream_sse(
+    State(state): State<GasStreamState>,
+    Query(query): Query<GasStreamQuery>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, QGuardError> {
+    let subscription = GasSubscription::open(&state, &query, &payment, "/api/gas/stream").await?;
+
+    let events = futures::stream::unfold(Some(subscription), |subscription| async move {
+        let mut subscription = subscription?;
+
+        match subscription.next_message().await {
+            Some(message) => {
+                let event = Event::default().event("prediction").json_data(&message);
+                Some((event, Some(subscription)))
+            }
+            // Tell the client why the stream ends, then stop
+            None if subscription.credits == 0 => {
+                let event = Event::default().event("credits_exhausted").data("{}");
+                Some((Ok(event), None))
+            }
+            None => None,
+        }
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -1,6 +1,6 @@
 use crate::{
-    models::HealthStatus,
-    services::{Analytics, CacheService, EthereumService},
+    models::{HealthStatus, MempoolConnection, MempoolHealth},
+    services::{Analytics, CacheService, ChainClient, ChainRegistry, EthereumService, MempoolService},
 };
 use axum::{extract::State, Json};
 use chrono::Utc;
@@ -10,6 +10,9 @@ use std::sync::Arc;
 pub struct HealthState {
     pub cache: Arc<CacheService>,
     pub ethereum: Arc<EthereumService>,
+    pub chains: Arc<ChainRegistry>,
+    pub payment_chain: Arc<dyn ChainClient>,
+    pub mempool: Option<Arc<MempoolService>>,
     pub analytics: Arc<Analytics>,
 }
 
@@ -17,9 +20,18 @@ pub async fn health_check(
     State(state): State<HealthState>,
 ) -> Json<HealthStatus> {
     let redis_ok = state.cache.ping().await.unwrap_or(false);
-    let ethereum_ok = state.ethereum.get_gas_prediction().await.is_ok();
+    let ethereum_ok = state.ethereum.get_gas_prediction(None).await.is_ok();
+    let mempool = match &state.mempool {
+        Some(mempool) => mempool.health(),
+        None => MempoolHealth::disabled(),
+    };
+    // Running without a mempool is a configuration choice, not a fault
+    let mempool_ok = matches!(
+        mempool.state,
+        MempoolConnection::Connected | MempoolConnection::Disabled
+    );
     
-    let status = if redis_ok && ethereum_ok {
+    let status = if redis_ok && ethereum_ok && mempool_ok {
         "healthy"
     } else if ethereum_ok {
         "degraded"
@@ -27,11 +39,20 @@ pub async fn health_check(
         "unhealthy"
     };
     
+    let mut rpc_providers: Vec<_> = state
+        .chains
+        .services()
+        .flat_map(|chain| chain.provider_metrics())
+        .collect();
+    rpc_providers.extend(state.payment_chain.provider_metrics());
+    
     Json(HealthStatus {
         status: status.to_string(),
         version: env!("CARGO_PKG_VERSION").to_string(),
         redis: redis_ok,
         ethereum_rpc: ethereum_ok,
+        rpc_providers,
+        mempool,
         uptime_seconds: state.analytics.uptime_seconds(),
         timestamp: Utc::now(),
     })
@@ -0,0 +1,153 @@
+use crate::{
+    error::QGuardError,
+    handlers::billing::charge_agent,
+    middleware::x402::PaymentVerification,
+    models::{ApiResponse, InclusionStats, MempoolSummary, TxStatus},
+    services::{
+        mempool_analytics::{self, SummaryFilter},
+        Analytics, DexRegistry, MempoolService, ReputationService, TxLifecycleTracker,
+    },
+};
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use ethers::types::{Address, H256};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct MempoolState {
+    /// Both `None` when the mempool WebSocket is not configured
+    pub mempool: Option<Arc<MempoolService>>,
+    pub tracker: Option<Arc<TxLifecycleTracker>>,
+    /// Swaps are only counted on these DEXes' routers
+    pub dexes: Arc<DexRegistry>,
+    pub analytics: Arc<Analytics>,
+    pub reputation: Arc<ReputationService>,
+}
+
+impl MempoolState {
+    fn tracker(&self) -> Result<&TxLifecycleTracker, QGuardError> {
+        self.tracker
+            .as_deref()
+            .ok_or_else(|| QGuardError::ServiceUnavailable("Mempool tracking is not configured".to_string()))
+    }
+    
+    fn connected_mempool(&self) -> Result<&MempoolService, QGuardError> {
+        self.mempool
+            .as_deref()
+            .filter(|mempool| mempool.is_connected())
+            .ok_or_else(|| QGuardError::ServiceUnavailable("Mempool stream is not connected".to_string()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MempoolSummaryQuery {
+    /// Only transactions to this address
+    pub to: Option<Address>,
+    /// Only transactions calling this 4-byte function selector (hex)
+    pub selector: Option<String>,
+}
+
+/// Whether a transaction is pending, mined, replaced or dropped
+pub async fn get_tx_status(
+    State(state): State<MempoolState>,
+    Path(hash): Path<String>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<TxStatus>>, QGuardError> {
+    let tracker = state.tracker()?;
+    let hash: H256 = hash
+        .parse()
+        .map_err(|_| QGuardError::InvalidRequest(format!("Invalid transaction hash: {}", hash)))?;
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.01,
+        "/api/mempool/tx",
+    )
+    .await?;
+    
+    let status = tracker
+        .status(hash)
+        .ok_or_else(|| QGuardError::NotFound(format!("Transaction {:?} was not seen in the mempool", hash)))?;
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: status,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "ethereum-mempool".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+/// Time to inclusion by priority fee paid, over recently mined transactions
+pub async fn get_inclusion_stats(
+    State(state): State<MempoolState>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<InclusionStats>>, QGuardError> {
+    let tracker = state.tracker()?;
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.01,
+        "/api/mempool/inclusion",
+    )
+    .await?;
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: tracker.inclusion_stats(),
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "ethereum-mempool".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+/// Snapshot of pending transactions: fees, busiest addresses, nonce gaps and DEX swaps
+pub async fn get_mempool_summary(
+    State(state): State<MempoolState>,
+    Query(query): Query<MempoolSummaryQuery>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<MempoolSummary>>, QGuardError> {
+    let mempool = state.connected_mempool()?;
+    let filter = SummaryFilter {
+        to: query.to,
+        selector: query.selector.as_deref().map(parse_selector).transpose()?,
+    };
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.05,
+        "/api/mempool/summary",
+    )
+    .await?;
+    
+    let summary = mempool_analytics::summarize(&mempool.get_pending_transactions(), &filter, &state.dexes);
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: summary,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "ethereum-mempool".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+fn parse_selector(selector: &str) -> Result<[u8; 4], QGuardError> {
+    hex::decode(selector.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| QGuardError::InvalidRequest(format!("Invalid function selector: {}", selector)))
+}
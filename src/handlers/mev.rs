@@ -1,78 +1,404 @@
 use crate::{
     error::QGuardError,
-    models::{ApiResponse, MEVOpportunity},
-    services::{Analytics, EthereumService, MEVDetector, MempoolService, ReputationService},
+    handlers::billing::charge_agent,
+    middleware::x402::PaymentVerification,
+    models::{
+        ApiResponse, BundleSimulation, MEVType, MevBlockReport, MevFilter, MevOpportunityPage, MevOpportunityQuery,
+        MevProtection, MevRangeReport, MevSort, ProtectRequest, SimulateBundleRequest,
+    },
+    services::{
+        bundle::MAX_BUNDLE_TRANSACTIONS, decode_raw_transaction, mev_history::MAX_RANGE_BLOCKS, Analytics, EthereumService,
+        MEVDetector, MempoolService, MevFeed, MevHistoryService, ReputationService,
+    },
+};
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
 };
-use axum::{extract::State, Extension, Json};
 use chrono::Utc;
-use ethers::types::Address;
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, Eip1559TransactionRequest, Transaction, U256,
+};
+use serde::Deserialize;
 use std::sync::Arc;
 use uuid::Uuid;
 
+/// Opportunities below this net profit are left out unless a request sets
+/// its own minimum
+const DEFAULT_MIN_NET_PROFIT_USD: f64 = 10.0;
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 200;
+
 #[derive(Clone)]
 pub struct MEVState {
     pub ethereum: Arc<EthereumService>,
-    pub mempool: Arc<MempoolService>,
+    /// `None` when `ETH_WS_URL` is not configured
+    pub mempool: Option<Arc<MempoolService>>,
     pub mev_detector: Arc<MEVDetector>,
+    /// Opportunities found as transactions enter the mempool
+    pub feed: Arc<MevFeed>,
+    pub history: Arc<MevHistoryService>,
     pub analytics: Arc<Analytics>,
     pub reputation: Arc<ReputationService>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct MevOpportunitiesQuery {
+    /// Defaults to $10
+    pub min_net_profit: Option<f64>,
+    /// Comma-separated MEV types
+    pub types: Option<String>,
+    /// Comma-separated token addresses
+    pub tokens: Option<String>,
+    /// Comma-separated pool addresses
+    pub pools: Option<String>,
+    /// `net_profit` (default), `confidence` or `expiry`
+    pub sort: Option<String>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl MevOpportunitiesQuery {
+    fn validate(&self) -> Result<MevOpportunityQuery, QGuardError> {
+        let filter = mev_filter(
+            self.types.as_deref(),
+            self.min_net_profit.unwrap_or(DEFAULT_MIN_NET_PROFIT_USD),
+            self.tokens.as_deref(),
+            self.pools.as_deref(),
+        )?;
+        let sort = match &self.sort {
+            Some(sort) => sort.parse().map_err(QGuardError::InvalidRequest)?,
+            None => MevSort::default(),
+        };
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if limit == 0 || limit > MAX_PAGE_SIZE {
+            return Err(QGuardError::InvalidRequest(format!(
+                "limit must be between 1 and {}",
+                MAX_PAGE_SIZE
+            )));
+        }
+
+        Ok(MevOpportunityQuery {
+            filter,
+            sort,
+            limit,
+            offset: self.offset.unwrap_or(0),
+        })
+    }
+}
+
+/// Builds a filter from comma-separated query lists
+pub(crate) fn mev_filter(
+    types: Option<&str>,
+    min_net_profit_usd: f64,
+    tokens: Option<&str>,
+    pools: Option<&str>,
+) -> Result<MevFilter, QGuardError> {
+    if !min_net_profit_usd.is_finite() {
+        return Err(QGuardError::InvalidRequest("min_net_profit must be a number".to_string()));
+    }
+
+    Ok(MevFilter {
+        types: split_list(types)
+            .map(str::parse::<MEVType>)
+            .collect::<Result<_, _>>()
+            .map_err(QGuardError::InvalidRequest)?,
+        min_net_profit_usd,
+        tokens: addresses(tokens, "token")?,
+        pools: addresses(pools, "pool")?,
+    })
+}
+
+fn split_list(list: Option<&str>) -> impl Iterator<Item = &str> {
+    list.unwrap_or_default().split(',').map(str::trim).filter(|item| !item.is_empty())
+}
+
+fn addresses(list: Option<&str>, kind: &str) -> Result<Vec<Address>, QGuardError> {
+    split_list(list)
+        .map(|address| {
+            address
+                .parse()
+                .map_err(|_| QGuardError::InvalidRequest(format!("Invalid {} address: {}", kind, address)))
+        })
+        .collect()
+}
+
 pub async fn get_mev_opportunities(
     State(state): State<MEVState>,
+    Query(query): Query<MevOpportunitiesQuery>,
     agent: Option<Extension<Address>>,
-) -> Result<Json<ApiResponse<Vec<MEVOpportunity>>>, QGuardError> {
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<MevOpportunityPage>>, QGuardError> {
     // This endpoint costs $0.10 USDC (premium)
     // Payment middleware already verified payment
+    let query = query.validate()?;
     
-    // Check agent reputation if provided
-    let mut actual_price = 0.10;
-    
-    if let Some(Extension(agent_addr)) = agent {
-        let reputation = state.reputation.get_reputation(agent_addr).await
-            .map_err(|e| QGuardError::ReputationError(e.to_string()))?;
-        
-        tracing::info!(
-            "Agent {:?} with reputation {} accessing MEV opportunities",
-            agent_addr,
-            reputation
-        );
-        
-        // Deny access for reputation < 100
-        if reputation < 100 {
-            return Err(QGuardError::InsufficientReputation {
-                current: reputation,
-                required: 100,
-            });
-        }
-        
-        // Calculate dynamic pricing
-        actual_price = state.reputation.calculate_price(0.10, reputation);
-        
-        state.analytics.record_payment(actual_price, "/api/mev/opportunities", &format!("{:?}", agent_addr)).await;
-    } else {
-        state.analytics.record_payment(actual_price, "/api/mev/opportunities", "anonymous").await;
+    state
+        .mempool
+        .as_ref()
+        .filter(|mempool| mempool.is_connected())
+        .ok_or_else(|| QGuardError::ServiceUnavailable("Mempool stream is not connected".to_string()))?;
+    
+    charge_agent(&state.reputation, &state.analytics, &payment, 0.10, "/api/mev/opportunities").await?;
+    
+    // Pending transactions are analysed as they arrive; bundles are built for
+    // the searcher named by `X-Agent-Address`, who signs them
+    let agent = agent.map(|Extension(addr)| addr);
+    let mut opportunities = state.feed.opportunities(agent);
+    opportunities.extend(state.mev_detector.open_liquidations(agent).await);
+    
+    let page = query.apply(opportunities);
+    
+    tracing::info!("MEV analysis complete: {} matching opportunities", page.total);
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: page,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "ethereum-mempool".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+
+pub async fn protect_transaction(
+    State(state): State<MEVState>,
+    Extension(payment): Extension<PaymentVerification>,
+    Json(request): Json<ProtectRequest>,
+) -> Result<Json<ApiResponse<MevProtection>>, QGuardError> {
+    // This endpoint costs $0.05 USDC
+    let mut tx = Transaction {
+        from: request.from,
+        to: Some(request.to),
+        input: request.data,
+        value: request.value,
+        gas_price: request.gas_price,
+        max_fee_per_gas: request.max_fee_per_gas,
+        max_priority_fee_per_gas: request.max_priority_fee_per_gas,
+        ..Default::default()
+    };
+    
+    // Nothing to assess, so nothing to charge for
+    let swaps = state.mev_detector.decode_swaps(&tx);
+    if swaps.is_empty() {
+        return Err(QGuardError::InvalidRequest(
+            "Transaction is not a recognised DEX router swap".to_string(),
+        ));
+    }
+    if swaps
+        .iter()
+        .any(|swap| u128::try_from(swap.amount_in).is_err() || u128::try_from(swap.amount_out).is_err())
+    {
+        return Err(QGuardError::InvalidRequest("Swap amounts must be below 2^128".to_string()));
     }
+    if [tx.gas_price, tx.max_fee_per_gas, tx.max_priority_fee_per_gas]
+        .iter()
+        .flatten()
+        .any(|fee| u128::try_from(*fee).is_err())
+    {
+        return Err(QGuardError::InvalidRequest("Fees must be below 2^128".to_string()));
+    }
+    tx.gas = match request.gas {
+        Some(gas) => gas,
+        None => default_gas(&state.ethereum, &tx).await?,
+    };
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.05,
+        "/api/mev/protect",
+    )
+    .await?;
+    
+    let protection = state
+        .mev_detector
+        .sandwich_exposure(&tx)
+        .await
+        .map_err(|e| QGuardError::InternalError(format!("MEV protection failed: {}", e)))?;
     
-    let pending_txs = state.mempool.get_pending_transactions().await;
-    let mut opportunities = Vec::new();
+    tracing::info!(
+        "MEV protection assessed {} swaps, private relay: {}",
+        protection.swaps.len(),
+        protection.use_private_relay
+    );
     
-    // Analyze top 10 pending transactions
-    for tx in pending_txs.iter().take(10) {
-        if let Some(opp) = state.mev_detector.analyze_transaction(tx).await {
-            opportunities.push(opp);
+    Ok(Json(ApiResponse {
+        success: true,
+        data: protection,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "ethereum-simulation".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+/// The transaction's gas estimate, or the block gas limit if it reverts; the
+/// simulation then reports the revert
+async fn default_gas(ethereum: &EthereumService, tx: &Transaction) -> Result<U256, QGuardError> {
+    let request: TypedTransaction = Eip1559TransactionRequest::new()
+        .from(tx.from)
+        .to(tx.to.unwrap_or_default())
+        .value(tx.value)
+        .data(tx.input.clone())
+        .into();
+    
+    match ethereum.estimate_gas(&request).await {
+        Ok(gas) => Ok(gas.into()),
+        Err(QGuardError::InvalidRequest(_)) => {
+            let head = ethereum.get_block_number().await?;
+            let block = ethereum
+                .get_block(head)
+                .await?
+                .ok_or_else(|| QGuardError::InternalError(format!("Block {} not found", head)))?;
+            Ok(block.gas_limit)
         }
+        Err(e) => Err(e),
+    }
+}
+
+pub async fn simulate_bundle(
+    State(state): State<MEVState>,
+    Extension(payment): Extension<PaymentVerification>,
+    Json(request): Json<SimulateBundleRequest>,
+) -> Result<Json<ApiResponse<BundleSimulation>>, QGuardError> {
+    // This endpoint costs $0.05 USDC
+    if request.txs.is_empty() {
+        return Err(QGuardError::InvalidRequest("Bundle has no transactions".to_string()));
     }
+    if request.txs.len() > MAX_BUNDLE_TRANSACTIONS {
+        return Err(QGuardError::InvalidRequest(format!(
+            "Bundle has {} transactions, the limit is {}",
+            request.txs.len(),
+            MAX_BUNDLE_TRANSACTIONS
+        )));
+    }
+    let txs = request
+        .txs
+        .iter()
+        .enumerate()
+        .map(|(i, raw)| {
+            decode_raw_transaction(raw).map_err(|e| QGuardError::InvalidRequest(format!("Transaction {}: {}", i, e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.05,
+        "/api/mev/bundle/simulate",
+    )
+    .await?;
     
-    tracing::info!("MEV analysis complete: {} opportunities found", opportunities.len());
+    let simulation = state
+        .mev_detector
+        .simulate_bundle(txs)
+        .await
+        .map_err(|e| QGuardError::InternalError(format!("Bundle simulation failed: {}", e)))?;
     
     Ok(Json(ApiResponse {
         success: true,
-        data: opportunities,
+        data: simulation,
         timestamp: Utc::now(),
         cache_hit: false,
-        data_source: "ethereum-mempool".to_string(),
+        data_source: "ethereum-simulation".to_string(),
         request_id: Uuid::new_v4().to_string(),
     }))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct MevHistoryQuery {
+    pub from_block: u64,
+    /// Inclusive
+    pub to_block: u64,
+}
+
+pub async fn get_mev_block_report(
+    State(state): State<MEVState>,
+    Path(number): Path<u64>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<MevBlockReport>>, QGuardError> {
+    // This endpoint costs $0.05 USDC
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.05,
+        "/api/mev/history/block",
+    )
+    .await?;
+    
+    let report = state
+        .history
+        .block_report(number)
+        .await
+        .map_err(|e| QGuardError::InternalError(format!("MEV history failed: {}", e)))?
+        .ok_or_else(|| QGuardError::NotFound(format!("Block {} is not mined yet", number)))?;
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: report,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "ethereum-mainnet".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+pub async fn get_mev_history(
+    State(state): State<MEVState>,
+    Query(query): Query<MevHistoryQuery>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<MevRangeReport>>, QGuardError> {
+    // This endpoint costs $0.05 USDC
+    if query.from_block > query.to_block || query.to_block - query.from_block >= MAX_RANGE_BLOCKS {
+        return Err(QGuardError::InvalidRequest(format!(
+            "from_block must not exceed to_block, and a range covers at most {} blocks",
+            MAX_RANGE_BLOCKS
+        )));
+    }
+    let head = state.ethereum.get_block_number().await?;
+    if query.from_block > head {
+        return Err(QGuardError::InvalidRequest(format!(
+            "from_block {} is past the latest block {}",
+            query.from_block, head
+        )));
+    }
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.05,
+        "/api/mev/history",
+    )
+    .await?;
+    
+    let report = state
+        .history
+        .range_report(query.from_block, query.to_block)
+        .await
+        .map_err(|e| QGuardError::InternalError(format!("MEV history failed: {}", e)))?
+        .ok_or_else(|| QGuardError::InvalidRequest(format!("Block {} is not mined yet", query.from_block)))?;
+    
+    tracing::info!(
+        "MEV history for blocks {}-{}: {} sandwiches, {} arbitrages, {} liquidations",
+        report.from_block,
+        report.to_block,
+        report.sandwiches,
+        report.arbitrages,
+        report.liquidations
+    );
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: report,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "ethereum-mainnet".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
@@ -0,0 +1,157 @@
+use crate::{
+    error::QGuardError,
+    handlers::{billing::stream_credits, mev::mev_filter},
+    middleware::x402::PaymentVerification,
+    models::{LiveOpportunity, MevFilter, MevStreamMessage},
+    services::{Analytics, MempoolService, MevFeed, ReputationService},
+};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    response::Response,
+    Extension,
+};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+#[derive(Clone)]
+pub struct MevStreamState {
+    /// `None` when `ETH_WS_URL` is not configured
+    pub mempool: Option<Arc<MempoolService>>,
+    pub feed: Arc<MevFeed>,
+    pub analytics: Arc<Analytics>,
+    pub reputation: Arc<ReputationService>,
+    /// Base price per delivered message, before reputation discounts
+    pub message_price: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MevStreamQuery {
+    /// Comma-separated MEV types
+    pub types: Option<String>,
+    pub min_net_profit: Option<f64>,
+    /// Comma-separated token addresses
+    pub tokens: Option<String>,
+    /// Comma-separated pool addresses
+    pub pools: Option<String>,
+}
+
+impl MevStreamQuery {
+    fn filter(&self) -> Result<MevFilter, QGuardError> {
+        mev_filter(
+            self.types.as_deref(),
+            self.min_net_profit.unwrap_or(0.0),
+            self.tokens.as_deref(),
+            self.pools.as_deref(),
+        )
+    }
+}
+
+/// A subscription to new opportunities matching a filter, with the message
+/// credits left
+struct MevSubscription {
+    feed: Arc<MevFeed>,
+    opportunities: broadcast::Receiver<Arc<LiveOpportunity>>,
+    filter: MevFilter,
+    credits: u64,
+    analytics: Arc<Analytics>,
+}
+
+impl MevSubscription {
+    /// Waits for the next matching opportunity; `None` once the feed is gone.
+    /// Cancelling it loses nothing, since only non-matching opportunities are
+    /// consumed before it returns.
+    async fn next_opportunity(&mut self) -> Option<Arc<LiveOpportunity>> {
+        loop {
+            match self.opportunities.recv().await {
+                // A slow subscriber may get opportunities that expired meanwhile
+                Ok(live) if live.expires_at_block > self.feed.head() && self.filter.matches(&live.opportunity) => {
+                    return Some(live)
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!("MEV stream subscriber missed {} opportunities", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+pub async fn mev_stream_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<MevStreamState>,
+    Query(query): Query<MevStreamQuery>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Response, QGuardError> {
+    state
+        .mempool
+        .as_ref()
+        .filter(|mempool| mempool.is_connected())
+        .ok_or_else(|| QGuardError::ServiceUnavailable("Mempool stream is not connected".to_string()))?;
+    let filter = query.filter()?;
+
+    let credits = stream_credits(&state.reputation, &state.analytics, &payment, state.message_price, "/ws/mev").await?;
+
+    if credits == 0 {
+        return Err(QGuardError::PaymentVerificationFailed(format!(
+            "Payment does not cover a single message at {} USDC",
+            state.message_price
+        )));
+    }
+
+    tracing::info!("MEV stream opened ({:?}, {} credits)", filter, credits);
+
+    let subscription = MevSubscription {
+        opportunities: state.feed.subscribe(),
+        feed: state.feed.clone(),
+        filter,
+        credits,
+        analytics: state.analytics.clone(),
+    };
+
+    Ok(ws.on_upgrade(move |socket| handle_mev_socket(socket, subscription)))
+}
+
+async fn handle_mev_socket(socket: WebSocket, mut subscription: MevSubscription) {
+    let (mut sender, mut receiver) = socket.split();
+
+    while subscription.credits > 0 {
+        tokio::select! {
+            opportunity = subscription.next_opportunity() => {
+                let Some(opportunity) = opportunity else { break };
+
+                let message = MevStreamMessage {
+                    opportunity: (*opportunity).clone(),
+                    credits_remaining: subscription.credits - 1,
+                };
+                let Ok(text) = serde_json::to_string(&message) else { continue };
+                if sender.send(Message::Text(text)).await.is_err() {
+                    return;
+                }
+                // Spent only once the opportunity was sent
+                subscription.credits -= 1;
+                subscription.analytics.record_stream_message("/ws/mev").await;
+            }
+
+            // Pings are answered by the WebSocket implementation
+            message = receiver.next() => match message {
+                Some(Ok(Message::Close(_))) | None => return,
+                _ => {}
+            },
+        }
+    }
+
+    if subscription.credits == 0 {
+        let _ = sender
+            .send(Message::Text(r#"{"event":"credits_exhausted"}"#.to_string()))
+            .await;
+    }
+    let _ = sender.send(Message::Close(None)).await;
+
+    tracing::debug!("MEV stream WebSocket closed");
+}
@@ -1,12 +1,27 @@
+pub mod agents;
+pub mod billing;
 pub mod gas;
+pub mod gas_history;
+pub mod gas_stream;
 pub mod health;
 pub mod dashboard;
 pub mod stats;
 pub mod mev;
+pub mod mev_stream;
+pub mod mempool;
+pub mod prices;
+pub mod watchlist;
 
+pub use agents::*;
 pub use gas::*;
+pub use gas_history::*;
+pub use gas_stream::*;
 pub use health::*;
 pub use dashboard::*;
 pub use stats::*;
 pub use mev::*;
+pub use mev_stream::*;
+pub use mempool::*;
+pub use prices::*;
+pub use watchlist::*;
 
@@ -0,0 +1,70 @@
+use crate::{
+    error::QGuardError,
+    handlers::{billing::charge_agent, AppState},
+    middleware::x402::PaymentVerification,
+    models::{ApiResponse, TokenPrices},
+};
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use ethers::types::Address;
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Tokens priced per request
+const MAX_TOKENS: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct PricesQuery {
+    /// Comma-separated ERC-20 addresses
+    pub tokens: String,
+}
+
+/// USD prices of ERC-20 tokens from Chainlink feeds or Uniswap V3 TWAPs
+pub async fn get_prices(
+    State(state): State<AppState>,
+    Query(query): Query<PricesQuery>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<TokenPrices>>, QGuardError> {
+    let mut tokens = Vec::new();
+    for token in query.tokens.split(',').map(str::trim).filter(|token| !token.is_empty()) {
+        let token: Address = token
+            .parse()
+            .map_err(|_| QGuardError::InvalidRequest(format!("Invalid token address: {}", token)))?;
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    }
+    if tokens.is_empty() || tokens.len() > MAX_TOKENS {
+        return Err(QGuardError::InvalidRequest(format!(
+            "Provide between 1 and {} token addresses",
+            MAX_TOKENS
+        )));
+    }
+    
+    charge_agent(
+        &state.reputation,
+        &state.analytics,
+        &payment,
+        0.01,
+        "/api/prices",
+    )
+    .await?;
+    
+    let prices = state
+        .prices
+        .token_prices(&tokens)
+        .await
+        .map_err(|e| QGuardError::ServiceUnavailable(format!("Token prices unavailable: {}", e)))?;
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: prices,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "ethereum-mainnet".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
@@ -0,0 +1,166 @@
+use crate::{
+    error::QGuardError,
+    handlers::billing::{charge_agent, stream_credits},
+    middleware::x402::PaymentVerification,
+    models::{ApiResponse, CreateWatchlistRequest, Watchlist, WatchlistMatch, WatchlistStreamMessage},
+    services::{Analytics, ReputationService, WatchlistService},
+};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    response::Response,
+    Extension, Json,
+};
+use chrono::Utc;
+use ethers::types::Address;
+use futures::{SinkExt, StreamExt};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct WatchlistState {
+    pub watchlists: Arc<WatchlistService>,
+    pub analytics: Arc<Analytics>,
+    pub reputation: Arc<ReputationService>,
+    /// Base price per streamed match, before reputation discounts
+    pub message_price: f64,
+}
+
+/// Watchlists belong to the address that sent the verified payment, which is
+/// charged. A header naming the agent could be spoofed.
+async fn charge_owner(
+    state: &WatchlistState,
+    payment: &PaymentVerification,
+    endpoint: &str,
+) -> Result<Address, QGuardError> {
+    charge_agent(&state.reputation, &state.analytics, payment, 0.01, endpoint).await?;
+    Ok(payment.payer)
+}
+
+pub async fn create_watchlist(
+    State(state): State<WatchlistState>,
+    Extension(payment): Extension<PaymentVerification>,
+    Json(request): Json<CreateWatchlistRequest>,
+) -> Result<Json<ApiResponse<Watchlist>>, QGuardError> {
+    let agent = charge_owner(&state, &payment, "/api/watchlists").await?;
+    let watchlist = state.watchlists.create(agent, request).await?;
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: watchlist,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "q-guard".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+pub async fn list_watchlists(
+    State(state): State<WatchlistState>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<Vec<Watchlist>>>, QGuardError> {
+    let agent = charge_owner(&state, &payment, "/api/watchlists").await?;
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: state.watchlists.list(agent).await,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "q-guard".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+pub async fn delete_watchlist(
+    State(state): State<WatchlistState>,
+    Path(id): Path<Uuid>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Json<ApiResponse<Uuid>>, QGuardError> {
+    let agent = charge_owner(&state, &payment, "/api/watchlists").await?;
+    
+    if !state.watchlists.delete(agent, id).await? {
+        return Err(QGuardError::NotFound(format!("Watchlist {}", id)));
+    }
+    
+    Ok(Json(ApiResponse {
+        success: true,
+        data: id,
+        timestamp: Utc::now(),
+        cache_hit: false,
+        data_source: "q-guard".to_string(),
+        request_id: Uuid::new_v4().to_string(),
+    }))
+}
+
+/// Streams matches for all of the agent's watchlists, one credit per match
+pub async fn watchlist_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<WatchlistState>,
+    Extension(payment): Extension<PaymentVerification>,
+) -> Result<Response, QGuardError> {
+    let agent = payment.payer;
+    let credits =
+        stream_credits(&state.reputation, &state.analytics, &payment, state.message_price, "/ws/watchlists").await?;
+
+    if credits == 0 {
+        return Err(QGuardError::PaymentVerificationFailed(format!(
+            "Payment does not cover a single message at {} USDC",
+            state.message_price
+        )));
+    }
+
+    let matches = state.watchlists.subscribe();
+    let analytics = state.analytics.clone();
+    Ok(ws.on_upgrade(move |socket| handle_watchlist_socket(socket, agent, matches, credits, analytics)))
+}
+
+async fn handle_watchlist_socket(
+    socket: WebSocket,
+    agent: Address,
+    mut matches: broadcast::Receiver<Arc<WatchlistMatch>>,
+    mut credits: u64,
+    analytics: Arc<Analytics>,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    
+    while credits > 0 {
+        tokio::select! {
+            watch_match = matches.recv() => match watch_match {
+                Ok(watch_match) if watch_match.agent == agent => {
+                    let message = WatchlistStreamMessage {
+                        watch_match: (*watch_match).clone(),
+                        credits_remaining: credits - 1,
+                    };
+                    let Ok(text) = serde_json::to_string(&message) else { continue };
+                    if sender.send(Message::Text(text)).await.is_err() {
+                        return;
+                    }
+                    // Spent only once the match was sent
+                    credits -= 1;
+                    analytics.record_stream_message("/ws/watchlists").await;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!("Watchlist WebSocket for {:?} missed {} matches", agent, missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            
+            // Pings are answered by the WebSocket implementation
+            message = receiver.next() => match message {
+                Some(Ok(Message::Close(_))) | None => return,
+                _ => {}
+            },
+        }
+    }
+    
+    if credits == 0 {
+        let _ = sender
+            .send(Message::Text(r#"{"event":"credits_exhausted"}"#.to_string()))
+            .await;
+    }
+    let _ = sender.send(Message::Close(None)).await;
+}
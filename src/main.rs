@@ -1,20 +1,12 @@
 use anyhow::Result;
-use axum::{
-    middleware as axum_middleware,
-    routing::get,
-    Router,
-};
 use q_guard::{
+    app::{build_router, AppServices},
     config::Config,
-    handlers::*,
-    middleware::{create_rate_limit_layer, extract_agent_address, x402_middleware_layer, X402Middleware},
+    models::ChainSpec,
     services::*,
 };
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::{
-    cors::CorsLayer,
-    trace::{DefaultMakeSpan, TraceLayer},
-};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 #[tokio::main]
@@ -33,143 +25,201 @@ async fn main() -> Result<()> {
     
     // Initialize services
     let cache = Arc::new(CacheService::new(&config.redis_url).await?);
+    let eth_provider = Arc::new(ProviderPool::connect(
+        "ethereum",
+        &config.eth_rpc_urls,
+        config.rpc_pool.clone(),
+    )?);
     let ethereum = Arc::new(
         EthereumService::new(
-            &config.eth_rpc_url,
-            config.eth_rpc_fallback.as_deref(),
+            ChainSpec::ethereum(),
+            eth_provider.clone(),
             cache.clone(),
+            config.gas_model,
         )
-        .await?,
+        .await,
     );
+    
+    let gas_history = Arc::new(GasHistoryStore::new(
+        cache.clone(),
+        config.gas_history_max_blocks,
+        config.gas_history_retention_days,
+    ));
+    
+    // L2s are optional - each is enabled by its RPC URL
+    let mut chains = ChainRegistry::new(ethereum.clone(), gas_history);
+    for chain in &config.l2_chains {
+        let provider = ProviderPool::connect(&chain.spec.name, &chain.rpc_urls, config.rpc_pool.clone())?;
+        let service = EthereumService::new(
+            chain.spec.clone(),
+            Arc::new(provider),
+            cache.clone(),
+            config.gas_model,
+        )
+        .await;
+        chains.insert(Arc::new(service));
+    }
+    let chains = Arc::new(chains);
+    tracing::info!("Gas prediction chains: {}", chains.names().join(", "));
+    chains.start_followers();
+    
     let analytics = Arc::new(Analytics::new(cache.clone()));
     
-    // Initialize reputation service (no contract deployed yet, uses mock)
+    // MEV and pricing run on Ethereum, so they only see its DEXes
+    let dexes = Arc::new(config.dex_registry.on_chain(ethereum.chain()));
+    tracing::info!(
+        "DEX registry: {}",
+        dexes.deployments().iter().map(|dex| dex.name.as_str()).collect::<Vec<_>>().join(", ")
+    );
+    
+    let prices = Arc::new(PriceService::new(
+        eth_provider.clone(),
+        cache.clone(),
+        dexes.clone(),
+        config.eth_usd_feed,
+        config.price_feeds.clone(),
+    ));
+    
+    // Payment verification shares one Base Sepolia provider pool
+    let base_sepolia = Arc::new(ProviderPool::connect(
+        "base-sepolia",
+        &config.base_sepolia_rpc_urls,
+        config.rpc_pool.clone(),
+    )?);
+    
+    // Reputation is computed locally from each agent's payment and usage
+    // history, blended with its score on the ERC-8004 registries of one
+    // network when those are configured
+    let erc8004 = match config.erc8004_deployment() {
+        Some(deployment) => {
+            let client: Arc<dyn ChainClient> = match deployment.network.as_str() {
+                "base-sepolia" => base_sepolia.clone(),
+                network => chains.get(Some(network))?.client.clone(),
+            };
+            // Agent ids are indexed from the Identity Registry in the background
+            let registry = Arc::new(Erc8004Registry::new(client, cache.clone(), deployment.clone()));
+            let indexer = registry.clone();
+            tokio::spawn(async move {
+                indexer.run().await;
+            });
+            Some(registry)
+        }
+        None => None,
+    };
+    let ledger = Arc::new(AgentLedger::new(cache.clone()));
     let reputation = Arc::new(
         ReputationService::new(
-            ethereum.primary.clone(),
             cache.clone(),
-            None, // No contract deployed yet
+            ledger,
+            erc8004,
+            config.reputation.clone(),
         ).await
     );
     
     // Initialize MEV services (optional - requires WebSocket)
-    // For now, using a placeholder - in production, configure ETH_WS_URL in .env
-    let eth_ws_url = std::env::var("ETH_WS_URL")
-        .unwrap_or_else(|_| "wss://eth-mainnet.g.alchemy.com/v2/demo".to_string());
-    
-    let mempool = Arc::new(
-        MempoolService::new(&eth_ws_url)
-            .await
-            .unwrap_or_else(|e| {
-                tracing::warn!("Mempool service unavailable: {}", e);
-                panic!("WebSocket required for MEV endpoint. Set ETH_WS_URL in .env");
-            })
-    );
+    let mempool = config
+        .eth_ws_url
+        .as_deref()
+        .map(|url| Arc::new(MempoolService::new(url, config.mempool.clone())));
+    
+    // Start mempool monitoring in background; it reconnects on its own
+    let tx_lifecycle = match &mempool {
+        Some(mempool) => {
+            let monitor = mempool.clone();
+            tokio::spawn(async move {
+                monitor.start_monitoring().await;
+            });
+            
+            // Correlate pending transactions with the blocks the Ethereum follower sees
+            let tracker = Arc::new(TxLifecycleTracker::new(mempool.clone(), ethereum.clone()));
+            let follower = chains.follower(None)?;
+            let lifecycle = tracker.clone();
+            tokio::spawn(async move {
+                lifecycle.run(follower).await;
+            });
+            Some(tracker)
+        }
+        None => {
+            tracing::warn!("ETH_WS_URL not set, MEV endpoints will report unavailable");
+            None
+        }
+    };
     
-    // Start mempool monitoring in background
-    let mempool_clone = mempool.clone();
+    // Watchlist webhooks are signed with the seller key
+    let watchlists = Arc::new(WatchlistService::new(&config.seller_private_key, cache.clone()));
+    if let Some(mempool) = &mempool {
+        let matcher = watchlists.clone();
+        let mempool = mempool.clone();
+        tokio::spawn(async move {
+            matcher.run(mempool).await;
+        });
+    }
+    
+    // Pool reserves for backrun arbitrage follow the Ethereum head
+    let arbitrage = Arc::new(ArbitrageScanner::new(
+        ethereum.client.clone(),
+        config.arbitrage_pools.clone(),
+        dexes.clone(),
+    ));
+    arbitrage.load().await;
+    let scanner = arbitrage.clone();
+    let follower = chains.follower(None)?;
     tokio::spawn(async move {
-        mempool_clone.start_monitoring().await;
+        scanner.run(follower).await;
     });
     
-    let mev_detector = Arc::new(MEVDetector::new(ethereum.clone()));
-    
-    // Initialize x402 middleware for gas prediction ($0.01)
-    let x402_gas = Arc::new(
-        X402Middleware::new(
-            config.facilitator_url.clone(),
-            config.base_sepolia_rpc_url.clone(),
-            config.recipient_address,
-            config.usdc_address,
-            "0.01".to_string(),
-        )
-        .await?,
-    );
-    
-    // Initialize x402 middleware for MEV ($0.10)
-    let x402_mev = Arc::new(
-        X402Middleware::new(
-            config.facilitator_url.clone(),
-            config.base_sepolia_rpc_url.clone(),
-            config.recipient_address,
-            config.usdc_address,
-            "0.10".to_string(),
-        )
-        .await?,
-    );
-    
-    // Build application state
-    let app_state = AppState {
-        ethereum: ethereum.clone(),
-        analytics: analytics.clone(),
-        reputation: reputation.clone(),
-    };
-    
-    let mev_state = MEVState {
-        ethereum: ethereum.clone(),
-        mempool: mempool.clone(),
-        mev_detector: mev_detector.clone(),
-        analytics: analytics.clone(),
-        reputation: reputation.clone(),
-    };
-    
-    let health_state = HealthState {
-        cache: cache.clone(),
-        ethereum: ethereum.clone(),
-        analytics: analytics.clone(),
-    };
+    // Lending positions load in the background from the first block
+    let liquidations = Arc::new(LiquidationMonitor::new(
+        ethereum.client.clone(),
+        config.lending_markets.clone(),
+        config.liquidation_lookback_blocks,
+        ethereum.chain().wrapped_native,
+    ));
+    let monitor = liquidations.clone();
+    let follower = chains.follower(None)?;
+    tokio::spawn(async move {
+        monitor.run(follower).await;
+    });
     
-    // Build router
-    let app = Router::new()
-        // Public endpoints (no payment required)
-        .route("/health", get(health_check))
-        .with_state(health_state)
-        
-        .route("/stats", get(get_stats))
-        .with_state(analytics.clone())
-        
-        .route("/ws/dashboard", get(websocket_handler))
-        .with_state(analytics.clone())
-        
-        // Protected endpoints (payment required)
-        .route(
-            "/api/gas/prediction",
-            get(predict_gas)
-                .layer(axum_middleware::from_fn(extract_agent_address))
-                .layer(axum_middleware::from_fn({
-                    let x402 = x402_gas.clone();
-                    move |req, next| {
-                        let x402 = x402.clone();
-                        async move { x402_middleware_layer(x402, req, next).await }
-                    }
-                })),
-        )
-        .with_state(app_state)
-        
-        .route(
-            "/api/mev/opportunities",
-            get(get_mev_opportunities)
-                .layer(axum_middleware::from_fn(extract_agent_address))
-                .layer(axum_middleware::from_fn({
-                    let x402 = x402_mev.clone();
-                    move |req, next| {
-                        let x402 = x402.clone();
-                        async move { x402_middleware_layer(x402, req, next).await }
-                    }
-                })),
-        )
-        .with_state(mev_state)
-        
-        // Global middleware
-        .layer(create_rate_limit_layer(
-            config.rate_limit_per_second,
-            config.rate_limit_burst,
-        ))
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(DefaultMakeSpan::default().include_headers(true)),
-        )
-        .layer(CorsLayer::permissive());
+    let mev_detector = Arc::new(MEVDetector::new(
+        ethereum.clone(),
+        prices.clone(),
+        dexes.clone(),
+        arbitrage,
+        liquidations,
+    ));
+    let mev_history = Arc::new(MevHistoryService::new(ethereum.client.clone(), cache.clone(), dexes));
+    
+    // Pending transactions are analysed for MEV as they arrive
+    let mev_feed = Arc::new(MevFeed::new(mev_detector.clone()));
+    if let Some(mempool) = &mempool {
+        let feed = mev_feed.clone();
+        let mempool = mempool.clone();
+        let follower = chains.follower(None)?;
+        tokio::spawn(async move {
+            feed.run(mempool, follower).await;
+        });
+    }
+    
+    let app = build_router(
+        &config,
+        AppServices {
+            cache,
+            ethereum,
+            chains,
+            analytics,
+            reputation,
+            prices,
+            payment_chain: base_sepolia,
+            mempool,
+            tx_lifecycle,
+            watchlists,
+            mev_detector,
+            mev_feed,
+            mev_history,
+        },
+    )
+    .await?;
     
     // Start server
     let addr = format!("{}:{}", config.host, config.port);
@@ -179,7 +229,8 @@ async fn main() -> Result<()> {
     tracing::info!("WebSocket dashboard: ws://{}/ws/dashboard", addr);
     tracing::info!("Health check: http://{}/health", addr);
     
-    axum::serve(listener, app)
+    // Peer addresses feed the per-IP rate limit
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await?;
     
@@ -0,0 +1,31 @@
+use crate::error::QGuardError;
+use crate::services::{MempoolService, TxLifecycleTracker};
+use axum::{extract::Request, middleware::Next, response::Response};
+use std::sync::Arc;
+
+// Layered outside the x402 middleware, so agents are not asked to pay for a
+// request that can only answer 503
+
+/// Answers 503 while the mempool stream is down or not configured
+pub async fn require_mempool(
+    mempool: Option<Arc<MempoolService>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, QGuardError> {
+    match mempool {
+        Some(mempool) if mempool.is_connected() => Ok(next.run(request).await),
+        _ => Err(QGuardError::ServiceUnavailable("Mempool stream is not connected".to_string())),
+    }
+}
+
+/// Answers 503 when mempool tracking is not configured
+pub async fn require_tx_tracking(
+    tracker: Option<Arc<TxLifecycleTracker>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, QGuardError> {
+    match tracker {
+        Some(_) => Ok(next.run(request).await),
+        None => Err(QGuardError::ServiceUnavailable("Mempool tracking is not configured".to_string())),
+    }
+}
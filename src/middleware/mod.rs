@@ -1,8 +1,10 @@
+pub mod availability;
 pub mod x402;
 pub mod rate_limit;
 pub mod reputation;
 
+pub use availability::{require_mempool, require_tx_tracking};
 pub use x402::{X402Middleware, x402_middleware_layer};
-pub use rate_limit::create_rate_limit_layer;
+pub use rate_limit::{create_rate_limit_layer, RateLimiter};
 pub use reputation::extract_agent_address;
 
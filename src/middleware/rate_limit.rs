@@ -1,43 +1,125 @@
-use axum::http::Request;
+use crate::error::QGuardError;
+use axum::{
+    extract::ConnectInfo,
+    http::Request,
+    response::{IntoResponse, Response},
+};
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
+use std::time::Instant;
 use tower::{Layer, Service};
 
-// Simplified rate limiting for now - can be enhanced later
+/// Full buckets are dropped once this many keys are tracked
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token bucket per key (a client IP, or the payer of a verified payment): up
+/// to `burst` requests at once, refilled at `per_second`
+pub struct RateLimiter<K> {
+    per_second: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    pub fn new(per_second: u64, burst: u32) -> Self {
+        Self {
+            per_second: per_second as f64,
+            burst: burst as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token from the key's bucket; false when it is empty
+    pub fn try_acquire(&self, key: K) -> bool {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_TRACKED_KEYS {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < self.burst);
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.burst,
+            refilled_at: now,
+        });
+        bucket.tokens = self.refill(bucket, now);
+        bucket.refilled_at = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    fn refill(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        (bucket.tokens + elapsed * self.per_second).min(self.burst)
+    }
+}
+
+/// Limits each client IP. Requests without a peer address (served without
+/// `ConnectInfo`) are not limited here; paid requests are also limited per
+/// payer by the x402 middleware.
 #[derive(Clone)]
-pub struct RateLimitLayer;
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter<IpAddr>>,
+}
 
 impl<S> Layer<S> for RateLimitLayer {
     type Service = RateLimitService<S>;
 
     fn layer(&self, service: S) -> Self::Service {
-        RateLimitService { inner: service }
+        RateLimitService {
+            inner: service,
+            limiter: self.limiter.clone(),
+        }
     }
 }
 
 #[derive(Clone)]
 pub struct RateLimitService<S> {
     inner: S,
+    limiter: Arc<RateLimiter<IpAddr>>,
 }
 
 impl<S, ReqBody> Service<Request<ReqBody>> for RateLimitService<S>
 where
-    S: Service<Request<ReqBody>>,
+    S: Service<Request<ReqBody>, Response = Response>,
+    S::Error: 'static,
+    S::Future: Send + 'static,
 {
-    type Response = S::Response;
+    type Response = Response;
     type Error = S::Error;
-    type Future = S::Future;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
     }
 
     fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
-        // TODO: Implement actual rate limiting logic
-        self.inner.call(req)
+        if let Some(ConnectInfo(peer)) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
+            if !self.limiter.try_acquire(peer.ip()) {
+                tracing::debug!("Rate limited {}", peer.ip());
+                return Box::pin(async { Ok(QGuardError::RateLimitExceeded.into_response()) });
+            }
+        }
+
+        Box::pin(self.inner.call(req))
     }
 }
 
-pub fn create_rate_limit_layer(_per_second: u64, _burst: u32) -> RateLimitLayer {
-    RateLimitLayer
+pub fn create_rate_limit_layer(per_second: u64, burst: u32) -> RateLimitLayer {
+    RateLimitLayer {
+        limiter: Arc::new(RateLimiter::new(per_second, burst)),
+    }
 }
-
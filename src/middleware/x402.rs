@@ -1,15 +1,13 @@
 use crate::error::QGuardError;
+use crate::middleware::RateLimiter;
+use crate::services::{AgentEvent, ChainClient, ReputationService};
 use anyhow::Result;
 use axum::{
     extract::Request,
     middleware::Next,
     response::Response,
 };
-use ethers::{
-    prelude::*,
-    providers::{Http, Provider},
-    types::{Address, TransactionReceipt, H256},
-};
+use ethers::types::{Address, TransactionReceipt, H256, U256};
 use serde::Serialize;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -18,29 +16,36 @@ use std::sync::Arc;
 pub struct X402Middleware {
     facilitator_url: String,
     client: reqwest::Client,
-    provider: Arc<Provider<Http>>,
+    provider: Arc<dyn ChainClient>,
     recipient_address: Address,
     usdc_address: Address,
     expected_amount_usd: String,
+    /// Registration gating, and the ledger of redeemed payments and of each
+    /// payer's history
+    reputation: Arc<ReputationService>,
+    /// Shared by every priced route, so a payer's requests are limited together
+    rate_limiter: Arc<RateLimiter<Address>>,
 }
 
 impl X402Middleware {
     pub async fn new(
         facilitator_url: String,
-        base_sepolia_rpc: String,
+        base_sepolia: Arc<dyn ChainClient>,
         recipient_address: Address,
         usdc_address: Address,
         expected_amount_usd: String,
+        reputation: Arc<ReputationService>,
+        rate_limiter: Arc<RateLimiter<Address>>,
     ) -> Result<Self> {
-        let provider = Arc::new(Provider::<Http>::try_from(base_sepolia_rpc)?);
-        
         Ok(Self {
             facilitator_url,
             client: reqwest::Client::new(),
-            provider,
+            provider: base_sepolia,
             recipient_address,
             usdc_address,
             expected_amount_usd,
+            reputation,
+            rate_limiter,
         })
     }
     
@@ -60,11 +65,39 @@ impl X402Middleware {
         let verification = self.verify_transaction(tx_hash).await?;
         
         if !verification.valid {
+            // A transfer to the wrong address, of too little or not in USDC was
+            // sent by its payer, so it counts against them; claiming the hash
+            // counts each transaction once however often it is presented. A
+            // failed transaction names no payer.
+            if !verification.payer.is_zero() && self.reputation.ledger().redeem_payment(tx_hash).await {
+                self.reputation.ledger().record(verification.payer, AgentEvent::PaymentFailed).await;
+            }
             return Err(QGuardError::PaymentVerificationFailed(verification.reason));
         }
         
-        // Optional: Report to facilitator for settlement
-        self.report_to_facilitator(&verification).await.ok();
+        // Registration is checked on the payer, before the payment is used up
+        let registered = self.reputation.verify_registration(verification.payer).await
+            .map_err(|e| QGuardError::ReputationError(e.to_string()))?;
+        if !registered {
+            return Err(QGuardError::AgentNotRegistered(format!("{:?}", verification.payer)));
+        }
+        
+        // Each payment buys one request (or one stream connection). It is claimed
+        // before the request is served, so concurrent uses are refused.
+        let ledger = self.reputation.ledger();
+        if !ledger.redeem_payment(tx_hash).await {
+            tracing::warn!("Payment {:?} presented again", tx_hash);
+            return Err(QGuardError::PaymentReplayed(tx_hash));
+        }
+        
+        // Only a payment presented for the first time is limited, so replaying
+        // someone else's transaction cannot use up their quota or their score.
+        // A limited payment is released and can be used once the window passes.
+        if !self.rate_limiter.try_acquire(verification.payer) {
+            ledger.release_payment(tx_hash).await;
+            ledger.record(verification.payer, AgentEvent::RateLimited).await;
+            return Err(QGuardError::RateLimitExceeded);
+        }
         
         Ok(verification)
     }
@@ -72,7 +105,7 @@ impl X402Middleware {
     async fn verify_transaction(&self, tx_hash: H256) -> Result<PaymentVerification, QGuardError> {
         // Get transaction receipt
         let receipt = self.provider
-            .get_transaction_receipt(tx_hash)
+            .receipt(tx_hash)
             .await
             .map_err(|e| QGuardError::PaymentVerificationFailed(format!("RPC error: {}", e)))?
             .ok_or_else(|| QGuardError::PaymentVerificationFailed("Transaction not found".to_string()))?;
@@ -90,7 +123,7 @@ impl X402Middleware {
         
         // Get transaction details
         let tx = self.provider
-            .get_transaction(tx_hash)
+            .transaction(tx_hash)
             .await
             .map_err(|e| QGuardError::PaymentVerificationFailed(format!("RPC error: {}", e)))?
             .ok_or_else(|| QGuardError::PaymentVerificationFailed("Transaction not found".to_string()))?;
@@ -227,7 +260,7 @@ struct USDCTransfer {
 // Axum middleware function
 pub async fn x402_middleware_layer(
     middleware: Arc<X402Middleware>,
-    request: Request,
+    mut request: Request,
     next: Next,
 ) -> Result<Response, QGuardError> {
     // Extract payment header
@@ -236,10 +269,26 @@ pub async fn x402_middleware_layer(
         .get("X-Payment")
         .and_then(|h| h.to_str().ok());
     
-    // Verify payment
-    let _verification = middleware.verify_payment_header(payment_header).await?;
+    let verification = middleware.verify_payment_header(payment_header).await?;
+    let amount_usd = U256::from_dec_str(&verification.amount)
+        .map(|amount| amount.as_u128() as f64 / 1e6)
+        .unwrap_or_default();
+    
+    // Payment verified, continue to handler (metered endpoints read the amount paid)
+    request.extensions_mut().insert(verification.clone());
+    let response = next.run(request).await;
     
-    // Payment verified, continue to handler
-    Ok(next.run(request).await)
+    // A request that was refused or failed did not use the payment up
+    let ledger = middleware.reputation.ledger();
+    if response.status().is_client_error() || response.status().is_server_error() {
+        tracing::debug!("Releasing payment {:?} after {}", verification.tx_hash, response.status());
+        ledger.release_payment(verification.tx_hash).await;
+        return Ok(response);
+    }
+    
+    // Only the verified payer's history is recorded; `X-Agent-Address` can be spoofed
+    ledger.record(verification.payer, AgentEvent::PaymentVerified { amount_usd }).await;
+    // Optional: Report to facilitator for settlement
+    middleware.report_to_facilitator(&verification).await.ok();
+    Ok(response)
 }
-
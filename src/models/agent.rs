@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+/// An ERC-8004 agent registration file (the Identity Registry token URI).
+/// Only `name` is required; unknown fields are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRegistration {
+    #[serde(rename = "type", default)]
+    pub type_: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub image: Option<String>,
+    /// Where the agent can be reached (A2A, MCP, ENS, wallet, ...)
+    #[serde(default)]
+    pub endpoints: Vec<AgentEndpoint>,
+    /// The registries the agent is registered in
+    #[serde(default)]
+    pub registrations: Vec<AgentRegistrationRef>,
+    /// Trust models the agent supports, e.g. "reputation" or "tee-attestation"
+    #[serde(default)]
+    pub supported_trust: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentEndpoint {
+    pub name: String,
+    pub endpoint: String,
+    #[serde(default)]
+    pub version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRegistrationRef {
+    pub agent_id: u64,
+    /// CAIP-10 style `eip155:<chain id>:<registry address>`
+    pub agent_registry: String,
+}
+
+/// Count and average (0-100) of ERC-8004 feedback or validation responses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TrustSummary {
+    pub count: u64,
+    pub average: u8,
+}
+
+/// What the ERC-8004 registries say about an agent address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentProfile {
+    pub address: Address,
+    pub network: String,
+    pub identity_registry: Address,
+    pub agent_id: U256,
+    pub token_uri: String,
+    /// `None` when the registration file could not be fetched or parsed
+    pub registration: Option<AgentRegistration>,
+    pub feedback: TrustSummary,
+    /// `None` when no Validation Registry is configured
+    pub validations: Option<TrustSummary>,
+    pub reputation: u64,
+}
+
+/// What Q-guard has observed of an agent (the payer of verified payments)
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AgentBehavior {
+    pub verified_payments: u64,
+    pub paid_usd: f64,
+    pub payment_failures: u64,
+    pub rate_limit_violations: u64,
+    /// `None` until the first recorded event
+    pub first_seen: Option<DateTime<Utc>>,
+    pub last_seen: Option<DateTime<Utc>>,
+}
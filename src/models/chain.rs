@@ -0,0 +1,85 @@
+use crate::contracts::{ARBITRUM_WETH, OP_STACK_WETH, WETH};
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+
+/// How a chain prices gas, which decides the extra fee components we report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainKind {
+    L1,
+    /// OP-stack rollups (Base, Optimism) - L1 data fee from the GasPriceOracle predeploy
+    OpStack,
+    /// Arbitrum Nitro - L1 component from ArbGasInfo / NodeInterface
+    Arbitrum,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainSpec {
+    pub name: String,
+    pub chain_id: u64,
+    pub kind: ChainKind,
+    pub block_time_ms: u64,
+    /// ERC-20 wrapper of the native token, which MEV is bought and measured in
+    pub wrapped_native: Address,
+}
+
+impl ChainSpec {
+    pub fn ethereum() -> Self {
+        Self::new("ethereum", 1, ChainKind::L1, 12_000, WETH)
+    }
+    
+    pub fn base() -> Self {
+        Self::new("base", 8453, ChainKind::OpStack, 2_000, OP_STACK_WETH)
+    }
+    
+    pub fn optimism() -> Self {
+        Self::new("optimism", 10, ChainKind::OpStack, 2_000, OP_STACK_WETH)
+    }
+    
+    pub fn arbitrum() -> Self {
+        Self::new("arbitrum", 42161, ChainKind::Arbitrum, 250, ARBITRUM_WETH)
+    }
+    
+    fn new(name: &str, chain_id: u64, kind: ChainKind, block_time_ms: u64, wrapped_native: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            chain_id,
+            kind,
+            block_time_ms,
+            wrapped_native: wrapped_native.parse().expect("valid address constant"),
+        }
+    }
+    
+    /// Block time rounded up to whole seconds (Arbitrum's 250ms reports as 1)
+    pub fn block_time_seconds(&self) -> u64 {
+        self.block_time_ms.div_ceil(1000).max(1)
+    }
+    
+    pub fn data_source(&self) -> String {
+        format!("{}-mainnet", self.name)
+    }
+}
+
+/// L1 cost components reported alongside an L2 gas prediction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct L1FeeComponents {
+    pub l1_base_fee_gwei: f64,
+    
+    /// OP-stack (Ecotone+) blob base fee
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_base_fee_gwei: Option<f64>,
+    
+    /// Arbitrum price per L1 calldata byte
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l1_calldata_byte_price_gwei: Option<f64>,
+}
+
+/// L1 data fee for a specific transaction on an L2
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct L1DataFee {
+    pub fee_wei: String,
+    pub fee_eth: f64,
+    pub fee_usd: f64,
+    /// Arbitrum folds the L1 component into the L2 gas limit; OP-stack charges it on top
+    pub included_in_gas_limit: bool,
+}
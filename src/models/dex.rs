@@ -0,0 +1,60 @@
+use ethers::abi::{self, Token};
+use ethers::types::{Address, H256};
+use ethers::utils::{get_create2_address_from_hash, keccak256};
+use serde::{Deserialize, Serialize};
+
+/// Pool design of a DEX, which decides how its swaps are decoded, simulated
+/// and priced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DexKind {
+    /// Constant-product pairs behind a V2-style router (Uniswap V2, SushiSwap)
+    UniswapV2,
+    /// Concentrated-liquidity pools with fee tiers
+    UniswapV3,
+}
+
+/// One DEX on one chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DexDeployment {
+    /// Also the venue name of arbitrage pools (`uniswap_v2`, `sushiswap`, ...)
+    pub name: String,
+    /// Chain name as served by the gas endpoints
+    pub chain: String,
+    pub kind: DexKind,
+    /// Routers whose swaps trade on this DEX's pools. Only swaps sent to a
+    /// registered router are decoded.
+    #[serde(default)]
+    pub routers: Vec<Address>,
+    /// Router with the Uniswap V2 Router02 interface, which simulated
+    /// frontruns and backruns go through. Only V2-style DEXes have one.
+    #[serde(default)]
+    pub v2_router: Option<Address>,
+    /// Deploys the pools with CREATE2
+    pub factory: Option<Address>,
+    /// CREATE2 init code hash of the factory's pools
+    pub init_code_hash: Option<H256>,
+    /// Pool fees in hundredths of a basis point, as V3 fee tiers
+    #[serde(default)]
+    pub fee_tiers: Vec<u32>,
+}
+
+impl DexDeployment {
+    /// Address of the pool for a token pair, derived without a factory call.
+    /// V3 pools also need the fee tier. `None` without a factory and init code
+    /// hash. The pool may not be deployed yet.
+    pub fn pool_address(&self, token_a: Address, token_b: Address, fee: Option<u32>) -> Option<Address> {
+        let (factory, init_code_hash) = (self.factory?, self.init_code_hash?);
+        let (token0, token1) = if token_a < token_b { (token_a, token_b) } else { (token_b, token_a) };
+        let salt = match (self.kind, fee) {
+            (DexKind::UniswapV2, _) => keccak256([token0.as_bytes(), token1.as_bytes()].concat()),
+            (DexKind::UniswapV3, Some(fee)) => keccak256(abi::encode(&[
+                Token::Address(token0),
+                Token::Address(token1),
+                Token::Uint(fee.into()),
+            ])),
+            (DexKind::UniswapV3, None) => return None,
+        };
+        Some(get_create2_address_from_hash(factory, salt, init_code_hash))
+    }
+}
@@ -1,5 +1,9 @@
 use chrono::{DateTime, Utc};
+use crate::models::{L1DataFee, L1FeeComponents};
+use ethers::types::U256;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GasPrediction {
@@ -10,11 +14,192 @@ pub struct GasPrediction {
     pub block_number: u64,
     pub predicted_at: DateTime<Utc>,
     pub next_block_time_seconds: u64,
+    pub model: GasModel,
+    pub chain: String,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l1_fee: Option<L1FeeComponents>,
 }
 
 impl GasPrediction {
     pub fn calculate_transaction_cost(&self, gas_limit: u64) -> f64 {
         (self.max_fee_gwei * gas_limit as f64) / 1e9
     }
+    
+    /// Expected cost in ETH if the transaction pays base fee plus priority fee
+    pub fn expected_transaction_cost(&self, gas_limit: u64) -> f64 {
+        ((self.base_fee_gwei + self.priority_fee_gwei) * gas_limit as f64) / 1e9
+    }
+    
+    /// Prediction adjusted for a speed tier: the priority fee is scaled and the
+    /// max fee keeps the model's base fee headroom.
+    pub fn for_tier(&self, tier: SpeedTier) -> GasPrediction {
+        let priority_fee_gwei = self.priority_fee_gwei * tier.priority_multiplier();
+        
+        GasPrediction {
+            priority_fee_gwei,
+            max_fee_gwei: self.max_fee_gwei - self.priority_fee_gwei + priority_fee_gwei,
+            ..self.clone()
+        }
+    }
+}
+
+/// Predictions for every model, published by the block follower on each new head
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasUpdate {
+    pub chain: String,
+    pub block_number: u64,
+    pub predictions: Vec<GasPrediction>,
+}
+
+impl GasUpdate {
+    pub fn prediction(&self, model: GasModel) -> Option<&GasPrediction> {
+        self.predictions.iter().find(|p| p.model == model)
+    }
+}
+
+/// A metered message on the gas prediction stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasStreamMessage {
+    pub prediction: GasPrediction,
+    pub credits_remaining: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeedTier {
+    Slow,
+    Standard,
+    Fast,
+}
+
+impl SpeedTier {
+    pub const ALL: [SpeedTier; 3] = [SpeedTier::Slow, SpeedTier::Standard, SpeedTier::Fast];
+    
+    pub fn priority_multiplier(&self) -> f64 {
+        match self {
+            SpeedTier::Slow => 0.5,
+            SpeedTier::Standard => 1.0,
+            SpeedTier::Fast => 2.0,
+        }
+    }
+}
+
+/// Where the gas limit of a cost estimate came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GasLimitSource {
+    Provided,
+    Estimated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub chain: String,
+    pub gas_limit: u64,
+    pub gas_limit_source: GasLimitSource,
+    pub eth_usd_price: f64,
+    pub block_number: u64,
+    pub model: GasModel,
+    
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l1_data_fee: Option<L1DataFee>,
+    
+    pub tiers: Vec<TierCost>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierCost {
+    pub tier: SpeedTier,
+    pub priority_fee_gwei: f64,
+    pub max_fee_gwei: f64,
+    /// Expected cost (base fee + priority fee), decimal string
+    pub expected_cost_wei: String,
+    pub expected_cost_eth: f64,
+    pub expected_cost_usd: f64,
+    /// Upper bound if the full max fee is charged
+    pub max_cost_eth: f64,
+    pub max_cost_usd: f64,
+}
+
+impl TierCost {
+    /// `extra_fee_wei` is charged on top of execution gas (the OP-stack L1 data fee)
+    pub fn new(
+        prediction: &GasPrediction,
+        tier: SpeedTier,
+        gas_limit: u64,
+        extra_fee_wei: U256,
+        eth_usd_price: f64,
+    ) -> Self {
+        let tiered = prediction.for_tier(tier);
+        let extra_fee_eth = extra_fee_wei.as_u128() as f64 / 1e18;
+        
+        let fee_per_gas_wei = ((tiered.base_fee_gwei + tiered.priority_fee_gwei) * 1e9) as u128;
+        let expected_cost_wei = U256::from(fee_per_gas_wei) * U256::from(gas_limit) + extra_fee_wei;
+        let expected_cost_eth = tiered.expected_transaction_cost(gas_limit) + extra_fee_eth;
+        let max_cost_eth = tiered.calculate_transaction_cost(gas_limit) + extra_fee_eth;
+        
+        Self {
+            tier,
+            priority_fee_gwei: tiered.priority_fee_gwei,
+            max_fee_gwei: tiered.max_fee_gwei,
+            expected_cost_wei: expected_cost_wei.to_string(),
+            expected_cost_eth,
+            expected_cost_usd: expected_cost_eth * eth_usd_price,
+            max_cost_eth,
+            max_cost_usd: max_cost_eth * eth_usd_price,
+        }
+    }
+}
+
+/// Gas prediction model, selectable per request with `?model=`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GasModel {
+    #[default]
+    Ewma,
+    Eip1559,
+    Percentile,
+    Kalman,
+}
+
+impl GasModel {
+    pub const ALL: [GasModel; 4] = [
+        GasModel::Ewma,
+        GasModel::Eip1559,
+        GasModel::Percentile,
+        GasModel::Kalman,
+    ];
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GasModel::Ewma => "ewma",
+            GasModel::Eip1559 => "eip1559",
+            GasModel::Percentile => "percentile",
+            GasModel::Kalman => "kalman",
+        }
+    }
+}
+
+impl fmt::Display for GasModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for GasModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ewma" => Ok(GasModel::Ewma),
+            "eip1559" | "eip-1559" => Ok(GasModel::Eip1559),
+            "percentile" => Ok(GasModel::Percentile),
+            "kalman" => Ok(GasModel::Kalman),
+            _ => Err(format!(
+                "Unknown gas model: {} (expected one of: ewma, eip1559, percentile, kalman)",
+                s
+            )),
+        }
+    }
+}
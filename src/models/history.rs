@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Fee data persisted for every block the follower sees
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockFeeRecord {
+    pub number: u64,
+    pub timestamp: u64,
+    pub base_fee_gwei: f64,
+    pub gas_used_ratio: f64,
+    pub priority_fee_p10_gwei: f64,
+    pub priority_fee_p50_gwei: f64,
+    pub priority_fee_p90_gwei: f64,
+}
+
+/// Distribution of one fee metric within a bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeStats {
+    pub min: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryBucket {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub blocks: usize,
+    pub first_block: u64,
+    pub last_block: u64,
+    pub base_fee_gwei: FeeStats,
+    /// Distribution of each block's median priority fee
+    pub priority_fee_gwei: FeeStats,
+    pub avg_gas_used_ratio: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasHistory {
+    pub chain: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub bucket_seconds: u64,
+    pub blocks: usize,
+    pub buckets: Vec<HistoryBucket>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeatmapCell {
+    /// 0 = Monday ... 6 = Sunday (UTC)
+    pub day_of_week: u32,
+    pub hour: u32,
+    pub samples: usize,
+    pub median_base_fee_gwei: f64,
+    pub p90_base_fee_gwei: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasHeatmap {
+    pub chain: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Up to 168 cells, one per hour of the week with data
+    pub cells: Vec<HeatmapCell>,
+    /// The five hours of the week with the lowest median base fee
+    pub cheapest: Vec<HeatmapCell>,
+}
@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+
+/// What happened to a transaction seen in the mempool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TxLifecycle {
+    Pending {
+        first_seen: DateTime<Utc>,
+        age_secs: u64,
+        max_fee_gwei: f64,
+        priority_fee_gwei: f64,
+    },
+    Mined(MinedTransaction),
+    /// Replaced by a transaction with the same sender and nonce and a higher fee
+    Replaced { replaced_by: H256 },
+    /// Left the mempool without being seen in a block
+    Dropped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxStatus {
+    pub hash: H256,
+    #[serde(flatten)]
+    pub lifecycle: TxLifecycle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinedTransaction {
+    pub block_number: u64,
+    /// Index of the transaction in the block
+    pub position: usize,
+    pub first_seen: DateTime<Utc>,
+    /// From first seen to the block timestamp
+    pub time_to_inclusion_secs: u64,
+    /// Price per gas actually paid
+    pub effective_gas_price_gwei: f64,
+    /// Effective gas price above the block base fee
+    pub priority_fee_gwei: f64,
+}
+
+/// Time to inclusion for transactions paying a priority fee in `[min, max)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionBucket {
+    pub min_priority_fee_gwei: f64,
+    /// `None` for the open-ended top bucket
+    pub max_priority_fee_gwei: Option<f64>,
+    pub transactions: usize,
+    pub mean_secs: Option<f64>,
+    pub p50_secs: Option<u64>,
+    pub p90_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionStats {
+    pub chain: String,
+    /// Recently mined transactions the statistics cover
+    pub sample_size: usize,
+    pub buckets: Vec<InclusionBucket>,
+}
+
+/// Snapshot of the pending transactions matching a summary filter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MempoolSummary {
+    pub pending_transactions: usize,
+    pub total_value_eth: f64,
+    /// Sum of gas limits
+    pub total_gas: u64,
+    /// Pending transactions by priority fee offered
+    pub fee_histogram: Vec<FeeHistogramBucket>,
+    pub top_senders: Vec<AddressActivity>,
+    /// Called contracts (transactions with calldata), by pending transactions
+    pub top_contracts: Vec<AddressActivity>,
+    pub nonce_gaps: Vec<NonceGap>,
+    /// Uniswap V2-style router swaps per token pair
+    pub dex_swaps: Vec<PairVolume>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeHistogramBucket {
+    pub min_gwei: f64,
+    /// `None` for the open-ended top bucket
+    pub max_gwei: Option<f64>,
+    pub transactions: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressActivity {
+    pub address: Address,
+    pub transactions: usize,
+    pub value_eth: f64,
+    pub gas: u64,
+}
+
+/// A sender whose pending nonces are not contiguous; transactions after the
+/// first missing nonce cannot be mined until it arrives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NonceGap {
+    pub sender: Address,
+    pub lowest_nonce: u64,
+    pub highest_nonce: u64,
+    /// First missing nonces (at most 10)
+    pub missing: Vec<u64>,
+    pub blocked_transactions: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairVolume {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub swaps: usize,
+    /// Input amount in the token's base units: exact, or the maximum for exact-output swaps
+    pub amount_in: String,
+    /// ETH sent with swaps paying in ETH
+    pub value_eth: f64,
+}
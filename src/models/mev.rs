@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
-use ethers::types::Address;
+use ethers::types::{Address, Bytes, H256, U256};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MEVOpportunity {
@@ -14,9 +16,12 @@ pub struct MEVOpportunity {
     pub execution_details: ExecutionDetails,
     pub expires_in_blocks: u64,
     pub detected_at: DateTime<Utc>,
+    /// Transactions to capture the opportunity, when the requesting agent is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<BundleTemplate>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MEVType {
     Sandwich,
     Arbitrage,
@@ -24,6 +29,147 @@ pub enum MEVType {
     BackRun,
 }
 
+impl FromStr for MEVType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sandwich" => Ok(MEVType::Sandwich),
+            "arbitrage" => Ok(MEVType::Arbitrage),
+            "liquidation" => Ok(MEVType::Liquidation),
+            "backrun" | "back_run" => Ok(MEVType::BackRun),
+            _ => Err(format!(
+                "Unknown MEV type: {} (expected sandwich, arbitrage, liquidation or backrun)",
+                s
+            )),
+        }
+    }
+}
+
+/// An opportunity found in the mempool, live until block `expires_at_block`
+/// has been mined
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveOpportunity {
+    #[serde(flatten)]
+    pub opportunity: MEVOpportunity,
+    pub expires_at_block: u64,
+}
+
+/// Which opportunities a request or subscriber receives. Empty lists match
+/// everything.
+#[derive(Debug, Clone, Default)]
+pub struct MevFilter {
+    pub types: Vec<MEVType>,
+    pub min_net_profit_usd: f64,
+    /// Opportunities that trade one of these tokens in or out
+    pub tokens: Vec<Address>,
+    /// Opportunities that target or route through one of these pools
+    pub pools: Vec<Address>,
+}
+
+impl MevFilter {
+    pub fn matches(&self, opportunity: &MEVOpportunity) -> bool {
+        let details = &opportunity.execution_details;
+        let trades_token = self.tokens.is_empty()
+            || self.tokens.contains(&details.token_in)
+            || self.tokens.contains(&details.token_out);
+        let uses_pool = self.pools.is_empty()
+            || self.pools.contains(&details.target_pool)
+            || details.route.iter().any(|pool| self.pools.contains(pool));
+
+        (self.types.is_empty() || self.types.contains(&opportunity.opportunity_type))
+            && opportunity.net_profit_usd >= self.min_net_profit_usd
+            && trades_token
+            && uses_pool
+    }
+}
+
+/// Order of returned opportunities
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MevSort {
+    /// Most profitable after gas first
+    #[default]
+    NetProfit,
+    /// Most likely to succeed first
+    Confidence,
+    /// Soonest to expire first
+    Expiry,
+}
+
+impl FromStr for MevSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "net_profit" => Ok(MevSort::NetProfit),
+            "confidence" => Ok(MevSort::Confidence),
+            "expiry" => Ok(MevSort::Expiry),
+            _ => Err(format!("Unknown sort: {} (expected net_profit, confidence or expiry)", s)),
+        }
+    }
+}
+
+/// A validated request for opportunities: which ones, in what order, and
+/// which page of them
+#[derive(Debug, Clone)]
+pub struct MevOpportunityQuery {
+    pub filter: MevFilter,
+    pub sort: MevSort,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+impl MevOpportunityQuery {
+    /// Filters, sorts and pages `opportunities`
+    pub fn apply(&self, mut opportunities: Vec<MEVOpportunity>) -> MevOpportunityPage {
+        opportunities.retain(|opportunity| self.filter.matches(opportunity));
+        match self.sort {
+            MevSort::NetProfit => opportunities.sort_by(|a, b| b.net_profit_usd.total_cmp(&a.net_profit_usd)),
+            MevSort::Confidence => opportunities.sort_by(|a, b| {
+                b.confidence
+                    .total_cmp(&a.confidence)
+                    .then(b.net_profit_usd.total_cmp(&a.net_profit_usd))
+            }),
+            MevSort::Expiry => {
+                opportunities.sort_by_key(|opportunity| (opportunity.expires_in_blocks, opportunity.detected_at))
+            }
+        }
+
+        let total = opportunities.len();
+        let opportunities: Vec<MEVOpportunity> =
+            opportunities.into_iter().skip(self.offset).take(self.limit).collect();
+
+        MevOpportunityPage {
+            has_more: self.offset + opportunities.len() < total,
+            opportunities,
+            total,
+            offset: self.offset,
+            limit: self.limit,
+            sort: self.sort,
+        }
+    }
+}
+
+/// One page of matching opportunities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MevOpportunityPage {
+    pub opportunities: Vec<MEVOpportunity>,
+    /// Matching opportunities across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub has_more: bool,
+    pub sort: MevSort,
+}
+
+/// A metered message on the MEV opportunity stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MevStreamMessage {
+    pub opportunity: LiveOpportunity,
+    pub credits_remaining: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionDetails {
     pub target_pool: Address,
@@ -31,5 +177,271 @@ pub struct ExecutionDetails {
     pub token_out: Address,
     pub amount_in: String,
     pub expected_profit: String,
+    /// Pools traded through, in order
+    pub route: Vec<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liquidation: Option<LiquidationDetails>,
+}
+
+/// The position behind a liquidation; `token_in` is the debt asset repaid
+/// and `token_out` the collateral seized
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidationDetails {
+    pub protocol: LendingProtocol,
+    pub borrower: Address,
+    pub health_factor: f64,
+    /// Collateral received above the repaid value, as a fraction
+    pub liquidation_bonus: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LendingProtocol {
+    AaveV3,
+    CompoundV3,
+}
+
+impl fmt::Display for LendingProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LendingProtocol::AaveV3 => "aave_v3",
+            LendingProtocol::CompoundV3 => "compound_v3",
+        })
+    }
+}
+
+impl FromStr for LendingProtocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "aave_v3" => Ok(LendingProtocol::AaveV3),
+            "compound_v3" => Ok(LendingProtocol::CompoundV3),
+            _ => Err(format!("Unknown lending protocol: {} (expected aave_v3 or compound_v3)", s)),
+        }
+    }
+}
+
+/// An unsigned transaction an agent is about to send
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProtectRequest {
+    pub from: Address,
+    pub to: Address,
+    pub data: Bytes,
+    #[serde(default)]
+    pub value: U256,
+    pub gas: Option<U256>,
+    pub gas_price: Option<U256>,
+    pub max_fee_per_gas: Option<U256>,
+    pub max_priority_fee_per_gas: Option<U256>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MevProtection {
+    /// True if any swap should go through a private relay
+    pub use_private_relay: bool,
+    pub swaps: Vec<SandwichExposure>,
 }
 
+/// How much a sandwich could take from one swap, and how to prevent it.
+/// Token amounts are in the token's smallest unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandwichExposure {
+    pub token_in: Address,
+    pub token_out: Address,
+    /// The V2 pair the swap trades on, when it was assessed
+    pub pool: Option<Address>,
+    pub amount_in: String,
+    pub amount_out_min: String,
+    /// Output if nobody trades ahead of the swap
+    pub expected_amount_out: Option<String>,
+    /// Tolerance implied by `amount_out_min`
+    pub slippage_bps: Option<f64>,
+    /// Most a sandwich can take before its gas
+    pub max_extractable_eth: Option<f64>,
+    pub max_extractable_usd: Option<f64>,
+    /// A sandwich is profitable after the searcher's gas
+    pub sandwichable: bool,
+    /// Largest tolerance that leaves no profitable sandwich
+    pub recommended_slippage_bps: Option<f64>,
+    pub recommended_amount_out_min: Option<String>,
+    pub use_private_relay: bool,
+    /// The sandwich was replayed with REVM rather than computed from reserves
+    pub simulated: bool,
+    pub reason: String,
+}
+
+/// A bundle in `eth_sendBundle` order. The agent signs its own transactions
+/// and submits the others as they are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTemplate {
+    /// Hex block number the bundle targets
+    pub block_number: String,
+    pub txs: Vec<BundleTransaction>,
+    pub reverting_tx_hashes: Vec<H256>,
+    pub suggested_priority_fee_gwei: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BundleTransaction {
+    /// To be signed by the agent
+    Unsigned(UnsignedTransaction),
+    /// Someone else's pending transaction, already signed
+    Signed { hash: H256, raw: Bytes },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsignedTransaction {
+    pub from: Address,
+    pub to: Address,
+    pub data: Bytes,
+    pub value: U256,
+    pub gas: U256,
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+/// Signed raw transactions, in order
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulateBundleRequest {
+    pub txs: Vec<Bytes>,
+}
+
+/// A bundle executed as the next block on top of `state_block`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleSimulation {
+    pub state_block: u64,
+    pub block_number: u64,
+    /// Every transaction was valid and none reverted
+    pub success: bool,
+    pub total_gas_used: u64,
+    /// Fees and transfers to the block builder, in wei
+    pub coinbase_diff: String,
+    /// `coinbase_diff` per unit of gas, which builders rank bundles by
+    pub bundle_gas_price_gwei: f64,
+    pub results: Vec<BundleTxResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleTxResult {
+    pub tx_hash: H256,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub success: bool,
+    pub gas_used: u64,
+    pub gas_price_gwei: f64,
+    pub coinbase_diff: String,
+    /// Revert reason, or why the transaction is invalid in the block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// MEV extracted in one mined block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MevBlockReport {
+    pub block_number: u64,
+    pub timestamp: u64,
+    /// Fee recipient, which is the builder for MEV-Boost blocks
+    pub builder: Address,
+    /// Priority fees paid to the builder
+    pub priority_fees_eth: f64,
+    /// Paid by the builder to the proposer in the block's last transaction
+    pub proposer_payment_eth: f64,
+    /// Earned by the builder before paying the proposer: its balance change
+    /// plus that payment, so direct transfers count. Just the priority fees
+    /// when the balances cannot be read.
+    pub builder_revenue_eth: f64,
+    pub sandwiches: Vec<MinedSandwich>,
+    pub arbitrages: Vec<MinedArbitrage>,
+    pub liquidations: Vec<MinedLiquidation>,
+    pub searchers: Vec<SearcherProfit>,
+}
+
+/// Frontrun and backrun by one searcher around swaps in the same pool.
+/// Amounts are in the smallest unit of `token`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinedSandwich {
+    pub pool: Address,
+    pub searcher: Address,
+    pub frontrun: H256,
+    pub victims: Vec<H256>,
+    pub backrun: H256,
+    /// Spent by the frontrun and returned by the backrun
+    pub token: Address,
+    pub profit: String,
+    /// Set when `token` is WETH
+    pub profit_eth: Option<f64>,
+    pub gas_cost_eth: f64,
+}
+
+/// Swaps in one transaction that start and end in the same token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinedArbitrage {
+    pub tx_hash: H256,
+    pub searcher: Address,
+    /// Pools in trade order
+    pub pools: Vec<Address>,
+    pub token: Address,
+    pub profit: String,
+    pub profit_eth: Option<f64>,
+    pub gas_cost_eth: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinedLiquidation {
+    pub tx_hash: H256,
+    pub protocol: LendingProtocol,
+    pub market: Address,
+    pub liquidator: Address,
+    pub borrower: Address,
+    /// Not logged by Comet absorptions, which take every collateral asset
+    pub collateral_asset: Option<Address>,
+    pub debt_asset: Option<Address>,
+    /// In debt token units; for Comet, base token paid out to the borrower
+    pub debt_repaid: String,
+    pub collateral_seized: Option<String>,
+    pub gas_cost_eth: f64,
+}
+
+/// What one searcher (transaction sender) extracted. Only WETH-denominated
+/// profit is counted; gas covers every sandwich and arbitrage transaction
+/// sent. Liquidations are counted but left out of profit, since the seized
+/// collateral is not priced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearcherProfit {
+    pub searcher: Address,
+    pub sandwiches: u64,
+    pub arbitrages: u64,
+    pub liquidations: u64,
+    pub gross_profit_eth: f64,
+    pub gas_cost_eth: f64,
+    pub net_profit_eth: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuilderProfit {
+    pub builder: Address,
+    pub blocks: u64,
+    pub priority_fees_eth: f64,
+    /// Priority fees and direct transfers received
+    pub revenue_eth: f64,
+    pub proposer_payments_eth: f64,
+    /// Revenue kept after paying proposers
+    pub net_profit_eth: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MevRangeReport {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub sandwiches: u64,
+    pub arbitrages: u64,
+    pub liquidations: u64,
+    /// Highest net profit first
+    pub searchers: Vec<SearcherProfit>,
+    pub builders: Vec<BuilderProfit>,
+    pub blocks: Vec<MevBlockReport>,
+}
@@ -1,10 +1,24 @@
+pub mod agent;
+pub mod chain;
+pub mod dex;
 pub mod gas;
+pub mod history;
+pub mod mempool;
 pub mod response;
 pub mod payment;
 pub mod mev;
+pub mod price;
+pub mod watchlist;
 
+pub use agent::*;
+pub use chain::*;
+pub use dex::*;
 pub use gas::*;
+pub use history::*;
+pub use mempool::*;
 pub use response::*;
 pub use payment::*;
 pub use mev::*;
+pub use price::*;
+pub use watchlist::*;
 
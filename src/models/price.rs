@@ -0,0 +1,41 @@
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+
+/// ERC-20 details needed to scale raw amounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenMetadata {
+    pub address: Address,
+    /// `None` when the token does not implement `symbol()`
+    pub symbol: Option<String>,
+    pub decimals: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    Chainlink,
+    /// Time-weighted average tick of a Uniswap V3 pool
+    UniswapV3Twap,
+    /// Current tick of a V3 pool whose oracle does not reach back far enough
+    UniswapV3Spot,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPrice {
+    pub address: Address,
+    pub symbol: Option<String>,
+    pub decimals: u8,
+    pub price_usd: f64,
+    pub source: PriceSource,
+    /// Chainlink feed or Uniswap V3 pool the price was read from
+    pub oracle: Address,
+    /// Token the pool prices against (WETH or USDC); `None` for feeds
+    pub quote_token: Option<Address>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenPrices {
+    pub prices: Vec<TokenPrice>,
+    /// Requested tokens with no feed and no V3 pool against WETH or USDC
+    pub unavailable: Vec<Address>,
+}
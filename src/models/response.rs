@@ -17,10 +17,61 @@ pub struct HealthStatus {
     pub version: String,
     pub redis: bool,
     pub ethereum_rpc: bool,
+    pub rpc_providers: Vec<ProviderMetrics>,
+    pub mempool: MempoolHealth,
     pub uptime_seconds: u64,
     pub timestamp: DateTime<Utc>,
 }
 
+/// Health of one RPC provider in a pool
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProviderMetrics {
+    pub pool: String,
+    pub url: String,
+    /// False while the circuit breaker has the provider out of rotation
+    pub available: bool,
+    pub latency_ms: Option<f64>,
+    pub requests: u64,
+    pub errors: u64,
+    pub consecutive_failures: u32,
+}
+
+/// State of the mempool WebSocket subscription
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MempoolConnection {
+    /// `ETH_WS_URL` is not set
+    Disabled,
+    Connecting,
+    Connected,
+    /// The stream dropped; waiting out the backoff before reconnecting
+    Reconnecting,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MempoolHealth {
+    pub state: MempoolConnection,
+    pub connected_since: Option<DateTime<Utc>>,
+    pub reconnects: u64,
+    pub last_error: Option<String>,
+    pub pending_transactions: usize,
+    /// Pending transactions replaced by a higher-fee one with the same nonce
+    pub replacements: u64,
+}
+
+impl MempoolHealth {
+    pub fn disabled() -> Self {
+        Self {
+            state: MempoolConnection::Disabled,
+            connected_since: None,
+            reconnects: 0,
+            last_error: None,
+            pending_transactions: 0,
+            replacements: 0,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Stats {
     pub total_payments: u64,
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Addresses an agent wants to hear about when they appear in the mempool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watchlist {
+    pub id: Uuid,
+    pub agent: Address,
+    pub name: Option<String>,
+    pub addresses: Vec<Address>,
+    /// Matches are also POSTed here, signed, when set
+    pub webhook_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateWatchlistRequest {
+    pub name: Option<String>,
+    pub addresses: Vec<Address>,
+    pub webhook_url: Option<String>,
+}
+
+/// Where a watched address appeared in a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchRole {
+    Sender,
+    Recipient,
+    /// As an argument in the calldata (e.g. a transfer recipient or swap path)
+    Calldata,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedAddress {
+    pub address: Address,
+    pub role: WatchRole,
+}
+
+/// A pending transaction that touched a watchlist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchlistMatch {
+    pub watchlist_id: Uuid,
+    pub agent: Address,
+    pub tx_hash: H256,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub value_eth: f64,
+    pub matches: Vec<WatchedAddress>,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// A metered message on the watchlist stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchlistStreamMessage {
+    #[serde(flatten)]
+    pub watch_match: WatchlistMatch,
+    pub credits_remaining: u64,
+}
+
+/// Body POSTed to a watchlist's webhook. The nonce and timestamp are signed
+/// along with the match so receivers can reject replayed deliveries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub nonce: Uuid,
+    /// Unix seconds when the delivery was signed
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub watch_match: WatchlistMatch,
+}
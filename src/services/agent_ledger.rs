@@ -0,0 +1,188 @@
+use crate::models::AgentBehavior;
+use crate::services::CacheService;
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256};
+use moka::future::Cache;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Agent behavior is kept for 90 days after the agent's last event
+const BEHAVIOR_TTL_SECS: u64 = 90 * 86400;
+/// Redeemed payments are remembered as long as payment records
+const REDEEMED_PAYMENT_TTL_SECS: u64 = 30 * 86400;
+/// Without Redis, the least recently used agents and payments beyond these are
+/// dropped
+const MEMORY_AGENTS: u64 = 10_000;
+const MEMORY_PAYMENTS: u64 = 100_000;
+
+/// Something an agent did that counts towards its local reputation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AgentEvent {
+    PaymentVerified { amount_usd: f64 },
+    /// A proof of a transaction the agent sent that does not pay for the request
+    PaymentFailed,
+    RateLimited,
+}
+
+/// Per-agent payment and usage history, and the payment transactions already
+/// redeemed. Both live in Redis, updated atomically field by field so every
+/// process sees the same history; without Redis (or when it fails) they are
+/// kept in bounded memory caches.
+pub struct AgentLedger {
+    redis: Option<ConnectionManager>,
+    agents: Cache<Address, AgentBehavior>,
+    redeemed: Cache<H256, ()>,
+}
+
+impl AgentLedger {
+    pub fn new(cache: Arc<CacheService>) -> Self {
+        Self {
+            redis: cache.redis(),
+            agents: Cache::builder()
+                .max_capacity(MEMORY_AGENTS)
+                .time_to_idle(Duration::from_secs(BEHAVIOR_TTL_SECS))
+                .build(),
+            redeemed: Cache::builder()
+                .max_capacity(MEMORY_PAYMENTS)
+                .time_to_live(Duration::from_secs(REDEEMED_PAYMENT_TTL_SECS))
+                .build(),
+        }
+    }
+
+    /// Everything recorded for `agent`; the default for agents never seen
+    pub async fn behavior(&self, agent: Address) -> AgentBehavior {
+        if let Some(mut redis) = self.redis.clone() {
+            match redis.hgetall::<_, HashMap<String, String>>(behavior_key(agent)).await {
+                Ok(fields) => return behavior_from_fields(&fields),
+                Err(e) => tracing::warn!("Redis error reading agent {:?}: {}", agent, e),
+            }
+        }
+        self.agents.get(&agent).await.unwrap_or_default()
+    }
+
+    pub async fn record(&self, agent: Address, event: AgentEvent) {
+        tracing::debug!("Agent {:?}: {:?}", agent, event);
+        let now = Utc::now();
+
+        if let Some(mut redis) = self.redis.clone() {
+            match record_in_redis(&mut redis, agent, event, now).await {
+                Ok(()) => return,
+                Err(e) => tracing::warn!("Redis error recording agent {:?}: {}", agent, e),
+            }
+        }
+        self.agents
+            .entry(agent)
+            .and_upsert_with(|entry| {
+                let mut behavior = entry.map(|entry| entry.into_value()).unwrap_or_default();
+                apply(&mut behavior, event, now);
+                async move { behavior }
+            })
+            .await;
+    }
+
+    /// Marks a verified payment transaction as used. False when it already was,
+    /// here or (through Redis) by another process.
+    pub async fn redeem_payment(&self, tx_hash: H256) -> bool {
+        if let Some(mut redis) = self.redis.clone() {
+            let claimed = redis::cmd("SET")
+                .arg(redeemed_key(tx_hash))
+                .arg(1)
+                .arg("NX")
+                .arg("EX")
+                .arg(REDEEMED_PAYMENT_TTL_SECS)
+                .query_async::<_, Option<String>>(&mut redis)
+                .await;
+            match claimed {
+                Ok(reply) => return reply.is_some(),
+                Err(e) => tracing::warn!("Redis error redeeming payment {:?}: {}", tx_hash, e),
+            }
+        }
+        self.redeemed.entry(tx_hash).or_insert(()).await.is_fresh()
+    }
+
+    /// Makes a redeemed payment usable again, for requests that were not served
+    pub async fn release_payment(&self, tx_hash: H256) {
+        if let Some(mut redis) = self.redis.clone() {
+            if let Err(e) = redis.del::<_, ()>(redeemed_key(tx_hash)).await {
+                tracing::warn!("Redis error releasing payment {:?}: {}", tx_hash, e);
+            }
+        }
+        self.redeemed.invalidate(&tx_hash).await;
+    }
+}
+
+async fn record_in_redis(
+    redis: &mut ConnectionManager,
+    agent: Address,
+    event: AgentEvent,
+    now: DateTime<Utc>,
+) -> redis::RedisResult<()> {
+    let key = behavior_key(agent);
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    match event {
+        AgentEvent::PaymentVerified { amount_usd } => {
+            pipe.hincr(&key, "verified_payments", 1).ignore();
+            pipe.hincr(&key, "paid_usd", amount_usd).ignore();
+        }
+        AgentEvent::PaymentFailed => {
+            pipe.hincr(&key, "payment_failures", 1).ignore();
+        }
+        AgentEvent::RateLimited => {
+            pipe.hincr(&key, "rate_limit_violations", 1).ignore();
+        }
+    }
+    pipe.hset_nx(&key, "first_seen", now.to_rfc3339())
+        .ignore()
+        .hset(&key, "last_seen", now.to_rfc3339())
+        .ignore()
+        .expire(&key, BEHAVIOR_TTL_SECS as i64)
+        .ignore();
+
+    pipe.query_async(redis).await
+}
+
+fn apply(behavior: &mut AgentBehavior, event: AgentEvent, now: DateTime<Utc>) {
+    behavior.first_seen.get_or_insert(now);
+    behavior.last_seen = Some(now);
+
+    match event {
+        AgentEvent::PaymentVerified { amount_usd } => {
+            behavior.verified_payments += 1;
+            behavior.paid_usd += amount_usd;
+        }
+        AgentEvent::PaymentFailed => behavior.payment_failures += 1,
+        AgentEvent::RateLimited => behavior.rate_limit_violations += 1,
+    }
+}
+
+/// Missing or unreadable fields read as their default
+fn behavior_from_fields(fields: &HashMap<String, String>) -> AgentBehavior {
+    let count = |field: &str| fields.get(field).and_then(|value| value.parse().ok()).unwrap_or_default();
+    let time = |field: &str| {
+        fields
+            .get(field)
+            .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+            .map(|time| time.with_timezone(&Utc))
+    };
+
+    AgentBehavior {
+        verified_payments: count("verified_payments"),
+        paid_usd: fields.get("paid_usd").and_then(|value| value.parse().ok()).unwrap_or_default(),
+        payment_failures: count("payment_failures"),
+        rate_limit_violations: count("rate_limit_violations"),
+        first_seen: time("first_seen"),
+        last_seen: time("last_seen"),
+    }
+}
+
+fn behavior_key(agent: Address) -> String {
+    format!("reputation:agent:{:?}", agent)
+}
+
+fn redeemed_key(tx_hash: H256) -> String {
+    format!("x402:redeemed:{:?}", tx_hash)
+}
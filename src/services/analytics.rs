@@ -48,6 +48,12 @@ impl Analytics {
         );
     }
     
+    /// Counts a message delivered on a metered stream
+    pub async fn record_stream_message(&self, endpoint: &str) {
+        let date = Utc::now().format("%Y-%m-%d").to_string();
+        let _ = self.cache.increment(&format!("analytics:stream_messages:{}:{}", endpoint, date), 1).await;
+    }
+    
     pub async fn get_stats(&self) -> Stats {
         let date = Utc::now().format("%Y-%m-%d").to_string();
         
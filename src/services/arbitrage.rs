@@ -0,0 +1,464 @@
+use crate::contracts::uniswap_v3::{
+    FeeCall, FeeReturn, LiquidityCall, LiquidityReturn, Slot0Call, Slot0Return, SwapFilter,
+};
+use crate::contracts::{
+    GetReservesCall, GetReservesReturn, SyncFilter, Token0Call, Token0Return, Token1Call, Token1Return,
+};
+use crate::models::DexKind;
+use crate::services::{
+    chain_client::{call_contract, ChainClient},
+    decode_swaps, BlockFollower, DexRegistry, SwapIntent, SwapKind,
+};
+use anyhow::{Context, Result};
+use ethers::contract::{parse_log, EthEvent};
+use ethers::types::{Address, Filter, Log, Transaction, U256};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// USDC / WETH on Uniswap V2, SushiSwap and the 0.05% and 0.3% V3 pools
+pub const DEFAULT_ARBITRAGE_POOLS: &str = "uniswap_v2:0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc,\
+     sushiswap:0x397FF1542f962076d0BFE58eA045FfA2d347ACa0,\
+     uniswap_v3:0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640,\
+     uniswap_v3:0x8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8";
+
+/// Fee of V2-style pairs whose DEX registers no fee tier, in hundredths of a
+/// basis point like V3 fee tiers
+const V2_FEE_TIER: u32 = 3_000;
+/// Longest cycle searched, in pools
+const MAX_CYCLE_POOLS: usize = 3;
+/// Blocks of logs fetched at once; a longer gap reloads every pool instead
+const MAX_LOG_RANGE: u64 = 16;
+const SEARCH_STEPS: usize = 100;
+
+/// Gas of a backrun transaction: the base cost plus one swap per pool
+const BASE_GAS: u64 = 21_000;
+const V2_HOP_GAS: u64 = 60_000;
+const V3_HOP_GAS: u64 = 110_000;
+
+/// A pool watched by the scanner, written `venue:address`. The venue names a
+/// deployment in the DEX registry, which decides how the pool is read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolSpec {
+    pub venue: String,
+    pub address: Address,
+}
+
+impl FromStr for PoolSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (venue, address) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Expected venue:address, got '{}'", s))?;
+        Ok(Self {
+            venue: venue.trim().to_ascii_lowercase(),
+            address: address
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid pool address '{}'", address))?,
+        })
+    }
+}
+
+/// A profitable cycle starting and ending in the wrapped native token, right
+/// after the victim
+#[derive(Debug, Clone)]
+pub struct ArbitrageCycle {
+    /// Pools in trade order
+    pub pools: Vec<Address>,
+    /// Tokens along the cycle; the first and last are the wrapped native token
+    pub tokens: Vec<Address>,
+    pub amount_in_wei: f64,
+    pub amount_out_wei: f64,
+    pub gas: u64,
+    /// V3 pools are priced from in-range liquidity only, so large trades
+    /// that cross ticks are less certain
+    pub uses_v3: bool,
+}
+
+impl ArbitrageCycle {
+    /// Before gas
+    pub fn profit_wei(&self) -> f64 {
+        self.amount_out_wei - self.amount_in_wei
+    }
+}
+
+/// Reserves of one pool. V3 pools hold virtual reserves derived from the
+/// current price and in-range liquidity (L / √P and L · √P).
+#[derive(Debug, Clone)]
+struct PoolState {
+    /// Registered DEX the pool belongs to
+    dex: String,
+    kind: DexKind,
+    token0: Address,
+    token1: Address,
+    fee_tier: u32,
+    reserve0: f64,
+    reserve1: f64,
+}
+
+impl PoolState {
+    fn fee(&self) -> f64 {
+        self.fee_tier as f64 / 1e6
+    }
+
+    fn other(&self, token: Address) -> Option<Address> {
+        if token == self.token0 {
+            Some(self.token1)
+        } else if token == self.token1 {
+            Some(self.token0)
+        } else {
+            None
+        }
+    }
+
+    fn reserves(&self, token_in: Address) -> (f64, f64) {
+        if token_in == self.token0 {
+            (self.reserve0, self.reserve1)
+        } else {
+            (self.reserve1, self.reserve0)
+        }
+    }
+
+    fn amount_out(&self, token_in: Address, amount_in: f64) -> f64 {
+        let (reserve_in, reserve_out) = self.reserves(token_in);
+        if reserve_in <= 0.0 || reserve_out <= 0.0 || amount_in <= 0.0 {
+            return 0.0;
+        }
+        let amount_in = amount_in * (1.0 - self.fee());
+        amount_in * reserve_out / (reserve_in + amount_in)
+    }
+
+    /// Input needed for `amount_out`; infinite if the pool cannot supply it
+    fn amount_in_for(&self, token_out: Address, amount_out: f64) -> f64 {
+        let (reserve_out, reserve_in) = self.reserves(token_out);
+        if amount_out >= reserve_out || reserve_in <= 0.0 {
+            return f64::INFINITY;
+        }
+        reserve_in * amount_out / ((reserve_out - amount_out) * (1.0 - self.fee()))
+    }
+
+    /// Applies a swap to the reserves. V2 fees stay in the pool; V3 fees are
+    /// kept outside the active liquidity.
+    fn swap(&mut self, token_in: Address, amount_in: f64) {
+        let amount_out = self.amount_out(token_in, amount_in);
+        let added = match self.kind {
+            DexKind::UniswapV3 => amount_in * (1.0 - self.fee()),
+            DexKind::UniswapV2 => amount_in,
+        };
+        if token_in == self.token0 {
+            self.reserve0 += added;
+            self.reserve1 -= amount_out;
+        } else {
+            self.reserve1 += added;
+            self.reserve0 -= amount_out;
+        }
+    }
+
+    fn set_v3_state(&mut self, sqrt_price_x96: U256, liquidity: u128) {
+        let sqrt_price = to_f64(sqrt_price_x96) / 2f64.powi(96);
+        let liquidity = liquidity as f64;
+        if sqrt_price > 0.0 {
+            self.reserve0 = liquidity / sqrt_price;
+            self.reserve1 = liquidity * sqrt_price;
+        }
+    }
+
+    fn hop_gas(&self) -> u64 {
+        match self.kind {
+            DexKind::UniswapV3 => V3_HOP_GAS,
+            DexKind::UniswapV2 => V2_HOP_GAS,
+        }
+    }
+}
+
+/// Keeps the reserves of a configured set of pools current from their
+/// `Sync` / `Swap` logs, and looks for cycles from the chain's wrapped native
+/// token that a pending swap makes profitable to backrun
+pub struct ArbitrageScanner {
+    client: Arc<dyn ChainClient>,
+    specs: Vec<PoolSpec>,
+    /// DEXes on the scanned chain; a venue is the name of one
+    dexes: Arc<DexRegistry>,
+    pools: RwLock<HashMap<Address, PoolState>>,
+}
+
+impl ArbitrageScanner {
+    pub fn new(client: Arc<dyn ChainClient>, specs: Vec<PoolSpec>, dexes: Arc<DexRegistry>) -> Self {
+        Self {
+            client,
+            specs,
+            dexes,
+            pools: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Pools with known reserves
+    pub fn pool_count(&self) -> usize {
+        self.pools.read().unwrap().len()
+    }
+
+    /// Reads tokens, fees and reserves of every configured pool. Pools that
+    /// fail to load are left out until the next reload.
+    pub async fn load(&self) {
+        let mut loaded = HashMap::new();
+        for spec in &self.specs {
+            match self.fetch_pool(spec).await {
+                Ok(pool) => {
+                    loaded.insert(spec.address, pool);
+                }
+                Err(e) => tracing::warn!("Could not load {} pool {:?}: {}", spec.venue, spec.address, e),
+            }
+        }
+
+        tracing::info!("Arbitrage scanner tracking {}/{} pools", loaded.len(), self.specs.len());
+        *self.pools.write().unwrap() = loaded;
+    }
+
+    /// Applies the logs of every block the follower publishes
+    pub async fn run(&self, follower: Arc<BlockFollower>) {
+        let mut updates = follower.subscribe();
+        let mut last_block: Option<u64> = None;
+
+        while updates.changed().await.is_ok() {
+            let Some(head) = updates.borrow_and_update().as_ref().map(|update| update.block_number) else {
+                continue;
+            };
+
+            match last_block {
+                Some(last) if head <= last => continue,
+                Some(last) if head - last > MAX_LOG_RANGE => self.load().await,
+                last => {
+                    let from = last.map_or(head, |last| last + 1);
+                    if let Err(e) = self.refresh(from, head).await {
+                        tracing::warn!("Pool refresh for blocks {}-{} failed: {}", from, head, e);
+                        self.load().await;
+                    }
+                }
+            }
+            last_block = Some(head);
+        }
+    }
+
+    /// Applies `Sync` and `Swap` logs emitted by the pools in `from..=to`
+    pub async fn refresh(&self, from: u64, to: u64) -> Result<()> {
+        let addresses: Vec<Address> = self.pools.read().unwrap().keys().copied().collect();
+        if addresses.is_empty() {
+            return Ok(());
+        }
+
+        let filter = Filter::new().address(addresses).from_block(from).to_block(to);
+        let logs = self.client.logs(&filter).await?;
+
+        let mut pools = self.pools.write().unwrap();
+        for log in logs {
+            if let Some(pool) = pools.get_mut(&log.address) {
+                apply_log(pool, log);
+            }
+        }
+        Ok(())
+    }
+
+    /// Profitable cycles through the pools `victim` trades on, best first
+    pub fn backrun_cycles(&self, victim: &Transaction) -> Vec<ArbitrageCycle> {
+        let swaps = decode_swaps(victim, &self.dexes);
+        if swaps.is_empty() {
+            return Vec::new();
+        }
+
+        let mut pools = self.pools.read().unwrap().clone();
+        let mut moved = HashSet::new();
+        for swap in &swaps {
+            apply_swap(&mut pools, swap, &mut moved);
+        }
+        if moved.is_empty() {
+            return Vec::new();
+        }
+
+        let weth = self.dexes.wrapped_native();
+        let mut cycles = Vec::new();
+        extend_cycles(&pools, weth, weth, &moved, &mut Vec::new(), &mut cycles);
+
+        let mut profitable: Vec<ArbitrageCycle> = cycles
+            .iter()
+            .filter_map(|cycle| size_cycle(&pools, cycle))
+            .collect();
+        profitable.sort_by(|a, b| b.profit_wei().total_cmp(&a.profit_wei()));
+        profitable
+    }
+
+    async fn fetch_pool(&self, spec: &PoolSpec) -> Result<PoolState> {
+        let dex = self
+            .dexes
+            .get(&spec.venue)
+            .with_context(|| format!("{} is not in the DEX registry", spec.venue))?;
+        let client = &*self.client;
+        let mut pool = PoolState {
+            dex: dex.name.clone(),
+            kind: dex.kind,
+            token0: call_contract::<_, _, Token0Return>(client, spec.address, Token0Call).await?.0,
+            token1: call_contract::<_, _, Token1Return>(client, spec.address, Token1Call).await?.0,
+            fee_tier: dex.fee_tiers.first().copied().unwrap_or(V2_FEE_TIER),
+            reserve0: 0.0,
+            reserve1: 0.0,
+        };
+
+        match dex.kind {
+            DexKind::UniswapV3 => {
+                pool.fee_tier = call_contract::<_, _, FeeReturn>(client, spec.address, FeeCall).await?.0;
+                let slot0 = call_contract::<_, _, Slot0Return>(client, spec.address, Slot0Call).await?;
+                let liquidity = call_contract::<_, _, LiquidityReturn>(client, spec.address, LiquidityCall).await?.0;
+                pool.set_v3_state(slot0.sqrt_price_x96, liquidity);
+            }
+            DexKind::UniswapV2 => {
+                let reserves = call_contract::<_, _, GetReservesReturn>(client, spec.address, GetReservesCall).await?;
+                pool.reserve0 = reserves.reserve_0 as f64;
+                pool.reserve1 = reserves.reserve_1 as f64;
+            }
+        }
+
+        Ok(pool)
+    }
+}
+
+fn apply_log(pool: &mut PoolState, log: Log) {
+    let topic = log.topics.first().copied();
+    match pool.kind {
+        DexKind::UniswapV3 if topic == Some(SwapFilter::signature()) => {
+            if let Ok(swap) = parse_log::<SwapFilter>(log) {
+                pool.set_v3_state(swap.sqrt_price_x96, swap.liquidity);
+            }
+        }
+        DexKind::UniswapV2 if topic == Some(SyncFilter::signature()) => {
+            if let Ok(sync) = parse_log::<SyncFilter>(log) {
+                pool.reserve0 = sync.reserve_0 as f64;
+                pool.reserve1 = sync.reserve_1 as f64;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Moves the tracked pools of the swap's DEX that it trades through; hops on
+/// untracked pools end the path
+fn apply_swap(pools: &mut HashMap<Address, PoolState>, swap: &SwapIntent, moved: &mut HashSet<Address>) {
+    let find = |pools: &HashMap<Address, PoolState>, token_in: Address, token_out: Address, fee: Option<u32>| {
+        pools
+            .iter()
+            .find(|(_, pool)| {
+                pool.dex == swap.dex
+                    && pool.other(token_in) == Some(token_out)
+                    && fee.is_none_or(|fee| fee == pool.fee_tier)
+            })
+            .map(|(address, _)| *address)
+    };
+
+    // (pool, token in, amount in) in trade order
+    let mut legs = Vec::new();
+    match swap.kind {
+        SwapKind::ExactIn => {
+            let mut amount = to_f64(swap.amount_in);
+            for hop in &swap.hops {
+                let Some(address) = find(pools, hop.token_in, hop.token_out, hop.fee) else { break };
+                legs.push((address, hop.token_in, amount));
+                amount = pools[&address].amount_out(hop.token_in, amount);
+            }
+        }
+        SwapKind::ExactOut => {
+            let mut amount = to_f64(swap.amount_out);
+            for hop in swap.hops.iter().rev() {
+                let Some(address) = find(pools, hop.token_in, hop.token_out, hop.fee) else { break };
+                amount = pools[&address].amount_in_for(hop.token_out, amount);
+                if !amount.is_finite() {
+                    break;
+                }
+                legs.push((address, hop.token_in, amount));
+            }
+            legs.reverse();
+        }
+    }
+
+    for (address, token_in, amount) in legs {
+        if amount > 0.0 {
+            pools.get_mut(&address).unwrap().swap(token_in, amount);
+            moved.insert(address);
+        }
+    }
+}
+
+/// Depth-first search for cycles back to `start` of up to `MAX_CYCLE_POOLS`
+/// distinct pools that trade through at least one moved pool. Each cycle is a
+/// list of (pool, token in).
+fn extend_cycles(
+    pools: &HashMap<Address, PoolState>,
+    start: Address,
+    token: Address,
+    moved: &HashSet<Address>,
+    path: &mut Vec<(Address, Address)>,
+    cycles: &mut Vec<Vec<(Address, Address)>>,
+) {
+    for (address, pool) in pools {
+        if path.iter().any(|(visited, _)| visited == address) {
+            continue;
+        }
+        let Some(next) = pool.other(token) else { continue };
+
+        path.push((*address, token));
+        if next == start {
+            if path.len() >= 2 && path.iter().any(|(pool, _)| moved.contains(pool)) {
+                cycles.push(path.clone());
+            }
+        } else if path.len() < MAX_CYCLE_POOLS {
+            extend_cycles(pools, start, next, moved, path, cycles);
+        }
+        path.pop();
+    }
+}
+
+fn cycle_output(pools: &HashMap<Address, PoolState>, cycle: &[(Address, Address)], amount_in: f64) -> f64 {
+    cycle
+        .iter()
+        .fold(amount_in, |amount, (pool, token_in)| pools[pool].amount_out(*token_in, amount))
+}
+
+/// Golden-section search for the most profitable input. Output is concave in
+/// the input along a chain of constant-product pools, so profit is unimodal.
+fn size_cycle(pools: &HashMap<Address, PoolState>, cycle: &[(Address, Address)]) -> Option<ArbitrageCycle> {
+    let (first_pool, weth) = cycle[0];
+    let profit = |amount: f64| cycle_output(pools, cycle, amount) - amount;
+
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (0.0, pools[&first_pool].reserves(weth).0);
+    for _ in 0..SEARCH_STEPS {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+        if profit(left) < profit(right) {
+            low = left;
+        } else {
+            high = right;
+        }
+    }
+
+    let amount_in = (low + high) / 2.0;
+    let amount_out = cycle_output(pools, cycle, amount_in);
+    if amount_out <= amount_in {
+        return None;
+    }
+
+    let mut tokens = vec![weth];
+    tokens.extend(cycle.iter().map(|(pool, token_in)| pools[pool].other(*token_in).unwrap()));
+
+    Some(ArbitrageCycle {
+        pools: cycle.iter().map(|(pool, _)| *pool).collect(),
+        tokens,
+        amount_in_wei: amount_in,
+        amount_out_wei: amount_out,
+        gas: BASE_GAS + cycle.iter().map(|(pool, _)| pools[pool].hop_gas()).sum::<u64>(),
+        uses_v3: cycle.iter().any(|(pool, _)| pools[pool].kind == DexKind::UniswapV3),
+    })
+}
+
+fn to_f64(value: U256) -> f64 {
+    value.to_string().parse().unwrap_or_default()
+}
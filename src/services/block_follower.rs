@@ -0,0 +1,195 @@
+use crate::{
+    models::{GasModel, GasUpdate},
+    services::{
+        ethereum::HISTORY_BLOCKS,
+        gas_history::{self, PRIORITY_PERCENTILES},
+        gas_predictor::BlockSample,
+        EthereumService, GasHistoryStore,
+    },
+};
+use std::collections::HashMap;
+use anyhow::Result;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Mutex};
+
+/// Blocks loaded from `eth_feeHistory` when a follower starts (the usual node limit)
+const BACKFILL_BLOCKS: u64 = 1024;
+
+/// Follows the head of one chain and publishes fresh predictions for every gas
+/// model on each new block. Streaming clients subscribe here instead of polling,
+/// and each update also refreshes the REST prediction cache.
+pub struct BlockFollower {
+    chain: Arc<EthereumService>,
+    window: Mutex<Vec<BlockSample>>,
+    history: Arc<GasHistoryStore>,
+    updates: watch::Sender<Option<Arc<GasUpdate>>>,
+}
+
+impl BlockFollower {
+    pub fn new(chain: Arc<EthereumService>, history: Arc<GasHistoryStore>) -> Self {
+        let (updates, _) = watch::channel(None);
+        
+        Self {
+            chain,
+            window: Mutex::new(Vec::new()),
+            history,
+            updates,
+        }
+    }
+    
+    pub fn chain_name(&self) -> &str {
+        &self.chain.chain().name
+    }
+    
+    /// Receives the latest update immediately (if any), then every new one
+    pub fn subscribe(&self) -> watch::Receiver<Option<Arc<GasUpdate>>> {
+        self.updates.subscribe()
+    }
+    
+    pub fn latest(&self) -> Option<Arc<GasUpdate>> {
+        self.updates.borrow().clone()
+    }
+    
+    /// Poll a few times per block, but no more than once a second
+    fn poll_interval(&self) -> Duration {
+        Duration::from_millis((self.chain.chain().block_time_ms / 4).max(1000))
+    }
+    
+    pub async fn run(&self) {
+        tracing::info!("Following {} head every {:?}", self.chain_name(), self.poll_interval());
+        
+        if let Err(e) = self.backfill().await {
+            tracing::warn!("{} gas history backfill failed: {}", self.chain_name(), e);
+        }
+        
+        let mut interval = tokio::time::interval(self.poll_interval());
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        
+        loop {
+            interval.tick().await;
+            
+            if let Err(e) = self.poll().await {
+                tracing::warn!("{} block follower error: {}", self.chain_name(), e);
+            }
+        }
+    }
+    
+    async fn poll(&self) -> Result<()> {
+        let head = self.chain.get_block_number().await?;
+        
+        let mut window = self.window.lock().await;
+        let last = window.last().map(|s| s.number);
+        
+        if last.is_some_and(|last| head <= last) {
+            return Ok(());
+        }
+        
+        // Only fetch the blocks we have not seen, unless we fell too far behind
+        let start = match last {
+            Some(last) if head - last < HISTORY_BLOCKS => last + 1,
+            _ => {
+                window.clear();
+                head.saturating_sub(HISTORY_BLOCKS - 1)
+            }
+        };
+        
+        let new_blocks = self.chain.fetch_block_samples(start, head).await?;
+        if new_blocks.is_empty() {
+            return Ok(());
+        }
+        
+        self.record_history(&new_blocks).await;
+        window.extend(new_blocks);
+        let excess = window.len().saturating_sub(HISTORY_BLOCKS as usize);
+        window.drain(..excess);
+        
+        let history = window.clone();
+        drop(window);
+        
+        self.publish(&history).await;
+        
+        Ok(())
+    }
+    
+    /// Seeds the history store with recent blocks. Only the oldest and newest
+    /// timestamps are fetched; the ones in between are interpolated.
+    async fn backfill(&self) -> Result<()> {
+        let head = self.chain.get_block_number().await?;
+        let fee_history = self.chain.fee_history(BACKFILL_BLOCKS, head, &PRIORITY_PERCENTILES).await?;
+        
+        let oldest = fee_history.oldest_block.as_u64();
+        let (Some(oldest_ts), Some(head_ts)) = (
+            self.chain.get_block_timestamp(oldest).await?,
+            self.chain.get_block_timestamp(head).await?,
+        ) else {
+            return Ok(());
+        };
+        
+        let seconds_per_block = if head > oldest {
+            head_ts.saturating_sub(oldest_ts) as f64 / (head - oldest) as f64
+        } else {
+            0.0
+        };
+        let records = gas_history::fee_records(&fee_history, |number| {
+            oldest_ts + ((number - oldest) as f64 * seconds_per_block) as u64
+        });
+        
+        tracing::info!("Backfilled {} blocks of {} gas history", records.len(), self.chain_name());
+        self.history.record(self.chain_name(), records).await;
+        
+        Ok(())
+    }
+    
+    /// Adds priority fee percentiles to freshly seen blocks and stores them.
+    /// A failed lookup leaves a gap rather than recording made-up rewards.
+    async fn record_history(&self, blocks: &[BlockSample]) {
+        let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else {
+            return;
+        };
+        
+        let count = last.number - first.number + 1;
+        let fee_history = match self.chain.fee_history(count, last.number, &PRIORITY_PERCENTILES).await {
+            Ok(fee_history) => fee_history,
+            Err(e) => {
+                tracing::warn!("{} fee history unavailable: {}", self.chain_name(), e);
+                return;
+            }
+        };
+        
+        let timestamps: HashMap<u64, u64> = blocks.iter().map(|b| (b.number, b.timestamp)).collect();
+        let records = gas_history::fee_records(&fee_history, |number| {
+            timestamps.get(&number).copied().unwrap_or(last.timestamp)
+        })
+        .into_iter()
+        .filter(|record| timestamps.contains_key(&record.number))
+        .collect();
+        
+        self.history.record(self.chain_name(), records).await;
+    }
+    
+    async fn publish(&self, history: &[BlockSample]) {
+        let l1_fee = self.chain.l1_fee_components().await;
+        
+        let mut predictions = Vec::with_capacity(GasModel::ALL.len());
+        for model in GasModel::ALL {
+            match self.chain.build_prediction(model, history, l1_fee.clone()) {
+                Ok(prediction) => {
+                    let _ = self.chain.cache_prediction(&prediction).await;
+                    predictions.push(prediction);
+                }
+                Err(e) => tracing::warn!("{} prediction failed for {}: {}", model, self.chain_name(), e),
+            }
+        }
+        let _ = self.chain.cache_block_history(history).await;
+        
+        let block_number = history.last().map(|s| s.number).unwrap_or_default();
+        tracing::debug!("{} head {}: published {} predictions", self.chain_name(), block_number, predictions.len());
+        
+        self.updates.send_replace(Some(Arc::new(GasUpdate {
+            chain: self.chain_name().to_string(),
+            block_number,
+            predictions,
+        })));
+    }
+}
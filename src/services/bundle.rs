@@ -0,0 +1,325 @@
+use crate::contracts::aave_v3::LiquidationCallCall;
+use crate::contracts::compound_v3::{AbsorbCall, BuyCollateralCall};
+use crate::contracts::{ApproveCall, SwapExactETHForTokensCall, SwapExactTokensForETHCall};
+use crate::models::{
+    BundleSimulation, BundleTemplate, BundleTransaction, BundleTxResult, ChainSpec, LendingProtocol,
+    UnsignedTransaction,
+};
+use crate::services::{
+    chain_client::ChainClient,
+    fork_db::from_revm_u256,
+    liquidation::{COMET_ABSORB_GAS, COMET_BUY_COLLATERAL_GAS},
+    sandwich::{next_block_env, tx_env},
+    ForkDb, LiquidationCandidate, SimulatedSandwich,
+};
+use anyhow::{anyhow, Context, Result};
+use ethers::abi::{self, AbiEncode, ParamType};
+use ethers::types::{Address, Bytes, Transaction, U256};
+use ethers::utils::rlp;
+use revm::db::CacheDB;
+use revm::primitives::{EVMError, ExecutionResult};
+use revm::{Database, EVM};
+use std::sync::{Arc, Mutex};
+
+/// Gas limits are the simulated gas plus this share
+const GAS_HEADROOM_PERCENT: u64 = 20;
+const APPROVE_GAS: u64 = 60_000;
+
+/// Most transactions simulated in one bundle
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 16;
+
+/// Selector of `Error(string)`, the standard revert payload
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Decodes a signed raw transaction and recovers its sender
+pub fn decode_raw_transaction(raw: &Bytes) -> Result<Transaction> {
+    let mut tx: Transaction = rlp::decode(raw).map_err(|e| anyhow!("Invalid transaction encoding: {}", e))?;
+    tx.recover_from_mut().context("Invalid transaction signature")?;
+    Ok(tx)
+}
+
+/// Executes client-signed bundles as the next block, on a fork of the latest
+/// state shared by every simulation of that block
+pub struct BundleSimulator {
+    client: Arc<dyn ChainClient>,
+    chain: ChainSpec,
+    fork: Mutex<Option<ForkDb>>,
+}
+
+impl BundleSimulator {
+    pub fn new(client: Arc<dyn ChainClient>, chain: ChainSpec) -> Self {
+        Self {
+            client,
+            chain,
+            fork: Mutex::new(None),
+        }
+    }
+
+    /// Runs every transaction in order. Reverted ones still spend their gas;
+    /// invalid ones (nonce, balance, fee cap) are skipped.
+    pub async fn simulate(&self, txs: Vec<Transaction>) -> Result<BundleSimulation> {
+        if txs.len() > MAX_BUNDLE_TRANSACTIONS {
+            return Err(anyhow!(
+                "Bundle has {} transactions, at most {} are simulated",
+                txs.len(),
+                MAX_BUNDLE_TRANSACTIONS
+            ));
+        }
+        let state_block = self.client.block_number().await?;
+        let block = self
+            .client
+            .block(state_block)
+            .await?
+            .context("Latest block not found")?;
+        let (env, base_fee) = next_block_env(&block, &self.chain)?;
+        let fork = self.fork_at(state_block);
+
+        let results = tokio::task::spawn_blocking(move || -> Result<Vec<BundleTxResult>> {
+            let coinbase = env.block.coinbase;
+            let mut evm = EVM::new();
+            evm.env = env;
+            evm.database(CacheDB::new(fork));
+
+            let mut results = Vec::new();
+            for tx in txs {
+                let before = coinbase_balance(&mut evm, coinbase)?;
+                evm.env.tx = tx_env(&tx);
+                evm.env.tx.nonce = Some(u64::try_from(tx.nonce).unwrap_or(u64::MAX));
+
+                let (gas_used, error) = match evm.transact_commit() {
+                    Ok(ExecutionResult::Success { gas_used, .. }) => (gas_used, None),
+                    Ok(ExecutionResult::Revert { gas_used, output }) => (gas_used, Some(revert_reason(&output))),
+                    Ok(ExecutionResult::Halt { gas_used, reason }) => (gas_used, Some(format!("Halted: {:?}", reason))),
+                    Err(EVMError::Database(e)) => return Err(e.into()),
+                    Err(e) => (0, Some(format!("Invalid in the block: {:?}", e))),
+                };
+                let coinbase_diff = coinbase_balance(&mut evm, coinbase)?.saturating_sub(before);
+
+                results.push(BundleTxResult {
+                    tx_hash: tx.hash,
+                    from: tx.from,
+                    to: tx.to,
+                    success: error.is_none(),
+                    gas_used,
+                    gas_price_gwei: gwei(effective_gas_price(&tx, base_fee)),
+                    coinbase_diff: coinbase_diff.to_string(),
+                    error,
+                });
+            }
+            Ok(results)
+        })
+        .await??;
+
+        let total_gas_used: u64 = results.iter().map(|result| result.gas_used).sum();
+        let coinbase_diff = results
+            .iter()
+            .map(|result| U256::from_dec_str(&result.coinbase_diff).unwrap_or_default())
+            .fold(U256::zero(), |total, diff| total.saturating_add(diff));
+        let bundle_gas_price_gwei = if total_gas_used == 0 {
+            0.0
+        } else {
+            gwei(coinbase_diff / total_gas_used)
+        };
+
+        Ok(BundleSimulation {
+            state_block,
+            block_number: state_block + 1,
+            success: results.iter().all(|result| result.success),
+            total_gas_used,
+            coinbase_diff: coinbase_diff.to_string(),
+            bundle_gas_price_gwei,
+            results,
+        })
+    }
+
+    fn fork_at(&self, block: u64) -> ForkDb {
+        let mut fork = self.fork.lock().unwrap();
+        match fork.as_ref() {
+            Some(existing) if existing.block() == block => existing.clone(),
+            _ => {
+                let new_fork = ForkDb::new(self.client.clone(), block);
+                *fork = Some(new_fork.clone());
+                new_fork
+            }
+        }
+    }
+}
+
+/// Frontrun, victim, approval and backrun as simulated. The frontrun must buy
+/// at least the simulated amount and the backrun must return the ETH spent,
+/// so the bundle reverts rather than lose money if the pool moved.
+pub fn sandwich_bundle(victim: &Transaction, sandwich: &SimulatedSandwich, searcher: Address) -> BundleTemplate {
+    let weth = sandwich.wrapped_native;
+    let base_fee = sandwich.backrun_gas_price;
+    let tip = sandwich.frontrun_gas_price.saturating_sub(base_fee);
+
+    let frontrun = SwapExactETHForTokensCall {
+        amount_out_min: sandwich.token_amount,
+        path: vec![weth, sandwich.token],
+        to: searcher,
+        deadline: U256::MAX,
+    };
+    let approve = ApproveCall {
+        spender: sandwich.router,
+        amount: sandwich.token_amount,
+    };
+    let backrun = SwapExactTokensForETHCall {
+        amount_in: sandwich.token_amount,
+        amount_out_min: sandwich.frontrun_wei,
+        path: vec![sandwich.token, weth],
+        to: searcher,
+        deadline: U256::MAX,
+    };
+    let unsigned = |to, data: Vec<u8>, value, gas, tip: U256| UnsignedTransaction {
+        from: searcher,
+        to,
+        data: data.into(),
+        value,
+        gas: U256::from(with_headroom(gas)),
+        max_fee_per_gas: base_fee + tip,
+        max_priority_fee_per_gas: tip,
+    };
+
+    BundleTemplate {
+        block_number: format!("{:#x}", sandwich.block + 1),
+        txs: vec![
+            BundleTransaction::Unsigned(unsigned(
+                sandwich.router,
+                frontrun.encode(),
+                sandwich.frontrun_wei,
+                sandwich.frontrun_gas,
+                tip,
+            )),
+            signed(victim),
+            BundleTransaction::Unsigned(unsigned(sandwich.token, approve.encode(), U256::zero(), APPROVE_GAS, U256::zero())),
+            BundleTransaction::Unsigned(unsigned(
+                sandwich.router,
+                backrun.encode(),
+                U256::zero(),
+                sandwich.backrun_gas,
+                U256::zero(),
+            )),
+        ],
+        reverting_tx_hashes: Vec::new(),
+        suggested_priority_fee_gwei: gwei(tip),
+    }
+}
+
+/// The liquidation, behind the oracle report that enables it if there is one.
+/// Aave repays as much as the close factor allows; Comet absorbs the whole
+/// position, then the absorbed collateral is bought with the base token.
+pub fn liquidation_bundle(
+    candidate: &LiquidationCandidate,
+    trigger: Option<&Transaction>,
+    searcher: Address,
+    target_block: u64,
+    max_fee_per_gas: U256,
+    max_priority_fee_per_gas: U256,
+) -> BundleTemplate {
+    let unsigned = |to, data: Vec<u8>, gas| {
+        BundleTransaction::Unsigned(UnsignedTransaction {
+            from: searcher,
+            to,
+            data: data.into(),
+            value: U256::zero(),
+            gas: U256::from(with_headroom(gas)),
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+        })
+    };
+
+    let mut txs: Vec<_> = trigger.map(signed).into_iter().collect();
+    match candidate.protocol {
+        LendingProtocol::AaveV3 => {
+            let approve = ApproveCall {
+                spender: candidate.market,
+                amount: U256::MAX,
+            };
+            let liquidate = LiquidationCallCall {
+                collateral_asset: candidate.collateral_asset,
+                debt_asset: candidate.debt_asset,
+                user: candidate.borrower,
+                // Capped by the pool at the close factor
+                debt_to_cover: U256::MAX,
+                receive_a_token: false,
+            };
+            txs.push(unsigned(candidate.debt_asset, approve.encode(), APPROVE_GAS));
+            txs.push(unsigned(candidate.market, liquidate.encode(), candidate.gas));
+        }
+        LendingProtocol::CompoundV3 => {
+            let absorb = AbsorbCall {
+                absorber: searcher,
+                accounts: vec![candidate.borrower],
+            };
+            let approve = ApproveCall {
+                spender: candidate.market,
+                amount: candidate
+                    .purchases
+                    .iter()
+                    .fold(U256::zero(), |total, purchase| total.saturating_add(purchase.base_amount)),
+            };
+            txs.push(unsigned(candidate.market, absorb.encode(), COMET_ABSORB_GAS));
+            txs.push(unsigned(candidate.debt_asset, approve.encode(), APPROVE_GAS));
+            for purchase in &candidate.purchases {
+                let buy = BuyCollateralCall {
+                    asset: purchase.asset,
+                    min_amount: purchase.min_amount,
+                    base_amount: purchase.base_amount,
+                    recipient: searcher,
+                };
+                txs.push(unsigned(candidate.market, buy.encode(), COMET_BUY_COLLATERAL_GAS));
+            }
+        }
+    }
+
+    BundleTemplate {
+        block_number: format!("{:#x}", target_block),
+        txs,
+        reverting_tx_hashes: Vec::new(),
+        suggested_priority_fee_gwei: gwei(max_priority_fee_per_gas),
+    }
+}
+
+fn signed(tx: &Transaction) -> BundleTransaction {
+    BundleTransaction::Signed {
+        hash: tx.hash,
+        raw: tx.rlp(),
+    }
+}
+
+fn with_headroom(gas: u64) -> u64 {
+    gas + gas * GAS_HEADROOM_PERCENT / 100
+}
+
+/// Wei to gwei, saturating at `u128::MAX` wei
+fn gwei(wei: U256) -> f64 {
+    u128::try_from(wei).unwrap_or(u128::MAX) as f64 / 1e9
+}
+
+/// What the transaction pays per gas in a block with `base_fee`
+fn effective_gas_price(tx: &Transaction, base_fee: U256) -> U256 {
+    match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+        (Some(max_fee), Some(tip)) => max_fee.min(base_fee.saturating_add(tip)),
+        _ => tx.gas_price.unwrap_or_default(),
+    }
+}
+
+fn coinbase_balance(
+    evm: &mut EVM<CacheDB<ForkDb>>,
+    coinbase: revm::primitives::Address,
+) -> Result<U256, ethers::providers::ProviderError> {
+    let db = evm.db.as_mut().expect("database is set");
+    Ok(db.basic(coinbase)?.map(|info| from_revm_u256(info.balance)).unwrap_or_default())
+}
+
+/// The `Error(string)` message if there is one, else the raw revert data
+fn revert_reason(output: &revm::primitives::Bytes) -> String {
+    if output.len() >= 4 && output[..4] == ERROR_SELECTOR {
+        if let Ok(tokens) = abi::decode(&[ParamType::String], &output[4..]) {
+            if let Some(reason) = tokens.into_iter().next().and_then(|token| token.into_string()) {
+                return format!("Reverted: {}", reason);
+            }
+        }
+    }
+    format!("Reverted: 0x{}", ethers::utils::hex::encode(output))
+}
@@ -41,6 +41,12 @@ impl CacheService {
         Ok(Self { redis, memory })
     }
     
+    /// The Redis connection, for callers that need more than plain keys;
+    /// `None` when running on the memory cache alone
+    pub fn redis(&self) -> Option<redis::aio::ConnectionManager> {
+        self.redis.clone()
+    }
+    
     pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
         // Try memory cache first
         if let Some(cached) = self.memory.get(key).await {
@@ -0,0 +1,177 @@
+use crate::models::ProviderMetrics;
+use crate::services::provider_pool::{ProviderPool, RpcProvider};
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::{
+    abi::{AbiDecode, AbiEncode},
+    providers::{JsonRpcClient, Middleware, Provider, ProviderError},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Block, BlockNumber, Bytes, FeeHistory, Filter,
+        Log, Transaction, TransactionReceipt, TransactionRequest, H256, U256,
+    },
+};
+
+/// Read access to blocks and fee data
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    async fn block_number(&self) -> Result<u64, ProviderError>;
+    async fn block(&self, number: u64) -> Result<Option<Block<H256>>, ProviderError>;
+    /// The block with its full transactions
+    async fn block_with_transactions(&self, number: u64) -> Result<Option<Block<Transaction>>, ProviderError>;
+    async fn fee_history(
+        &self,
+        count: u64,
+        newest: u64,
+        percentiles: &[f64],
+    ) -> Result<FeeHistory, ProviderError>;
+}
+
+/// Lookup of mined transactions and their receipts
+#[async_trait]
+pub trait TransactionSource: Send + Sync {
+    async fn transaction(&self, hash: H256) -> Result<Option<Transaction>, ProviderError>;
+    async fn receipt(&self, hash: H256) -> Result<Option<TransactionReceipt>, ProviderError>;
+    /// Every receipt of a block in one call (`eth_getBlockReceipts`)
+    async fn block_receipts(&self, number: u64) -> Result<Vec<TransactionReceipt>, ProviderError>;
+}
+
+/// `eth_getLogs`
+#[async_trait]
+pub trait LogSource: Send + Sync {
+    async fn logs(&self, filter: &Filter) -> Result<Vec<Log>, ProviderError>;
+}
+
+/// `eth_call` and `eth_estimateGas` against the latest block
+#[async_trait]
+pub trait ContractCaller: Send + Sync {
+    async fn call(&self, tx: &TypedTransaction) -> Result<Bytes, ProviderError>;
+    async fn estimate_gas(&self, tx: &TypedTransaction) -> Result<U256, ProviderError>;
+}
+
+/// Account state at a given block, for forking it into a local EVM
+#[async_trait]
+pub trait StateSource: Send + Sync {
+    async fn balance(&self, address: Address, block: u64) -> Result<U256, ProviderError>;
+    async fn nonce(&self, address: Address, block: u64) -> Result<U256, ProviderError>;
+    async fn code(&self, address: Address, block: u64) -> Result<Bytes, ProviderError>;
+    async fn storage(&self, address: Address, slot: H256, block: u64) -> Result<H256, ProviderError>;
+}
+
+/// Everything a chain service needs. Implemented by the RPC provider pool and
+/// by [`FakeChain`](crate::services::FakeChain) for offline testing.
+pub trait ChainClient: BlockSource + TransactionSource + LogSource + ContractCaller + StateSource {
+    fn provider_metrics(&self) -> Vec<ProviderMetrics> {
+        Vec::new()
+    }
+}
+
+impl ChainClient for RpcProvider {
+    fn provider_metrics(&self) -> Vec<ProviderMetrics> {
+        AsRef::<ProviderPool>::as_ref(self).metrics()
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> BlockSource for Provider<P> {
+    async fn block_number(&self) -> Result<u64, ProviderError> {
+        Ok(self.get_block_number().await?.as_u64())
+    }
+
+    async fn block(&self, number: u64) -> Result<Option<Block<H256>>, ProviderError> {
+        self.get_block(number).await
+    }
+
+    async fn block_with_transactions(&self, number: u64) -> Result<Option<Block<Transaction>>, ProviderError> {
+        self.get_block_with_txs(number).await
+    }
+
+    async fn fee_history(
+        &self,
+        count: u64,
+        newest: u64,
+        percentiles: &[f64],
+    ) -> Result<FeeHistory, ProviderError> {
+        Middleware::fee_history(self, count, BlockNumber::Number(newest.into()), percentiles).await
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> TransactionSource for Provider<P> {
+    async fn transaction(&self, hash: H256) -> Result<Option<Transaction>, ProviderError> {
+        self.get_transaction(hash).await
+    }
+
+    async fn receipt(&self, hash: H256) -> Result<Option<TransactionReceipt>, ProviderError> {
+        self.get_transaction_receipt(hash).await
+    }
+
+    async fn block_receipts(&self, number: u64) -> Result<Vec<TransactionReceipt>, ProviderError> {
+        self.get_block_receipts(number).await
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> LogSource for Provider<P> {
+    async fn logs(&self, filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+        self.get_logs(filter).await
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> ContractCaller for Provider<P> {
+    async fn call(&self, tx: &TypedTransaction) -> Result<Bytes, ProviderError> {
+        Middleware::call(self, tx, None).await
+    }
+
+    async fn estimate_gas(&self, tx: &TypedTransaction) -> Result<U256, ProviderError> {
+        Middleware::estimate_gas(self, tx, None).await
+    }
+}
+
+#[async_trait]
+impl<P: JsonRpcClient> StateSource for Provider<P> {
+    async fn balance(&self, address: Address, block: u64) -> Result<U256, ProviderError> {
+        self.get_balance(address, Some(block.into())).await
+    }
+
+    async fn nonce(&self, address: Address, block: u64) -> Result<U256, ProviderError> {
+        self.get_transaction_count(address, Some(block.into())).await
+    }
+
+    async fn code(&self, address: Address, block: u64) -> Result<Bytes, ProviderError> {
+        self.get_code(address, Some(block.into())).await
+    }
+
+    async fn storage(&self, address: Address, slot: H256, block: u64) -> Result<H256, ProviderError> {
+        self.get_storage_at(address, slot, Some(block.into())).await
+    }
+}
+
+/// Calls a view function using the call and return types generated by `abigen!`
+pub async fn call_contract<M, C, R>(caller: &M, to: Address, call: C) -> Result<R>
+where
+    M: ContractCaller + ?Sized,
+    C: AbiEncode + Send,
+    R: AbiDecode,
+{
+    let tx: TypedTransaction = TransactionRequest::new().to(to).data(call.encode()).into();
+    let output = caller.call(&tx).await?;
+    Ok(R::decode(output)?)
+}
+
+/// Like [`call_contract`], but `None` when nothing is deployed at `to`, for
+/// pools derived from their factory that may not exist: a call to an account
+/// without code succeeds with empty output
+pub async fn call_deployed<M, C, R>(caller: &M, to: Address, call: C) -> Result<Option<R>>
+where
+    M: ContractCaller + ?Sized,
+    C: AbiEncode + Send,
+    R: AbiDecode,
+{
+    let tx: TypedTransaction = TransactionRequest::new().to(to).data(call.encode()).into();
+    let output = caller.call(&tx).await?;
+    if output.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(R::decode(output)?))
+}
@@ -0,0 +1,85 @@
+use crate::{
+    error::QGuardError,
+    services::{BlockFollower, EthereumService, GasHistoryStore},
+};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+struct ChainEntry {
+    service: Arc<EthereumService>,
+    follower: Arc<BlockFollower>,
+}
+
+/// Gas data services keyed by chain name, selected with `?chain=`
+pub struct ChainRegistry {
+    chains: BTreeMap<String, ChainEntry>,
+    default_chain: String,
+    history: Arc<GasHistoryStore>,
+}
+
+impl ChainRegistry {
+    pub fn new(default: Arc<EthereumService>, history: Arc<GasHistoryStore>) -> Self {
+        let mut registry = Self {
+            chains: BTreeMap::new(),
+            default_chain: default.chain().name.clone(),
+            history,
+        };
+        registry.insert(default);
+        registry
+    }
+    
+    pub fn insert(&mut self, service: Arc<EthereumService>) {
+        let follower = Arc::new(BlockFollower::new(service.clone(), self.history.clone()));
+        self.chains.insert(
+            service.chain().name.clone(),
+            ChainEntry { service, follower },
+        );
+    }
+    
+    pub fn get(&self, chain: Option<&str>) -> Result<Arc<EthereumService>, QGuardError> {
+        self.entry(chain).map(|entry| entry.service.clone())
+    }
+    
+    pub fn services(&self) -> impl Iterator<Item = &Arc<EthereumService>> {
+        self.chains.values().map(|entry| &entry.service)
+    }
+    
+    pub fn follower(&self, chain: Option<&str>) -> Result<Arc<BlockFollower>, QGuardError> {
+        self.entry(chain).map(|entry| entry.follower.clone())
+    }
+    
+    pub fn history(&self) -> Arc<GasHistoryStore> {
+        self.history.clone()
+    }
+    
+    /// Spawns the head follower for every chain and the history flusher
+    pub fn start_followers(&self) {
+        let history = self.history.clone();
+        tokio::spawn(async move {
+            history.run_flusher().await;
+        });
+        
+        for entry in self.chains.values() {
+            let follower = entry.follower.clone();
+            tokio::spawn(async move {
+                follower.run().await;
+            });
+        }
+    }
+    
+    pub fn names(&self) -> Vec<&str> {
+        self.chains.keys().map(String::as_str).collect()
+    }
+    
+    fn entry(&self, chain: Option<&str>) -> Result<&ChainEntry, QGuardError> {
+        let name = chain.unwrap_or(&self.default_chain).to_lowercase();
+        
+        self.chains.get(&name).ok_or_else(|| {
+            QGuardError::InvalidRequest(format!(
+                "Unsupported chain: {} (available: {})",
+                name,
+                self.names().join(", ")
+            ))
+        })
+    }
+}
@@ -0,0 +1,136 @@
+use crate::contracts::{
+    SUSHISWAP_FACTORY, SUSHISWAP_ROUTER, UNISWAP_SWAP_ROUTER_02, UNISWAP_V2_FACTORY, UNISWAP_V2_ROUTER,
+    UNISWAP_V3_ROUTER, UNIVERSAL_ROUTER,
+};
+use crate::contracts::uniswap_v3::UNISWAP_V3_FACTORY;
+use crate::models::{ChainSpec, DexDeployment, DexKind};
+use crate::services::DexProtocol;
+use anyhow::{Context, Result};
+use ethers::types::Address;
+
+const UNISWAP_V2_INIT_CODE_HASH: &str = "0x96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f";
+const SUSHISWAP_INIT_CODE_HASH: &str = "0xe18a34eb0e04b04f7a0ac29a6e80748dca96319b42c54d679cb821dca90c6303";
+const UNISWAP_V3_INIT_CODE_HASH: &str = "0xe34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54";
+
+/// Known DEXes per chain: their routers, factories, pool init code hashes and
+/// fee tiers. Services get the deployments of the chain they serve. Swaps are
+/// decoded only for registered routers, and attributed to the deployment
+/// whose pool design matches the call; pools are derived from the factory and
+/// init code hash. Only Uniswap V2- and V3-style pools are modelled.
+#[derive(Debug, Clone)]
+pub struct DexRegistry {
+    deployments: Vec<DexDeployment>,
+    /// Of the chain picked with `on_chain`, Ethereum's until then
+    wrapped_native: Address,
+}
+
+impl DexRegistry {
+    pub fn new(deployments: Vec<DexDeployment>) -> Self {
+        let mut registry = Self {
+            deployments: Vec::new(),
+            wrapped_native: ChainSpec::ethereum().wrapped_native,
+        };
+        for deployment in deployments {
+            registry.insert(deployment);
+        }
+        registry
+    }
+
+    /// Uniswap V2 and V3 and SushiSwap on Ethereum mainnet
+    pub fn mainnet() -> Self {
+        Self::new(vec![
+            DexDeployment {
+                name: "uniswap_v2".to_string(),
+                chain: "ethereum".to_string(),
+                kind: DexKind::UniswapV2,
+                routers: addresses(&[UNISWAP_V2_ROUTER, UNISWAP_SWAP_ROUTER_02, UNIVERSAL_ROUTER]),
+                v2_router: Some(UNISWAP_V2_ROUTER.parse().unwrap()),
+                factory: Some(UNISWAP_V2_FACTORY.parse().unwrap()),
+                init_code_hash: Some(UNISWAP_V2_INIT_CODE_HASH.parse().unwrap()),
+                fee_tiers: vec![3_000],
+            },
+            DexDeployment {
+                name: "sushiswap".to_string(),
+                chain: "ethereum".to_string(),
+                kind: DexKind::UniswapV2,
+                routers: addresses(&[SUSHISWAP_ROUTER]),
+                v2_router: Some(SUSHISWAP_ROUTER.parse().unwrap()),
+                factory: Some(SUSHISWAP_FACTORY.parse().unwrap()),
+                init_code_hash: Some(SUSHISWAP_INIT_CODE_HASH.parse().unwrap()),
+                fee_tiers: vec![3_000],
+            },
+            DexDeployment {
+                name: "uniswap_v3".to_string(),
+                chain: "ethereum".to_string(),
+                kind: DexKind::UniswapV3,
+                routers: addresses(&[UNISWAP_V3_ROUTER, UNISWAP_SWAP_ROUTER_02, UNIVERSAL_ROUTER]),
+                v2_router: None,
+                factory: Some(UNISWAP_V3_FACTORY.parse().unwrap()),
+                init_code_hash: Some(UNISWAP_V3_INIT_CODE_HASH.parse().unwrap()),
+                fee_tiers: vec![100, 500, 3_000, 10_000],
+            },
+        ])
+    }
+
+    /// The mainnet defaults plus a JSON array of deployments read from
+    /// `path`, which replace defaults with the same chain and name
+    pub fn load(path: &str) -> Result<Self> {
+        let json = std::fs::read_to_string(path).with_context(|| format!("Cannot read DEX registry {}", path))?;
+        let deployments: Vec<DexDeployment> =
+            serde_json::from_str(&json).with_context(|| format!("Invalid DEX registry {}", path))?;
+
+        let mut registry = Self::mainnet();
+        for deployment in deployments {
+            registry.insert(deployment);
+        }
+        Ok(registry)
+    }
+
+    pub fn insert(&mut self, deployment: DexDeployment) {
+        self.deployments
+            .retain(|existing| existing.chain != deployment.chain || existing.name != deployment.name);
+        self.deployments.push(deployment);
+    }
+
+    /// Only the deployments on `chain`, with its wrapped native token
+    pub fn on_chain(&self, chain: &ChainSpec) -> Self {
+        Self {
+            deployments: self.deployments.iter().filter(|d| d.chain == chain.name).cloned().collect(),
+            wrapped_native: chain.wrapped_native,
+        }
+    }
+
+    /// The token swaps are priced in and sandwiches and arbitrage start from
+    pub fn wrapped_native(&self) -> Address {
+        self.wrapped_native
+    }
+
+    pub fn deployments(&self) -> &[DexDeployment] {
+        &self.deployments
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DexDeployment> {
+        self.deployments.iter().find(|d| d.name == name)
+    }
+
+    /// Whether `address` is a public router of any registered DEX
+    pub fn is_router(&self, address: Address) -> bool {
+        self.deployments.iter().any(|d| d.routers.contains(&address))
+    }
+
+    /// The DEX whose pools `router` trades on for swaps decoded as `protocol`.
+    /// Routers such as the Universal Router belong to several.
+    pub fn for_router(&self, router: Address, protocol: DexProtocol) -> Option<&DexDeployment> {
+        let kind = match protocol {
+            DexProtocol::UniswapV2 => DexKind::UniswapV2,
+            DexProtocol::UniswapV3 => DexKind::UniswapV3,
+        };
+        self.deployments
+            .iter()
+            .find(|d| d.kind == kind && d.routers.contains(&router))
+    }
+}
+
+fn addresses(list: &[&str]) -> Vec<Address> {
+    list.iter().map(|address| address.parse().unwrap()).collect()
+}
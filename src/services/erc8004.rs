@@ -0,0 +1,300 @@
+use crate::contracts::identity_registry::{
+    BalanceOfCall, BalanceOfReturn, IdentityRegistryEvents, TokenURICall, TokenURIReturn,
+};
+use crate::contracts::reputation_registry::{
+    GetSummaryCall as FeedbackSummaryCall, GetSummaryReturn as FeedbackSummaryReturn,
+};
+use crate::contracts::validation_registry::{
+    GetSummaryCall as ValidationSummaryCall, GetSummaryReturn as ValidationSummaryReturn,
+};
+use crate::models::{AgentRegistration, TrustSummary};
+use crate::services::{
+    chain_client::{call_contract, ChainClient},
+    outbound::public_client,
+    CacheService,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ethers::contract::parse_log;
+use ethers::types::{Address, Filter, Log, U256};
+use reqwest::Url;
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Blocks of Identity Registry logs fetched per `eth_getLogs` request
+const LOG_CHUNK_BLOCKS: u64 = 2_000;
+/// The ownership index is brought up to the head this often
+const INDEX_INTERVAL: Duration = Duration::from_secs(12);
+/// `ipfs://` registration files are fetched through this gateway
+const IPFS_GATEWAY: &str = "https://ipfs.io/ipfs/";
+const REGISTRATION_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Larger registration files are refused
+const MAX_REGISTRATION_BYTES: usize = 64 * 1024;
+/// Parsed registration files are cached this long
+const REGISTRATION_TTL_SECS: u64 = 3600;
+
+/// The ERC-8004 registries on one network, written
+/// `network:identity:reputation:[validation]:from_block`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Erc8004Deployment {
+    pub network: String,
+    pub identity: Address,
+    pub reputation: Address,
+    pub validation: Option<Address>,
+    /// Block the Identity Registry was deployed at; ownership is indexed from here
+    pub from_block: u64,
+}
+
+impl FromStr for Erc8004Deployment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').map(str::trim).collect();
+        if parts.len() != 5 || parts[0].is_empty() {
+            return Err(format!(
+                "Expected network:identity:reputation:[validation]:from_block, got '{}'",
+                s
+            ));
+        }
+        let address = |part: &str| {
+            part.parse::<Address>()
+                .map_err(|_| format!("Invalid registry address '{}'", part))
+        };
+
+        Ok(Self {
+            network: parts[0].to_string(),
+            identity: address(parts[1])?,
+            reputation: address(parts[2])?,
+            validation: match parts[3] {
+                "" => None,
+                part => Some(address(part)?),
+            },
+            from_block: parts[4]
+                .parse()
+                .map_err(|_| format!("Invalid from_block '{}'", parts[4]))?,
+        })
+    }
+}
+
+/// Current owner of each agent id and the ids each owner holds, rebuilt from
+/// Identity Registry transfers
+#[derive(Default)]
+struct OwnerIndex {
+    owners: HashMap<U256, Address>,
+    ids: HashMap<Address, BTreeSet<U256>>,
+    /// Last block whose logs were applied
+    synced_to: Option<u64>,
+}
+
+impl OwnerIndex {
+    /// Mints come from and burns go to the zero address
+    fn apply(&mut self, log: Log) {
+        let Ok(IdentityRegistryEvents::TransferFilter(transfer)) = parse_log(log) else {
+            return;
+        };
+        if let Some(previous) = self.owners.remove(&transfer.token_id) {
+            if let Some(ids) = self.ids.get_mut(&previous) {
+                ids.remove(&transfer.token_id);
+                if ids.is_empty() {
+                    self.ids.remove(&previous);
+                }
+            }
+        }
+        if !transfer.to.is_zero() {
+            self.owners.insert(transfer.token_id, transfer.to);
+            self.ids.entry(transfer.to).or_default().insert(transfer.token_id);
+        }
+    }
+}
+
+/// Read access to the ERC-8004 Identity, Reputation and Validation registries
+/// on one network.
+///
+/// The Identity Registry is an ERC-721 with no lookup from owner to agent id,
+/// so ids are found by indexing its `Transfer` events from the deployment
+/// block. [`run`](Self::run) keeps the index current in the background;
+/// lookups read it as of the last sync.
+pub struct Erc8004Registry {
+    client: Arc<dyn ChainClient>,
+    cache: Arc<CacheService>,
+    deployment: Erc8004Deployment,
+    index: RwLock<OwnerIndex>,
+    /// Held by the one sync in progress; lookups never wait on it
+    syncing: Mutex<()>,
+}
+
+impl Erc8004Registry {
+    pub fn new(client: Arc<dyn ChainClient>, cache: Arc<CacheService>, deployment: Erc8004Deployment) -> Self {
+        tracing::info!(
+            "ERC-8004 registries on {}: identity {:?}, reputation {:?}, validation {:?}",
+            deployment.network,
+            deployment.identity,
+            deployment.reputation,
+            deployment.validation
+        );
+
+        Self {
+            client,
+            cache,
+            deployment,
+            index: RwLock::new(OwnerIndex::default()),
+            syncing: Mutex::new(()),
+        }
+    }
+
+    /// Indexes ownership up to the head, then again every few seconds
+    pub async fn run(&self) {
+        loop {
+            if let Err(e) = self.sync().await {
+                tracing::warn!("ERC-8004 ownership index on {} failed to sync: {}", self.deployment.network, e);
+            }
+            tokio::time::sleep(INDEX_INTERVAL).await;
+        }
+    }
+
+    pub fn deployment(&self) -> &Erc8004Deployment {
+        &self.deployment
+    }
+
+    /// Whether `agent` owns at least one Identity Registry token
+    pub async fn is_registered(&self, agent: Address) -> Result<bool> {
+        let BalanceOfReturn(balance) =
+            call_contract(self.client.as_ref(), self.deployment.identity, BalanceOfCall { owner: agent }).await?;
+        Ok(!balance.is_zero())
+    }
+
+    /// The agent id owned by `agent` (the lowest, if it owns several), as of
+    /// the last sync
+    pub fn agent_id(&self, agent: Address) -> Option<U256> {
+        let index = self.index.read().unwrap();
+        index.ids.get(&agent).and_then(|ids| ids.first().copied())
+    }
+
+    /// Feedback from all clients, with no tag filter
+    pub async fn feedback_summary(&self, agent_id: U256) -> Result<TrustSummary> {
+        let call = FeedbackSummaryCall {
+            agent_id,
+            client_addresses: Vec::new(),
+            tag_1: [0; 32],
+            tag_2: [0; 32],
+        };
+        let FeedbackSummaryReturn { count, average_score } =
+            call_contract(self.client.as_ref(), self.deployment.reputation, call).await?;
+
+        Ok(TrustSummary {
+            count,
+            average: average_score,
+        })
+    }
+
+    /// Validation responses from all validators; `None` without a Validation Registry
+    pub async fn validation_summary(&self, agent_id: U256) -> Result<Option<TrustSummary>> {
+        let Some(validation) = self.deployment.validation else {
+            return Ok(None);
+        };
+
+        let call = ValidationSummaryCall {
+            agent_id,
+            validator_addresses: Vec::new(),
+            tag: [0; 32],
+        };
+        let ValidationSummaryReturn { count, avg_response } =
+            call_contract(self.client.as_ref(), validation, call).await?;
+
+        Ok(Some(TrustSummary {
+            count,
+            average: avg_response,
+        }))
+    }
+
+    pub async fn token_uri(&self, agent_id: U256) -> Result<String> {
+        let TokenURIReturn(uri) =
+            call_contract(self.client.as_ref(), self.deployment.identity, TokenURICall { agent_id }).await?;
+        Ok(uri)
+    }
+
+    /// The agent's registration file, from a `data:`, `ipfs://` or HTTP(S) token URI
+    pub async fn registration(&self, agent_id: U256) -> Result<AgentRegistration> {
+        let cache_key = format!("erc8004:registration:{}:{}", self.deployment.network, agent_id);
+        if let Some(cached) = self.cache.get::<AgentRegistration>(&cache_key).await.ok().flatten() {
+            return Ok(cached);
+        }
+
+        let uri = self.token_uri(agent_id).await?;
+        let body = self
+            .fetch(&uri)
+            .await
+            .with_context(|| format!("Failed to fetch registration file of agent {}", agent_id))?;
+        let registration: AgentRegistration = serde_json::from_slice(&body)
+            .with_context(|| format!("Invalid registration file of agent {}", agent_id))?;
+
+        let _ = self.cache.set(&cache_key, &registration, REGISTRATION_TTL_SECS).await;
+        Ok(registration)
+    }
+
+    async fn fetch(&self, uri: &str) -> Result<Vec<u8>> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (media_type, payload) = data.split_once(',').ok_or_else(|| anyhow!("Malformed data URI"))?;
+            return if media_type.ends_with(";base64") {
+                Ok(STANDARD.decode(payload)?)
+            } else {
+                Ok(payload.as_bytes().to_vec())
+            };
+        }
+
+        let url = match uri.strip_prefix("ipfs://") {
+            Some(cid) => format!("{}{}", IPFS_GATEWAY, cid),
+            None if uri.starts_with("http://") || uri.starts_with("https://") => uri.to_string(),
+            None => bail!("Unsupported registration URI '{}'", uri),
+        };
+        let url = Url::parse(&url)?;
+
+        // Token URIs are set by agents, so they may point anywhere
+        let client = public_client(&url, REGISTRATION_FETCH_TIMEOUT).await?;
+        let mut response = client.get(url).send().await?.error_for_status()?;
+        if response.content_length().is_some_and(|length| length > MAX_REGISTRATION_BYTES as u64) {
+            bail!("Registration file is larger than {} bytes", MAX_REGISTRATION_BYTES);
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > MAX_REGISTRATION_BYTES {
+                bail!("Registration file is larger than {} bytes", MAX_REGISTRATION_BYTES);
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+
+    /// Applies Identity Registry transfers up to the current head. Logs are
+    /// fetched without holding the index, and each chunk is applied at once.
+    pub async fn sync(&self) -> Result<()> {
+        let _syncing = self.syncing.lock().await;
+        let head = self.client.block_number().await?;
+        let synced_to = self.index.read().unwrap().synced_to;
+        let mut start = synced_to.map_or(self.deployment.from_block, |block| block + 1);
+
+        while start <= head {
+            let end = (start + LOG_CHUNK_BLOCKS - 1).min(head);
+            let filter = Filter::new()
+                .address(self.deployment.identity)
+                .from_block(start)
+                .to_block(end);
+            let logs = self.client.logs(&filter).await?;
+
+            {
+                let mut index = self.index.write().unwrap();
+                for log in logs {
+                    index.apply(log);
+                }
+                index.synced_to = Some(end);
+            }
+            start = end + 1;
+        }
+
+        Ok(())
+    }
+}
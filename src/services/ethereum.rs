@@ -1,73 +1,97 @@
-use crate::{error::QGuardError, models::GasPrediction, services::CacheService};
+use crate::{
+    error::QGuardError,
+    models::{ChainKind, ChainSpec, GasModel, GasPrediction, L1FeeComponents, ProviderMetrics},
+    services::{
+        chain_client::ChainClient,
+        gas_predictor::{BlockSample, MIN_PRIORITY_FEE_GWEI},
+        l2_fees, CacheService,
+    },
+};
 use anyhow::Result;
 use chrono::Utc;
 use ethers::{
-    prelude::*,
-    providers::{Http, Provider},
-    types::Block,
+    providers::RpcError,
+    types::{transaction::eip2718::TypedTransaction, Block, FeeHistory, H256, U256},
 };
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+
+/// Number of recent blocks fed to the gas models
+pub const HISTORY_BLOCKS: u64 = 20;
 
 pub struct EthereumService {
-    pub primary: Arc<Provider<Http>>,
-    fallback: Option<Arc<Provider<Http>>>,
+    chain: ChainSpec,
+    pub client: Arc<dyn ChainClient>,
     cache: Arc<CacheService>,
+    default_model: GasModel,
+    /// Priority fee that recently got transactions included in time, observed
+    /// in the mempool; replaces the models' fixed default when set
+    observed_priority_fee_gwei: RwLock<Option<f64>>,
 }
 
 impl EthereumService {
     pub async fn new(
-        rpc_url: &str,
-        fallback_url: Option<&str>,
+        chain: ChainSpec,
+        client: Arc<dyn ChainClient>,
         cache: Arc<CacheService>,
-    ) -> Result<Self> {
-        let primary = Arc::new(Provider::<Http>::try_from(rpc_url)?);
-        
-        let fallback = if let Some(url) = fallback_url {
-            Some(Arc::new(Provider::<Http>::try_from(url)?))
-        } else {
-            None
-        };
-        
-        // Test connection
-        let block_number = primary.get_block_number().await?;
-        tracing::info!("Ethereum RPC connected, current block: {}", block_number);
+        default_model: GasModel,
+    ) -> Self {
+        // An unreachable RPC is reported but not fatal; requests fail until it recovers
+        match client.block_number().await {
+            Ok(block_number) => {
+                tracing::info!("{} RPC connected, current block: {}", chain.name, block_number)
+            }
+            Err(e) => tracing::warn!("{} RPC unreachable at startup: {}", chain.name, e),
+        }
         
-        Ok(Self {
-            primary,
-            fallback,
+        Self {
+            chain,
+            client,
             cache,
-        })
+            default_model,
+            observed_priority_fee_gwei: RwLock::new(None),
+        }
     }
     
-    pub async fn get_gas_prediction(&self) -> Result<GasPrediction, QGuardError> {
-        // Check cache with 12-second TTL (1 block time)
-        let cache_key = "gas:prediction";
-        if let Some(cached) = self.cache.get(cache_key).await.ok().flatten() {
-            tracing::debug!("Returning cached gas prediction");
-            return Ok(cached);
-        }
-        
-        // Fetch last 20 blocks
-        let latest_block = self.get_block_number().await?;
-        let start_block = latest_block.saturating_sub(19);
-        
-        let blocks = self.fetch_blocks(start_block, latest_block).await?;
+    pub fn chain(&self) -> &ChainSpec {
+        &self.chain
+    }
+    
+    pub fn provider_metrics(&self) -> Vec<ProviderMetrics> {
+        self.client.provider_metrics()
+    }
+    
+    pub fn default_model(&self) -> GasModel {
+        self.default_model
+    }
+    
+    /// Sets (or with `None`, clears) the priority fee used by every model
+    pub fn set_observed_priority_fee(&self, priority_fee_gwei: Option<f64>) {
+        *self.observed_priority_fee_gwei.write().unwrap() = priority_fee_gwei;
+    }
+    
+    pub fn observed_priority_fee(&self) -> Option<f64> {
+        *self.observed_priority_fee_gwei.read().unwrap()
+    }
+    
+    pub async fn get_gas_prediction(&self, model: Option<GasModel>) -> Result<GasPrediction, QGuardError> {
+        let model = model.unwrap_or(self.default_model);
         
-        if blocks.is_empty() {
-            return Err(QGuardError::RpcError(
-                ethers::providers::ProviderError::CustomError("No blocks fetched".to_string())
-            ));
+        // Check cache with a TTL of one block time
+        if let Some(cached) = self.cache.get(&self.prediction_cache_key(model)).await.ok().flatten() {
+            tracing::debug!("Returning cached {} gas prediction for {}", model, self.chain.name);
+            return Ok(cached);
         }
         
-        // Calculate prediction
-        let prediction = self.calculate_prediction(&blocks)?;
+        let history = self.get_block_history().await?;
+        let l1_fee = self.l1_fee_components().await;
         
-        // Cache for 12 seconds
-        self.cache.set(cache_key, &prediction, 12).await
-            .map_err(|e| QGuardError::CacheError(e.to_string()))?;
+        let prediction = self.build_prediction(model, &history, l1_fee)?;
+        self.cache_prediction(&prediction).await?;
         
         tracing::info!(
-            "Gas prediction: base={:.2} gwei, max={:.2} gwei, confidence={:.2}",
+            "Gas prediction ({}, {}): base={:.2} gwei, max={:.2} gwei, confidence={:.2}",
+            self.chain.name,
+            model,
             prediction.base_fee_gwei,
             prediction.max_fee_gwei,
             prediction.confidence
@@ -76,80 +100,145 @@ impl EthereumService {
         Ok(prediction)
     }
     
-    fn calculate_prediction(&self, blocks: &[Block<H256>]) -> Result<GasPrediction> {
-        let n = blocks.len();
-        
-        // Generate exponential weights (more recent = higher weight)
-        let weights = self.generate_exponential_weights(n);
-        
-        // Calculate weighted average base fee
-        let weighted_base_fee: f64 = blocks
-            .iter()
-            .zip(weights.iter())
-            .filter_map(|(block, weight)| {
-                block.base_fee_per_gas.map(|fee| {
-                    let fee_gwei = fee.as_u128() as f64 / 1e9;
-                    fee_gwei * weight
-                })
-            })
-            .sum();
-        
-        // Standard priority fee (2 gwei is typical)
-        let priority_fee_gwei = 2.0;
+    /// Runs `model` over `history` (oldest to newest)
+    pub fn build_prediction(
+        &self,
+        model: GasModel,
+        history: &[BlockSample],
+        l1_fee: Option<L1FeeComponents>,
+    ) -> Result<GasPrediction, QGuardError> {
+        let estimate = model
+            .predictor()
+            .predict(history)
+            .map_err(|e| QGuardError::InternalError(e.to_string()))?;
         
-        // Add 20% buffer for safety
-        let max_fee_gwei = weighted_base_fee * 1.2 + priority_fee_gwei;
-        
-        // Calculate confidence based on variance
-        let confidence = self.calculate_confidence(blocks, weighted_base_fee);
+        // The max fee keeps the model's base fee headroom
+        let priority_fee_gwei = self
+            .observed_priority_fee()
+            .map(|fee| fee.max(MIN_PRIORITY_FEE_GWEI))
+            .unwrap_or(estimate.priority_fee_gwei);
         
         Ok(GasPrediction {
-            base_fee_gwei: weighted_base_fee,
+            base_fee_gwei: estimate.base_fee_gwei,
             priority_fee_gwei,
-            max_fee_gwei,
-            confidence,
-            block_number: blocks.last().unwrap().number.unwrap().as_u64(),
+            max_fee_gwei: estimate.max_fee_gwei - estimate.priority_fee_gwei + priority_fee_gwei,
+            confidence: estimate.confidence,
+            block_number: history.last().map(|s| s.number).unwrap_or_default(),
             predicted_at: Utc::now(),
-            next_block_time_seconds: 12, // Ethereum block time
+            next_block_time_seconds: self.chain.block_time_seconds(),
+            model,
+            chain: self.chain.name.clone(),
+            l1_fee,
         })
     }
     
-    fn generate_exponential_weights(&self, n: usize) -> Vec<f64> {
-        let decay: f64 = 0.95;
-        let weights: Vec<f64> = (0..n)
-            .rev() // Reverse so most recent gets highest weight
-            .map(|i| decay.powi(i as i32))
-            .collect();
-        
-        let sum: f64 = weights.iter().sum();
-        weights.iter().map(|w| w / sum).collect()
+    pub async fn cache_prediction(&self, prediction: &GasPrediction) -> Result<(), QGuardError> {
+        self.cache
+            .set(
+                &self.prediction_cache_key(prediction.model),
+                prediction,
+                self.chain.block_time_seconds(),
+            )
+            .await
+            .map_err(|e| QGuardError::CacheError(e.to_string()))
     }
     
-    fn calculate_confidence(&self, blocks: &[Block<H256>], mean: f64) -> f64 {
-        let base_fees: Vec<f64> = blocks
-            .iter()
-            .filter_map(|b| b.base_fee_per_gas.map(|f| f.as_u128() as f64 / 1e9))
-            .collect();
+    fn prediction_cache_key(&self, model: GasModel) -> String {
+        format!("gas:prediction:{}:{}", self.chain.name, model)
+    }
+    
+    /// L1 components are informative; a failing oracle should not fail the prediction
+    pub async fn l1_fee_components(&self) -> Option<L1FeeComponents> {
+        match l2_fees::l1_fee_components(self.client.as_ref(), self.chain.kind).await {
+            Ok(components) => components,
+            Err(e) => {
+                tracing::warn!("L1 fee components unavailable for {}: {}", self.chain.name, e);
+                None
+            }
+        }
+    }
+    
+    /// Fee samples for the last 20 blocks, shared by every gas model
+    pub async fn get_block_history(&self) -> Result<Vec<BlockSample>, QGuardError> {
+        let cache_key = self.history_cache_key();
+        if let Some(cached) = self.cache.get(&cache_key).await.ok().flatten() {
+            return Ok(cached);
+        }
+        
+        // Fetch last 20 blocks
+        let latest_block = self.get_block_number().await?;
+        let start_block = latest_block.saturating_sub(HISTORY_BLOCKS - 1);
         
-        if base_fees.len() < 2 {
-            return 0.5;
+        let history = self.fetch_block_samples(start_block, latest_block).await?;
+        
+        if history.is_empty() {
+            return Err(QGuardError::RpcError(
+                ethers::providers::ProviderError::CustomError("No blocks fetched".to_string())
+            ));
         }
         
-        // Calculate standard deviation
-        let variance: f64 = base_fees
-            .iter()
-            .map(|fee| {
-                let diff = fee - mean;
-                diff * diff
-            })
-            .sum::<f64>() / base_fees.len() as f64;
+        self.cache_block_history(&history).await?;
         
-        let std_dev = variance.sqrt();
+        Ok(history)
+    }
+    
+    pub async fn cache_block_history(&self, history: &[BlockSample]) -> Result<(), QGuardError> {
+        self.cache
+            .set(&self.history_cache_key(), &history, self.chain.block_time_seconds())
+            .await
+            .map_err(|e| QGuardError::CacheError(e.to_string()))
+    }
+    
+    fn history_cache_key(&self) -> String {
+        format!("gas:history:{}", self.chain.name)
+    }
+    
+    pub async fn fetch_block_samples(&self, start: u64, end: u64) -> Result<Vec<BlockSample>> {
+        Ok(self
+            .fetch_blocks(start, end)
+            .await?
+            .iter()
+            .filter_map(BlockSample::from_block)
+            .collect())
+    }
+    
+    /// Runs `eth_estimateGas`. A revert reported by the node is the caller's
+    /// problem and maps to an invalid request rather than an upstream error.
+    pub async fn estimate_gas(&self, tx: &TypedTransaction) -> Result<u64, QGuardError> {
+        match self.client.estimate_gas(tx).await {
+            Ok(gas) => Ok(gas.as_u64()),
+            Err(e) => match RpcError::as_error_response(&e) {
+                Some(rpc_error) => Err(QGuardError::InvalidRequest(format!(
+                    "Gas estimation failed: {}",
+                    rpc_error.message
+                ))),
+                None => Err(e.into()),
+            },
+        }
+    }
+    
+    /// L1 data fee for `tx` on an L2, or `None` on L1
+    pub async fn l1_data_fee(&self, tx: &TypedTransaction) -> Result<Option<(U256, bool)>, QGuardError> {
+        let fee = match self.chain.kind {
+            ChainKind::L1 => return Ok(None),
+            ChainKind::OpStack => (l2_fees::op_stack_l1_data_fee(self.client.as_ref(), tx).await?, false),
+            ChainKind::Arbitrum => {
+                let to = tx.to().and_then(|to| to.as_address().copied());
+                let data = tx.data().cloned().unwrap_or_default();
+                (l2_fees::arbitrum_l1_component(self.client.as_ref(), to, data).await?, true)
+            }
+        };
         
-        // Lower std_dev = higher confidence
-        // Normalize to 0-1 range
-        let confidence = 1.0 / (1.0 + std_dev / mean);
-        confidence.clamp(0.0, 1.0)
+        Ok(Some(fee))
+    }
+    
+    /// `eth_feeHistory` for `count` blocks ending at `newest`
+    pub async fn fee_history(&self, count: u64, newest: u64, percentiles: &[f64]) -> Result<FeeHistory> {
+        Ok(self.client.fee_history(count, newest, percentiles).await?)
+    }
+    
+    pub async fn get_block_timestamp(&self, block_number: u64) -> Result<Option<u64>> {
+        Ok(self.get_block(block_number).await?.map(|block| block.timestamp.as_u64()))
     }
     
     async fn fetch_blocks(&self, start: u64, end: u64) -> Result<Vec<Block<H256>>> {
@@ -172,32 +261,12 @@ impl EthereumService {
         Ok(blocks)
     }
     
-    async fn get_block(&self, block_number: u64) -> Result<Option<Block<H256>>> {
-        match self.primary.get_block(block_number).await {
-            Ok(block) => Ok(block),
-            Err(_) if self.fallback.is_some() => {
-                tracing::warn!("Primary RPC failed, trying fallback");
-                self.fallback.as_ref().unwrap()
-                    .get_block(block_number)
-                    .await
-                    .map_err(Into::into)
-            }
-            Err(e) => Err(e.into()),
-        }
+    pub async fn get_block(&self, block_number: u64) -> Result<Option<Block<H256>>> {
+        Ok(self.client.block(block_number).await?)
     }
     
-    async fn get_block_number(&self) -> Result<u64> {
-        match self.primary.get_block_number().await {
-            Ok(num) => Ok(num.as_u64()),
-            Err(_) if self.fallback.is_some() => {
-                self.fallback.as_ref().unwrap()
-                    .get_block_number()
-                    .await
-                    .map(|n| n.as_u64())
-                    .map_err(Into::into)
-            }
-            Err(e) => Err(e.into()),
-        }
+    pub async fn get_block_number(&self) -> Result<u64> {
+        Ok(self.client.block_number().await?)
     }
 }
 
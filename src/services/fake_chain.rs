@@ -0,0 +1,367 @@
+use crate::services::chain_client::{
+    BlockSource, ChainClient, ContractCaller, LogSource, StateSource, TransactionSource,
+};
+use async_trait::async_trait;
+use ethers::{
+    abi::AbiEncode,
+    contract::EthCall,
+    providers::{HttpClientError, JsonRpcError, ProviderError},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Block, Bytes, FeeHistory, Filter, Log,
+        Transaction, TransactionReceipt, ValueOrArray, H256, U256, U64,
+    },
+};
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Timestamp of block 0 on a new fake chain
+const GENESIS_TIMESTAMP: u64 = 1_700_000_000;
+
+#[derive(Debug, Clone)]
+enum ScriptedCall {
+    Return(Bytes),
+    Revert(String),
+}
+
+#[derive(Debug, Clone)]
+struct FakeBlock {
+    block: Block<H256>,
+    /// Paid by every percentile in `eth_feeHistory`
+    priority_fee: U256,
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    blocks: Vec<FakeBlock>,
+    block_time: u64,
+    transactions: HashMap<H256, Transaction>,
+    receipts: HashMap<H256, TransactionReceipt>,
+    /// Returned by `eth_getLogs`, in the order added
+    logs: Vec<Log>,
+    /// Keyed by target and full calldata, or by target and selector
+    exact_calls: HashMap<(Address, Bytes), ScriptedCall>,
+    selector_calls: HashMap<(Address, [u8; 4]), ScriptedCall>,
+    gas_estimate: Option<ScriptedCall>,
+    /// Balance and code per account; state is the same at every block
+    accounts: HashMap<Address, (U256, Bytes)>,
+    /// Balances that differ from `accounts` at one block
+    balances_at: HashMap<(Address, u64), U256>,
+    /// Author of the blocks mined from now on
+    fee_recipient: Option<Address>,
+    storage: HashMap<(Address, H256), H256>,
+    offline: bool,
+}
+
+/// In-memory chain for running the services offline. Blocks, transactions,
+/// receipts (with their logs) and contract call results are scripted up front;
+/// unscripted calls revert like a contract without that function would. Calls
+/// to an address with no code and nothing scripted return empty output, as
+/// calls to an account without code do.
+#[derive(Debug)]
+pub struct FakeChain {
+    state: RwLock<FakeState>,
+}
+
+impl Default for FakeChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeChain {
+    pub fn new() -> Self {
+        Self {
+            state: RwLock::new(FakeState {
+                block_time: 12,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Seconds between mined blocks (default 12)
+    pub fn set_block_time(&self, seconds: u64) {
+        self.state.write().unwrap().block_time = seconds;
+    }
+
+    /// Appends a block and returns its number
+    pub fn mine(&self, base_fee_wei: U256, gas_used: u64, gas_limit: u64, priority_fee_wei: U256) -> u64 {
+        let mut state = self.state.write().unwrap();
+        let number = state.blocks.len() as u64;
+        let timestamp = GENESIS_TIMESTAMP + number * state.block_time;
+
+        let block = Block {
+            hash: Some(H256::from_low_u64_be(number + 1)),
+            number: Some(U64::from(number)),
+            timestamp: timestamp.into(),
+            base_fee_per_gas: Some(base_fee_wei),
+            gas_used: gas_used.into(),
+            gas_limit: gas_limit.into(),
+            author: state.fee_recipient,
+            ..Default::default()
+        };
+
+        state.blocks.push(FakeBlock {
+            block,
+            priority_fee: priority_fee_wei,
+        });
+        number
+    }
+
+    /// Adds a mined transaction with its receipt. It is listed in its block
+    /// when `block_number` names a mined one.
+    pub fn add_transaction(&self, tx: Transaction, receipt: TransactionReceipt) {
+        let mut state = self.state.write().unwrap();
+        if let Some(block) = tx.block_number.and_then(|number| state.blocks.get_mut(number.as_usize())) {
+            block.block.transactions.push(tx.hash);
+        }
+        state.receipts.insert(tx.hash, receipt);
+        state.transactions.insert(tx.hash, tx);
+    }
+
+    /// Adds an event log; `block_number` must be set for range filters to match it
+    pub fn add_log(&self, log: Log) {
+        self.state.write().unwrap().logs.push(log);
+    }
+
+    /// Answers every call of `C` on `to` with `output`
+    pub fn on_call<C: EthCall>(&self, to: Address, output: impl AbiEncode) {
+        self.state
+            .write()
+            .unwrap()
+            .selector_calls
+            .insert((to, C::selector()), ScriptedCall::Return(output.encode().into()));
+    }
+
+    /// Answers this exact call (function and arguments) on `to` with `output`
+    pub fn on_exact_call<C: EthCall>(&self, to: Address, call: C, output: impl AbiEncode) {
+        self.state
+            .write()
+            .unwrap()
+            .exact_calls
+            .insert((to, call.encode().into()), ScriptedCall::Return(output.encode().into()));
+    }
+
+    /// Makes every call of `C` on `to` revert
+    pub fn revert_call<C: EthCall>(&self, to: Address, reason: &str) {
+        self.state
+            .write()
+            .unwrap()
+            .selector_calls
+            .insert((to, C::selector()), ScriptedCall::Revert(reason.to_string()));
+    }
+
+    /// Result of `eth_estimateGas` for any transaction (default 21000)
+    pub fn set_gas_estimate(&self, gas: u64) {
+        self.state.write().unwrap().gas_estimate = Some(ScriptedCall::Return(U256::from(gas).encode().into()));
+    }
+
+    pub fn revert_gas_estimate(&self, reason: &str) {
+        self.state.write().unwrap().gas_estimate = Some(ScriptedCall::Revert(reason.to_string()));
+    }
+
+    /// Balance and code of `address` at every block
+    pub fn set_account(&self, address: Address, balance: U256, code: Bytes) {
+        self.state.write().unwrap().accounts.insert(address, (balance, code));
+    }
+
+    /// Balance of `address` at `block` only, over the one from `set_account`
+    pub fn set_balance_at(&self, address: Address, block: u64, balance: U256) {
+        self.state.write().unwrap().balances_at.insert((address, block), balance);
+    }
+
+    /// Fee recipient (`miner`) of the blocks mined after this
+    pub fn set_fee_recipient(&self, address: Address) {
+        self.state.write().unwrap().fee_recipient = Some(address);
+    }
+
+    pub fn set_storage(&self, address: Address, slot: H256, value: H256) {
+        self.state.write().unwrap().storage.insert((address, slot), value);
+    }
+
+    /// While offline every request fails with a transport error
+    pub fn set_offline(&self, offline: bool) {
+        self.state.write().unwrap().offline = offline;
+    }
+
+    fn read(&self) -> Result<std::sync::RwLockReadGuard<'_, FakeState>, ProviderError> {
+        let state = self.state.read().unwrap();
+        if state.offline {
+            return Err(ProviderError::CustomError("fake chain is offline".to_string()));
+        }
+        Ok(state)
+    }
+}
+
+impl FakeState {
+    fn is_empty_account(&self, address: Address) -> bool {
+        !self.accounts.get(&address).is_some_and(|(_, code)| !code.is_empty())
+            && !self.selector_calls.keys().any(|(to, _)| *to == address)
+            && !self.exact_calls.keys().any(|(to, _)| *to == address)
+    }
+}
+
+fn revert(reason: &str) -> ProviderError {
+    ProviderError::JsonRpcClientError(Box::new(HttpClientError::JsonRpcError(JsonRpcError {
+        code: 3,
+        message: format!("execution reverted: {}", reason),
+        data: None,
+    })))
+}
+
+fn scripted_output(call: &ScriptedCall) -> Result<Bytes, ProviderError> {
+    match call {
+        ScriptedCall::Return(output) => Ok(output.clone()),
+        ScriptedCall::Revert(reason) => Err(revert(reason)),
+    }
+}
+
+#[async_trait]
+impl BlockSource for FakeChain {
+    async fn block_number(&self) -> Result<u64, ProviderError> {
+        let state = self.read()?;
+        Ok(state.blocks.len().saturating_sub(1) as u64)
+    }
+
+    async fn block(&self, number: u64) -> Result<Option<Block<H256>>, ProviderError> {
+        Ok(self.read()?.blocks.get(number as usize).map(|b| b.block.clone()))
+    }
+
+    async fn block_with_transactions(&self, number: u64) -> Result<Option<Block<Transaction>>, ProviderError> {
+        let state = self.read()?;
+        Ok(state.blocks.get(number as usize).map(|b| {
+            let transactions = b
+                .block
+                .transactions
+                .iter()
+                .filter_map(|hash| state.transactions.get(hash).cloned())
+                .collect();
+            b.block.clone().into_full_block(transactions)
+        }))
+    }
+
+    async fn fee_history(
+        &self,
+        count: u64,
+        newest: u64,
+        percentiles: &[f64],
+    ) -> Result<FeeHistory, ProviderError> {
+        let state = self.read()?;
+        let newest = newest.min(state.blocks.len().saturating_sub(1) as u64);
+        let oldest = (newest + 1).saturating_sub(count);
+        let blocks = state.blocks.get(oldest as usize..=newest as usize).unwrap_or_default();
+
+        let mut base_fee_per_gas: Vec<U256> = blocks
+            .iter()
+            .map(|b| b.block.base_fee_per_gas.unwrap_or_default())
+            .collect();
+        // The node also reports the base fee of the block after `newest`
+        base_fee_per_gas.push(base_fee_per_gas.last().copied().unwrap_or_default());
+
+        Ok(FeeHistory {
+            oldest_block: oldest.into(),
+            base_fee_per_gas,
+            gas_used_ratio: blocks
+                .iter()
+                .map(|b| b.block.gas_used.as_u64() as f64 / b.block.gas_limit.as_u64().max(1) as f64)
+                .collect(),
+            reward: blocks
+                .iter()
+                .map(|b| vec![b.priority_fee; percentiles.len()])
+                .collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl TransactionSource for FakeChain {
+    async fn transaction(&self, hash: H256) -> Result<Option<Transaction>, ProviderError> {
+        Ok(self.read()?.transactions.get(&hash).cloned())
+    }
+
+    async fn receipt(&self, hash: H256) -> Result<Option<TransactionReceipt>, ProviderError> {
+        Ok(self.read()?.receipts.get(&hash).cloned())
+    }
+
+    async fn block_receipts(&self, number: u64) -> Result<Vec<TransactionReceipt>, ProviderError> {
+        let state = self.read()?;
+        let hashes = state.blocks.get(number as usize).map(|b| &b.block.transactions[..]).unwrap_or_default();
+        Ok(hashes.iter().filter_map(|hash| state.receipts.get(hash).cloned()).collect())
+    }
+}
+
+/// Matches on address and block range only; topics are not filtered
+#[async_trait]
+impl LogSource for FakeChain {
+    async fn logs(&self, filter: &Filter) -> Result<Vec<Log>, ProviderError> {
+        let state = self.read()?;
+        let from = filter.get_from_block().unwrap_or_default();
+        let to = filter.get_to_block().unwrap_or(U64::MAX);
+
+        Ok(state
+            .logs
+            .iter()
+            .filter(|log| {
+                let address_matches = match &filter.address {
+                    Some(ValueOrArray::Value(address)) => log.address == *address,
+                    Some(ValueOrArray::Array(addresses)) => addresses.contains(&log.address),
+                    None => true,
+                };
+                let block = log.block_number.unwrap_or_default();
+                address_matches && block >= from && block <= to
+            })
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl ContractCaller for FakeChain {
+    async fn call(&self, tx: &TypedTransaction) -> Result<Bytes, ProviderError> {
+        let state = self.read()?;
+        let to = tx.to().and_then(|to| to.as_address().copied()).unwrap_or_default();
+        let data = tx.data().cloned().unwrap_or_default();
+
+        if let Some(call) = state.exact_calls.get(&(to, data.clone())) {
+            return scripted_output(call);
+        }
+
+        let selector: Option<[u8; 4]> = data.get(..4).and_then(|s| s.try_into().ok());
+        match selector.and_then(|selector| state.selector_calls.get(&(to, selector))) {
+            Some(call) => scripted_output(call),
+            None if state.is_empty_account(to) => Ok(Bytes::new()),
+            None => Err(revert("no scripted result")),
+        }
+    }
+
+    async fn estimate_gas(&self, _tx: &TypedTransaction) -> Result<U256, ProviderError> {
+        match &self.read()?.gas_estimate {
+            Some(call) => scripted_output(call).map(|output| U256::from_big_endian(&output)),
+            None => Ok(U256::from(21_000)),
+        }
+    }
+}
+
+#[async_trait]
+impl StateSource for FakeChain {
+    async fn balance(&self, address: Address, block: u64) -> Result<U256, ProviderError> {
+        let state = self.read()?;
+        if let Some(balance) = state.balances_at.get(&(address, block)) {
+            return Ok(*balance);
+        }
+        Ok(state.accounts.get(&address).map(|(balance, _)| *balance).unwrap_or_default())
+    }
+
+    async fn nonce(&self, _address: Address, _block: u64) -> Result<U256, ProviderError> {
+        self.read().map(|_| U256::zero())
+    }
+
+    async fn code(&self, address: Address, _block: u64) -> Result<Bytes, ProviderError> {
+        Ok(self.read()?.accounts.get(&address).map(|(_, code)| code.clone()).unwrap_or_default())
+    }
+
+    async fn storage(&self, address: Address, slot: H256, _block: u64) -> Result<H256, ProviderError> {
+        Ok(self.read()?.storage.get(&(address, slot)).copied().unwrap_or_default())
+    }
+}
+
+impl ChainClient for FakeChain {}
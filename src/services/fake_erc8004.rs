@@ -0,0 +1,126 @@
+use crate::contracts::identity_registry::{
+    BalanceOfCall, BalanceOfReturn, OwnerOfCall, OwnerOfReturn, TokenURICall, TokenURIReturn, TransferFilter,
+};
+use crate::contracts::reputation_registry::{
+    GetSummaryCall as FeedbackSummaryCall, GetSummaryReturn as FeedbackSummaryReturn,
+};
+use crate::contracts::validation_registry::{
+    GetSummaryCall as ValidationSummaryCall, GetSummaryReturn as ValidationSummaryReturn,
+};
+use crate::services::{erc8004::Erc8004Deployment, FakeChain};
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Log, H256, U256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub const FAKE_IDENTITY_REGISTRY: Address = Address::repeat_byte(0x84);
+pub const FAKE_REPUTATION_REGISTRY: Address = Address::repeat_byte(0x85);
+pub const FAKE_VALIDATION_REGISTRY: Address = Address::repeat_byte(0x86);
+
+/// The three ERC-8004 registries scripted on a `FakeChain`. Registering mines
+/// a block with the mint's `Transfer` log; agents start with no feedback and
+/// no validations.
+pub struct FakeErc8004 {
+    chain: Arc<FakeChain>,
+    network: String,
+    next_id: AtomicU64,
+    balances: Mutex<HashMap<Address, u64>>,
+}
+
+impl FakeErc8004 {
+    pub fn new(chain: Arc<FakeChain>, network: &str) -> Self {
+        // An empty chain already reports block 0, so registrations start at block 1
+        chain.mine(U256::zero(), 0, 30_000_000, U256::zero());
+        chain.on_call::<BalanceOfCall>(FAKE_IDENTITY_REGISTRY, BalanceOfReturn(U256::zero()));
+        chain.on_call::<FeedbackSummaryCall>(
+            FAKE_REPUTATION_REGISTRY,
+            FeedbackSummaryReturn { count: 0, average_score: 0 },
+        );
+        chain.on_call::<ValidationSummaryCall>(
+            FAKE_VALIDATION_REGISTRY,
+            ValidationSummaryReturn { count: 0, avg_response: 0 },
+        );
+
+        Self {
+            chain,
+            network: network.to_string(),
+            next_id: AtomicU64::new(1),
+            balances: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn chain(&self) -> Arc<FakeChain> {
+        self.chain.clone()
+    }
+
+    pub fn deployment(&self) -> Erc8004Deployment {
+        Erc8004Deployment {
+            network: self.network.clone(),
+            identity: FAKE_IDENTITY_REGISTRY,
+            reputation: FAKE_REPUTATION_REGISTRY,
+            validation: Some(FAKE_VALIDATION_REGISTRY),
+            from_block: 0,
+        }
+    }
+
+    /// Mints the next agent id to `owner` with `token_uri` and returns it
+    pub fn register(&self, owner: Address, token_uri: &str) -> U256 {
+        let agent_id = U256::from(self.next_id.fetch_add(1, Ordering::SeqCst));
+        let balance = {
+            let mut balances = self.balances.lock().unwrap();
+            let balance = balances.entry(owner).or_default();
+            *balance += 1;
+            *balance
+        };
+
+        let block = self.chain.mine(U256::zero(), 0, 30_000_000, U256::zero());
+        let mut id_topic = [0u8; 32];
+        agent_id.to_big_endian(&mut id_topic);
+        self.chain.add_log(Log {
+            address: FAKE_IDENTITY_REGISTRY,
+            topics: vec![
+                TransferFilter::signature(),
+                H256::from(Address::zero()),
+                H256::from(owner),
+                H256(id_topic),
+            ],
+            block_number: Some(block.into()),
+            ..Default::default()
+        });
+
+        self.chain
+            .on_exact_call(FAKE_IDENTITY_REGISTRY, BalanceOfCall { owner }, BalanceOfReturn(balance.into()));
+        self.chain
+            .on_exact_call(FAKE_IDENTITY_REGISTRY, OwnerOfCall { agent_id }, OwnerOfReturn(owner));
+        self.chain.on_exact_call(
+            FAKE_IDENTITY_REGISTRY,
+            TokenURICall { agent_id },
+            TokenURIReturn(token_uri.to_string()),
+        );
+        agent_id
+    }
+
+    /// Feedback summary of `agent_id` across all clients and tags
+    pub fn give_feedback(&self, agent_id: U256, count: u64, average_score: u8) {
+        let call = FeedbackSummaryCall {
+            agent_id,
+            client_addresses: Vec::new(),
+            tag_1: [0; 32],
+            tag_2: [0; 32],
+        };
+        self.chain
+            .on_exact_call(FAKE_REPUTATION_REGISTRY, call, FeedbackSummaryReturn { count, average_score });
+    }
+
+    /// Validation summary of `agent_id` across all validators and tags
+    pub fn validate(&self, agent_id: U256, count: u64, avg_response: u8) {
+        let call = ValidationSummaryCall {
+            agent_id,
+            validator_addresses: Vec::new(),
+            tag: [0; 32],
+        };
+        self.chain
+            .on_exact_call(FAKE_VALIDATION_REGISTRY, call, ValidationSummaryReturn { count, avg_response });
+    }
+}
@@ -0,0 +1,146 @@
+use crate::services::chain_client::ChainClient;
+use ethers::providers::ProviderError;
+use ethers::types::{H160, H256};
+use revm::db::DatabaseRef;
+use revm::primitives::{AccountInfo, Address, Bytecode, Bytes, B256, U256};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::runtime::Handle;
+
+#[derive(Default)]
+struct ForkCache {
+    accounts: HashMap<Address, AccountInfo>,
+    storage: HashMap<(Address, U256), U256>,
+    block_hashes: HashMap<U256, B256>,
+}
+
+struct ForkState {
+    client: Arc<dyn ChainClient>,
+    block: u64,
+    runtime: Handle,
+    cache: RwLock<ForkCache>,
+}
+
+/// Chain state as of one block for REVM. Accounts and storage slots are
+/// fetched from the chain the first time they are read and cached; clones
+/// share the cache, so later simulations against the same block are local.
+///
+/// Reads block on the runtime, so execute against it from a blocking thread
+/// (`tokio::task::spawn_blocking`), not from async code.
+#[derive(Clone)]
+pub struct ForkDb {
+    state: Arc<ForkState>,
+}
+
+impl ForkDb {
+    /// Must be called from within the Tokio runtime
+    pub fn new(client: Arc<dyn ChainClient>, block: u64) -> Self {
+        Self {
+            state: Arc::new(ForkState {
+                client,
+                block,
+                runtime: Handle::current(),
+                cache: RwLock::new(ForkCache::default()),
+            }),
+        }
+    }
+
+    pub fn block(&self) -> u64 {
+        self.state.block
+    }
+
+    fn fetch_account(&self, address: Address) -> Result<AccountInfo, ProviderError> {
+        let client = &self.state.client;
+        let (address_h160, block) = (from_revm_address(address), self.state.block);
+        let (balance, nonce, code) = self.state.runtime.block_on(async {
+            tokio::try_join!(
+                client.balance(address_h160, block),
+                client.nonce(address_h160, block),
+                client.code(address_h160, block),
+            )
+        })?;
+
+        let bytecode = Bytecode::new_raw(Bytes::from(code.to_vec()));
+        Ok(AccountInfo::new(
+            to_revm_u256(balance),
+            nonce.as_u64(),
+            bytecode.hash_slow(),
+            bytecode,
+        ))
+    }
+}
+
+impl DatabaseRef for ForkDb {
+    type Error = ProviderError;
+
+    fn basic(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        if let Some(info) = self.state.cache.read().unwrap().accounts.get(&address) {
+            return Ok(Some(info.clone()));
+        }
+
+        let info = self.fetch_account(address)?;
+        self.state.cache.write().unwrap().accounts.insert(address, info.clone());
+        Ok(Some(info))
+    }
+
+    fn code_by_hash(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // `basic` always returns the code with the account, so REVM only asks
+        // for code it has already seen
+        let cache = self.state.cache.read().unwrap();
+        Ok(cache
+            .accounts
+            .values()
+            .find(|info| info.code_hash == code_hash)
+            .and_then(|info| info.code.clone())
+            .unwrap_or_default())
+    }
+
+    fn storage(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        if let Some(value) = self.state.cache.read().unwrap().storage.get(&(address, index)) {
+            return Ok(*value);
+        }
+
+        let slot = H256::from(index.to_be_bytes::<32>());
+        let value = self.state.runtime.block_on(self.state.client.storage(
+            from_revm_address(address),
+            slot,
+            self.state.block,
+        ))?;
+        let value = U256::from_be_bytes(value.0);
+        self.state.cache.write().unwrap().storage.insert((address, index), value);
+        Ok(value)
+    }
+
+    fn block_hash(&self, number: U256) -> Result<B256, Self::Error> {
+        if let Some(hash) = self.state.cache.read().unwrap().block_hashes.get(&number) {
+            return Ok(*hash);
+        }
+
+        let block = self
+            .state
+            .runtime
+            .block_on(self.state.client.block(number.saturating_to::<u64>()))?;
+        let hash = block
+            .and_then(|block| block.hash)
+            .map(|hash| B256::from(hash.0))
+            .unwrap_or_default();
+        self.state.cache.write().unwrap().block_hashes.insert(number, hash);
+        Ok(hash)
+    }
+}
+
+pub fn to_revm_address(address: H160) -> Address {
+    Address::from(address.0)
+}
+
+pub fn from_revm_address(address: Address) -> H160 {
+    H160(address.into_array())
+}
+
+pub fn to_revm_u256(value: ethers::types::U256) -> U256 {
+    U256::from_limbs(value.0)
+}
+
+pub fn from_revm_u256(value: U256) -> ethers::types::U256 {
+    ethers::types::U256(value.into_limbs())
+}
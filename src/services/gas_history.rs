@@ -0,0 +1,311 @@
+use crate::{
+    models::{BlockFeeRecord, FeeStats, GasHeatmap, GasHistory, HeatmapCell, HistoryBucket},
+    services::{gas_predictor::percentile, CacheService},
+};
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
+use ethers::types::FeeHistory;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Priority fee percentiles requested from `eth_feeHistory`
+pub const PRIORITY_PERCENTILES: [f64; 3] = [10.0, 50.0, 90.0];
+
+const HOUR_SECS: u64 = 3600;
+
+#[derive(Default)]
+struct ChainHistory {
+    /// Ordered by block number
+    records: VecDeque<BlockFeeRecord>,
+    /// Hours (timestamp / 3600) with records not yet persisted
+    dirty_hours: BTreeSet<u64>,
+}
+
+/// Per-block fee history. Recent blocks are kept in memory; everything is
+/// persisted through `CacheService` in one key per chain and hour, so older
+/// ranges are still served after the in-memory window has moved on.
+pub struct GasHistoryStore {
+    cache: Arc<CacheService>,
+    max_blocks: usize,
+    retention_secs: u64,
+    chains: RwLock<HashMap<String, ChainHistory>>,
+}
+
+impl GasHistoryStore {
+    pub fn new(cache: Arc<CacheService>, max_blocks: usize, retention_days: u64) -> Self {
+        Self {
+            cache,
+            max_blocks,
+            retention_secs: retention_days * 86400,
+            chains: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn retention_secs(&self) -> u64 {
+        self.retention_secs
+    }
+
+    pub async fn record(&self, chain: &str, records: Vec<BlockFeeRecord>) {
+        if records.is_empty() {
+            return;
+        }
+
+        let mut chains = self.chains.write().await;
+        let history = chains.entry(chain.to_string()).or_default();
+
+        for record in records {
+            history.dirty_hours.insert(record.timestamp / HOUR_SECS);
+
+            // Blocks usually arrive in order; backfills and reorgs take the slow path
+            match history.records.back() {
+                Some(last) if record.number <= last.number => {
+                    match history.records.binary_search_by_key(&record.number, |r| r.number) {
+                        Ok(i) => history.records[i] = record,
+                        Err(i) => history.records.insert(i, record),
+                    }
+                }
+                _ => history.records.push_back(record),
+            }
+        }
+
+        let excess = history.records.len().saturating_sub(self.max_blocks);
+        history.records.drain(..excess);
+    }
+
+    /// Persists every hour bucket touched since the last flush. Records are
+    /// merged into what is already stored, so buckets written before a restart
+    /// (or by blocks since dropped from memory) keep their records.
+    pub async fn flush(&self) {
+        let mut buckets = Vec::new();
+
+        {
+            let mut chains = self.chains.write().await;
+            for (chain, history) in chains.iter_mut() {
+                for hour in std::mem::take(&mut history.dirty_hours) {
+                    let records: Vec<BlockFeeRecord> = history
+                        .records
+                        .iter()
+                        .filter(|r| r.timestamp / HOUR_SECS == hour)
+                        .cloned()
+                        .collect();
+                    buckets.push((Self::hour_key(chain, hour), records));
+                }
+            }
+        }
+
+        for (key, records) in &buckets {
+            let mut merged: Vec<BlockFeeRecord> = self.cache.get(key).await.ok().flatten().unwrap_or_default();
+            merged.retain(|stored| !records.iter().any(|r| r.number == stored.number));
+            merged.extend(records.iter().cloned());
+            merged.sort_by_key(|r| r.number);
+
+            if let Err(e) = self.cache.set(key, &merged, self.retention_secs).await {
+                tracing::warn!("Failed to persist gas history bucket {}: {}", key, e);
+            }
+        }
+
+        if !buckets.is_empty() {
+            tracing::debug!("Persisted {} gas history buckets", buckets.len());
+        }
+    }
+
+    pub async fn run_flusher(&self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            self.flush().await;
+        }
+    }
+
+    /// Records with `from <= timestamp < to`, ordered by block number
+    pub async fn records_between(&self, chain: &str, from: u64, to: u64) -> Vec<BlockFeeRecord> {
+        let (mut records, oldest_in_memory) = {
+            let chains = self.chains.read().await;
+            match chains.get(chain) {
+                Some(history) => (
+                    history
+                        .records
+                        .iter()
+                        .filter(|r| r.timestamp >= from && r.timestamp < to)
+                        .cloned()
+                        .collect::<Vec<_>>(),
+                    history.records.front().map(|r| r.timestamp),
+                ),
+                None => (Vec::new(), None),
+            }
+        };
+
+        // Anything older than the in-memory window comes from the persisted hour buckets
+        let persisted_until = oldest_in_memory.unwrap_or(to).min(to);
+        if from < persisted_until {
+            let mut persisted = Vec::new();
+            for hour in (from / HOUR_SECS)..=(persisted_until.saturating_sub(1) / HOUR_SECS) {
+                let bucket: Vec<BlockFeeRecord> = self
+                    .cache
+                    .get(&Self::hour_key(chain, hour))
+                    .await
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default();
+                persisted.extend(
+                    bucket
+                        .into_iter()
+                        .filter(|r| r.timestamp >= from && r.timestamp < persisted_until),
+                );
+            }
+
+            persisted.append(&mut records);
+            records = persisted;
+            records.sort_by_key(|r| r.number);
+            records.dedup_by_key(|r| r.number);
+        }
+
+        records
+    }
+
+    /// Estimated timestamp of `block`, extrapolated from the nearest known block
+    pub async fn estimate_timestamp(&self, chain: &str, block: u64, block_time_secs: f64) -> Option<u64> {
+        let chains = self.chains.read().await;
+        let records = &chains.get(chain)?.records;
+
+        let anchor = match records.binary_search_by_key(&block, |r| r.number) {
+            Ok(i) => return Some(records[i].timestamp),
+            Err(0) => records.front()?,
+            Err(_) => records.back()?,
+        };
+
+        let offset = (block as f64 - anchor.number as f64) * block_time_secs;
+        Some((anchor.timestamp as f64 + offset).max(0.0) as u64)
+    }
+
+    fn hour_key(chain: &str, hour: u64) -> String {
+        format!("gas:fee_history:{}:{}", chain, hour)
+    }
+}
+
+/// Builds history records from an `eth_feeHistory` response. `timestamp_of`
+/// maps a block number to its timestamp.
+pub fn fee_records(history: &FeeHistory, timestamp_of: impl Fn(u64) -> u64) -> Vec<BlockFeeRecord> {
+    let oldest = history.oldest_block.as_u64();
+    let gwei = |wei: Option<&ethers::types::U256>| wei.map(|w| w.as_u128() as f64 / 1e9).unwrap_or(0.0);
+
+    // base_fee_per_gas has one extra entry for the block after the newest
+    history
+        .gas_used_ratio
+        .iter()
+        .enumerate()
+        .map(|(i, gas_used_ratio)| {
+            let number = oldest + i as u64;
+            let rewards = history.reward.get(i);
+            let reward = |p: usize| gwei(rewards.and_then(|r| r.get(p)));
+
+            BlockFeeRecord {
+                number,
+                timestamp: timestamp_of(number),
+                base_fee_gwei: gwei(history.base_fee_per_gas.get(i)),
+                gas_used_ratio: *gas_used_ratio,
+                priority_fee_p10_gwei: reward(0),
+                priority_fee_p50_gwei: reward(1),
+                priority_fee_p90_gwei: reward(2),
+            }
+        })
+        .collect()
+}
+
+fn fee_stats(values: &[f64]) -> Option<FeeStats> {
+    Some(FeeStats {
+        min: values.iter().copied().reduce(f64::min)?,
+        median: percentile(values, 0.5)?,
+        p90: percentile(values, 0.9)?,
+        max: values.iter().copied().reduce(f64::max)?,
+    })
+}
+
+fn to_datetime(timestamp: u64) -> DateTime<Utc> {
+    Utc.timestamp_opt(timestamp as i64, 0).single().unwrap_or_default()
+}
+
+/// Time-bucketed min/median/p90/max over `[from, to)`; empty buckets are omitted
+pub fn aggregate(
+    chain: &str,
+    records: &[BlockFeeRecord],
+    from: u64,
+    to: u64,
+    bucket_secs: u64,
+) -> GasHistory {
+    let mut grouped: BTreeMap<u64, Vec<&BlockFeeRecord>> = BTreeMap::new();
+    for record in records {
+        grouped
+            .entry(record.timestamp.saturating_sub(from) / bucket_secs)
+            .or_default()
+            .push(record);
+    }
+
+    let buckets = grouped
+        .into_iter()
+        .filter_map(|(index, records)| {
+            let base_fees: Vec<f64> = records.iter().map(|r| r.base_fee_gwei).collect();
+            let priority_fees: Vec<f64> = records.iter().map(|r| r.priority_fee_p50_gwei).collect();
+            let start = from + index * bucket_secs;
+
+            Some(HistoryBucket {
+                start: to_datetime(start),
+                end: to_datetime((start + bucket_secs).min(to)),
+                blocks: records.len(),
+                first_block: records.first()?.number,
+                last_block: records.last()?.number,
+                base_fee_gwei: fee_stats(&base_fees)?,
+                priority_fee_gwei: fee_stats(&priority_fees)?,
+                avg_gas_used_ratio: records.iter().map(|r| r.gas_used_ratio).sum::<f64>()
+                    / records.len() as f64,
+            })
+        })
+        .collect();
+
+    GasHistory {
+        chain: chain.to_string(),
+        from: to_datetime(from),
+        to: to_datetime(to),
+        bucket_seconds: bucket_secs,
+        blocks: records.len(),
+        buckets,
+    }
+}
+
+/// Median and p90 base fee per UTC hour of the week
+pub fn heatmap(chain: &str, records: &[BlockFeeRecord], from: u64, to: u64) -> GasHeatmap {
+    let mut grouped: BTreeMap<(u32, u32), Vec<f64>> = BTreeMap::new();
+    for record in records {
+        let time = to_datetime(record.timestamp);
+        grouped
+            .entry((time.weekday().num_days_from_monday(), time.hour()))
+            .or_default()
+            .push(record.base_fee_gwei);
+    }
+
+    let cells: Vec<HeatmapCell> = grouped
+        .into_iter()
+        .filter_map(|((day_of_week, hour), base_fees)| {
+            Some(HeatmapCell {
+                day_of_week,
+                hour,
+                samples: base_fees.len(),
+                median_base_fee_gwei: percentile(&base_fees, 0.5)?,
+                p90_base_fee_gwei: percentile(&base_fees, 0.9)?,
+            })
+        })
+        .collect();
+
+    let mut cheapest = cells.clone();
+    cheapest.sort_by(|a, b| a.median_base_fee_gwei.total_cmp(&b.median_base_fee_gwei));
+    cheapest.truncate(5);
+
+    GasHeatmap {
+        chain: chain.to_string(),
+        from: to_datetime(from),
+        to: to_datetime(to),
+        cells,
+        cheapest,
+    }
+}
@@ -0,0 +1,316 @@
+use crate::models::GasModel;
+use anyhow::{bail, Result};
+use ethers::types::Block;
+use serde::{Deserialize, Serialize};
+
+/// Standard priority fee (2 gwei is typical)
+pub const DEFAULT_PRIORITY_FEE_GWEI: f64 = 2.0;
+
+/// Lowest priority fee advertised when it is taken from observed inclusions
+pub const MIN_PRIORITY_FEE_GWEI: f64 = 0.1;
+
+/// Fee data for a single block - the common input to every gas model
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BlockSample {
+    pub number: u64,
+    #[serde(default)]
+    pub timestamp: u64,
+    pub base_fee_gwei: f64,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+}
+
+impl BlockSample {
+    /// Returns `None` for pre-London blocks and pending blocks without a number
+    pub fn from_block<TX>(block: &Block<TX>) -> Option<Self> {
+        Some(Self {
+            number: block.number?.as_u64(),
+            timestamp: block.timestamp.as_u64(),
+            base_fee_gwei: block.base_fee_per_gas?.as_u128() as f64 / 1e9,
+            gas_used: block.gas_used.as_u64(),
+            gas_limit: block.gas_limit.as_u64(),
+        })
+    }
+
+    pub fn gas_used_ratio(&self) -> f64 {
+        if self.gas_limit == 0 {
+            return 0.0;
+        }
+        self.gas_used as f64 / self.gas_limit as f64
+    }
+}
+
+/// Fee estimate for the next block produced by a [`GasPredictor`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeEstimate {
+    pub base_fee_gwei: f64,
+    pub priority_fee_gwei: f64,
+    pub max_fee_gwei: f64,
+    pub confidence: f64, // 0.0-1.0
+}
+
+/// A gas price model. `history` is ordered oldest to newest.
+pub trait GasPredictor: Send + Sync {
+    fn model(&self) -> GasModel;
+    fn predict(&self, history: &[BlockSample]) -> Result<FeeEstimate>;
+}
+
+impl GasModel {
+    /// Predictor with the default tuning for this model
+    pub fn predictor(self) -> Box<dyn GasPredictor> {
+        match self {
+            GasModel::Ewma => Box::new(EwmaPredictor::default()),
+            GasModel::Eip1559 => Box::new(Eip1559Predictor::default()),
+            GasModel::Percentile => Box::new(PercentilePredictor::default()),
+            GasModel::Kalman => Box::new(KalmanPredictor::default()),
+        }
+    }
+}
+
+/// Exponentially weighted average of recent base fees (more recent = higher weight)
+#[derive(Debug, Clone)]
+pub struct EwmaPredictor {
+    pub decay: f64,
+    /// Multiplier applied to the base fee for the max fee safety margin
+    pub buffer: f64,
+    pub priority_fee_gwei: f64,
+}
+
+impl Default for EwmaPredictor {
+    fn default() -> Self {
+        Self {
+            decay: 0.95,
+            buffer: 1.2, // 20% buffer for safety
+            priority_fee_gwei: DEFAULT_PRIORITY_FEE_GWEI,
+        }
+    }
+}
+
+impl EwmaPredictor {
+    fn weights(&self, n: usize) -> Vec<f64> {
+        let weights: Vec<f64> = (0..n)
+            .rev() // Reverse so most recent gets highest weight
+            .map(|i| self.decay.powi(i as i32))
+            .collect();
+
+        let sum: f64 = weights.iter().sum();
+        weights.iter().map(|w| w / sum).collect()
+    }
+}
+
+impl GasPredictor for EwmaPredictor {
+    fn model(&self) -> GasModel {
+        GasModel::Ewma
+    }
+
+    fn predict(&self, history: &[BlockSample]) -> Result<FeeEstimate> {
+        if history.is_empty() {
+            bail!("EWMA model needs at least one block");
+        }
+
+        let base_fee_gwei: f64 = history
+            .iter()
+            .zip(self.weights(history.len()))
+            .map(|(sample, weight)| sample.base_fee_gwei * weight)
+            .sum();
+
+        Ok(FeeEstimate {
+            base_fee_gwei,
+            priority_fee_gwei: self.priority_fee_gwei,
+            max_fee_gwei: base_fee_gwei * self.buffer + self.priority_fee_gwei,
+            confidence: variance_confidence(history, base_fee_gwei),
+        })
+    }
+}
+
+/// Applies the EIP-1559 base fee update rule to the latest block, which is exact
+/// for the next block. The max fee covers `headroom_blocks` further full blocks.
+#[derive(Debug, Clone)]
+pub struct Eip1559Predictor {
+    pub headroom_blocks: u32,
+    pub priority_fee_gwei: f64,
+}
+
+impl Default for Eip1559Predictor {
+    fn default() -> Self {
+        Self {
+            headroom_blocks: 2,
+            priority_fee_gwei: DEFAULT_PRIORITY_FEE_GWEI,
+        }
+    }
+}
+
+const BASE_FEE_MAX_CHANGE_DENOMINATOR: f64 = 8.0;
+const ELASTICITY_MULTIPLIER: u64 = 2;
+
+/// Base fee of the block following `parent`, per EIP-1559
+pub fn next_base_fee_gwei(parent: &BlockSample) -> f64 {
+    let target = parent.gas_limit / ELASTICITY_MULTIPLIER;
+    if target == 0 {
+        return parent.base_fee_gwei;
+    }
+
+    let delta = (parent.gas_used as f64 - target as f64) / target as f64;
+    (parent.base_fee_gwei * (1.0 + delta / BASE_FEE_MAX_CHANGE_DENOMINATOR)).max(0.0)
+}
+
+impl GasPredictor for Eip1559Predictor {
+    fn model(&self) -> GasModel {
+        GasModel::Eip1559
+    }
+
+    fn predict(&self, history: &[BlockSample]) -> Result<FeeEstimate> {
+        let Some(latest) = history.last() else {
+            bail!("EIP-1559 model needs at least one block");
+        };
+
+        let base_fee_gwei = next_base_fee_gwei(latest);
+        let max_increase = (1.0 + 1.0 / BASE_FEE_MAX_CHANGE_DENOMINATOR)
+            .powi(self.headroom_blocks as i32);
+
+        Ok(FeeEstimate {
+            base_fee_gwei,
+            priority_fee_gwei: self.priority_fee_gwei,
+            max_fee_gwei: base_fee_gwei * max_increase + self.priority_fee_gwei,
+            confidence: variance_confidence(history, base_fee_gwei),
+        })
+    }
+}
+
+/// Uses a percentile of the observed base fees, so short spikes are ignored
+#[derive(Debug, Clone)]
+pub struct PercentilePredictor {
+    pub percentile: f64, // 0.0-1.0
+    pub buffer: f64,
+    pub priority_fee_gwei: f64,
+}
+
+impl Default for PercentilePredictor {
+    fn default() -> Self {
+        Self {
+            percentile: 0.75,
+            buffer: 1.125, // One full block of base fee growth
+            priority_fee_gwei: DEFAULT_PRIORITY_FEE_GWEI,
+        }
+    }
+}
+
+/// Nearest-rank percentile of `values`; `p` is in 0.0-1.0
+pub fn percentile(values: &[f64], p: f64) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let rank = (p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64).round() as usize;
+    Some(sorted[rank])
+}
+
+impl GasPredictor for PercentilePredictor {
+    fn model(&self) -> GasModel {
+        GasModel::Percentile
+    }
+
+    fn predict(&self, history: &[BlockSample]) -> Result<FeeEstimate> {
+        let base_fees: Vec<f64> = history.iter().map(|s| s.base_fee_gwei).collect();
+        let Some(base_fee_gwei) = percentile(&base_fees, self.percentile) else {
+            bail!("Percentile model needs at least one block");
+        };
+
+        Ok(FeeEstimate {
+            base_fee_gwei,
+            priority_fee_gwei: self.priority_fee_gwei,
+            max_fee_gwei: base_fee_gwei * self.buffer + self.priority_fee_gwei,
+            confidence: variance_confidence(history, base_fee_gwei),
+        })
+    }
+}
+
+/// One-dimensional Kalman filter over the log base fee, modelled as a random walk.
+/// Working in log space makes the noise terms relative, so the same tuning holds
+/// at 5 gwei and at 500 gwei.
+#[derive(Debug, Clone)]
+pub struct KalmanPredictor {
+    /// Variance of the per-block log base fee change
+    pub process_noise: f64,
+    /// Variance of a single block's deviation from the underlying level
+    pub measurement_noise: f64,
+    /// Standard deviations above the predicted level used for the max fee
+    pub max_fee_sigmas: f64,
+    pub priority_fee_gwei: f64,
+}
+
+impl Default for KalmanPredictor {
+    fn default() -> Self {
+        Self {
+            process_noise: 0.004,
+            measurement_noise: 0.002,
+            max_fee_sigmas: 2.0,
+            priority_fee_gwei: DEFAULT_PRIORITY_FEE_GWEI,
+        }
+    }
+}
+
+impl GasPredictor for KalmanPredictor {
+    fn model(&self) -> GasModel {
+        GasModel::Kalman
+    }
+
+    fn predict(&self, history: &[BlockSample]) -> Result<FeeEstimate> {
+        let mut observations = history
+            .iter()
+            .filter(|s| s.base_fee_gwei > 0.0)
+            .map(|s| s.base_fee_gwei.ln());
+
+        let Some(first) = observations.next() else {
+            bail!("Kalman model needs at least one block with a non-zero base fee");
+        };
+
+        let mut level = first;
+        let mut variance = self.measurement_noise;
+
+        for observation in observations {
+            // Predict
+            variance += self.process_noise;
+
+            // Update
+            let gain = variance / (variance + self.measurement_noise);
+            level += gain * (observation - level);
+            variance *= 1.0 - gain;
+        }
+
+        // One step ahead
+        let sigma = (variance + self.process_noise).sqrt();
+        let base_fee_gwei = level.exp();
+
+        Ok(FeeEstimate {
+            base_fee_gwei,
+            priority_fee_gwei: self.priority_fee_gwei,
+            max_fee_gwei: (level + self.max_fee_sigmas * sigma).exp() + self.priority_fee_gwei,
+            confidence: (1.0 / (1.0 + sigma)).clamp(0.0, 1.0),
+        })
+    }
+}
+
+/// Confidence based on the spread of base fees around `mean`.
+/// Lower standard deviation = higher confidence, normalized to the 0-1 range.
+pub fn variance_confidence(history: &[BlockSample], mean: f64) -> f64 {
+    if history.len() < 2 || mean <= 0.0 {
+        return 0.5;
+    }
+
+    let variance: f64 = history
+        .iter()
+        .map(|sample| {
+            let diff = sample.base_fee_gwei - mean;
+            diff * diff
+        })
+        .sum::<f64>() / history.len() as f64;
+
+    let std_dev = variance.sqrt();
+
+    let confidence = 1.0 / (1.0 + std_dev / mean);
+    confidence.clamp(0.0, 1.0)
+}
@@ -0,0 +1,85 @@
+use crate::contracts::{
+    BlobBaseFeeCall, BlobBaseFeeReturn, GasEstimateComponentsCall, GasEstimateComponentsReturn,
+    GetL1BaseFeeEstimateCall, GetL1BaseFeeEstimateReturn, GetL1FeeCall, GetL1FeeReturn,
+    GetPricesInWeiCall, GetPricesInWeiReturn, IsEcotoneCall, IsEcotoneReturn, L1BaseFeeCall,
+    L1BaseFeeReturn, ARB_GAS_INFO, ARB_NODE_INTERFACE, OP_GAS_PRICE_ORACLE,
+};
+use crate::models::{ChainKind, L1FeeComponents};
+use crate::services::chain_client::{call_contract, ContractCaller};
+use anyhow::Result;
+use ethers::types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256};
+
+fn wei_to_gwei(wei: U256) -> f64 {
+    wei.as_u128() as f64 / 1e9
+}
+
+/// Current L1 pricing inputs for an L2, or `None` on L1
+pub async fn l1_fee_components<C: ContractCaller + ?Sized>(
+    caller: &C,
+    kind: ChainKind,
+) -> Result<Option<L1FeeComponents>> {
+    match kind {
+        ChainKind::L1 => Ok(None),
+        ChainKind::OpStack => {
+            let oracle = OP_GAS_PRICE_ORACLE.parse::<Address>()?;
+            let L1BaseFeeReturn(l1_base_fee) = call_contract(caller, oracle, L1BaseFeeCall).await?;
+
+            // blobBaseFee only exists after the Ecotone upgrade
+            let blob_base_fee = match call_contract(caller, oracle, IsEcotoneCall).await {
+                Ok(IsEcotoneReturn(true)) => {
+                    let BlobBaseFeeReturn(fee) = call_contract(caller, oracle, BlobBaseFeeCall).await?;
+                    Some(wei_to_gwei(fee))
+                }
+                _ => None,
+            };
+
+            Ok(Some(L1FeeComponents {
+                l1_base_fee_gwei: wei_to_gwei(l1_base_fee),
+                blob_base_fee_gwei: blob_base_fee,
+                l1_calldata_byte_price_gwei: None,
+            }))
+        }
+        ChainKind::Arbitrum => {
+            let gas_info = ARB_GAS_INFO.parse::<Address>()?;
+            let GetL1BaseFeeEstimateReturn(l1_base_fee) =
+                call_contract(caller, gas_info, GetL1BaseFeeEstimateCall).await?;
+            let GetPricesInWeiReturn(_, per_l1_calldata_byte, _, _, _, _) =
+                call_contract(caller, gas_info, GetPricesInWeiCall).await?;
+
+            Ok(Some(L1FeeComponents {
+                l1_base_fee_gwei: wei_to_gwei(l1_base_fee),
+                blob_base_fee_gwei: None,
+                l1_calldata_byte_price_gwei: Some(wei_to_gwei(per_l1_calldata_byte)),
+            }))
+        }
+    }
+}
+
+/// L1 data fee in wei charged on top of L2 execution for an OP-stack transaction
+pub async fn op_stack_l1_data_fee<C: ContractCaller + ?Sized>(
+    caller: &C,
+    tx: &TypedTransaction,
+) -> Result<U256> {
+    let oracle = OP_GAS_PRICE_ORACLE.parse::<Address>()?;
+    let GetL1FeeReturn(fee) = call_contract(caller, oracle, GetL1FeeCall { data: tx.rlp() }).await?;
+    Ok(fee)
+}
+
+/// L1 portion in wei of an Arbitrum transaction. Arbitrum reports it as L2 gas
+/// (`gasEstimateForL1`) that is already part of `eth_estimateGas`.
+pub async fn arbitrum_l1_component<C: ContractCaller + ?Sized>(
+    caller: &C,
+    to: Option<Address>,
+    data: Bytes,
+) -> Result<U256> {
+    let node = ARB_NODE_INTERFACE.parse::<Address>()?;
+    let call = GasEstimateComponentsCall {
+        to: to.unwrap_or_default(),
+        contract_creation: to.is_none(),
+        data,
+    };
+    let GasEstimateComponentsReturn { gas_estimate_for_l1, base_fee, .. } =
+        call_contract(caller, node, call).await?;
+
+    Ok(U256::from(gas_estimate_for_l1) * base_fee)
+}
@@ -0,0 +1,757 @@
+use crate::contracts::aave_v3::{
+    AaveV3PoolEvents, AddressesProviderCall, AddressesProviderReturn, BaseCurrencyUnitCall, BaseCurrencyUnitReturn,
+    GetAssetsPricesCall, GetAssetsPricesReturn, GetPriceOracleCall, GetPriceOracleReturn, GetReserveDataCall,
+    GetReserveDataReturn, GetReservesListCall, GetReservesListReturn, GetSourceOfAssetCall, GetSourceOfAssetReturn,
+    GetUserAccountDataCall, GetUserAccountDataReturn, GetUserConfigurationCall, GetUserConfigurationReturn,
+};
+use crate::contracts::compound_v3::{
+    BaseScaleCall, BaseScaleReturn, BaseTokenCall, BaseTokenPriceFeedCall, BaseTokenPriceFeedReturn,
+    BaseTokenReturn, BorrowBalanceOfCall, BorrowBalanceOfReturn, CollateralBalanceOfCall,
+    CollateralBalanceOfReturn, CometEvents, GetAssetInfoCall, GetAssetInfoReturn, GetPriceCall, GetPriceReturn,
+    NumAssetsCall, NumAssetsReturn, StoreFrontPriceFactorCall, StoreFrontPriceFactorReturn,
+};
+use crate::contracts::{AggregatorCall, AggregatorReturn, BalanceOfCall, BalanceOfReturn, DecimalsCall, DecimalsReturn};
+use crate::models::LendingProtocol;
+use crate::services::{
+    chain_client::{call_contract, ChainClient},
+    BlockFollower,
+};
+use anyhow::Result;
+use ethers::abi::{self, ParamType, Token};
+use ethers::contract::parse_log;
+use ethers::types::{Address, Filter, Log, Transaction, U256};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+/// The Aave V3 pool and the USDC and WETH Comet markets
+pub const DEFAULT_LENDING_MARKETS: &str = "aave_v3:0x87870Bca3F3fD6335C3F4ce8392D69350B4fA4E2,\
+     compound_v3:0xc3d688B66703497DAA19211EEdff47f25384cdc3,\
+     compound_v3:0xA17581A9E3356d9A858b789D68B4d866e593aE94";
+
+/// Blocks of logs fetched per `eth_getLogs` request
+const LOG_CHUNK_BLOCKS: u64 = 2_000;
+/// Most recently active accounts kept per market
+const MAX_ACCOUNTS_PER_MARKET: usize = 2_000;
+/// Aave positions healthier than this are not tracked until their next event
+const WATCH_HEALTH_FACTOR: f64 = 2.0;
+/// Below this health factor Aave lets the whole debt be repaid, not half
+const AAVE_FULL_CLOSE_HEALTH_FACTOR: f64 = 0.95;
+const AAVE_CLOSE_FACTOR: f64 = 0.5;
+
+const AAVE_LIQUIDATION_GAS: u64 = 400_000;
+pub const COMET_ABSORB_GAS: u64 = 250_000;
+pub const COMET_BUY_COLLATERAL_GAS: u64 = 150_000;
+/// Comet collateral is bought for this share of its absorbed amount, so a
+/// price move cannot ask for more than the reserves hold
+const COMET_BUY_SHARE: f64 = 0.99;
+/// Less collateral than quoted for the base paid makes the purchase revert
+const COMET_BUY_SLIPPAGE: f64 = 0.005;
+
+/// A lending market, written `protocol:address`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LendingMarket {
+    pub protocol: LendingProtocol,
+    pub address: Address,
+}
+
+impl FromStr for LendingMarket {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (protocol, address) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Expected protocol:address, got '{}'", s))?;
+        Ok(Self {
+            protocol: protocol.trim().parse()?,
+            address: address
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid market address '{}'", address))?,
+        })
+    }
+}
+
+/// A position that can be liquidated, or will be once a pending price update lands
+#[derive(Debug, Clone)]
+pub struct LiquidationCandidate {
+    pub protocol: LendingProtocol,
+    pub market: Address,
+    pub borrower: Address,
+    pub health_factor: f64,
+    /// Repaid by the liquidator (the Comet base token)
+    pub debt_asset: Address,
+    /// Largest collateral position, seized or bought at a discount
+    pub collateral_asset: Address,
+    /// Largest repayment allowed, in debt token units
+    pub max_repay: f64,
+    pub repay_usd: f64,
+    pub bonus: f64,
+    /// Collateral bonus before gas
+    pub profit_usd: f64,
+    pub gas: u64,
+    /// Comet collateral to buy back after `absorb`; empty for Aave
+    pub purchases: Vec<CollateralPurchase>,
+}
+
+/// One Comet `buyCollateral`, in token units
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollateralPurchase {
+    pub asset: Address,
+    /// Base token paid
+    pub base_amount: U256,
+    /// Collateral received at least
+    pub min_amount: U256,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AaveReserve {
+    id: u16,
+    a_token: Address,
+    stable_debt: Address,
+    variable_debt: Address,
+}
+
+#[derive(Debug, Clone)]
+struct Asset {
+    /// 10^decimals
+    scale: f64,
+    /// In the market's price unit: USD, or ETH on the WETH Comet market
+    price: f64,
+    /// Where the market reads the price: the Aave oracle source or Comet price feed
+    price_feed: Address,
+    /// Aggregator receiving the Chainlink reports behind `price_feed`, and
+    /// the answer's decimals; `None` for feeds that are not plain proxies
+    aggregator: Option<(Address, u8)>,
+    /// Share of the collateral value counted against debt before liquidation
+    liquidation_threshold: f64,
+    /// Collateral received above the repaid value, as a fraction
+    bonus: f64,
+    aave: Option<AaveReserve>,
+}
+
+#[derive(Debug, Clone)]
+struct MarketInfo {
+    spec: LendingMarket,
+    assets: HashMap<Address, Asset>,
+    /// Aave oracle and its base currency unit
+    aave_oracle: Option<(Address, f64)>,
+    /// Comet base token, the only borrowable asset
+    base_token: Option<Address>,
+    priced_in_eth: bool,
+}
+
+/// Token amounts (in whole tokens) per asset
+#[derive(Debug, Clone, Default)]
+struct Position {
+    collateral: HashMap<Address, f64>,
+    debt: HashMap<Address, f64>,
+}
+
+impl Position {
+    fn health_factor(&self, assets: &HashMap<Address, Asset>) -> f64 {
+        let debt = value(&self.debt, assets);
+        if debt <= 0.0 {
+            return f64::INFINITY;
+        }
+        let collateral: f64 = self
+            .collateral
+            .iter()
+            .filter_map(|(asset, amount)| assets.get(asset).map(|a| amount * a.price * a.liquidation_threshold))
+            .sum();
+        collateral / debt
+    }
+}
+
+#[derive(Debug)]
+struct Market {
+    info: MarketInfo,
+    positions: HashMap<Address, Position>,
+}
+
+/// Tracks borrowers of Aave V3 and Compound V3 markets from their events and
+/// recomputes health factors when a block lands or a pending Chainlink
+/// report would move a collateral or debt price
+pub struct LiquidationMonitor {
+    client: Arc<dyn ChainClient>,
+    specs: Vec<LendingMarket>,
+    lookback_blocks: u64,
+    /// Comet markets with this base token are priced in ETH
+    wrapped_native: Address,
+    markets: RwLock<Vec<Market>>,
+}
+
+impl LiquidationMonitor {
+    pub fn new(
+        client: Arc<dyn ChainClient>,
+        specs: Vec<LendingMarket>,
+        lookback_blocks: u64,
+        wrapped_native: Address,
+    ) -> Self {
+        Self {
+            client,
+            specs,
+            lookback_blocks,
+            wrapped_native,
+            markets: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Borrowers tracked across all markets
+    pub fn position_count(&self) -> usize {
+        self.markets.read().unwrap().iter().map(|market| market.positions.len()).sum()
+    }
+
+    /// Loads the markets on the first block, then follows their events
+    pub async fn run(&self, follower: Arc<BlockFollower>) {
+        let mut updates = follower.subscribe();
+        let mut last_block: Option<u64> = None;
+
+        while updates.changed().await.is_ok() {
+            let Some(head) = updates.borrow_and_update().as_ref().map(|update| update.block_number) else {
+                continue;
+            };
+
+            match last_block {
+                Some(last) if head <= last => continue,
+                Some(last) => {
+                    let from = (last + 1).max(head.saturating_sub(self.lookback_blocks));
+                    // Retried with the next block
+                    if let Err(e) = self.process_blocks(from, head).await {
+                        tracing::warn!("Liquidation monitor failed on blocks {}-{}: {}", from, head, e);
+                        continue;
+                    }
+                }
+                // Retried on the next block if no market could be read
+                None if !self.load(head).await => continue,
+                None => {}
+            }
+            last_block = Some(head);
+        }
+    }
+
+    /// Reads every market and the positions of accounts that borrowed in the
+    /// lookback window. False if no market loaded.
+    pub async fn load(&self, head: u64) -> bool {
+        let from = head.saturating_sub(self.lookback_blocks);
+        let mut markets = Vec::new();
+
+        for spec in &self.specs {
+            let info = match self.load_market(spec).await {
+                Ok(info) => info,
+                Err(e) => {
+                    tracing::warn!("Could not load {} market {:?}: {}", spec.protocol, spec.address, e);
+                    continue;
+                }
+            };
+
+            let accounts = match self.active_accounts(spec, from, head, true).await {
+                Ok(accounts) => accounts,
+                Err(e) => {
+                    tracing::warn!("Could not read {} borrowers of {:?}: {}", spec.protocol, spec.address, e);
+                    Vec::new()
+                }
+            };
+
+            let mut positions = HashMap::new();
+            for account in accounts {
+                match self.fetch_position(&info, account).await {
+                    Ok(Some(position)) => {
+                        positions.insert(account, position);
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::debug!("Position of {:?} unavailable: {}", account, e),
+                }
+            }
+
+            tracing::info!(
+                "Liquidation monitor tracking {} positions on {} market {:?}",
+                positions.len(),
+                spec.protocol,
+                spec.address
+            );
+            markets.push(Market { info, positions });
+        }
+
+        let loaded = !markets.is_empty() || self.specs.is_empty();
+        *self.markets.write().unwrap() = markets;
+        loaded
+    }
+
+    /// Refreshes prices, then re-reads every account with an event in `from..=to`
+    pub async fn process_blocks(&self, from: u64, to: u64) -> Result<()> {
+        let infos: Vec<MarketInfo> = self.markets.read().unwrap().iter().map(|m| m.info.clone()).collect();
+
+        for info in infos {
+            let prices = self.fetch_prices(&info).await?;
+            let accounts = self.active_accounts(&info.spec, from, to, false).await?;
+
+            let mut updated = Vec::new();
+            for account in accounts {
+                updated.push((account, self.fetch_position(&info, account).await?));
+            }
+
+            let mut markets = self.markets.write().unwrap();
+            let Some(market) = markets.iter_mut().find(|market| market.info.spec == info.spec) else {
+                continue;
+            };
+            for (asset, price) in prices {
+                if let Some(asset) = market.info.assets.get_mut(&asset) {
+                    asset.price = price;
+                }
+            }
+            for (account, position) in updated {
+                match position {
+                    Some(position) => market.positions.insert(account, position),
+                    None => market.positions.remove(&account),
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Positions that can be liquidated at the latest prices
+    pub fn open_liquidations(&self, eth_usd: f64) -> Vec<LiquidationCandidate> {
+        let markets = self.markets.read().unwrap();
+        let mut candidates: Vec<LiquidationCandidate> = markets
+            .iter()
+            .flat_map(|market| {
+                market
+                    .positions
+                    .iter()
+                    .filter(|(_, position)| position.health_factor(&market.info.assets) < 1.0)
+                    .filter_map(|(borrower, position)| candidate(&market.info, *borrower, position, eth_usd))
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.profit_usd.total_cmp(&a.profit_usd));
+        candidates
+    }
+
+    /// Whether `tx` is a report to the aggregator of a tracked price feed
+    pub fn is_price_report(&self, tx: &Transaction) -> bool {
+        let Some(aggregator) = tx.to else { return false };
+        let tracked = self.markets.read().unwrap().iter().any(|market| {
+            let mut assets = market.info.assets.values();
+            assets.any(|asset| asset.aggregator.is_some_and(|(address, _)| address == aggregator))
+        });
+        tracked && report_median(&tx.input).is_some()
+    }
+
+    /// Positions that become liquidatable once `tx`, a Chainlink report, is mined
+    pub fn after_price_update(&self, tx: &Transaction, eth_usd: f64) -> Vec<LiquidationCandidate> {
+        let Some(aggregator) = tx.to else { return Vec::new() };
+        let Some(answer) = report_median(&tx.input) else { return Vec::new() };
+
+        let markets = self.markets.read().unwrap();
+        let mut candidates = Vec::new();
+        for market in markets.iter() {
+            let mut updated = market.info.clone();
+            let mut moved = false;
+            for asset in updated.assets.values_mut() {
+                if let Some((_, decimals)) = asset.aggregator.filter(|(address, _)| *address == aggregator) {
+                    asset.price = answer / 10f64.powi(decimals as i32);
+                    moved = true;
+                }
+            }
+            if !moved {
+                continue;
+            }
+
+            candidates.extend(market.positions.iter().filter_map(|(borrower, position)| {
+                let healthy_now = position.health_factor(&market.info.assets) >= 1.0;
+                (healthy_now && position.health_factor(&updated.assets) < 1.0)
+                    .then(|| candidate(&updated, *borrower, position, eth_usd))
+                    .flatten()
+            }));
+        }
+
+        candidates.sort_by(|a, b| b.profit_usd.total_cmp(&a.profit_usd));
+        candidates
+    }
+
+    async fn load_market(&self, spec: &LendingMarket) -> Result<MarketInfo> {
+        let mut info = match spec.protocol {
+            LendingProtocol::AaveV3 => self.load_aave(spec).await?,
+            LendingProtocol::CompoundV3 => self.load_comet(spec).await?,
+        };
+
+        for (asset, price) in self.fetch_prices(&info).await? {
+            if let Some(asset) = info.assets.get_mut(&asset) {
+                asset.price = price;
+            }
+        }
+        Ok(info)
+    }
+
+    async fn load_aave(&self, spec: &LendingMarket) -> Result<MarketInfo> {
+        let client = &*self.client;
+        let pool = spec.address;
+        let provider = call_contract::<_, _, AddressesProviderReturn>(client, pool, AddressesProviderCall).await?.0;
+        let oracle = call_contract::<_, _, GetPriceOracleReturn>(client, provider, GetPriceOracleCall).await?.0;
+        let base_unit = call_contract::<_, _, BaseCurrencyUnitReturn>(client, oracle, BaseCurrencyUnitCall).await?.0;
+        let reserves = call_contract::<_, _, GetReservesListReturn>(client, pool, GetReservesListCall).await?.0;
+
+        let mut assets = HashMap::new();
+        for reserve in reserves {
+            let data = call_contract::<_, _, GetReserveDataReturn>(client, pool, GetReserveDataCall { asset: reserve }).await?;
+            let source = call_contract::<_, _, GetSourceOfAssetReturn>(client, oracle, GetSourceOfAssetCall { asset: reserve })
+                .await?
+                .0;
+            let config = data.configuration;
+
+            assets.insert(
+                reserve,
+                Asset {
+                    scale: 10f64.powi(config_bits(config, 48, 8) as i32),
+                    price: 0.0,
+                    price_feed: source,
+                    aggregator: self.resolve_aggregator(source).await,
+                    liquidation_threshold: config_bits(config, 16, 16) as f64 / 1e4,
+                    bonus: (config_bits(config, 32, 16) as f64 / 1e4 - 1.0).max(0.0),
+                    aave: Some(AaveReserve {
+                        id: data.id,
+                        a_token: data.a_token_address,
+                        stable_debt: data.stable_debt_token_address,
+                        variable_debt: data.variable_debt_token_address,
+                    }),
+                },
+            );
+        }
+
+        Ok(MarketInfo {
+            spec: *spec,
+            assets,
+            aave_oracle: Some((oracle, to_f64(base_unit))),
+            base_token: None,
+            priced_in_eth: false,
+        })
+    }
+
+    async fn load_comet(&self, spec: &LendingMarket) -> Result<MarketInfo> {
+        let client = &*self.client;
+        let comet = spec.address;
+        let base_token = call_contract::<_, _, BaseTokenReturn>(client, comet, BaseTokenCall).await?.0;
+        let base_feed = call_contract::<_, _, BaseTokenPriceFeedReturn>(client, comet, BaseTokenPriceFeedCall).await?.0;
+        let base_scale = call_contract::<_, _, BaseScaleReturn>(client, comet, BaseScaleCall).await?.0;
+        let store_front = call_contract::<_, _, StoreFrontPriceFactorReturn>(client, comet, StoreFrontPriceFactorCall)
+            .await?
+            .0;
+        let count = call_contract::<_, _, NumAssetsReturn>(client, comet, NumAssetsCall).await?.0;
+
+        let mut assets = HashMap::new();
+        assets.insert(
+            base_token,
+            Asset {
+                scale: to_f64(base_scale),
+                price: 0.0,
+                price_feed: base_feed,
+                aggregator: self.resolve_aggregator(base_feed).await,
+                liquidation_threshold: 0.0,
+                bonus: 0.0,
+                aave: None,
+            },
+        );
+
+        for i in 0..count {
+            let asset_info = call_contract::<_, _, GetAssetInfoReturn>(client, comet, GetAssetInfoCall { i }).await?;
+            // Collateral is absorbed at the liquidation factor, then sold at a
+            // share of that discount
+            let discount = to_f64(store_front) / 1e18 * (1.0 - asset_info.liquidation_factor as f64 / 1e18);
+            assets.insert(
+                asset_info.asset,
+                Asset {
+                    scale: asset_info.scale as f64,
+                    price: 0.0,
+                    price_feed: asset_info.price_feed,
+                    aggregator: self.resolve_aggregator(asset_info.price_feed).await,
+                    liquidation_threshold: asset_info.liquidate_collateral_factor as f64 / 1e18,
+                    bonus: discount,
+                    aave: None,
+                },
+            );
+        }
+
+        Ok(MarketInfo {
+            spec: *spec,
+            assets,
+            aave_oracle: None,
+            base_token: Some(base_token),
+            priced_in_eth: base_token == self.wrapped_native,
+        })
+    }
+
+    /// The aggregator behind a Chainlink proxy; wrapped or computed feeds have none
+    async fn resolve_aggregator(&self, feed: Address) -> Option<(Address, u8)> {
+        let client = &*self.client;
+        let aggregator = call_contract::<_, _, AggregatorReturn>(client, feed, AggregatorCall).await.ok()?.0;
+        let decimals = call_contract::<_, _, DecimalsReturn>(client, feed, DecimalsCall).await.ok()?.0;
+        Some((aggregator, decimals))
+    }
+
+    async fn fetch_prices(&self, info: &MarketInfo) -> Result<Vec<(Address, f64)>> {
+        let client = &*self.client;
+
+        if let Some((oracle, base_unit)) = info.aave_oracle {
+            let assets: Vec<Address> = info.assets.keys().copied().collect();
+            let prices = call_contract::<_, _, GetAssetsPricesReturn>(client, oracle, GetAssetsPricesCall { assets: assets.clone() })
+                .await?
+                .0;
+            return Ok(assets
+                .into_iter()
+                .zip(prices)
+                .map(|(asset, price)| (asset, to_f64(price) / base_unit))
+                .collect());
+        }
+
+        // Comet prices have 8 decimals whatever the feed's
+        let mut prices = Vec::new();
+        for (address, asset) in &info.assets {
+            let price = call_contract::<_, _, GetPriceReturn>(client, info.spec.address, GetPriceCall { price_feed: asset.price_feed })
+                .await?
+                .0;
+            prices.push((*address, to_f64(price) / 1e8));
+        }
+        Ok(prices)
+    }
+
+    /// `None` when the account has no debt (or, on Aave, is far from liquidation)
+    async fn fetch_position(&self, info: &MarketInfo, account: Address) -> Result<Option<Position>> {
+        let client = &*self.client;
+        let market = info.spec.address;
+        let mut position = Position::default();
+
+        match info.base_token {
+            None => {
+                let summary = call_contract::<_, _, GetUserAccountDataReturn>(client, market, GetUserAccountDataCall { user: account }).await?;
+                if summary.total_debt_base.is_zero() || to_f64(summary.health_factor) / 1e18 > WATCH_HEALTH_FACTOR {
+                    return Ok(None);
+                }
+
+                let config = call_contract::<_, _, GetUserConfigurationReturn>(client, market, GetUserConfigurationCall { user: account })
+                    .await?
+                    .0;
+                for (address, asset) in &info.assets {
+                    let Some(reserve) = asset.aave else { continue };
+                    let id = reserve.id as usize * 2;
+
+                    if config.bit(id + 1) {
+                        let balance = self.balance(reserve.a_token, account).await?;
+                        position.collateral.insert(*address, balance / asset.scale);
+                    }
+                    if config.bit(id) {
+                        let debt = self.balance(reserve.variable_debt, account).await?
+                            + self.balance(reserve.stable_debt, account).await?;
+                        position.debt.insert(*address, debt / asset.scale);
+                    }
+                }
+            }
+            Some(base_token) => {
+                let borrowed = call_contract::<_, _, BorrowBalanceOfReturn>(client, market, BorrowBalanceOfCall { account }).await?.0;
+                if borrowed.is_zero() {
+                    return Ok(None);
+                }
+                position.debt.insert(base_token, to_f64(borrowed) / info.assets[&base_token].scale);
+
+                for (address, asset) in info.assets.iter().filter(|(address, _)| **address != base_token) {
+                    let balance = call_contract::<_, _, CollateralBalanceOfReturn>(
+                        client,
+                        market,
+                        CollateralBalanceOfCall {
+                            account,
+                            asset: *address,
+                        },
+                    )
+                    .await?
+                    .0;
+                    if balance > 0 {
+                        position.collateral.insert(*address, balance as f64 / asset.scale);
+                    }
+                }
+            }
+        }
+
+        Ok(Some(position))
+    }
+
+    async fn balance(&self, token: Address, owner: Address) -> Result<f64> {
+        if token.is_zero() {
+            return Ok(0.0);
+        }
+        let balance = call_contract::<_, _, BalanceOfReturn>(&*self.client, token, BalanceOfCall { owner }).await?.0;
+        Ok(to_f64(balance))
+    }
+
+    /// Accounts named by the market's events in `from..=to`, most recent
+    /// first. With `borrows_only`, just borrowers (Aave `Borrow`, Comet base
+    /// `Withdraw`).
+    async fn active_accounts(&self, spec: &LendingMarket, from: u64, to: u64, borrows_only: bool) -> Result<Vec<Address>> {
+        let mut logs = Vec::new();
+        let mut start = from;
+        while start <= to {
+            let end = (start + LOG_CHUNK_BLOCKS - 1).min(to);
+            let filter = Filter::new().address(spec.address).from_block(start).to_block(end);
+            logs.extend(self.client.logs(&filter).await?);
+            start = end + 1;
+        }
+
+        let mut seen = HashSet::new();
+        Ok(logs
+            .into_iter()
+            .rev()
+            .filter_map(|log| event_account(spec.protocol, log, borrows_only))
+            .filter(|account| seen.insert(*account))
+            .take(MAX_ACCOUNTS_PER_MARKET)
+            .collect())
+    }
+}
+
+fn event_account(protocol: LendingProtocol, log: Log, borrows_only: bool) -> Option<Address> {
+    match protocol {
+        LendingProtocol::AaveV3 => match parse_log::<AaveV3PoolEvents>(log).ok()? {
+            AaveV3PoolEvents::BorrowFilter(event) => Some(event.on_behalf_of),
+            _ if borrows_only => None,
+            AaveV3PoolEvents::SupplyFilter(event) => Some(event.on_behalf_of),
+            AaveV3PoolEvents::WithdrawFilter(event) => Some(event.user),
+            AaveV3PoolEvents::RepayFilter(event) => Some(event.user),
+            AaveV3PoolEvents::LiquidationCallFilter(event) => Some(event.user),
+        },
+        LendingProtocol::CompoundV3 => match parse_log::<CometEvents>(log).ok()? {
+            CometEvents::WithdrawFilter(event) => Some(event.src),
+            _ if borrows_only => None,
+            CometEvents::SupplyFilter(event) => Some(event.dst),
+            CometEvents::SupplyCollateralFilter(event) => Some(event.dst),
+            CometEvents::WithdrawCollateralFilter(event) => Some(event.src),
+            CometEvents::AbsorbDebtFilter(event) => Some(event.borrower),
+        },
+    }
+}
+
+/// Sizes the liquidation of the largest debt against the largest collateral.
+/// Aave repays up to the close factor and seizes collateral plus the bonus;
+/// Comet absorbs the whole debt and sells all collateral at a discount.
+fn candidate(info: &MarketInfo, borrower: Address, position: &Position, eth_usd: f64) -> Option<LiquidationCandidate> {
+    let assets = &info.assets;
+    let largest = |amounts: &HashMap<Address, f64>| {
+        amounts
+            .iter()
+            .filter_map(|(address, amount)| assets.get(address).map(|asset| (*address, amount * asset.price)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    };
+    let (debt_asset, debt_value) = largest(&position.debt)?;
+    let (collateral_asset, collateral_value) = largest(&position.collateral)?;
+    let health_factor = position.health_factor(assets);
+    let unit_usd = if info.priced_in_eth { eth_usd } else { 1.0 };
+
+    let (repay_value, profit_value, gas, purchases) = match info.spec.protocol {
+        LendingProtocol::AaveV3 => {
+            let close_factor = if health_factor < AAVE_FULL_CLOSE_HEALTH_FACTOR { 1.0 } else { AAVE_CLOSE_FACTOR };
+            let bonus = assets[&collateral_asset].bonus;
+            let repay = (debt_value * close_factor).min(collateral_value / (1.0 + bonus));
+            (repay, repay * bonus, AAVE_LIQUIDATION_GAS, Vec::new())
+        }
+        LendingProtocol::CompoundV3 => {
+            let base = assets.get(&info.base_token?)?;
+            if base.price <= 0.0 {
+                return None;
+            }
+            // Absorbing pays nothing: the profit is buying the absorbed
+            // collateral back below its price
+            let mut profit = 0.0;
+            let mut purchases = Vec::new();
+            for (address, amount) in &position.collateral {
+                let Some(asset) = assets.get(address) else { continue };
+                let bought = amount * COMET_BUY_SHARE;
+                let paid = bought * asset.price * (1.0 - asset.bonus);
+                profit += bought * asset.price * asset.bonus;
+                purchases.push(CollateralPurchase {
+                    asset: *address,
+                    base_amount: to_units(paid / base.price, base.scale),
+                    min_amount: to_units(bought * (1.0 - COMET_BUY_SLIPPAGE), asset.scale),
+                });
+            }
+            let gas = COMET_ABSORB_GAS + COMET_BUY_COLLATERAL_GAS * purchases.len() as u64;
+            (value(&position.debt, assets), profit, gas, purchases)
+        }
+    };
+
+    Some(LiquidationCandidate {
+        protocol: info.spec.protocol,
+        market: info.spec.address,
+        borrower,
+        health_factor,
+        debt_asset,
+        collateral_asset,
+        max_repay: repay_value / assets[&debt_asset].price,
+        repay_usd: repay_value * unit_usd,
+        bonus: assets[&collateral_asset].bonus,
+        profit_usd: profit_value * unit_usd,
+        gas,
+        purchases,
+    })
+}
+
+fn value(amounts: &HashMap<Address, f64>, assets: &HashMap<Address, Asset>) -> f64 {
+    amounts
+        .iter()
+        .filter_map(|(address, amount)| assets.get(address).map(|asset| amount * asset.price))
+        .sum()
+}
+
+/// Median observation of a Chainlink OCR `transmit`: the answer the
+/// aggregator stores once the report is mined. Handles OCR1 and OCR2 reports.
+fn report_median(input: &[u8]) -> Option<f64> {
+    let (selector, params) = (input.get(..4)?, input.get(4..)?);
+
+    let ocr1 = [ParamType::Bytes, bytes32_array(), bytes32_array(), ParamType::FixedBytes(32)];
+    let ocr2 = [
+        ParamType::FixedArray(Box::new(ParamType::FixedBytes(32)), 3),
+        ParamType::Bytes,
+        bytes32_array(),
+        bytes32_array(),
+        ParamType::FixedBytes(32),
+    ];
+
+    // (position of the report among the arguments, report layout)
+    let (types, report_index, report_types) = if selector == abi::short_signature("transmit", &ocr1) {
+        (&ocr1[..], 0, vec![ParamType::FixedBytes(32), ParamType::FixedBytes(32), observations()])
+    } else if selector == abi::short_signature("transmit", &ocr2) {
+        (
+            &ocr2[..],
+            1,
+            vec![ParamType::Uint(32), ParamType::FixedBytes(32), observations(), ParamType::Int(192)],
+        )
+    } else {
+        return None;
+    };
+
+    let report = abi::decode(types, params).ok()?.into_iter().nth(report_index)?.into_bytes()?;
+    let observations = abi::decode(&report_types, &report).ok()?.into_iter().nth(2)?.into_array()?;
+
+    // Observations are sorted, so the median is the middle one
+    match observations.get(observations.len() / 2)? {
+        Token::Int(answer) if !answer.bit(255) => Some(to_f64(*answer)),
+        _ => None,
+    }
+}
+
+fn bytes32_array() -> ParamType {
+    ParamType::Array(Box::new(ParamType::FixedBytes(32)))
+}
+
+fn observations() -> ParamType {
+    ParamType::Array(Box::new(ParamType::Int(192)))
+}
+
+fn config_bits(data: U256, offset: usize, width: usize) -> u64 {
+    ((data >> offset) & ((U256::one() << width) - 1)).as_u64()
+}
+
+/// Whole tokens to token units, rounded down
+fn to_units(amount: f64, scale: f64) -> U256 {
+    U256::from((amount * scale).max(0.0) as u128)
+}
+
+fn to_f64(value: U256) -> f64 {
+    value.to_string().parse().unwrap_or_default()
+}
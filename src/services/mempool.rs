@@ -1,56 +1,280 @@
-use anyhow::Result;
+use crate::models::{MempoolConnection, MempoolHealth};
+use crate::services::mempool_store::{InsertOutcome, MempoolStore, PendingTransaction, Removal};
+use anyhow::{Context, Result};
+use chrono::Utc;
 use ethers::providers::{Middleware, Provider, Ws};
-use ethers::types::Transaction;
+use ethers::types::{Address, Transaction, H256};
 use futures::StreamExt;
-use std::collections::VecDeque;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// New transactions buffered per subscriber before it starts missing some
+const ANNOUNCE_BUFFER: usize = 4096;
+
+/// Which pending transaction subscription to open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubscriptionMode {
+    /// `newPendingTransactions` with hashes only; each is looked up separately
+    Hashes,
+    /// `newPendingTransactions` with full transaction bodies (geth, reth, erigon)
+    #[default]
+    Full,
+    /// `alchemy_pendingTransactions`, filtered by the node
+    Alchemy,
+}
+
+impl FromStr for SubscriptionMode {
+    type Err = String;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hashes" => Ok(Self::Hashes),
+            "full" => Ok(Self::Full),
+            "alchemy" => Ok(Self::Alchemy),
+            other => Err(format!("Unknown mempool subscription '{}' (use hashes, full or alchemy)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MempoolConfig {
+    pub mode: SubscriptionMode,
+    /// Keep only transactions to / from these addresses (empty keeps all)
+    pub to_addresses: Vec<Address>,
+    pub from_addresses: Vec<Address>,
+    /// Pending transactions kept; the oldest are evicted first
+    pub capacity: usize,
+    /// Pending transactions older than this are dropped
+    pub ttl: Duration,
+    /// Transaction lookups in flight at once in `Hashes` mode
+    pub lookup_concurrency: usize,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            mode: SubscriptionMode::default(),
+            to_addresses: Vec::new(),
+            from_addresses: Vec::new(),
+            capacity: 5000,
+            ttl: Duration::from_secs(300),
+            lookup_concurrency: 16,
+        }
+    }
+}
+
+impl MempoolConfig {
+    fn subscription_params(&self) -> Value {
+        match self.mode {
+            SubscriptionMode::Hashes => json!(["newPendingTransactions"]),
+            SubscriptionMode::Full => json!(["newPendingTransactions", true]),
+            SubscriptionMode::Alchemy => {
+                let mut filter = json!({ "hashesOnly": false });
+                if !self.to_addresses.is_empty() {
+                    filter["toAddress"] = json!(self.to_addresses);
+                }
+                if !self.from_addresses.is_empty() {
+                    filter["fromAddress"] = json!(self.from_addresses);
+                }
+                json!(["alchemy_pendingTransactions", filter])
+            }
+        }
+    }
+    
+    /// The same filters Alchemy applies server-side, for the other modes
+    fn accepts(&self, tx: &Transaction) -> bool {
+        let to_ok = self.to_addresses.is_empty()
+            || tx.to.is_some_and(|to| self.to_addresses.contains(&to));
+        let from_ok = self.from_addresses.is_empty() || self.from_addresses.contains(&tx.from);
+        to_ok && from_ok
+    }
+}
+
+/// A subscription notification: a hash, or a full transaction
+enum PendingItem {
+    Hash(H256),
+    Full(Box<Transaction>),
+}
+
+impl PendingItem {
+    fn parse(value: Value) -> Option<Self> {
+        match value {
+            Value::String(_) => serde_json::from_value(value).ok().map(PendingItem::Hash),
+            _ => serde_json::from_value(value).ok().map(|tx| PendingItem::Full(Box::new(tx))),
+        }
+    }
+}
 
 pub struct MempoolService {
-    ws_provider: Arc<Provider<Ws>>,
-    pending_txs: Arc<RwLock<VecDeque<Transaction>>>,
-    max_pending: usize,
+    ws_url: String,
+    config: MempoolConfig,
+    store: MempoolStore,
+    health: RwLock<MempoolHealth>,
+    new_transactions: broadcast::Sender<Arc<Transaction>>,
 }
 
 impl MempoolService {
-    pub async fn new(ws_url: &str) -> Result<Self> {
-        let ws = Ws::connect(ws_url).await?;
-        let provider = Provider::new(ws);
-        
-        tracing::info!("Mempool service connected to WebSocket");
-        
-        Ok(Self {
-            ws_provider: Arc::new(provider),
-            pending_txs: Arc::new(RwLock::new(VecDeque::new())),
-            max_pending: 100,
-        })
+    /// Nothing is connected until [`start_monitoring`](Self::start_monitoring) runs
+    pub fn new(ws_url: &str, config: MempoolConfig) -> Self {
+        Self {
+            ws_url: ws_url.to_string(),
+            store: MempoolStore::new(config.capacity, config.ttl),
+            config,
+            health: RwLock::new(MempoolHealth {
+                state: MempoolConnection::Connecting,
+                ..MempoolHealth::disabled()
+            }),
+            new_transactions: broadcast::channel(ANNOUNCE_BUFFER).0,
+        }
     }
     
+    /// Follows pending transactions forever, reconnecting and resubscribing
+    /// with exponential backoff whenever the WebSocket fails or the stream ends
     pub async fn start_monitoring(&self) {
         tracing::info!("Starting mempool monitoring");
+        let mut backoff = INITIAL_BACKOFF;
         
-        // Subscribe to pending transactions
-        let mut stream = match self.ws_provider.subscribe_pending_txs().await {
-            Ok(s) => s,
-            Err(e) => {
-                tracing::error!("Failed to subscribe to pending transactions: {}", e);
-                return;
+        loop {
+            let started = Instant::now();
+            let error = match self.follow_pending().await {
+                Ok(()) => "subscription stream ended".to_string(),
+                Err(e) => format!("{:#}", e),
+            };
+            
+            // A connection that stayed up for a while starts the backoff over
+            if started.elapsed() > MAX_BACKOFF {
+                backoff = INITIAL_BACKOFF;
             }
-        };
+            
+            tracing::warn!("Mempool subscription lost ({}), reconnecting in {:?}", error, backoff);
+            {
+                let mut health = self.health.write().unwrap();
+                health.state = MempoolConnection::Reconnecting;
+                health.connected_since = None;
+                health.last_error = Some(error);
+                health.reconnects += 1;
+            }
+            
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+    
+    /// One connection: subscribe and store transactions until the stream ends.
+    /// Nodes that ignore the full-body flag send hashes, which are looked up.
+    async fn follow_pending(&self) -> Result<()> {
+        let ws = tokio::time::timeout(CONNECT_TIMEOUT, Ws::connect(&self.ws_url))
+            .await
+            .context("WebSocket connect timed out")?
+            .context("WebSocket connect failed")?;
+        let provider = Provider::new(ws);
         
-        while let Some(tx_hash) = stream.next().await {
-            if let Ok(Some(tx)) = self.ws_provider.get_transaction(tx_hash).await {
-                let mut pending = self.pending_txs.write().await;
-                if pending.len() >= self.max_pending {
-                    pending.pop_front();
+        let stream = provider
+            .subscribe::<_, Value>(self.config.subscription_params())
+            .await
+            .context("Failed to subscribe to pending transactions")?;
+        
+        tracing::info!("Mempool subscription established ({:?})", self.config.mode);
+        {
+            let mut health = self.health.write().unwrap();
+            health.state = MempoolConnection::Connected;
+            health.connected_since = Some(Utc::now());
+        }
+        
+        // Bounded lookups: a slow RPC holds back the stream instead of piling up tasks
+        let provider = &provider;
+        let mut transactions = stream
+            .filter_map(|value| futures::future::ready(PendingItem::parse(value)))
+            .map(|item| async move {
+                match item {
+                    PendingItem::Full(tx) => Some(*tx),
+                    PendingItem::Hash(tx_hash) => match provider.get_transaction(tx_hash).await {
+                        Ok(tx) => tx,
+                        Err(e) => {
+                            tracing::debug!("Pending transaction lookup failed for {:?}: {}", tx_hash, e);
+                            None
+                        }
+                    },
                 }
-                pending.push_back(tx);
+            })
+            .buffer_unordered(self.config.lookup_concurrency.max(1));
+        
+        while let Some(result) = transactions.next().await {
+            // `None`: already mined or dropped before the lookup
+            if let Some(tx) = result {
+                self.insert(tx);
             }
         }
+        
+        Ok(())
+    }
+    
+    /// Stores a pending transaction as if it arrived on the subscription
+    pub fn insert(&self, tx: Transaction) {
+        if !self.config.accepts(&tx) {
+            return;
+        }
+        
+        let hash = tx.hash;
+        let announcement = (self.new_transactions.receiver_count() > 0).then(|| Arc::new(tx.clone()));
+        match self.store.insert(tx) {
+            InsertOutcome::New => {}
+            InsertOutcome::Replaced(previous) => {
+                tracing::debug!("Pending transaction {:?} replaced by {:?}", previous, hash);
+            }
+            InsertOutcome::Duplicate | InsertOutcome::Underpriced => return,
+        }
+        
+        if let Some(tx) = announcement {
+            let _ = self.new_transactions.send(tx);
+        }
+    }
+    
+    /// Every transaction newly added to the mempool store
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Transaction>> {
+        self.new_transactions.subscribe()
     }
     
-    pub async fn get_pending_transactions(&self) -> Vec<Transaction> {
-        self.pending_txs.read().await.iter().cloned().collect()
+    pub fn is_connected(&self) -> bool {
+        self.health.read().unwrap().state == MempoolConnection::Connected
+    }
+    
+    pub fn health(&self) -> MempoolHealth {
+        MempoolHealth {
+            pending_transactions: self.store.len(),
+            replacements: self.store.replacements(),
+            ..self.health.read().unwrap().clone()
+        }
+    }
+    
+    /// Pending transactions, newest first
+    pub fn get_pending_transactions(&self) -> Vec<Transaction> {
+        self.store.transactions()
+    }
+    
+    pub fn get_pending(&self, hash: H256) -> Option<PendingTransaction> {
+        self.store.get(hash)
+    }
+    
+    /// The pending transaction that replaced `hash` (same sender and nonce, higher fee)
+    pub fn replaced_by(&self, hash: H256) -> Option<H256> {
+        self.store.replaced_by(hash)
+    }
+    
+    /// Why `hash` left the mempool, if it did recently
+    pub fn removal(&self, hash: H256) -> Option<Removal> {
+        self.store.removal(hash)
+    }
+    
+    /// Marks a transaction seen in a block as included and returns it
+    pub fn take_included(&self, hash: H256) -> Option<PendingTransaction> {
+        self.store.take_included(hash)
     }
 }
-
@@ -0,0 +1,167 @@
+use crate::models::{AddressActivity, FeeHistogramBucket, MempoolSummary, NonceGap, PairVolume};
+use crate::services::{decode_swaps, DexRegistry};
+use ethers::types::{Address, Transaction, U256};
+use std::collections::{BTreeSet, HashMap};
+
+/// Entries in each top-N list
+const TOP_N: usize = 10;
+
+/// Senders with nonce gaps reported, and missing nonces listed per sender
+const MAX_NONCE_GAPS: usize = 20;
+const MAX_MISSING_NONCES: usize = 10;
+
+/// Lower bounds of the priority fee histogram buckets, in gwei
+const FEE_HISTOGRAM_GWEI: [f64; 8] = [0.0, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0];
+
+/// Restricts a summary to transactions to one address and/or calling one function
+#[derive(Debug, Clone, Default)]
+pub struct SummaryFilter {
+    pub to: Option<Address>,
+    pub selector: Option<[u8; 4]>,
+}
+
+impl SummaryFilter {
+    fn matches(&self, tx: &Transaction) -> bool {
+        let to_ok = match self.to {
+            Some(to) => tx.to == Some(to),
+            None => true,
+        };
+        let selector_ok = match self.selector {
+            Some(selector) => tx.input.get(..4) == Some(&selector[..]),
+            None => true,
+        };
+        to_ok && selector_ok
+    }
+}
+
+/// Swaps are counted only when sent to a router in `dexes`
+pub fn summarize(transactions: &[Transaction], filter: &SummaryFilter, dexes: &DexRegistry) -> MempoolSummary {
+    let txs: Vec<&Transaction> = transactions.iter().filter(|tx| filter.matches(tx)).collect();
+
+    MempoolSummary {
+        pending_transactions: txs.len(),
+        total_value_eth: txs.iter().map(|tx| to_eth(tx.value)).sum(),
+        total_gas: txs.iter().map(|tx| tx.gas.low_u64()).sum(),
+        fee_histogram: fee_histogram(&txs),
+        top_senders: top_by(&txs, |tx| Some(tx.from)),
+        top_contracts: top_by(&txs, |tx| tx.to.filter(|_| !tx.input.is_empty())),
+        nonce_gaps: nonce_gaps(&txs),
+        dex_swaps: dex_swaps(&txs, dexes),
+    }
+}
+
+fn fee_histogram(txs: &[&Transaction]) -> Vec<FeeHistogramBucket> {
+    let mut buckets: Vec<FeeHistogramBucket> = FEE_HISTOGRAM_GWEI
+        .iter()
+        .enumerate()
+        .map(|(i, &min_gwei)| FeeHistogramBucket {
+            min_gwei,
+            max_gwei: FEE_HISTOGRAM_GWEI.get(i + 1).copied(),
+            transactions: 0,
+        })
+        .collect();
+
+    for tx in txs {
+        let tip_gwei = to_gwei(tx.max_priority_fee_per_gas.or(tx.gas_price).unwrap_or_default());
+        if let Some(bucket) = buckets.iter_mut().rev().find(|bucket| tip_gwei >= bucket.min_gwei) {
+            bucket.transactions += 1;
+        }
+    }
+
+    buckets
+}
+
+/// Busiest addresses by pending transactions, then by gas
+fn top_by(txs: &[&Transaction], key: impl Fn(&Transaction) -> Option<Address>) -> Vec<AddressActivity> {
+    let mut activity: HashMap<Address, AddressActivity> = HashMap::new();
+    for tx in txs {
+        let Some(address) = key(tx) else { continue };
+        let entry = activity.entry(address).or_insert(AddressActivity {
+            address,
+            transactions: 0,
+            value_eth: 0.0,
+            gas: 0,
+        });
+        entry.transactions += 1;
+        entry.value_eth += to_eth(tx.value);
+        entry.gas += tx.gas.low_u64();
+    }
+
+    let mut top: Vec<AddressActivity> = activity.into_values().collect();
+    top.sort_by(|a, b| b.transactions.cmp(&a.transactions).then(b.gas.cmp(&a.gas)));
+    top.truncate(TOP_N);
+    top
+}
+
+fn nonce_gaps(txs: &[&Transaction]) -> Vec<NonceGap> {
+    let mut nonces: HashMap<Address, BTreeSet<u64>> = HashMap::new();
+    for tx in txs {
+        nonces.entry(tx.from).or_default().insert(tx.nonce.low_u64());
+    }
+
+    let mut gaps: Vec<NonceGap> = nonces
+        .into_iter()
+        .filter_map(|(sender, nonces)| {
+            let lowest = *nonces.first()?;
+            let highest = *nonces.last()?;
+            // Walks consecutive pending nonces so huge gaps cost no more than small ones
+            let mut missing = Vec::new();
+            for (previous, next) in nonces.iter().zip(nonces.iter().skip(1)) {
+                let room = MAX_MISSING_NONCES - missing.len();
+                missing.extend((previous + 1..*next).take(room));
+                if missing.len() == MAX_MISSING_NONCES {
+                    break;
+                }
+            }
+            let first_missing = *missing.first()?;
+
+            Some(NonceGap {
+                sender,
+                lowest_nonce: lowest,
+                highest_nonce: highest,
+                blocked_transactions: nonces.range(first_missing..).count(),
+                missing,
+            })
+        })
+        .collect();
+
+    gaps.sort_by_key(|gap| std::cmp::Reverse(gap.blocked_transactions));
+    gaps.truncate(MAX_NONCE_GAPS);
+    gaps
+}
+
+fn dex_swaps(txs: &[&Transaction], dexes: &DexRegistry) -> Vec<PairVolume> {
+    let mut pairs: HashMap<(Address, Address), (usize, U256, U256)> = HashMap::new();
+    for tx in txs {
+        // ETH sent with a multi-swap call counts once, towards its first swap
+        for (i, swap) in decode_swaps(tx, dexes).iter().enumerate() {
+            let (swaps, amount, value) = pairs.entry((swap.token_in(), swap.token_out())).or_default();
+            *swaps += 1;
+            *amount = amount.saturating_add(swap.amount_in);
+            if i == 0 {
+                *value = value.saturating_add(tx.value);
+            }
+        }
+    }
+
+    let mut volumes: Vec<PairVolume> = pairs
+        .into_iter()
+        .map(|((token_in, token_out), (swaps, amount_in, value))| PairVolume {
+            token_in,
+            token_out,
+            swaps,
+            amount_in: amount_in.to_string(),
+            value_eth: to_eth(value),
+        })
+        .collect();
+    volumes.sort_by_key(|volume| std::cmp::Reverse(volume.swaps));
+    volumes
+}
+
+fn to_eth(wei: U256) -> f64 {
+    wei.as_u128() as f64 / 1e18
+}
+
+fn to_gwei(wei: U256) -> f64 {
+    wei.as_u128() as f64 / 1e9
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, Transaction, H256, U256};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A pending transaction and when it was first seen
+#[derive(Debug, Clone)]
+pub struct PendingTransaction {
+    pub tx: Transaction,
+    pub first_seen: DateTime<Utc>,
+    /// Hash of the transaction this one replaced (same sender and nonce)
+    pub replaces: Option<H256>,
+    seen_at: Instant,
+    seq: u64,
+}
+
+/// Why a transaction left the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Removal {
+    /// Seen in a block
+    Included,
+    /// Replaced by this hash (same sender and nonce, higher fee)
+    Replaced(H256),
+    /// Evicted by TTL or capacity without being seen in a block
+    Dropped,
+}
+
+/// What happened to a transaction offered to the store
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertOutcome {
+    New,
+    Duplicate,
+    /// Replaced the pending transaction with this hash
+    Replaced(H256),
+    /// Same sender and nonce as a pending transaction, without a higher fee
+    Underpriced,
+}
+
+#[derive(Debug)]
+struct RemovedTransaction {
+    reason: Removal,
+    removed_at: Instant,
+    pending: PendingTransaction,
+}
+
+#[derive(Debug, Default)]
+struct StoreState {
+    by_hash: HashMap<H256, PendingTransaction>,
+    by_sender_nonce: HashMap<(Address, U256), H256>,
+    /// Insertion order, oldest first
+    order: BTreeMap<u64, H256>,
+    /// Why recent transactions left, kept as long as a pending transaction and
+    /// up to the same capacity
+    removed: HashMap<H256, RemovedTransaction>,
+    /// `removed` by removal time, oldest first; an entry is stale once its
+    /// transaction was removed again (included after being replaced)
+    removed_order: VecDeque<(Instant, H256)>,
+    next_seq: u64,
+    replacements: u64,
+}
+
+/// Pending transactions keyed by hash, deduplicated, with replacement
+/// tracking, a capacity limit (oldest evicted first) and a time to live
+#[derive(Debug)]
+pub struct MempoolStore {
+    capacity: usize,
+    ttl: Duration,
+    state: Mutex<StoreState>,
+}
+
+impl MempoolStore {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            ttl,
+            state: Mutex::new(StoreState::default()),
+        }
+    }
+
+    pub fn insert(&self, tx: Transaction) -> InsertOutcome {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        self.evict_expired(&mut state, now);
+
+        if state.by_hash.contains_key(&tx.hash) {
+            return InsertOutcome::Duplicate;
+        }
+
+        let key = (tx.from, tx.nonce);
+        let mut outcome = InsertOutcome::New;
+        if let Some(existing_hash) = state.by_sender_nonce.get(&key).copied() {
+            let existing_fee = state.by_hash.get(&existing_hash).map(|p| fee_cap(&p.tx));
+            if existing_fee.is_some_and(|fee| fee_cap(&tx) <= fee) {
+                return InsertOutcome::Underpriced;
+            }
+
+            remove(&mut state, existing_hash, Removal::Replaced(tx.hash), now);
+            state.replacements += 1;
+            outcome = InsertOutcome::Replaced(existing_hash);
+        }
+
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.order.insert(seq, tx.hash);
+        state.by_sender_nonce.insert(key, tx.hash);
+        state.by_hash.insert(
+            tx.hash,
+            PendingTransaction {
+                tx,
+                first_seen: Utc::now(),
+                replaces: match outcome {
+                    InsertOutcome::Replaced(hash) => Some(hash),
+                    _ => None,
+                },
+                seen_at: now,
+                seq,
+            },
+        );
+
+        while state.by_hash.len() > self.capacity {
+            let Some((_, oldest)) = state.order.pop_first() else { break };
+            remove(&mut state, oldest, Removal::Dropped, now);
+        }
+
+        outcome
+    }
+
+    pub fn get(&self, hash: H256) -> Option<PendingTransaction> {
+        let mut state = self.state.lock().unwrap();
+        self.evict_expired(&mut state, Instant::now());
+        state.by_hash.get(&hash).cloned()
+    }
+
+    /// Marks a transaction seen in a block as included. Also covers one that
+    /// was already replaced or dropped, since the chain has the last word.
+    pub fn take_included(&self, hash: H256) -> Option<PendingTransaction> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if let Some(pending) = remove(&mut state, hash, Removal::Included, now) {
+            return Some(pending);
+        }
+
+        let removed = state.removed.get_mut(&hash)?;
+        if removed.reason == Removal::Included {
+            return None;
+        }
+        removed.reason = Removal::Included;
+        removed.removed_at = now;
+        let pending = removed.pending.clone();
+        state.removed_order.push_back((now, hash));
+        Some(pending)
+    }
+
+    /// Why `hash` left the store, if it did recently
+    pub fn removal(&self, hash: H256) -> Option<Removal> {
+        let mut state = self.state.lock().unwrap();
+        self.evict_expired(&mut state, Instant::now());
+        state.removed.get(&hash).map(|removed| removed.reason)
+    }
+
+    /// The transaction that replaced `hash`, if it was replaced recently
+    pub fn replaced_by(&self, hash: H256) -> Option<H256> {
+        match self.removal(hash)? {
+            Removal::Replaced(replacement) => Some(replacement),
+            _ => None,
+        }
+    }
+
+    /// Pending transactions, newest first
+    pub fn transactions(&self) -> Vec<Transaction> {
+        let mut state = self.state.lock().unwrap();
+        self.evict_expired(&mut state, Instant::now());
+        state
+            .order
+            .values()
+            .rev()
+            .filter_map(|hash| state.by_hash.get(hash))
+            .map(|pending| pending.tx.clone())
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().by_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Transactions replaced by a higher fee since startup
+    pub fn replacements(&self) -> u64 {
+        self.state.lock().unwrap().replacements
+    }
+
+    fn evict_expired(&self, state: &mut StoreState, now: Instant) {
+        while let Some((_, hash)) = state.order.first_key_value() {
+            let hash = *hash;
+            match state.by_hash.get(&hash) {
+                Some(pending) if now.duration_since(pending.seen_at) < self.ttl => break,
+                _ => {
+                    state.order.pop_first();
+                    remove(state, hash, Removal::Dropped, now);
+                }
+            }
+        }
+
+        while let Some(&(removed_at, hash)) = state.removed_order.front() {
+            if now.duration_since(removed_at) < self.ttl && state.removed.len() <= self.capacity {
+                break;
+            }
+            state.removed_order.pop_front();
+            if state.removed.get(&hash).is_some_and(|removed| removed.removed_at == removed_at) {
+                state.removed.remove(&hash);
+            }
+        }
+    }
+}
+
+fn remove(state: &mut StoreState, hash: H256, reason: Removal, now: Instant) -> Option<PendingTransaction> {
+    let pending = state.by_hash.remove(&hash)?;
+    state.order.remove(&pending.seq);
+    let key = (pending.tx.from, pending.tx.nonce);
+    if state.by_sender_nonce.get(&key) == Some(&hash) {
+        state.by_sender_nonce.remove(&key);
+    }
+    state.removed.insert(
+        hash,
+        RemovedTransaction {
+            reason,
+            removed_at: now,
+            pending: pending.clone(),
+        },
+    );
+    state.removed_order.push_back((now, hash));
+    Some(pending)
+}
+
+/// Most the sender will pay per gas: the EIP-1559 max fee, or the legacy gas price
+fn fee_cap(tx: &Transaction) -> U256 {
+    tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default()
+}
@@ -1,74 +1,530 @@
-use crate::models::{ExecutionDetails, MEVOpportunity, MEVType};
-use crate::services::EthereumService;
+use crate::models::{
+    BundleSimulation, BundleTemplate, ExecutionDetails, LiquidationDetails, MEVOpportunity, MEVType, MevProtection,
+    SandwichExposure,
+};
+use crate::services::{
+    bundle::{liquidation_bundle, sandwich_bundle},
+    decode_swaps, mev_protection, ArbitrageCycle, ArbitrageScanner, BundleSimulator, DexProtocol, DexRegistry,
+    EthereumService, LiquidationCandidate, LiquidationMonitor, PriceService, SandwichSimulator, SimulatedSandwich,
+    SwapIntent, SwapKind, VictimReverted,
+};
+use anyhow::Result;
 use chrono::Utc;
-use ethers::types::Transaction;
+use ethers::types::{Address, Transaction, U256};
 use std::sync::Arc;
 
+/// Share of simulated sandwiches expected to still work when submitted; the
+/// state can move before the next block
+const SIMULATION_CONFIDENCE: f64 = 0.8;
+
+/// Arbitrage is priced from tracked reserves rather than simulated; V3 pools
+/// only account for in-range liquidity
+const ARBITRAGE_CONFIDENCE: f64 = 0.7;
+const V3_ARBITRAGE_CONFIDENCE: f64 = 0.5;
+
+/// Open liquidations are priced from on-chain state; ones waiting on a pending
+/// oracle report also depend on it landing first
+const LIQUIDATION_CONFIDENCE: f64 = 0.9;
+const PENDING_LIQUIDATION_CONFIDENCE: f64 = 0.6;
+
+/// Frontrun plus backrun, for pricing sandwiches that are not simulated
+const SANDWICH_GAS: u64 = 250_000;
+/// Tip searchers add on top of the predicted priority fee, in gwei
+const SANDWICH_TIP_BUMP_GWEI: f64 = 1.0;
+/// Tolerances tighter than this fail on ordinary price movement; a swap that
+/// needs one to be safe should skip the public mempool
+const MIN_PUBLIC_SLIPPAGE_BPS: f64 = 10.0;
+
+/// What the bundle of an opportunity is built from, so bundles for different
+/// searchers need no second simulation of the transaction
+#[derive(Debug, Clone)]
+pub enum BundlePlan {
+    Sandwich(SimulatedSandwich),
+    /// Backruns the oracle report at its fees
+    Liquidation {
+        candidate: LiquidationCandidate,
+        max_fee: U256,
+        tip: U256,
+    },
+}
+
+impl BundlePlan {
+    /// The bundle for `searcher` around `tx`. Liquidations target
+    /// `target_block`; sandwiches the block after the one they were simulated on.
+    pub fn bundle(&self, tx: &Transaction, searcher: Address, target_block: u64) -> BundleTemplate {
+        match self {
+            BundlePlan::Sandwich(sandwich) => sandwich_bundle(tx, sandwich, searcher),
+            BundlePlan::Liquidation { candidate, max_fee, tip } => {
+                liquidation_bundle(candidate, Some(tx), searcher, target_block, *max_fee, *tip)
+            }
+        }
+    }
+}
+
 pub struct MEVDetector {
     ethereum: Arc<EthereumService>,
-    min_profit_usd: f64,
+    prices: Arc<PriceService>,
+    dexes: Arc<DexRegistry>,
+    sandwich: SandwichSimulator,
+    bundles: BundleSimulator,
+    arbitrage: Arc<ArbitrageScanner>,
+    liquidations: Arc<LiquidationMonitor>,
 }
 
 impl MEVDetector {
-    pub fn new(ethereum: Arc<EthereumService>) -> Self {
+    pub fn new(
+        ethereum: Arc<EthereumService>,
+        prices: Arc<PriceService>,
+        dexes: Arc<DexRegistry>,
+        arbitrage: Arc<ArbitrageScanner>,
+        liquidations: Arc<LiquidationMonitor>,
+    ) -> Self {
         Self {
+            sandwich: SandwichSimulator::new(ethereum.client.clone(), ethereum.chain().clone(), dexes.clone()),
+            bundles: BundleSimulator::new(ethereum.client.clone(), ethereum.chain().clone()),
             ethereum,
-            min_profit_usd: 10.0, // Minimum $10 profit
+            prices,
+            dexes,
+            arbitrage,
+            liquidations,
         }
     }
     
-    pub async fn analyze_transaction(&self, tx: &Transaction) -> Option<MEVOpportunity> {
-        // Check if transaction interacts with DEX
-        if !self.is_dex_transaction(tx) {
-            return None;
+    /// The DEXes whose swaps are analyzed
+    pub fn dexes(&self) -> &Arc<DexRegistry> {
+        &self.dexes
+    }
+    
+    /// The best sandwich of `tx`, if any, the best backrun arbitrage, and the
+    /// liquidations it enables if it is an oracle price update. Only those
+    /// profitable after gas are returned; callers apply their own minimum.
+    /// Sandwiches and liquidations carry a bundle for `searcher` when one is
+    /// given.
+    pub async fn analyze_transaction(&self, tx: &Transaction, searcher: Option<Address>) -> Vec<MEVOpportunity> {
+        let found = self.find_opportunities(tx).await;
+        let Some(searcher) = searcher else {
+            return found.into_iter().map(|(opportunity, _)| opportunity).collect();
+        };
+        
+        let mut target_block = None;
+        if found.iter().any(|(_, plan)| matches!(plan, Some(BundlePlan::Liquidation { .. }))) {
+            match self.ethereum.client.block_number().await {
+                Ok(head) => target_block = Some(head + 1),
+                Err(e) => tracing::warn!("No block number for liquidation bundles: {}", e),
+            }
         }
         
-        // Analyze for sandwich opportunity
-        if let Some(opportunity) = self.check_sandwich_opportunity(tx).await {
-            if opportunity.net_profit_usd > self.min_profit_usd {
-                return Some(opportunity);
+        found
+            .into_iter()
+            .filter_map(|(mut opportunity, plan)| {
+                opportunity.bundle = match plan {
+                    Some(BundlePlan::Liquidation { .. }) if target_block.is_none() => return None,
+                    Some(plan) => Some(plan.bundle(tx, searcher, target_block.unwrap_or_default())),
+                    None => None,
+                };
+                Some(opportunity)
+            })
+            .collect()
+    }
+    
+    /// The opportunities of [`analyze_transaction`](Self::analyze_transaction)
+    /// without bundles, each with what its bundle is built from if it has one
+    pub async fn find_opportunities(&self, tx: &Transaction) -> Vec<(MEVOpportunity, Option<BundlePlan>)> {
+        let mut opportunities = Vec::new();
+        
+        // Only router swaps can be sandwiched
+        let swaps = self.decode_swaps(tx);
+        
+        for swap in &swaps {
+            // Analyze for sandwich opportunity
+            if let Some((opportunity, sandwich)) = self.check_sandwich_opportunity(tx, swap).await {
+                if opportunity.net_profit_usd > 0.0 {
+                    opportunities.push((opportunity, Some(BundlePlan::Sandwich(sandwich))));
+                    break;
+                }
             }
         }
         
-        None
+        if let Some(opportunity) = self.check_arbitrage_opportunity(tx).await {
+            opportunities.push((opportunity, None));
+        }
+        
+        opportunities.extend(
+            self.check_liquidation_opportunities(tx)
+                .await
+                .into_iter()
+                .map(|(opportunity, plan)| (opportunity, Some(plan))),
+        );
+        
+        opportunities
     }
     
-    fn is_dex_transaction(&self, tx: &Transaction) -> bool {
-        // Check if transaction is to Uniswap, Sushiswap, etc
-        const UNISWAP_V2: &str = "0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D";
-        const UNISWAP_V3: &str = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45";
-        
-        if let Some(to) = tx.to {
-            let addr = format!("{:?}", to);
-            return addr.to_lowercase().contains(&UNISWAP_V2.to_lowercase()) 
-                || addr.to_lowercase().contains(&UNISWAP_V3.to_lowercase());
+    /// Whether [`find_opportunities`](Self::find_opportunities) can find
+    /// anything in `tx` without simulating it: it swaps on a registered DEX or
+    /// is a report to a tracked price feed
+    pub fn may_have_opportunities(&self, tx: &Transaction) -> bool {
+        !self.decode_swaps(tx).is_empty() || self.liquidations.is_price_report(tx)
+    }
+    
+    /// Swaps in `tx` on the registered DEXes
+    pub fn decode_swaps(&self, tx: &Transaction) -> Vec<SwapIntent> {
+        decode_swaps(tx, &self.dexes)
+    }
+    
+    /// Positions that can be liquidated right now, paying the predicted max fee
+    pub async fn open_liquidations(&self, searcher: Option<Address>) -> Vec<MEVOpportunity> {
+        let Some(eth_usd) = self.eth_usd().await else {
+            return Vec::new();
+        };
+        let candidates = self.liquidations.open_liquidations(eth_usd);
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+        
+        let prediction = match self.ethereum.get_gas_prediction(None).await {
+            Ok(prediction) => prediction,
+            Err(e) => {
+                tracing::warn!("No gas prediction for liquidation costs: {}", e);
+                return Vec::new();
+            }
+        };
+        let fees = (gwei(prediction.max_fee_gwei), gwei(prediction.priority_fee_gwei));
+        
+        candidates
+            .iter()
+            .map(|candidate| {
+                let mut opportunity = liquidation_opportunity(candidate, None, prediction.max_fee_gwei, eth_usd);
+                opportunity.bundle = searcher.map(|searcher| {
+                    liquidation_bundle(candidate, None, searcher, prediction.block_number + 1, fees.0, fees.1)
+                });
+                opportunity
+            })
+            .filter(|opportunity| opportunity.net_profit_usd > 0.0)
+            .collect()
+    }
+    
+    /// Runs client-signed transactions in order as the next block
+    pub async fn simulate_bundle(&self, txs: Vec<Transaction>) -> Result<BundleSimulation> {
+        self.bundles.simulate(txs).await
+    }
+    
+    /// How much a sandwich could take from each swap in `tx`, which has not
+    /// been sent, and the tolerance that would leave nothing to take
+    pub async fn sandwich_exposure(&self, tx: &Transaction) -> Result<MevProtection> {
+        let eth_usd = self.prices.eth_usd_price().await?;
+        let prediction = self.ethereum.get_gas_prediction(None).await?;
+        let gas_price_gwei = prediction.base_fee_gwei + prediction.priority_fee_gwei + SANDWICH_TIP_BUMP_GWEI;
+        let gas_cost_wei = SANDWICH_GAS as f64 * gas_price_gwei * 1e9;
+        
+        let mut swaps = Vec::new();
+        for swap in self.decode_swaps(tx) {
+            swaps.push(self.swap_exposure(tx, &swap, gas_cost_wei, eth_usd).await?);
         }
-        false
+        
+        Ok(MevProtection {
+            use_private_relay: swaps.iter().any(|swap| swap.use_private_relay),
+            swaps,
+        })
     }
     
-    async fn check_sandwich_opportunity(&self, tx: &Transaction) -> Option<MEVOpportunity> {
-        // Simplified sandwich detection
-        // Real implementation would decode swap data and simulate with REVM
+    /// Swaps buying with the wrapped native token are replayed with REVM;
+    /// those selling for it are priced from the pair's reserves
+    async fn swap_exposure(
+        &self,
+        tx: &Transaction,
+        swap: &SwapIntent,
+        gas_cost_wei: f64,
+        eth_usd: f64,
+    ) -> Result<SandwichExposure> {
+        let mut exposure = SandwichExposure {
+            token_in: swap.token_in(),
+            token_out: swap.token_out(),
+            pool: None,
+            amount_in: swap.amount_in.to_string(),
+            amount_out_min: swap.amount_out.to_string(),
+            expected_amount_out: None,
+            slippage_bps: None,
+            max_extractable_eth: None,
+            max_extractable_usd: None,
+            sandwichable: false,
+            recommended_slippage_bps: None,
+            recommended_amount_out_min: None,
+            use_private_relay: true,
+            simulated: false,
+            reason: String::new(),
+        };
+        
+        let weth = self.dexes.wrapped_native();
+        if swap.protocol != DexProtocol::UniswapV2
+            || swap.kind != SwapKind::ExactIn
+            || swap.hops.len() != 1
+            || swap.amount_in.is_zero()
+        {
+            exposure.reason = "Only single-hop exact-input V2 swaps can be assessed; route it privately".to_string();
+            return Ok(exposure);
+        }
+        if exposure.token_in != weth && exposure.token_out != weth {
+            exposure.reason = "Only swaps to or from the wrapped native token can be priced; route it privately".to_string();
+            return Ok(exposure);
+        }
+        let Some((pool, reserve_in, reserve_out)) = self.sandwich.pair_reserves(swap).await? else {
+            exposure.reason = "No V2 pair found for the swap's router; route it privately".to_string();
+            return Ok(exposure);
+        };
+        exposure.pool = Some(pool);
+        
+        // Checked by the handler first; uint112 reserves always fit
+        let (Some(amount_in), Some(min_out)) = (checked_f64(swap.amount_in), checked_f64(swap.amount_out)) else {
+            exposure.reason = "Swap amounts are too large to assess; route it privately".to_string();
+            return Ok(exposure);
+        };
+        let (reserve_in, reserve_out) = (wei(reserve_in), wei(reserve_out));
+        // Wei per smallest unit of the input token
+        let wei_per_in = if exposure.token_in == weth { 1.0 } else { reserve_out / reserve_in };
+        
+        let mut profit = mev_protection::max_sandwich_profit(reserve_in, reserve_out, amount_in, min_out);
+        let mut cost = gas_cost_wei / wei_per_in;
+        if exposure.token_in == weth {
+            match self.sandwich.simulate(tx, swap).await {
+                Ok(Some(sandwich)) => {
+                    profit = wei(sandwich.profit_wei());
+                    cost = wei(sandwich.gas_cost_wei());
+                    exposure.simulated = true;
+                }
+                Ok(None) => {
+                    profit = 0.0;
+                    exposure.simulated = true;
+                }
+                // Nothing can be said about a swap that fails by itself
+                Err(e) if e.is::<VictimReverted>() => {
+                    exposure.reason = "The swap reverts against the latest block, so it was not simulated".to_string();
+                    return Ok(exposure);
+                }
+                Err(e) => tracing::debug!("Sandwich simulation of an unsent swap failed: {}", e),
+            }
+        }
         
-        // Mock opportunity for testing
-        Some(MEVOpportunity {
+        let expected = mev_protection::amount_out(amount_in, reserve_in, reserve_out);
+        let slippage = mev_protection::slippage_bps(reserve_in, reserve_out, amount_in, min_out);
+        let safe = mev_protection::safe_slippage_bps(reserve_in, reserve_out, amount_in, cost);
+        let recommended = slippage.min(safe);
+        let max_extractable_eth = profit * wei_per_in / 1e18;
+        
+        exposure.expected_amount_out = Some(format!("{:.0}", expected));
+        exposure.slippage_bps = Some(slippage);
+        exposure.max_extractable_eth = Some(max_extractable_eth);
+        exposure.max_extractable_usd = Some(max_extractable_eth * eth_usd);
+        exposure.sandwichable = profit > cost;
+        exposure.recommended_slippage_bps = Some(recommended);
+        exposure.recommended_amount_out_min = Some(format!("{:.0}", (expected * (1.0 - recommended / 1e4)).ceil()));
+        exposure.use_private_relay = exposure.sandwichable && safe < MIN_PUBLIC_SLIPPAGE_BPS;
+        exposure.reason = if !exposure.sandwichable {
+            "No sandwich covers its gas at this slippage".to_string()
+        } else if exposure.use_private_relay {
+            format!("Sandwichable even at {:.1} bps slippage; route it privately", safe)
+        } else {
+            format!("Sandwichable; tighten slippage to {:.1} bps", recommended)
+        };
+        
+        Ok(exposure)
+    }
+    
+    async fn check_sandwich_opportunity(
+        &self,
+        tx: &Transaction,
+        swap: &SwapIntent,
+    ) -> Option<(MEVOpportunity, SimulatedSandwich)> {
+        let sandwich = match self.sandwich.simulate(tx, swap).await {
+            Ok(sandwich) => sandwich?,
+            Err(e) => {
+                tracing::debug!("Sandwich simulation of {:?} on {} failed: {}", tx.hash, self.ethereum.chain().name, e);
+                return None;
+            }
+        };
+        
+        let eth_usd = match self.prices.eth_usd_price().await {
+            Ok(price) => price,
+            Err(e) => {
+                tracing::warn!("No ETH price for MEV profit: {}", e);
+                return None;
+            }
+        };
+        
+        let profit_usd = to_eth(sandwich.profit_wei()) * eth_usd;
+        let gas_cost_usd = to_eth(sandwich.gas_cost_wei()) * eth_usd;
+        
+        let opportunity = MEVOpportunity {
             opportunity_type: MEVType::Sandwich,
-            profit_usd: 25.50,
-            gas_cost_usd: 5.50,
-            net_profit_usd: 20.00,
-            confidence: 0.75,
+            profit_usd,
+            gas_cost_usd,
+            net_profit_usd: profit_usd - gas_cost_usd,
+            confidence: SIMULATION_CONFIDENCE,
             target_transaction: format!("{:?}", tx.hash),
-            suggested_gas_price: 50.0,
-            execution_details: ExecutionDetails {
-                target_pool: "0x0000000000000000000000000000000000000000".parse().unwrap(),
-                token_in: "0x0000000000000000000000000000000000000000".parse().unwrap(),
-                token_out: "0x0000000000000000000000000000000000000000".parse().unwrap(),
-                amount_in: "1000.0".to_string(),
-                expected_profit: "20.0".to_string(),
-            },
+            suggested_gas_price: wei(sandwich.frontrun_gas_price) / 1e9,
+            execution_details: execution_details(&sandwich),
             expires_in_blocks: 1,
             detected_at: Utc::now(),
-        })
+            bundle: None,
+        };
+        Some((opportunity, sandwich))
+    }
+    
+    /// Liquidations unlocked by a pending Chainlink report, backrunning it at
+    /// the same gas price
+    async fn check_liquidation_opportunities(&self, tx: &Transaction) -> Vec<(MEVOpportunity, BundlePlan)> {
+        let Some(eth_usd) = self.eth_usd().await else {
+            return Vec::new();
+        };
+        let candidates = self.liquidations.after_price_update(tx, eth_usd);
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+        
+        let max_fee = tx.gas_price.or(tx.max_fee_per_gas).unwrap_or_default();
+        let tip = tx.max_priority_fee_per_gas.unwrap_or(max_fee);
+        
+        candidates
+            .into_iter()
+            .map(|candidate| {
+                let opportunity = liquidation_opportunity(&candidate, Some(tx), wei(max_fee) / 1e9, eth_usd);
+                (opportunity, BundlePlan::Liquidation { candidate, max_fee, tip })
+            })
+            .filter(|(opportunity, _)| opportunity.net_profit_usd > 0.0)
+            .collect()
+    }
+    
+    async fn eth_usd(&self) -> Option<f64> {
+        match self.prices.eth_usd_price().await {
+            Ok(price) => Some(price),
+            Err(e) => {
+                tracing::warn!("No ETH price for MEV profit: {}", e);
+                None
+            }
+        }
+    }
+    
+    /// Most profitable cycle after gas, paying the victim's gas price so the
+    /// backrun lands right behind it
+    async fn check_arbitrage_opportunity(&self, tx: &Transaction) -> Option<MEVOpportunity> {
+        let cycles = self.arbitrage.backrun_cycles(tx);
+        if cycles.is_empty() {
+            return None;
+        }
+        
+        let eth_usd = match self.prices.eth_usd_price().await {
+            Ok(price) => price,
+            Err(e) => {
+                tracing::warn!("No ETH price for MEV profit: {}", e);
+                return None;
+            }
+        };
+        
+        let gas_price = tx.gas_price.or(tx.max_fee_per_gas).unwrap_or_default();
+        let gas_price_eth = to_eth(gas_price);
+        
+        cycles
+            .into_iter()
+            .map(|cycle| {
+                let profit_usd = cycle.profit_wei() / 1e18 * eth_usd;
+                let gas_cost_usd = cycle.gas as f64 * gas_price_eth * eth_usd;
+                
+                MEVOpportunity {
+                    opportunity_type: MEVType::Arbitrage,
+                    profit_usd,
+                    gas_cost_usd,
+                    net_profit_usd: profit_usd - gas_cost_usd,
+                    confidence: if cycle.uses_v3 { V3_ARBITRAGE_CONFIDENCE } else { ARBITRAGE_CONFIDENCE },
+                    target_transaction: format!("{:?}", tx.hash),
+                    suggested_gas_price: wei(gas_price) / 1e9,
+                    execution_details: arbitrage_details(&cycle),
+                    expires_in_blocks: 1,
+                    detected_at: Utc::now(),
+                    // Cycles across venues need a searcher contract to execute atomically
+                    bundle: None,
+                }
+            })
+            .filter(|opportunity| opportunity.net_profit_usd > 0.0)
+            .max_by(|a, b| a.net_profit_usd.total_cmp(&b.net_profit_usd))
+    }
+}
+
+/// `trigger` is the oracle update to backrun; open liquidations have none and
+/// an empty `target_transaction`
+fn liquidation_opportunity(
+    candidate: &LiquidationCandidate,
+    trigger: Option<&Transaction>,
+    gas_price_gwei: f64,
+    eth_usd: f64,
+) -> MEVOpportunity {
+    let gas_cost_usd = candidate.gas as f64 * gas_price_gwei / 1e9 * eth_usd;
+    
+    MEVOpportunity {
+        opportunity_type: MEVType::Liquidation,
+        profit_usd: candidate.profit_usd,
+        gas_cost_usd,
+        net_profit_usd: candidate.profit_usd - gas_cost_usd,
+        confidence: if trigger.is_some() { PENDING_LIQUIDATION_CONFIDENCE } else { LIQUIDATION_CONFIDENCE },
+        target_transaction: trigger.map(|tx| format!("{:?}", tx.hash)).unwrap_or_default(),
+        suggested_gas_price: gas_price_gwei,
+        execution_details: ExecutionDetails {
+            target_pool: candidate.market,
+            token_in: candidate.debt_asset,
+            token_out: candidate.collateral_asset,
+            amount_in: candidate.max_repay.to_string(),
+            expected_profit: (candidate.profit_usd / eth_usd).to_string(),
+            route: vec![candidate.market],
+            liquidation: Some(LiquidationDetails {
+                protocol: candidate.protocol,
+                borrower: candidate.borrower,
+                health_factor: candidate.health_factor,
+                liquidation_bonus: candidate.bonus,
+            }),
+        },
+        expires_in_blocks: 1,
+        detected_at: Utc::now(),
+        bundle: None,
+    }
+}
+
+fn arbitrage_details(cycle: &ArbitrageCycle) -> ExecutionDetails {
+    ExecutionDetails {
+        target_pool: cycle.pools[0],
+        token_in: cycle.tokens[0],
+        token_out: cycle.tokens[1],
+        amount_in: (cycle.amount_in_wei / 1e18).to_string(),
+        expected_profit: (cycle.profit_wei() / 1e18).to_string(),
+        route: cycle.pools.clone(),
+        liquidation: None,
+    }
+}
+
+fn execution_details(sandwich: &SimulatedSandwich) -> ExecutionDetails {
+    ExecutionDetails {
+        target_pool: sandwich.pair,
+        token_in: sandwich.wrapped_native,
+        token_out: sandwich.token,
+        amount_in: to_eth(sandwich.frontrun_wei).to_string(),
+        expected_profit: to_eth(sandwich.profit_wei()).to_string(),
+        route: vec![sandwich.pair],
+        liquidation: None,
     }
 }
 
+fn gwei(gwei: f64) -> U256 {
+    U256::from((gwei * 1e9) as u128)
+}
+
+fn to_eth(amount: U256) -> f64 {
+    wei(amount) / 1e18
+}
+
+/// Amounts past `u128::MAX` saturate rather than panic; transaction fees and
+/// values come from untrusted senders
+fn wei(amount: U256) -> f64 {
+    checked_f64(amount).unwrap_or(u128::MAX as f64)
+}
+
+/// `None` past `u128::MAX`
+fn checked_f64(amount: U256) -> Option<f64> {
+    u128::try_from(amount).ok().map(|amount| amount as f64)
+}
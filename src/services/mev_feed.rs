@@ -0,0 +1,168 @@
+use crate::models::{LiveOpportunity, MEVOpportunity};
+use crate::services::{BlockFollower, BundlePlan, MEVDetector, MempoolService};
+use ethers::types::{Address, Transaction};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::{broadcast, OwnedSemaphorePermit, Semaphore};
+
+/// Opportunities buffered per stream subscriber
+const OPPORTUNITY_BUFFER: usize = 1024;
+/// Oldest opportunities are dropped beyond this many
+const MAX_LIVE_OPPORTUNITIES: usize = 1000;
+/// Pending transactions simulated at once
+const MAX_CONCURRENT_ANALYSES: usize = 16;
+
+/// An opportunity, the pending transaction it was found in and what its
+/// bundle is built from
+struct LiveEntry {
+    tx: Arc<Transaction>,
+    opportunity: Arc<LiveOpportunity>,
+    plan: Option<BundlePlan>,
+}
+
+/// Runs MEV detection on every transaction entering the mempool and keeps the
+/// opportunities found until they expire. New ones go out over a broadcast
+/// channel for the opportunity stream.
+pub struct MevFeed {
+    detector: Arc<MEVDetector>,
+    live: RwLock<Vec<LiveEntry>>,
+    /// Latest block seen by the follower
+    head: AtomicU64,
+    opportunities: broadcast::Sender<Arc<LiveOpportunity>>,
+    analysis_slots: Arc<Semaphore>,
+}
+
+impl MevFeed {
+    pub fn new(detector: Arc<MEVDetector>) -> Self {
+        Self {
+            detector,
+            live: RwLock::new(Vec::new()),
+            head: AtomicU64::new(0),
+            opportunities: broadcast::channel(OPPORTUNITY_BUFFER).0,
+            analysis_slots: Arc::new(Semaphore::new(MAX_CONCURRENT_ANALYSES)),
+        }
+    }
+
+    /// Every new opportunity, as it is found
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<LiveOpportunity>> {
+        self.opportunities.subscribe()
+    }
+
+    /// Latest block seen by the follower
+    pub fn head(&self) -> u64 {
+        self.head.load(Ordering::Relaxed)
+    }
+
+    /// Opportunities that have not expired, oldest first
+    pub fn live(&self) -> Vec<Arc<LiveOpportunity>> {
+        self.live.read().unwrap().iter().map(|entry| entry.opportunity.clone()).collect()
+    }
+
+    /// The live opportunities, with bundles for `searcher` built from the
+    /// stored simulations; liquidations target the block after the head
+    pub fn opportunities(&self, searcher: Option<Address>) -> Vec<MEVOpportunity> {
+        let target_block = self.head() + 1;
+        self.live
+            .read()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                let mut opportunity = entry.opportunity.opportunity.clone();
+                if let (Some(searcher), Some(plan)) = (searcher, &entry.plan) {
+                    opportunity.bundle = Some(plan.bundle(&entry.tx, searcher, target_block));
+                }
+                opportunity
+            })
+            .collect()
+    }
+
+    /// Stores `opportunities` found in `tx` and sends them to subscribers.
+    /// Each expires `expires_in_blocks` after the current head.
+    pub fn publish(&self, tx: Arc<Transaction>, opportunities: Vec<(MEVOpportunity, Option<BundlePlan>)>) {
+        if opportunities.is_empty() {
+            return;
+        }
+
+        let head = self.head.load(Ordering::Relaxed);
+        let mut live = self.live.write().unwrap();
+        for (opportunity, plan) in opportunities {
+            let opportunity = Arc::new(LiveOpportunity {
+                expires_at_block: head + opportunity.expires_in_blocks,
+                opportunity,
+            });
+            live.push(LiveEntry {
+                tx: tx.clone(),
+                opportunity: opportunity.clone(),
+                plan,
+            });
+            let _ = self.opportunities.send(opportunity);
+        }
+
+        let excess = live.len().saturating_sub(MAX_LIVE_OPPORTUNITIES);
+        live.drain(..excess);
+    }
+
+    /// Moves the head to `block` and drops the opportunities that expired
+    /// once it was mined
+    pub fn advance(&self, block: u64) {
+        if self.head.fetch_max(block, Ordering::Relaxed) >= block {
+            return;
+        }
+
+        let mut live = self.live.write().unwrap();
+        let before = live.len();
+        live.retain(|entry| entry.opportunity.expires_at_block > block);
+        if live.len() < before {
+            tracing::debug!("Block {} expired {} MEV opportunities", block, before - live.len());
+        }
+    }
+
+    /// Analyses each new pending transaction that swaps on a registered DEX or
+    /// reports a tracked price, and expires opportunities on each new head,
+    /// until the mempool goes away. While every analysis slot is taken the
+    /// stream is not read, so a backlog shows up as skipped transactions.
+    pub async fn run(self: Arc<Self>, mempool: Arc<MempoolService>, follower: Arc<BlockFollower>) {
+        let mut transactions = mempool.subscribe();
+        let mut heads = follower.subscribe();
+        if let Some(update) = follower.latest() {
+            self.advance(update.block_number);
+        }
+
+        loop {
+            tokio::select! {
+                tx = transactions.recv() => match tx {
+                    Ok(tx) if self.detector.may_have_opportunities(&tx) => {
+                        let Ok(slot) = self.analysis_slots.clone().acquire_owned().await else { return };
+                        self.spawn_analysis(tx, slot);
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!("MEV detection fell behind, skipped {} transactions", missed);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+
+                changed = heads.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                    let head = heads.borrow_and_update().as_ref().map(|update| update.block_number);
+                    if let Some(head) = head {
+                        self.advance(head);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Analyses `tx` holding `slot` until it is done
+    fn spawn_analysis(self: &Arc<Self>, tx: Arc<Transaction>, slot: OwnedSemaphorePermit) {
+        let feed = self.clone();
+
+        tokio::spawn(async move {
+            let opportunities = feed.detector.find_opportunities(&tx).await;
+            feed.publish(tx, opportunities);
+            drop(slot);
+        });
+    }
+}
@@ -0,0 +1,535 @@
+use crate::contracts::aave_v3::LiquidationCallFilter;
+use crate::contracts::compound_v3::AbsorbDebtFilter;
+use crate::contracts::uniswap_v3::SwapFilter as V3SwapFilter;
+use crate::contracts::{SwapFilter as V2SwapFilter, Token0Call, Token0Return, Token1Call, Token1Return};
+use crate::models::{
+    BuilderProfit, LendingProtocol, MevBlockReport, MevRangeReport, MinedArbitrage, MinedLiquidation,
+    MinedSandwich, SearcherProfit,
+};
+use crate::services::{
+    chain_client::{call_contract, ChainClient},
+    CacheService, DexRegistry,
+};
+use anyhow::{Context, Result};
+use ethers::contract::{parse_log, EthEvent};
+use ethers::types::{Address, Block, Transaction, TransactionReceipt, I256, U256};
+use futures::{stream, StreamExt, TryStreamExt};
+use std::collections::{HashMap, HashSet};
+use moka::future::Cache;
+use std::sync::Arc;
+
+/// Most blocks in one range report
+pub const MAX_RANGE_BLOCKS: u64 = 100;
+/// Blocks this deep are not expected to reorg, so their reports are cached
+const REORG_DEPTH: u64 = 12;
+const REPORT_CACHE_TTL_SECS: u64 = 86400;
+/// Receipts fetched at once from nodes without `eth_getBlockReceipts`
+const FETCH_CONCURRENCY: usize = 16;
+/// Pools whose tokens are remembered
+const MAX_POOLS: u64 = 10_000;
+
+/// A mined block's transactions with their receipts, in block order
+pub type MinedTransactions = Vec<(Transaction, TransactionReceipt)>;
+
+/// One swap event, normalised to what went in and out of the pool
+#[derive(Debug, Clone)]
+struct PoolSwap {
+    tx: usize,
+    pool: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    amount_out: U256,
+}
+
+/// Measures MEV in mined blocks from their swap and liquidation logs
+pub struct MevHistoryService {
+    client: Arc<dyn ChainClient>,
+    cache: Arc<CacheService>,
+    /// DEXes on the chain, whose routers are not searchers
+    dexes: Arc<DexRegistry>,
+    /// (token0, token1) per pool; pools never change tokens
+    pool_tokens: Cache<Address, (Address, Address)>,
+}
+
+impl MevHistoryService {
+    pub fn new(client: Arc<dyn ChainClient>, cache: Arc<CacheService>, dexes: Arc<DexRegistry>) -> Self {
+        Self {
+            client,
+            cache,
+            dexes,
+            pool_tokens: Cache::new(MAX_POOLS),
+        }
+    }
+
+    /// `None` if the block is not mined yet
+    pub async fn block_report(&self, number: u64) -> Result<Option<MevBlockReport>> {
+        let head = self.client.block_number().await?;
+        if number > head {
+            return Ok(None);
+        }
+        self.report_at(number, head).await.map(Some)
+    }
+
+    /// Blocks `from..=to`, which must be at most [`MAX_RANGE_BLOCKS`] long.
+    /// Blocks past the head are left out; `None` if `from` is not mined yet.
+    pub async fn range_report(&self, from: u64, to: u64) -> Result<Option<MevRangeReport>> {
+        let head = self.client.block_number().await?;
+        if from > head {
+            return Ok(None);
+        }
+        let mut blocks = Vec::new();
+        for number in from..=to.min(head) {
+            blocks.push(self.report_at(number, head).await?);
+        }
+        Ok(Some(summarize_range(from, to.min(head), blocks)))
+    }
+
+    async fn report_at(&self, number: u64, head: u64) -> Result<MevBlockReport> {
+        let cache_key = format!("mev_history:block:{}", number);
+        if let Some(cached) = self.cache.get(&cache_key).await.ok().flatten() {
+            return Ok(cached);
+        }
+
+        let (block, txs) = self.mined_block(number).await?;
+        let pool_tokens = self.pool_tokens(&txs).await;
+        let builder_balance_change = self.builder_balance_change(&block).await;
+        let report = analyze_block(&block, &txs, &pool_tokens, &self.dexes, builder_balance_change);
+
+        if number + REORG_DEPTH <= head {
+            let _ = self.cache.set(&cache_key, &report, REPORT_CACHE_TTL_SECS).await;
+        }
+        Ok(report)
+    }
+
+    /// The block with its transactions and receipts, from one
+    /// `eth_getBlockByNumber` and one `eth_getBlockReceipts` call. Nodes
+    /// without the latter are asked for each receipt instead.
+    async fn mined_block(&self, number: u64) -> Result<(Block<Transaction>, MinedTransactions)> {
+        let (block, receipts) = tokio::join!(
+            self.client.block_with_transactions(number),
+            self.client.block_receipts(number)
+        );
+        let mut block = block?.with_context(|| format!("Block {} not found", number))?;
+        let transactions = std::mem::take(&mut block.transactions);
+
+        let receipts = match receipts {
+            Ok(receipts) if receipts.len() == transactions.len() => receipts,
+            result => {
+                if let Err(e) = result {
+                    tracing::debug!("eth_getBlockReceipts failed for block {}: {}", number, e);
+                }
+                stream::iter(&transactions)
+                    .map(|tx| async move { self.client.receipt(tx.hash).await?.context("Receipt not found") })
+                    .buffered(FETCH_CONCURRENCY)
+                    .try_collect()
+                    .await?
+            }
+        };
+        Ok((block, transactions.into_iter().zip(receipts).collect()))
+    }
+
+    /// What the fee recipient's balance changed by over the block, in ETH.
+    /// `None` for the first block, or when the node has no state that old.
+    async fn builder_balance_change<T>(&self, block: &Block<T>) -> Option<f64> {
+        let builder = block.author.filter(|author| !author.is_zero())?;
+        let previous = block.number?.as_u64().checked_sub(1)?;
+        let (before, after) =
+            tokio::join!(self.client.balance(builder, previous), self.client.balance(builder, previous + 1));
+        match (before, after) {
+            (Ok(before), Ok(after)) => Some(to_eth(after) - to_eth(before)),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::debug!("Builder balance unavailable around block {}: {}", previous + 1, e);
+                None
+            }
+        }
+    }
+
+    /// Tokens of the pools that swapped in `txs`. Pools whose tokens cannot
+    /// be read are left out of the analysis.
+    async fn pool_tokens(&self, txs: &MinedTransactions) -> HashMap<Address, (Address, Address)> {
+        let pools: HashSet<Address> = txs
+            .iter()
+            .flat_map(|(_, receipt)| &receipt.logs)
+            .filter(|log| {
+                let topic = log.topics.first();
+                topic == Some(&V2SwapFilter::signature()) || topic == Some(&V3SwapFilter::signature())
+            })
+            .map(|log| log.address)
+            .collect();
+
+        let mut tokens = HashMap::new();
+        for pool in pools {
+            if let Some(known) = self.pool_tokens.get(&pool).await {
+                tokens.insert(pool, known);
+                continue;
+            }
+            let lookup = async {
+                let token0 = call_contract::<_, _, Token0Return>(&*self.client, pool, Token0Call).await?.0;
+                let token1 = call_contract::<_, _, Token1Return>(&*self.client, pool, Token1Call).await?.0;
+                anyhow::Ok((token0, token1))
+            };
+            match lookup.await {
+                Ok(pair) => {
+                    self.pool_tokens.insert(pool, pair).await;
+                    tokens.insert(pool, pair);
+                }
+                Err(e) => tracing::debug!("Skipping pool {:?} without readable tokens: {}", pool, e),
+            }
+        }
+        tokens
+    }
+}
+
+/// Finds sandwiches, atomic arbitrage and liquidations in a mined block.
+/// `txs` are its transactions with receipts, in block order, and
+/// `builder_balance_change` what the fee recipient's balance changed by.
+pub fn analyze_block<T>(
+    block: &Block<T>,
+    txs: &[(Transaction, TransactionReceipt)],
+    pool_tokens: &HashMap<Address, (Address, Address)>,
+    dexes: &DexRegistry,
+    builder_balance_change: Option<f64>,
+) -> MevBlockReport {
+    let builder = block.author.unwrap_or_default();
+    let base_fee = block.base_fee_per_gas.unwrap_or_default();
+
+    let mut swaps = Vec::new();
+    for (index, (_, receipt)) in txs.iter().enumerate() {
+        swaps.extend(receipt.logs.iter().filter_map(|log| {
+            let &(token0, token1) = pool_tokens.get(&log.address)?;
+            let (zero_for_one, amount_in, amount_out) = if let Ok(swap) = parse_log::<V2SwapFilter>(log.clone()) {
+                if swap.amount_0_in > swap.amount_1_in {
+                    (true, swap.amount_0_in, swap.amount_1_out)
+                } else {
+                    (false, swap.amount_1_in, swap.amount_0_out)
+                }
+            } else if let Ok(swap) = parse_log::<V3SwapFilter>(log.clone()) {
+                // Positive amounts went into the pool
+                if swap.amount_0 > I256::zero() {
+                    (true, swap.amount_0.into_raw(), swap.amount_1.unsigned_abs())
+                } else {
+                    (false, swap.amount_1.into_raw(), swap.amount_0.unsigned_abs())
+                }
+            } else {
+                return None;
+            };
+
+            let (token_in, token_out) = if zero_for_one { (token0, token1) } else { (token1, token0) };
+            Some(PoolSwap {
+                tx: index,
+                pool: log.address,
+                token_in,
+                token_out,
+                amount_in,
+                amount_out,
+            })
+        }));
+    }
+
+    let gas_cost = |index: usize| gas_cost_eth(&txs[index].1);
+    let sandwiches = find_sandwiches(txs, &swaps, dexes);
+    let sandwich_txs: HashSet<usize> = sandwiches
+        .iter()
+        .flat_map(|(front, back, _)| [swaps[*front].tx, swaps[*back].tx])
+        .collect();
+
+    let mined_sandwiches: Vec<MinedSandwich> = sandwiches
+        .iter()
+        .map(|&(front, back, ref victims)| {
+            let (front_swap, back_swap) = (&swaps[front], &swaps[back]);
+            let profit = signed_difference(back_swap.amount_out, front_swap.amount_in);
+            MinedSandwich {
+                pool: front_swap.pool,
+                searcher: txs[front_swap.tx].0.from,
+                frontrun: txs[front_swap.tx].0.hash,
+                victims: victims.iter().map(|&victim| txs[swaps[victim].tx].0.hash).collect(),
+                backrun: txs[back_swap.tx].0.hash,
+                token: front_swap.token_in,
+                profit: format!("{:.0}", profit),
+                profit_eth: eth_value(front_swap.token_in, profit, dexes),
+                gas_cost_eth: gas_cost(front_swap.tx) + gas_cost(back_swap.tx),
+            }
+        })
+        .collect();
+
+    let arbitrages: Vec<MinedArbitrage> = (0..txs.len())
+        .filter(|index| !sandwich_txs.contains(index))
+        .filter_map(|index| {
+            let hops: Vec<&PoolSwap> = swaps.iter().filter(|swap| swap.tx == index).collect();
+            let (first, last) = (hops.first()?, hops.last()?);
+            let chained = hops.windows(2).all(|pair| pair[0].token_out == pair[1].token_in);
+            let profit = signed_difference(last.amount_out, first.amount_in);
+            if hops.len() < 2 || !chained || first.token_in != last.token_out || profit <= 0.0 {
+                return None;
+            }
+
+            Some(MinedArbitrage {
+                tx_hash: txs[index].0.hash,
+                searcher: txs[index].0.from,
+                pools: hops.iter().map(|hop| hop.pool).collect(),
+                token: first.token_in,
+                profit: format!("{:.0}", profit),
+                profit_eth: eth_value(first.token_in, profit, dexes),
+                gas_cost_eth: gas_cost(index),
+            })
+        })
+        .collect();
+
+    let liquidations: Vec<MinedLiquidation> = txs
+        .iter()
+        .flat_map(|(tx, receipt)| receipt.logs.iter().map(move |log| (tx, receipt, log)))
+        .filter_map(|(tx, receipt, log)| {
+            if let Ok(event) = parse_log::<LiquidationCallFilter>(log.clone()) {
+                Some(MinedLiquidation {
+                    tx_hash: tx.hash,
+                    protocol: LendingProtocol::AaveV3,
+                    market: log.address,
+                    liquidator: event.liquidator,
+                    borrower: event.user,
+                    collateral_asset: Some(event.collateral_asset),
+                    debt_asset: Some(event.debt_asset),
+                    debt_repaid: event.debt_to_cover.to_string(),
+                    collateral_seized: Some(event.liquidated_collateral_amount.to_string()),
+                    gas_cost_eth: gas_cost_eth(receipt),
+                })
+            } else if let Ok(event) = parse_log::<AbsorbDebtFilter>(log.clone()) {
+                Some(MinedLiquidation {
+                    tx_hash: tx.hash,
+                    protocol: LendingProtocol::CompoundV3,
+                    market: log.address,
+                    liquidator: event.absorber,
+                    borrower: event.borrower,
+                    collateral_asset: None,
+                    debt_asset: None,
+                    debt_repaid: event.base_paid_out.to_string(),
+                    collateral_seized: None,
+                    gas_cost_eth: gas_cost_eth(receipt),
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let priority_fees_eth = txs
+        .iter()
+        .map(|(_, receipt)| {
+            let tip = receipt.effective_gas_price.unwrap_or_default().saturating_sub(base_fee);
+            to_eth(tip * receipt.gas_used.unwrap_or_default())
+        })
+        .sum();
+    let proposer_payment_eth = txs
+        .last()
+        .filter(|(tx, _)| !builder.is_zero() && tx.from == builder)
+        .map(|(tx, _)| to_eth(tx.value))
+        .unwrap_or_default();
+    // The balance has already paid the proposer, and takes in direct transfers
+    // that receipts do not show; without it only priority fees are known
+    let builder_revenue_eth = builder_balance_change
+        .map(|change| change + proposer_payment_eth)
+        .unwrap_or(priority_fees_eth);
+
+    let searchers = searcher_profits(&mined_sandwiches, &arbitrages, &liquidations, txs);
+
+    MevBlockReport {
+        block_number: block.number.unwrap_or_default().as_u64(),
+        timestamp: block.timestamp.as_u64(),
+        builder,
+        priority_fees_eth,
+        proposer_payment_eth,
+        builder_revenue_eth,
+        sandwiches: mined_sandwiches,
+        arbitrages,
+        liquidations,
+        searchers,
+    }
+}
+
+/// (frontrun, backrun, victims) as indexes into `swaps`. A frontrun and a
+/// backrun are sent by the same searcher, trade the same pool in opposite
+/// directions, and enclose at least one swap by someone else in the
+/// frontrun's direction.
+fn find_sandwiches(
+    txs: &[(Transaction, TransactionReceipt)],
+    swaps: &[PoolSwap],
+    dexes: &DexRegistry,
+) -> Vec<(usize, usize, Vec<usize>)> {
+    let mut sandwiches = Vec::new();
+    let mut used = HashSet::new();
+
+    for (front, front_swap) in swaps.iter().enumerate() {
+        if used.contains(&front) {
+            continue;
+        }
+        let searcher = &txs[front_swap.tx].0;
+
+        let backrun = swaps.iter().enumerate().skip(front + 1).find(|(back, back_swap)| {
+            !used.contains(back)
+                && back_swap.tx > front_swap.tx
+                && back_swap.pool == front_swap.pool
+                && back_swap.token_in == front_swap.token_out
+                && same_searcher(searcher, &txs[back_swap.tx].0, dexes)
+        });
+        let Some((back, back_swap)) = backrun else {
+            continue;
+        };
+
+        let victims: Vec<usize> = swaps
+            .iter()
+            .enumerate()
+            .filter(|(_, swap)| {
+                swap.tx > front_swap.tx
+                    && swap.tx < back_swap.tx
+                    && swap.pool == front_swap.pool
+                    && swap.token_in == front_swap.token_in
+                    && !same_searcher(searcher, &txs[swap.tx].0, dexes)
+            })
+            .map(|(victim, _)| victim)
+            .collect();
+        if victims.is_empty() {
+            continue;
+        }
+
+        used.insert(front);
+        used.insert(back);
+        sandwiches.push((front, back, victims));
+    }
+    sandwiches
+}
+
+/// Same sender, or the same contract when it is not a registered router
+fn same_searcher(a: &Transaction, b: &Transaction, dexes: &DexRegistry) -> bool {
+    if a.from == b.from {
+        return true;
+    }
+    match (a.to, b.to) {
+        (Some(a), Some(b)) if a == b => !dexes.is_router(a),
+        _ => false,
+    }
+}
+
+fn searcher_profits(
+    sandwiches: &[MinedSandwich],
+    arbitrages: &[MinedArbitrage],
+    liquidations: &[MinedLiquidation],
+    txs: &[(Transaction, TransactionReceipt)],
+) -> Vec<SearcherProfit> {
+    let mut searchers: HashMap<Address, SearcherProfit> = HashMap::new();
+
+    for sandwich in sandwiches {
+        let searcher = searcher_entry(&mut searchers, sandwich.searcher);
+        searcher.sandwiches += 1;
+        searcher.gross_profit_eth += sandwich.profit_eth.unwrap_or_default();
+        searcher.gas_cost_eth += sandwich.gas_cost_eth;
+    }
+    for arbitrage in arbitrages {
+        let searcher = searcher_entry(&mut searchers, arbitrage.searcher);
+        searcher.arbitrages += 1;
+        searcher.gross_profit_eth += arbitrage.profit_eth.unwrap_or_default();
+        searcher.gas_cost_eth += arbitrage.gas_cost_eth;
+    }
+    for liquidation in liquidations {
+        // Attributed to the sender, who may differ from the logged liquidator.
+        // The seized collateral has no price here, so neither it nor the gas
+        // counts towards profit.
+        let sender = txs
+            .iter()
+            .find(|(tx, _)| tx.hash == liquidation.tx_hash)
+            .map(|(tx, _)| tx.from)
+            .unwrap_or(liquidation.liquidator);
+        searcher_entry(&mut searchers, sender).liquidations += 1;
+    }
+
+    let mut searchers: Vec<SearcherProfit> = searchers
+        .into_values()
+        .map(|mut searcher| {
+            searcher.net_profit_eth = searcher.gross_profit_eth - searcher.gas_cost_eth;
+            searcher
+        })
+        .collect();
+    searchers.sort_by(|a, b| b.net_profit_eth.total_cmp(&a.net_profit_eth));
+    searchers
+}
+
+fn searcher_entry(searchers: &mut HashMap<Address, SearcherProfit>, searcher: Address) -> &mut SearcherProfit {
+    searchers.entry(searcher).or_insert_with(|| SearcherProfit {
+        searcher,
+        sandwiches: 0,
+        arbitrages: 0,
+        liquidations: 0,
+        gross_profit_eth: 0.0,
+        gas_cost_eth: 0.0,
+        net_profit_eth: 0.0,
+    })
+}
+
+/// Totals of consecutive block reports, per searcher and per builder
+pub fn summarize_range(from: u64, to: u64, blocks: Vec<MevBlockReport>) -> MevRangeReport {
+    let mut searchers: HashMap<Address, SearcherProfit> = HashMap::new();
+    let mut builders: HashMap<Address, BuilderProfit> = HashMap::new();
+
+    for block in &blocks {
+        for profit in &block.searchers {
+            let total = searcher_entry(&mut searchers, profit.searcher);
+            total.sandwiches += profit.sandwiches;
+            total.arbitrages += profit.arbitrages;
+            total.liquidations += profit.liquidations;
+            total.gross_profit_eth += profit.gross_profit_eth;
+            total.gas_cost_eth += profit.gas_cost_eth;
+            total.net_profit_eth += profit.net_profit_eth;
+        }
+
+        let builder = builders.entry(block.builder).or_insert_with(|| BuilderProfit {
+            builder: block.builder,
+            blocks: 0,
+            priority_fees_eth: 0.0,
+            revenue_eth: 0.0,
+            proposer_payments_eth: 0.0,
+            net_profit_eth: 0.0,
+        });
+        builder.blocks += 1;
+        builder.priority_fees_eth += block.priority_fees_eth;
+        builder.revenue_eth += block.builder_revenue_eth;
+        builder.proposer_payments_eth += block.proposer_payment_eth;
+        builder.net_profit_eth += block.builder_revenue_eth - block.proposer_payment_eth;
+    }
+
+    let mut searchers: Vec<SearcherProfit> = searchers.into_values().collect();
+    searchers.sort_by(|a, b| b.net_profit_eth.total_cmp(&a.net_profit_eth));
+    let mut builders: Vec<BuilderProfit> = builders.into_values().collect();
+    builders.sort_by(|a, b| b.net_profit_eth.total_cmp(&a.net_profit_eth));
+
+    MevRangeReport {
+        from_block: from,
+        to_block: to,
+        sandwiches: blocks.iter().map(|block| block.sandwiches.len() as u64).sum(),
+        arbitrages: blocks.iter().map(|block| block.arbitrages.len() as u64).sum(),
+        liquidations: blocks.iter().map(|block| block.liquidations.len() as u64).sum(),
+        searchers,
+        builders,
+        blocks,
+    }
+}
+
+fn gas_cost_eth(receipt: &TransactionReceipt) -> f64 {
+    to_eth(receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or_default())
+}
+
+/// `a - b` in raw token units
+fn signed_difference(a: U256, b: U256) -> f64 {
+    to_f64(a) - to_f64(b)
+}
+
+/// Set when `token` is the chain's wrapped native token
+fn eth_value(token: Address, amount: f64, dexes: &DexRegistry) -> Option<f64> {
+    (token == dexes.wrapped_native()).then_some(amount / 1e18)
+}
+
+fn to_eth(wei: U256) -> f64 {
+    to_f64(wei) / 1e18
+}
+
+fn to_f64(value: U256) -> f64 {
+    value.to_string().parse().unwrap_or_default()
+}
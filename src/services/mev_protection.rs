@@ -0,0 +1,93 @@
+//! Constant-product model of a sandwich around a single-hop V2 swap, used to
+//! find the slippage tolerance at which sandwiching stops paying. Amounts and
+//! reserves are in the input or output token's smallest unit.
+
+/// V2 pairs keep 0.3% of every input
+const FEE_MULTIPLIER: f64 = 0.997;
+/// Frontruns are searched up to this multiple of the input reserve
+const MAX_FRONTRUN_RESERVES: f64 = 10.0;
+const SEARCH_STEPS: usize = 100;
+
+pub fn amount_out(amount_in: f64, reserve_in: f64, reserve_out: f64) -> f64 {
+    if amount_in <= 0.0 || reserve_in <= 0.0 || reserve_out <= 0.0 {
+        return 0.0;
+    }
+    let amount_in = amount_in * FEE_MULTIPLIER;
+    amount_in * reserve_out / (reserve_in + amount_in)
+}
+
+/// Profit in input-token units of buying with `frontrun` ahead of the swap and
+/// selling after it; `None` if the swap would then fail its `min_out`
+pub fn sandwich_profit(reserve_in: f64, reserve_out: f64, amount_in: f64, min_out: f64, frontrun: f64) -> Option<f64> {
+    let bought = amount_out(frontrun, reserve_in, reserve_out);
+    let (reserve_in, reserve_out) = (reserve_in + frontrun, reserve_out - bought);
+
+    let victim_out = amount_out(amount_in, reserve_in, reserve_out);
+    if victim_out < min_out {
+        return None;
+    }
+    let (reserve_in, reserve_out) = (reserve_in + amount_in, reserve_out - victim_out);
+
+    Some(amount_out(bought, reserve_out, reserve_in) - frontrun)
+}
+
+/// Best sandwich profit the swap's `min_out` allows, in input-token units
+pub fn max_sandwich_profit(reserve_in: f64, reserve_out: f64, amount_in: f64, min_out: f64) -> f64 {
+    let profit = |frontrun| sandwich_profit(reserve_in, reserve_out, amount_in, min_out, frontrun);
+
+    // Largest frontrun the swap still tolerates
+    let (mut low, mut high) = (0.0, reserve_in * MAX_FRONTRUN_RESERVES);
+    if profit(high).is_none() {
+        for _ in 0..SEARCH_STEPS {
+            let mid = (low + high) / 2.0;
+            if profit(mid).is_some() {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        high = low;
+    }
+
+    // Pool fees on the frontrun make profit rise, then fall, with its size
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let mut low = 0.0;
+    for _ in 0..SEARCH_STEPS {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+        if profit(left).unwrap_or(f64::MIN) < profit(right).unwrap_or(f64::MIN) {
+            low = left;
+        } else {
+            high = right;
+        }
+    }
+
+    profit((low + high) / 2.0).unwrap_or_default().max(0.0)
+}
+
+/// Slippage tolerance implied by `min_out`, in basis points
+pub fn slippage_bps(reserve_in: f64, reserve_out: f64, amount_in: f64, min_out: f64) -> f64 {
+    let expected = amount_out(amount_in, reserve_in, reserve_out);
+    if expected <= 0.0 {
+        return 0.0;
+    }
+    ((1.0 - min_out / expected) * 1e4).max(0.0)
+}
+
+/// Largest slippage tolerance, in basis points, at which the best sandwich
+/// earns no more than `cost` (the searcher's gas, in input-token units)
+pub fn safe_slippage_bps(reserve_in: f64, reserve_out: f64, amount_in: f64, cost: f64) -> f64 {
+    let expected = amount_out(amount_in, reserve_in, reserve_out);
+    let profit_at = |bps: f64| max_sandwich_profit(reserve_in, reserve_out, amount_in, expected * (1.0 - bps / 1e4));
+
+    let (mut low, mut high) = (0.0, 1e4);
+    for _ in 0..SEARCH_STEPS / 2 {
+        let mid = (low + high) / 2.0;
+        if profit_at(mid) <= cost {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
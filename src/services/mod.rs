@@ -1,14 +1,64 @@
+pub mod agent_ledger;
+pub mod arbitrage;
+pub mod block_follower;
+pub mod bundle;
 pub mod cache;
+pub mod chain_client;
+pub mod chains;
+pub mod dex_registry;
+pub mod erc8004;
 pub mod ethereum;
+pub mod fake_chain;
+pub mod fake_erc8004;
+pub mod fork_db;
+pub mod gas_history;
+pub mod gas_predictor;
+pub mod l2_fees;
+pub mod liquidation;
 pub mod reputation;
+pub mod sandwich;
 pub mod analytics;
 pub mod mempool;
+pub mod mempool_analytics;
+pub mod mempool_store;
 pub mod mev_detector;
+pub mod mev_feed;
+pub mod mev_history;
+pub mod mev_protection;
+pub mod outbound;
+pub mod price;
+pub mod provider_pool;
+pub mod swap_decoder;
+pub mod tx_lifecycle;
+pub mod watchlist;
 
+pub use agent_ledger::{AgentEvent, AgentLedger};
+pub use arbitrage::{ArbitrageCycle, ArbitrageScanner, PoolSpec};
+pub use block_follower::BlockFollower;
+pub use bundle::{decode_raw_transaction, BundleSimulator};
 pub use cache::CacheService;
+pub use chain_client::{BlockSource, ChainClient, ContractCaller, LogSource, StateSource, TransactionSource};
+pub use chains::ChainRegistry;
+pub use dex_registry::DexRegistry;
+pub use erc8004::{Erc8004Deployment, Erc8004Registry};
 pub use ethereum::EthereumService;
-pub use reputation::ReputationService;
+pub use fake_chain::FakeChain;
+pub use fake_erc8004::FakeErc8004;
+pub use fork_db::ForkDb;
+pub use gas_history::GasHistoryStore;
+pub use gas_predictor::GasPredictor;
+pub use liquidation::{LendingMarket, LiquidationCandidate, LiquidationMonitor};
+pub use reputation::{ReputationConfig, ReputationService};
+pub use sandwich::{SandwichSimulator, SimulatedSandwich, VictimReverted};
 pub use analytics::Analytics;
-pub use mempool::MempoolService;
-pub use mev_detector::MEVDetector;
+pub use mempool::{MempoolConfig, MempoolService, SubscriptionMode};
+pub use mempool_store::{MempoolStore, PendingTransaction};
+pub use mev_detector::{BundlePlan, MEVDetector};
+pub use mev_feed::MevFeed;
+pub use mev_history::MevHistoryService;
+pub use price::{PriceFeed, PriceService};
+pub use provider_pool::{PoolConfig, ProviderPool, RpcProvider};
+pub use swap_decoder::{decode_swaps, DexProtocol, SwapHop, SwapIntent, SwapKind};
+pub use tx_lifecycle::TxLifecycleTracker;
+pub use watchlist::WatchlistService;
 
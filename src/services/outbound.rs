@@ -0,0 +1,66 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// A client for one caller-supplied URL (webhooks, registration files). The
+/// host is resolved once and must only have public addresses; the client is
+/// pinned to them so a second lookup cannot point it elsewhere, and it does not
+/// follow redirects.
+pub async fn public_client(url: &Url, timeout: Duration) -> Result<reqwest::Client> {
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("only HTTP(S) URLs are allowed");
+    }
+    let host = url.host_str().context("URL has no host")?;
+    let port = url.port_or_known_default().context("URL has no port")?;
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .with_context(|| format!("cannot resolve {}", host))?
+        .collect();
+    if addrs.is_empty() {
+        bail!("{} has no addresses", host);
+    }
+    if let Some(private) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+        bail!("{} resolves to non-public address {}", host, private.ip());
+    }
+
+    Ok(reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(host, &addrs)
+        .timeout(timeout)
+        .build()?)
+}
+
+/// False for loopback, private, link-local, shared, multicast and other
+/// special-purpose addresses
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // 0.0.0.0/8, carrier-grade NAT (100.64.0.0/10) and 240.0.0.0/4
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use crate::contracts::uniswap_v3::{LiquidityCall, LiquidityReturn, ObserveCall, ObserveReturn, Slot0Call, Slot0Return};
+use crate::contracts::{
+    DecimalsCall, DecimalsReturn, LatestRoundDataCall, LatestRoundDataReturn, SymbolCall, SymbolReturn, USDC,
+};
+use crate::models::{DexDeployment, DexKind, PriceSource, TokenMetadata, TokenPrice, TokenPrices};
+use crate::services::{
+    chain_client::{call_contract, call_deployed, ContractCaller},
+    CacheService, DexRegistry,
+};
+use anyhow::{bail, Result};
+use ethers::types::Address;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Chainlink USD feeds on Ethereum mainnet for USDC, USDT, DAI, WBTC (from
+/// BTC / USD) and LINK
+pub const DEFAULT_PRICE_FEEDS: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48:0x8fFfFfd4AfB6115b954Bd326cbe7B4BA576818f6,\
+     0xdAC17F958D2ee523a2206206994597C13D831ec7:0x3E7d1eAB13ad0104d2750B8863b2B6a3d0E6C4D6,\
+     0x6B175474E89094C44Da98b954EedeAC495271d0F:0xAed0c38402a5d19df6E4c03F4E2DceD6e29c1ee9,\
+     0x2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599:0xF4030086522a5bEEa4988F8cA5B36dbC97BeE88c,\
+     0x514910771AF9Ca656af840dff83E8264EcF986CA:0x2c1d072e956AFFC0D435Cb7AC38EF18d24d9127c";
+
+/// Feeds older than this are still used, but logged as stale
+const MAX_FEED_AGE_SECS: u64 = 3600;
+/// Prices are cached for a minute; token metadata never changes
+const PRICE_TTL_SECS: u64 = 60;
+const METADATA_TTL_SECS: u64 = 86_400;
+/// Averaging window of pool prices, in seconds
+const TWAP_WINDOW_SECS: u32 = 1800;
+
+/// A token priced from a Chainlink USD feed, written `token:feed`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceFeed {
+    pub token: Address,
+    pub feed: Address,
+}
+
+impl FromStr for PriceFeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, feed) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Expected token:feed, got '{}'", s))?;
+        Ok(Self {
+            token: token
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid token address '{}'", token))?,
+            feed: feed
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid feed address '{}'", feed))?,
+        })
+    }
+}
+
+pub struct PriceService {
+    provider: Arc<dyn ContractCaller>,
+    cache: Arc<CacheService>,
+    /// Uniswap V3-style DEXes searched for pools
+    dexes: Arc<DexRegistry>,
+    eth_usd_feed: Address,
+    feeds: HashMap<Address, Address>,
+}
+
+impl PriceService {
+    pub fn new(
+        provider: Arc<dyn ContractCaller>,
+        cache: Arc<CacheService>,
+        dexes: Arc<DexRegistry>,
+        eth_usd_feed: Address,
+        feeds: Vec<PriceFeed>,
+    ) -> Self {
+        Self {
+            provider,
+            cache,
+            dexes,
+            eth_usd_feed,
+            feeds: feeds.into_iter().map(|feed| (feed.token, feed.feed)).collect(),
+        }
+    }
+    
+    /// ETH/USD price from the Chainlink feed, cached for 60 seconds
+    pub async fn eth_usd_price(&self) -> Result<f64> {
+        let cache_key = "price:eth_usd";
+        if let Some(cached) = self.cache.get::<f64>(cache_key).await.ok().flatten() {
+            return Ok(cached);
+        }
+        
+        let price = self.read_feed(self.eth_usd_feed, "ETH/USD").await?;
+        
+        let _ = self.cache.set(cache_key, &price, PRICE_TTL_SECS).await;
+        
+        tracing::debug!("ETH/USD price: {:.2}", price);
+        
+        Ok(price)
+    }
+
+    /// Decimals and symbol of an ERC-20, cached for a day
+    pub async fn token_metadata(&self, token: Address) -> Result<TokenMetadata> {
+        let cache_key = format!("token:metadata:{:?}", token);
+        if let Some(cached) = self.cache.get::<TokenMetadata>(&cache_key).await.ok().flatten() {
+            return Ok(cached);
+        }
+
+        let caller = self.provider.as_ref();
+        let DecimalsReturn(decimals) = call_contract(caller, token, DecimalsCall).await?;
+        // Some older tokens return bytes32 or have no symbol at all
+        let symbol = call_contract::<_, _, SymbolReturn>(caller, token, SymbolCall)
+            .await
+            .ok()
+            .map(|SymbolReturn(symbol)| symbol);
+
+        let metadata = TokenMetadata {
+            address: token,
+            symbol,
+            decimals,
+        };
+        let _ = self.cache.set(&cache_key, &metadata, METADATA_TTL_SECS).await;
+
+        Ok(metadata)
+    }
+
+    /// USD price of one whole `token`, cached for 60 seconds. The wrapped
+    /// native token (WETH) and tokens with a configured feed are priced by
+    /// Chainlink; the rest by the TWAP of their most liquid pool on a
+    /// registered Uniswap V3-style DEX against WETH, or else USDC. `None`
+    /// when the token has neither.
+    pub async fn token_price(&self, token: Address) -> Result<Option<TokenPrice>> {
+        let cache_key = format!("price:token:{:?}", token);
+        if let Some(cached) = self.cache.get::<TokenPrice>(&cache_key).await.ok().flatten() {
+            return Ok(Some(cached));
+        }
+
+        let metadata = self.token_metadata(token).await?;
+        let feed = if token == self.dexes.wrapped_native() {
+            Some(self.eth_usd_feed)
+        } else {
+            self.feeds.get(&token).copied()
+        };
+
+        let price = match feed {
+            Some(feed) => {
+                let label = format!("{}/USD", metadata.symbol.as_deref().unwrap_or("token"));
+                TokenPrice {
+                    price_usd: self.read_feed(feed, &label).await?,
+                    address: token,
+                    symbol: metadata.symbol,
+                    decimals: metadata.decimals,
+                    source: PriceSource::Chainlink,
+                    oracle: feed,
+                    quote_token: None,
+                }
+            }
+            None => match self.pool_price(metadata).await? {
+                Some(price) => price,
+                None => return Ok(None),
+            },
+        };
+        let _ = self.cache.set(&cache_key, &price, PRICE_TTL_SECS).await;
+
+        Ok(Some(price))
+    }
+
+    /// Prices of each token, listing those that cannot be priced separately
+    pub async fn token_prices(&self, tokens: &[Address]) -> Result<TokenPrices> {
+        let mut prices = Vec::new();
+        let mut unavailable = Vec::new();
+        for &token in tokens {
+            match self.token_price(token).await {
+                Ok(Some(price)) => prices.push(price),
+                Ok(None) => unavailable.push(token),
+                Err(e) => {
+                    tracing::debug!("Cannot price {:?}: {}", token, e);
+                    unavailable.push(token);
+                }
+            }
+        }
+
+        Ok(TokenPrices { prices, unavailable })
+    }
+
+    /// Price of `token` in its most liquid V3 pool against WETH, or against
+    /// USDC when there is none. Pools are derived from each registered V3-style
+    /// DEX and fee tier; those not deployed are skipped.
+    async fn pool_price(&self, token: TokenMetadata) -> Result<Option<TokenPrice>> {
+        let caller = self.provider.as_ref();
+        let dexes: Vec<&DexDeployment> = self
+            .dexes
+            .deployments()
+            .iter()
+            .filter(|dex| dex.kind == DexKind::UniswapV3)
+            .collect();
+
+        for quote in [self.dexes.wrapped_native(), USDC.parse().unwrap()] {
+            if quote == token.address {
+                continue;
+            }
+
+            let mut best: Option<(Address, u128)> = None;
+            for dex in &dexes {
+                for &fee in &dex.fee_tiers {
+                    let Some(pool) = dex.pool_address(token.address, quote, Some(fee)) else {
+                        continue;
+                    };
+                    let Some(LiquidityReturn(liquidity)) = call_deployed(caller, pool, LiquidityCall).await? else {
+                        continue;
+                    };
+                    if liquidity > best.map_or(0, |(_, most)| most) {
+                        best = Some((pool, liquidity));
+                    }
+                }
+            }
+            let Some((pool, _)) = best else {
+                continue;
+            };
+
+            let (tick, source) = self.pool_tick(pool).await?;
+            let quote_decimals = self.token_metadata(quote).await?.decimals;
+            // 1.0001^tick is token1 per token0 in smallest units
+            let raw = 1.0001f64.powf(tick);
+            let raw = if token.address < quote { raw } else { 1.0 / raw };
+            let price_in_quote = raw * 10f64.powi(token.decimals as i32 - quote_decimals as i32);
+
+            return Ok(Some(TokenPrice {
+                price_usd: price_in_quote * self.quote_usd(quote).await?,
+                address: token.address,
+                symbol: token.symbol,
+                decimals: token.decimals,
+                source,
+                oracle: pool,
+                quote_token: Some(quote),
+            }));
+        }
+
+        Ok(None)
+    }
+
+    /// Average tick over the TWAP window, or the current tick when the pool's
+    /// observations do not reach back that far
+    async fn pool_tick(&self, pool: Address) -> Result<(f64, PriceSource)> {
+        let caller = self.provider.as_ref();
+        let call = ObserveCall {
+            seconds_agos: vec![TWAP_WINDOW_SECS, 0],
+        };
+        match call_contract::<_, _, ObserveReturn>(caller, pool, call).await {
+            Ok(ObserveReturn(tick_cumulatives, _)) if tick_cumulatives.len() == 2 => {
+                let delta = tick_cumulatives[1] - tick_cumulatives[0];
+                Ok((delta as f64 / TWAP_WINDOW_SECS as f64, PriceSource::UniswapV3Twap))
+            }
+            _ => {
+                let slot0 = call_contract::<_, _, Slot0Return>(caller, pool, Slot0Call).await?;
+                Ok((slot0.tick as f64, PriceSource::UniswapV3Spot))
+            }
+        }
+    }
+
+    /// WETH is priced by the ETH/USD feed; USDC by its feed if configured
+    async fn quote_usd(&self, quote: Address) -> Result<f64> {
+        if quote == self.dexes.wrapped_native() {
+            return self.eth_usd_price().await;
+        }
+        match self.feeds.get(&quote) {
+            Some(&feed) => self.read_feed(feed, "USDC/USD").await,
+            None => Ok(1.0),
+        }
+    }
+
+    async fn read_feed(&self, feed: Address, label: &str) -> Result<f64> {
+        let caller = self.provider.as_ref();
+        let DecimalsReturn(decimals) = call_contract(caller, feed, DecimalsCall).await?;
+        let LatestRoundDataReturn { answer, updated_at, .. } =
+            call_contract(caller, feed, LatestRoundDataCall).await?;
+
+        if !answer.is_positive() {
+            bail!("Chainlink {} feed returned non-positive answer: {}", label, answer);
+        }
+
+        let age = (chrono::Utc::now().timestamp() as u64).saturating_sub(updated_at.as_u64());
+        if age > MAX_FEED_AGE_SECS {
+            tracing::warn!("Chainlink {} feed is stale ({}s old)", label, age);
+        }
+
+        Ok(answer.into_raw().as_u128() as f64 / 10f64.powi(decimals as i32))
+    }
+}
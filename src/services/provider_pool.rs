@@ -0,0 +1,396 @@
+use crate::models::ProviderMetrics;
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use ethers::{
+    providers::{Http, HttpClientError, JsonRpcClient, JsonRpcError, Provider, ProviderError, RpcError},
+    types::U64,
+};
+use futures::{stream::FuturesUnordered, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::fmt::Debug;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Provider backed by a [`ProviderPool`]
+pub type RpcProvider = Provider<ProviderPool>;
+
+/// Weight of the newest sample in the latency average
+const LATENCY_SMOOTHING: f64 = 0.2;
+/// A request cancelled because another provider answered first counts as this
+/// much slower than it had been running (or than its average, if higher)
+const CANCELLED_LATENCY_PENALTY: f64 = 1.5;
+
+#[derive(Debug, Clone)]
+pub struct PoolConfig {
+    /// Send the request to the next provider if the first has not answered
+    /// within this delay. `None` disables hedging.
+    pub hedge_delay: Option<Duration>,
+    /// Consecutive transport failures before a provider is taken out of rotation
+    pub failure_threshold: u32,
+    /// How long an open circuit stays open before the provider is retried
+    pub cooldown: Duration,
+    /// Providers that must have reached a head block before it is reported
+    pub quorum: usize,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            hedge_delay: Some(Duration::from_millis(250)),
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(30),
+            quorum: 1,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PoolError {
+    #[error(transparent)]
+    Client(#[from] HttpClientError),
+
+    #[error("Invalid RPC response: {0}")]
+    Serde(#[from] serde_json::Error),
+
+    #[error("No RPC provider available: {0}")]
+    Unavailable(String),
+
+    #[error("RPC quorum not reached: {0}")]
+    Quorum(String),
+}
+
+impl RpcError for PoolError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            PoolError::Client(e) => e.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            PoolError::Client(e) => e.as_serde_error(),
+            PoolError::Serde(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PoolError> for ProviderError {
+    fn from(e: PoolError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+#[derive(Debug, Default)]
+struct EndpointStats {
+    latency_ms: Option<f64>,
+    requests: u64,
+    errors: u64,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl EndpointStats {
+    /// Closed, or open but past its cooldown (half-open)
+    fn available(&self, now: Instant) -> bool {
+        match self.open_until {
+            Some(until) => now >= until,
+            None => true,
+        }
+    }
+
+    /// Lower is better: smoothed latency, penalised by the error rate.
+    /// Providers never measured get `neutral_latency_ms`.
+    fn score(&self, neutral_latency_ms: f64) -> f64 {
+        let error_rate = if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        };
+        self.latency_ms.unwrap_or(neutral_latency_ms) * (1.0 + 4.0 * error_rate)
+    }
+
+    fn record_latency(&mut self, sample_ms: f64) {
+        self.latency_ms = Some(match self.latency_ms {
+            Some(avg) => avg + LATENCY_SMOOTHING * (sample_ms - avg),
+            None => sample_ms,
+        });
+    }
+}
+
+/// Records a latency penalty if the request is dropped before it completes,
+/// which is how hedged requests that lose the race end
+struct InFlight<'a> {
+    endpoint: &'a Endpoint,
+    started: Instant,
+    completed: bool,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+
+        let elapsed_ms = self.started.elapsed().as_secs_f64() * 1000.0;
+        let mut stats = self.endpoint.stats.lock().unwrap();
+        let penalty = stats.latency_ms.map_or(elapsed_ms, |avg| avg.max(elapsed_ms)) * CANCELLED_LATENCY_PENALTY;
+        stats.requests += 1;
+        stats.record_latency(penalty);
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    /// Scheme and host only, so API keys in the path never reach logs or /health
+    label: String,
+    client: Http,
+    stats: Mutex<EndpointStats>,
+}
+
+/// JSON-RPC client spreading requests over several providers. Each provider is
+/// scored by latency and error rate, taken out of rotation after repeated
+/// failures, and retried after a cooldown. Slow requests are hedged to the next
+/// provider, and `eth_blockNumber` can require a quorum of providers.
+#[derive(Debug)]
+pub struct ProviderPool {
+    name: String,
+    endpoints: Vec<Endpoint>,
+    config: PoolConfig,
+}
+
+impl ProviderPool {
+    pub fn new(name: &str, urls: &[String], config: PoolConfig) -> Result<Self> {
+        if urls.is_empty() {
+            bail!("{} needs at least one RPC URL", name);
+        }
+
+        let endpoints = urls
+            .iter()
+            .map(|url| {
+                let client = Http::from_str(url)?;
+                let parsed = reqwest::Url::parse(url)?;
+                Ok(Endpoint {
+                    label: format!("{}://{}", parsed.scheme(), parsed.host_str().unwrap_or_default()),
+                    client,
+                    stats: Mutex::new(EndpointStats::default()),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if config.quorum > endpoints.len() {
+            tracing::warn!(
+                "{} RPC quorum of {} exceeds its {} providers; using {}",
+                name,
+                config.quorum,
+                endpoints.len(),
+                endpoints.len()
+            );
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            endpoints,
+            config,
+        })
+    }
+
+    pub fn connect(name: &str, urls: &[String], config: PoolConfig) -> Result<RpcProvider> {
+        Ok(Provider::new(Self::new(name, urls, config)?))
+    }
+
+    pub fn metrics(&self) -> Vec<ProviderMetrics> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let stats = endpoint.stats.lock().unwrap();
+                ProviderMetrics {
+                    pool: self.name.clone(),
+                    url: endpoint.label.clone(),
+                    available: stats.available(now),
+                    latency_ms: stats.latency_ms,
+                    requests: stats.requests,
+                    errors: stats.errors,
+                    consecutive_failures: stats.consecutive_failures,
+                }
+            })
+            .collect()
+    }
+
+    fn quorum(&self) -> usize {
+        self.config.quorum.clamp(1, self.endpoints.len())
+    }
+
+    /// Available providers, best first. If every circuit is open, all of them
+    /// are returned so a request still has somewhere to go.
+    fn ranked(&self) -> Vec<&Endpoint> {
+        let now = Instant::now();
+        let measured: Vec<f64> = self
+            .endpoints
+            .iter()
+            .filter_map(|endpoint| endpoint.stats.lock().unwrap().latency_ms)
+            .collect();
+        // Unmeasured providers rank like an average measured one
+        let neutral_latency_ms = if measured.is_empty() {
+            0.0
+        } else {
+            measured.iter().sum::<f64>() / measured.len() as f64
+        };
+
+        let mut scored: Vec<(&Endpoint, bool, f64)> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                let stats = endpoint.stats.lock().unwrap();
+                (endpoint, stats.available(now), stats.score(neutral_latency_ms))
+            })
+            .collect();
+
+        if scored.iter().any(|(_, available, _)| *available) {
+            scored.retain(|(_, available, _)| *available);
+        }
+        scored.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+        scored.into_iter().map(|(endpoint, _, _)| endpoint).collect()
+    }
+
+    async fn call(&self, endpoint: &Endpoint, method: &str, params: &Value) -> Result<Value, HttpClientError> {
+        let mut in_flight = InFlight {
+            endpoint,
+            started: Instant::now(),
+            completed: false,
+        };
+        let result = endpoint.client.request::<_, Value>(method, params).await;
+        in_flight.completed = true;
+        let elapsed_ms = in_flight.started.elapsed().as_secs_f64() * 1000.0;
+
+        let mut stats = endpoint.stats.lock().unwrap();
+        stats.requests += 1;
+
+        match &result {
+            // A JSON-RPC error (e.g. a revert) means the provider is working fine
+            Err(e) if e.as_error_response().is_none() => {
+                stats.errors += 1;
+                stats.consecutive_failures += 1;
+
+                if stats.consecutive_failures >= self.config.failure_threshold {
+                    if stats.available(Instant::now()) {
+                        tracing::warn!(
+                            "{} RPC {} failing ({}), out of rotation for {:?}",
+                            self.name,
+                            endpoint.label,
+                            e,
+                            self.config.cooldown
+                        );
+                    }
+                    stats.open_until = Some(Instant::now() + self.config.cooldown);
+                }
+            }
+            _ => {
+                stats.record_latency(elapsed_ms);
+                stats.consecutive_failures = 0;
+                stats.open_until = None;
+            }
+        }
+
+        result
+    }
+
+    /// Tries providers best first, hedging to the next one when a request is slow
+    async fn request_value(&self, method: &str, params: &Value) -> Result<Value, PoolError> {
+        let ranked = self.ranked();
+        let mut remaining = ranked.iter();
+        let mut pending = FuturesUnordered::new();
+        let mut last_error = None;
+
+        if let Some(endpoint) = remaining.next() {
+            pending.push(self.call(endpoint, method, params));
+        }
+
+        while !pending.is_empty() {
+            let hedge = async {
+                match self.config.hedge_delay {
+                    Some(delay) if remaining.len() > 0 => tokio::time::sleep(delay).await,
+                    _ => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                Some(result) = pending.next() => match result {
+                    Ok(value) => return Ok(value),
+                    Err(e) if e.as_error_response().is_some() => return Err(e.into()),
+                    Err(e) => {
+                        last_error = Some(e);
+                        if let Some(endpoint) = remaining.next() {
+                            pending.push(self.call(endpoint, method, params));
+                        }
+                    }
+                },
+                _ = hedge => {
+                    if let Some(endpoint) = remaining.next() {
+                        tracing::debug!("{} {} slow, hedging to {}", self.name, method, endpoint.label);
+                        pending.push(self.call(endpoint, method, params));
+                    }
+                }
+            }
+        }
+
+        Err(match last_error {
+            Some(e) => PoolError::Unavailable(format!("{} (last error: {})", self.name, e)),
+            None => PoolError::Unavailable(self.name.clone()),
+        })
+    }
+
+    /// Highest block that at least `quorum` providers have reached
+    async fn quorum_block_number(&self, params: &Value) -> Result<Value, PoolError> {
+        let quorum = self.quorum();
+        let results = futures::future::join_all(
+            self.ranked()
+                .into_iter()
+                .map(|endpoint| self.call(endpoint, "eth_blockNumber", params)),
+        )
+        .await;
+
+        let mut heights: Vec<U64> = results
+            .into_iter()
+            .filter_map(|result| serde_json::from_value(result.ok()?).ok())
+            .collect();
+
+        if heights.len() < quorum {
+            return Err(PoolError::Quorum(format!(
+                "{}: {} of {} providers answered",
+                self.name,
+                heights.len(),
+                quorum
+            )));
+        }
+
+        heights.sort_by(|a, b| b.cmp(a));
+        Ok(serde_json::to_value(heights[quorum - 1])?)
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for ProviderPool {
+    type Error = PoolError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, PoolError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        let params = serde_json::to_value(params)?;
+
+        let value = if method == "eth_blockNumber" && self.quorum() > 1 {
+            self.quorum_block_number(&params).await?
+        } else {
+            self.request_value(method, &params).await?
+        };
+
+        Ok(serde_json::from_value(value)?)
+    }
+}
@@ -1,84 +1,108 @@
-use crate::contracts::AgentRegistry;
-use crate::services::CacheService;
+use crate::models::{AgentBehavior, AgentProfile, TrustSummary};
+use crate::services::{agent_ledger::AgentLedger, erc8004::Erc8004Registry, CacheService};
 use anyhow::Result;
-use ethers::providers::{Http, Provider};
+use chrono::{DateTime, Utc};
 use ethers::types::Address;
-use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Score of new agents, of agents without ERC-8004 feedback, and of any agent
+/// when the registries fail
+const DEFAULT_REPUTATION: u64 = 250;
+/// Reputation points per point of average feedback (0-100)
+const FEEDBACK_WEIGHT: u64 = 10;
+/// Reputation points per point of average validation response (0-100)
+const VALIDATION_WEIGHT: u64 = 5;
+
+/// Local score: points per verified payment and per day since first seen, up
+/// to a cap each
+const PAYMENT_POINTS: u64 = 5;
+const MAX_PAYMENT_POINTS: u64 = 750;
+const TENURE_POINTS_PER_DAY: u64 = 2;
+const MAX_TENURE_POINTS: u64 = 250;
+/// Local score: points lost per incident
+const PAYMENT_FAILURE_PENALTY: u64 = 25;
+const RATE_LIMIT_PENALTY: u64 = 10;
+
+/// ERC-8004 scores are cached this long
+const ONCHAIN_SCORE_TTL_SECS: u64 = 3600;
+
+#[derive(Debug, Clone)]
+pub struct ReputationConfig {
+    /// Only agents with an ERC-8004 identity are served
+    pub require_registration: bool,
+    /// Share of the ERC-8004 score in the blend with the local score (0-1);
+    /// ignored without registries, where the local score is used alone
+    pub onchain_weight: f64,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            require_registration: false,
+            onchain_weight: 0.5,
+        }
+    }
+}
+
 pub struct ReputationService {
-    provider: Arc<Provider<Http>>,
-    registry_address: Option<Address>,
+    /// `None` when no ERC-8004 registries are configured
+    registry: Option<Arc<Erc8004Registry>>,
+    ledger: Arc<AgentLedger>,
+    config: ReputationConfig,
     cache: Arc<CacheService>,
-    mock_reputations: HashMap<Address, u64>,
 }
 
 impl ReputationService {
     pub async fn new(
-        provider: Arc<Provider<Http>>,
         cache: Arc<CacheService>,
-        registry_address: Option<Address>,
+        ledger: Arc<AgentLedger>,
+        registry: Option<Arc<Erc8004Registry>>,
+        config: ReputationConfig,
     ) -> Self {
-        
-        // Mock reputations for testing when no contract deployed
-        let mut mock_reputations = HashMap::new();
-        mock_reputations.insert(
-            "0x1111111111111111111111111111111111111111".parse().unwrap(),
-            100  // Low reputation
-        );
-        mock_reputations.insert(
-            "0x2222222222222222222222222222222222222222".parse().unwrap(),
-            500  // Medium reputation
-        );
-        mock_reputations.insert(
-            "0x3333333333333333333333333333333333333333".parse().unwrap(),
-            1000  // High reputation
-        );
-        
         tracing::info!(
-            "Reputation service initialized (contract mode: {})",
-            registry_address.is_some()
+            "Reputation service initialized (ERC-8004 network: {}, on-chain weight: {}, registration required: {})",
+            registry.as_ref().map_or("none", |registry| registry.deployment().network.as_str()),
+            if registry.is_some() { config.onchain_weight } else { 0.0 },
+            config.require_registration && registry.is_some()
         );
-        
+
         Self {
-            provider,
-            registry_address,
+            registry,
+            ledger,
+            config,
             cache,
-            mock_reputations,
         }
     }
-    
+
+    /// The ERC-8004 registries; `None` when not configured
+    pub fn registry(&self) -> Option<&Arc<Erc8004Registry>> {
+        self.registry.as_ref()
+    }
+
+    /// Payment and usage history the local score is computed from
+    pub fn ledger(&self) -> &Arc<AgentLedger> {
+        &self.ledger
+    }
+
+    /// The local score from the agent's history, blended with its ERC-8004
+    /// score when registries are configured
     pub async fn get_reputation(&self, agent: Address) -> Result<u64> {
-        // Check cache first (1 hour TTL)
-        let cache_key = format!("reputation:{}", agent);
-        if let Some(cached) = self.cache.get::<u64>(&cache_key).await.ok().flatten() {
-            tracing::debug!("Reputation cache hit for {}", agent);
-            return Ok(cached);
-        }
-        
-        let reputation = if let Some(registry_addr) = self.registry_address {
-            // Real contract call
-            let registry = AgentRegistry::new(registry_addr, self.provider.clone());
-            match registry.get_reputation(agent).call().await {
-                Ok(rep) => rep.as_u64(),
-                Err(e) => {
-                    tracing::warn!("Contract call failed for {}: {}, using default", agent, e);
-                    250 // Default reputation on error
-                }
+        let local = local_score(&self.ledger.behavior(agent).await, Utc::now());
+
+        let reputation = match &self.registry {
+            Some(registry) => {
+                let onchain = self.onchain_score(registry, agent).await;
+                let weight = self.config.onchain_weight;
+                (onchain as f64 * weight + local as f64 * (1.0 - weight)).round() as u64
             }
-        } else {
-            // Mock mode
-            *self.mock_reputations.get(&agent).unwrap_or(&250)
+            None => local,
         };
-        
-        // Cache for 1 hour
-        let _ = self.cache.set(&cache_key, &reputation, 3600).await;
-        
+
         tracing::info!("Agent {} reputation: {}", agent, reputation);
-        
+
         Ok(reputation)
     }
-    
+
     pub fn calculate_price(&self, base_price: f64, reputation: u64) -> f64 {
         match reputation {
             0..=99 => f64::MAX,           // Access denied
@@ -87,7 +111,7 @@ impl ReputationService {
             _ => base_price * 0.5,        // 50% discount for reputation > 1000
         }
     }
-    
+
     pub async fn verify_access(&self, agent: Option<Address>, min_reputation: u64) -> Result<bool> {
         if let Some(addr) = agent {
             let reputation = self.get_reputation(addr).await?;
@@ -96,4 +120,126 @@ impl ReputationService {
             Ok(true) // Allow anonymous access
         }
     }
+
+    /// Whether `agent` owns an ERC-8004 identity; every agent does without registries
+    pub async fn is_registered(&self, agent: Address) -> Result<bool> {
+        let Some(registry) = &self.registry else {
+            return Ok(true);
+        };
+
+        // Only positive answers are cached, so a new registration counts at once
+        let cache_key = format!("erc8004:registered:{}", agent);
+        if let Some(true) = self.cache.get::<bool>(&cache_key).await.ok().flatten() {
+            return Ok(true);
+        }
+
+        let registered = registry.is_registered(agent).await?;
+        if registered {
+            let _ = self.cache.set(&cache_key, &true, 3600).await;
+        }
+        Ok(registered)
+    }
+
+    /// False when registration is required and `payer`, the verified sender
+    /// of a payment, has no ERC-8004 identity
+    pub async fn verify_registration(&self, payer: Address) -> Result<bool> {
+        if !self.config.require_registration || self.registry.is_none() {
+            return Ok(true);
+        }
+
+        self.is_registered(payer).await
+    }
+
+    /// The agent's identity, registration file and trust summaries; `None`
+    /// when it is not registered or no registries are configured
+    pub async fn agent_profile(&self, agent: Address) -> Result<Option<AgentProfile>> {
+        let Some(registry) = &self.registry else {
+            return Ok(None);
+        };
+        let Some(agent_id) = registry.agent_id(agent) else {
+            return Ok(None);
+        };
+
+        let registration = match registry.registration(agent_id).await {
+            Ok(registration) => Some(registration),
+            Err(e) => {
+                tracing::warn!("{:#}", e);
+                None
+            }
+        };
+
+        Ok(Some(AgentProfile {
+            address: agent,
+            network: registry.deployment().network.clone(),
+            identity_registry: registry.deployment().identity,
+            agent_id,
+            token_uri: registry.token_uri(agent_id).await?,
+            registration,
+            feedback: registry.feedback_summary(agent_id).await?,
+            validations: registry.validation_summary(agent_id).await?,
+            reputation: self.get_reputation(agent).await?,
+        }))
+    }
+
+    /// The ERC-8004 score, cached for an hour
+    async fn onchain_score(&self, registry: &Erc8004Registry, agent: Address) -> u64 {
+        let cache_key = format!("reputation:erc8004:{}", agent);
+        if let Some(cached) = self.cache.get::<u64>(&cache_key).await.ok().flatten() {
+            tracing::debug!("ERC-8004 score cache hit for {}", agent);
+            return cached;
+        }
+
+        let score = match registry_score(registry, agent).await {
+            Ok(score) => score,
+            Err(e) => {
+                tracing::warn!("ERC-8004 lookup failed for {}: {}, using default", agent, e);
+                return DEFAULT_REPUTATION;
+            }
+        };
+
+        let _ = self.cache.set(&cache_key, &score, ONCHAIN_SCORE_TTL_SECS).await;
+        score
+    }
+}
+
+/// Starts at the default, gains points for verified payments and tenure and
+/// loses them for failed payments and rate-limit violations
+pub fn local_score(behavior: &AgentBehavior, now: DateTime<Utc>) -> u64 {
+    let tenure_days = behavior
+        .first_seen
+        .map_or(0, |first_seen| (now - first_seen).num_days().max(0) as u64);
+
+    let earned = DEFAULT_REPUTATION
+        + (behavior.verified_payments * PAYMENT_POINTS).min(MAX_PAYMENT_POINTS)
+        + (tenure_days * TENURE_POINTS_PER_DAY).min(MAX_TENURE_POINTS);
+    let lost = behavior.payment_failures * PAYMENT_FAILURE_PENALTY
+        + behavior.rate_limit_violations * RATE_LIMIT_PENALTY;
+
+    earned.saturating_sub(lost)
+}
+
+/// Average feedback scaled to 0-1000, plus up to 500 for validations.
+/// Unregistered agents and agents without feedback get the default score.
+async fn registry_score(registry: &Erc8004Registry, agent: Address) -> Result<u64> {
+    let Some(agent_id) = registry.agent_id(agent) else {
+        return Ok(DEFAULT_REPUTATION);
+    };
+    let feedback = registry.feedback_summary(agent_id).await?;
+    let validations = registry.validation_summary(agent_id).await?;
+
+    Ok(score(feedback, validations))
+}
+
+fn score(feedback: TrustSummary, validations: Option<TrustSummary>) -> u64 {
+    let base = if feedback.count == 0 {
+        DEFAULT_REPUTATION
+    } else {
+        feedback.average as u64 * FEEDBACK_WEIGHT
+    };
+    let bonus = match validations {
+        Some(validations) if validations.count > 0 => validations.average as u64 * VALIDATION_WEIGHT,
+        _ => 0,
+    };
+
+    base + bonus
 }
@@ -0,0 +1,423 @@
+use crate::contracts::{
+    ApproveCall, GetReservesCall, GetReservesReturn, SwapExactETHForTokensCall, SwapExactETHForTokensReturn,
+    SwapExactTokensForETHCall, SwapExactTokensForETHReturn,
+};
+use crate::models::{ChainSpec, DexDeployment, DexKind};
+use crate::services::{
+    chain_client::{call_deployed, ChainClient},
+    fork_db::{to_revm_address, to_revm_u256},
+    gas_predictor::{next_base_fee_gwei, BlockSample},
+    DexProtocol, DexRegistry, ForkDb, SwapIntent,
+};
+use anyhow::{Context, Result};
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::providers::ProviderError;
+use ethers::types::{Address, Block, Bytes, Transaction, H256, U256};
+use revm::db::CacheDB;
+use revm::primitives::{
+    AccountInfo, BlobExcessGasAndPrice, EVMError, Env, ExecutionResult, SpecId, TransactTo, TxEnv, B256,
+};
+use revm::EVM;
+use std::sync::{Arc, Mutex};
+
+/// Largest frontrun tried, in ETH
+const MAX_FRONTRUN_ETH: u64 = 100;
+/// Frontrun sizes are searched down to 0.001 ETH
+const SEARCH_PRECISION_WEI: u64 = 1_000_000_000_000_000;
+const MAX_SEARCH_STEPS: usize = 40;
+/// Added to the victim's tip so the frontrun is ordered first
+const FRONTRUN_TIP_BUMP_WEI: u64 = 1_000_000_000;
+const LEG_GAS_LIMIT: u64 = 500_000;
+
+/// Sends the simulated frontrun and backrun; funded in the fork
+const SEARCHER: [u8; 20] = [0x5e; 20];
+
+/// The victim's transaction fails against the latest state even without a
+/// frontrun, so nothing can be simulated
+#[derive(Debug, thiserror::Error)]
+#[error("victim transaction reverts without a sandwich")]
+pub struct VictimReverted;
+
+/// Outcome of frontrun → victim → backrun executed against the latest state
+#[derive(Debug, Clone)]
+pub struct SimulatedSandwich {
+    /// Block whose state the simulation forked
+    pub block: u64,
+    pub pair: Address,
+    /// Router both legs trade through
+    pub router: Address,
+    /// The chain's wrapped native token, which the frontrun buys with
+    pub wrapped_native: Address,
+    /// Bought by the frontrun and sold back by the backrun
+    pub token: Address,
+    /// Amount of `token` bought
+    pub token_amount: U256,
+    /// ETH spent by the frontrun
+    pub frontrun_wei: U256,
+    /// ETH returned by the backrun
+    pub backrun_wei: U256,
+    pub frontrun_gas: u64,
+    pub backrun_gas: u64,
+    /// Max fee of the frontrun, which outbids the victim's tip
+    pub frontrun_gas_price: U256,
+    /// The next block's base fee, paid by the backrun
+    pub backrun_gas_price: U256,
+}
+
+impl SimulatedSandwich {
+    /// Before gas
+    pub fn profit_wei(&self) -> U256 {
+        self.backrun_wei.saturating_sub(self.frontrun_wei)
+    }
+
+    pub fn gas_cost_wei(&self) -> U256 {
+        self.frontrun_gas_price * self.frontrun_gas + self.backrun_gas_price * self.backrun_gas
+    }
+}
+
+/// Simulates sandwiching pending V2 swaps that buy a token with ETH. The
+/// frontrun is sized by search: first the largest one the victim's slippage
+/// limit still lets through, then the most profitable one below it.
+pub struct SandwichSimulator {
+    client: Arc<dyn ChainClient>,
+    chain: ChainSpec,
+    /// DEXes on `chain`, for the router and pair of the victim's swap
+    dexes: Arc<DexRegistry>,
+    /// Fork of the latest block, replaced when a new block arrives
+    fork: Mutex<Option<ForkDb>>,
+}
+
+impl SandwichSimulator {
+    pub fn new(client: Arc<dyn ChainClient>, chain: ChainSpec, dexes: Arc<DexRegistry>) -> Self {
+        Self {
+            client,
+            chain,
+            dexes,
+            fork: Mutex::new(None),
+        }
+    }
+
+    /// `None` if the swap cannot be sandwiched at a profit before gas;
+    /// [`VictimReverted`] if it fails by itself
+    pub async fn simulate(&self, victim: &Transaction, swap: &SwapIntent) -> Result<Option<SimulatedSandwich>> {
+        let Some(dex) = self.v2_dex(swap) else {
+            return Ok(None);
+        };
+        let hop = &swap.hops[0];
+        let (Some(router), Some(pair)) = (dex.v2_router, dex.pool_address(hop.token_in, hop.token_out, None)) else {
+            return Ok(None);
+        };
+        if hop.token_in != self.chain.wrapped_native {
+            return Ok(None);
+        }
+
+        let block_number = self.client.block_number().await?;
+        let block = self
+            .client
+            .block(block_number)
+            .await?
+            .context("Latest block not found")?;
+
+        let plan = SandwichPlan::new(&block, &self.chain, router, hop.token_out, victim.clone())?;
+        let fork = self.fork_at(block_number);
+        let sandwich = tokio::task::spawn_blocking(move || plan.search(&fork)).await??;
+
+        Ok(sandwich.map(|sandwich| SimulatedSandwich {
+            block: block_number,
+            pair,
+            ..sandwich
+        }))
+    }
+
+    /// The V2 pair of the swap's first hop with its reserves as (input,
+    /// output), if the swap trades on a registered V2-style DEX and the pair
+    /// is deployed
+    pub async fn pair_reserves(&self, swap: &SwapIntent) -> Result<Option<(Address, U256, U256)>> {
+        let hop = &swap.hops[0];
+        let Some(pair) = self.v2_dex(swap).and_then(|dex| dex.pool_address(hop.token_in, hop.token_out, None)) else {
+            return Ok(None);
+        };
+        let reserves = call_deployed::<_, _, GetReservesReturn>(&*self.client, pair, GetReservesCall).await?;
+        let Some(reserves) = reserves else {
+            return Ok(None);
+        };
+        let (reserve0, reserve1) = (U256::from(reserves.reserve_0), U256::from(reserves.reserve_1));
+
+        // Pairs order their tokens by address
+        Ok(Some(if hop.token_in < hop.token_out {
+            (pair, reserve0, reserve1)
+        } else {
+            (pair, reserve1, reserve0)
+        }))
+    }
+
+    /// The V2-style DEX the swap trades on, whose router and pair the frontrun
+    /// and backrun go through
+    fn v2_dex(&self, swap: &SwapIntent) -> Option<&DexDeployment> {
+        if swap.protocol != DexProtocol::UniswapV2 {
+            return None;
+        }
+        self.dexes.get(&swap.dex).filter(|dex| dex.kind == DexKind::UniswapV2)
+    }
+
+    fn fork_at(&self, block: u64) -> ForkDb {
+        let mut fork = self.fork.lock().unwrap();
+        match fork.as_ref() {
+            Some(existing) if existing.block() == block => existing.clone(),
+            _ => {
+                let new_fork = ForkDb::new(self.client.clone(), block);
+                *fork = Some(new_fork.clone());
+                new_fork
+            }
+        }
+    }
+}
+
+/// Everything needed to replay the sandwich on a blocking thread
+struct SandwichPlan {
+    env: Env,
+    router: Address,
+    wrapped_native: Address,
+    token: Address,
+    victim: Transaction,
+    base_fee: U256,
+    frontrun_tip: U256,
+}
+
+impl SandwichPlan {
+    fn new(latest: &Block<H256>, chain: &ChainSpec, router: Address, token: Address, victim: Transaction) -> Result<Self> {
+        let (env, base_fee) = next_block_env(latest, chain)?;
+        let victim_tip = match (victim.max_fee_per_gas, victim.max_priority_fee_per_gas) {
+            (Some(max_fee), Some(tip)) => tip.min(max_fee.saturating_sub(base_fee)),
+            _ => victim.gas_price.unwrap_or_default().saturating_sub(base_fee),
+        };
+
+        Ok(Self {
+            env,
+            router,
+            wrapped_native: chain.wrapped_native,
+            token,
+            victim,
+            base_fee,
+            frontrun_tip: victim_tip.saturating_add(U256::from(FRONTRUN_TIP_BUMP_WEI)),
+        })
+    }
+
+    fn search(&self, fork: &ForkDb) -> Result<Option<SimulatedSandwich>> {
+        let precision = U256::from(SEARCH_PRECISION_WEI);
+        if !self.victim_succeeds(fork)? {
+            return Err(VictimReverted.into());
+        }
+
+        // Largest frontrun after which the victim's swap still succeeds
+        let mut low = U256::zero();
+        let mut high = U256::from(MAX_FRONTRUN_ETH) * U256::exp10(18);
+        if self.run(fork, high)?.is_none() {
+            for _ in 0..MAX_SEARCH_STEPS {
+                if high - low <= precision {
+                    break;
+                }
+                let mid = (low + high) / 2;
+                if self.run(fork, mid)?.is_some() {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            high = low;
+        }
+        if high.is_zero() {
+            return Ok(None);
+        }
+
+        // Below that limit, profit rises with size until pool fees on the
+        // frontrun outweigh the victim's price impact
+        let mut low = U256::zero();
+        for _ in 0..MAX_SEARCH_STEPS {
+            if high - low <= precision {
+                break;
+            }
+            let third = (high - low) / 3;
+            let (left, right) = (low + third, high - third);
+            if net_eth(&self.run(fork, left)?) < net_eth(&self.run(fork, right)?) {
+                low = left;
+            } else {
+                high = right;
+            }
+        }
+
+        let best = self.run(fork, (low + high) / 2)?;
+        Ok(best.filter(|sandwich| sandwich.backrun_wei > sandwich.frontrun_wei))
+    }
+
+    fn victim_succeeds(&self, fork: &ForkDb) -> Result<bool> {
+        let mut evm = EVM::new();
+        evm.env = self.env.clone();
+        evm.database(CacheDB::new(fork.clone()));
+        Ok(execute(&mut evm, tx_env(&self.victim))?.is_some())
+    }
+
+    /// Frontrun with `amount` ETH, the victim, then sell everything bought.
+    /// `None` if any of them fails.
+    fn run(&self, fork: &ForkDb, amount: U256) -> Result<Option<SimulatedSandwich>> {
+        let searcher = Address::from(SEARCHER);
+        let weth = self.wrapped_native;
+
+        let mut db = CacheDB::new(fork.clone());
+        db.insert_account_info(
+            to_revm_address(searcher),
+            AccountInfo {
+                balance: to_revm_u256(U256::from(MAX_FRONTRUN_ETH + 10) * U256::exp10(18)),
+                ..Default::default()
+            },
+        );
+        let mut evm = EVM::new();
+        evm.env = self.env.clone();
+        evm.database(db);
+
+        let frontrun_gas_price = self.base_fee.saturating_add(self.frontrun_tip);
+        let frontrun = SwapExactETHForTokensCall {
+            amount_out_min: U256::zero(),
+            path: vec![weth, self.token],
+            to: searcher,
+            deadline: U256::MAX,
+        };
+        let Some((frontrun_gas, output)) = execute(
+            &mut evm,
+            leg(searcher, self.router, amount, frontrun.encode(), frontrun_gas_price, self.frontrun_tip),
+        )?
+        else {
+            return Ok(None);
+        };
+        let bought = SwapExactETHForTokensReturn::decode(&output)?.amounts.last().copied().unwrap_or_default();
+
+        if execute(&mut evm, tx_env(&self.victim))?.is_none() {
+            return Ok(None);
+        }
+
+        // A searcher contract would approve once up front, so this is not costed
+        let approve = ApproveCall {
+            spender: self.router,
+            amount: U256::MAX,
+        };
+        if execute(&mut evm, leg(searcher, self.token, U256::zero(), approve.encode(), self.base_fee, U256::zero()))?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let backrun = SwapExactTokensForETHCall {
+            amount_in: bought,
+            amount_out_min: U256::zero(),
+            path: vec![self.token, weth],
+            to: searcher,
+            deadline: U256::MAX,
+        };
+        let Some((backrun_gas, output)) = execute(
+            &mut evm,
+            leg(searcher, self.router, U256::zero(), backrun.encode(), self.base_fee, U256::zero()),
+        )?
+        else {
+            return Ok(None);
+        };
+        let received = SwapExactTokensForETHReturn::decode(&output)?.amounts.last().copied().unwrap_or_default();
+
+        Ok(Some(SimulatedSandwich {
+            block: 0,
+            pair: Address::zero(),
+            router: self.router,
+            wrapped_native: self.wrapped_native,
+            token: self.token,
+            token_amount: bought,
+            frontrun_wei: amount,
+            backrun_wei: received,
+            frontrun_gas,
+            backrun_gas,
+            frontrun_gas_price,
+            backrun_gas_price: self.base_fee,
+        }))
+    }
+}
+
+/// Environment of the block after `latest`, and that block's base fee
+pub(crate) fn next_block_env(latest: &Block<H256>, chain: &ChainSpec) -> Result<(Env, U256)> {
+    let sample = BlockSample::from_block(latest).context("Latest block has no base fee")?;
+    let base_fee = U256::from((next_base_fee_gwei(&sample) * 1e9) as u128);
+
+    let mut env = Env::default();
+    env.cfg.chain_id = chain.chain_id;
+    env.cfg.spec_id = SpecId::CANCUN;
+    env.block.number = to_revm_u256(U256::from(sample.number + 1));
+    env.block.timestamp = to_revm_u256(U256::from(sample.timestamp + chain.block_time_ms / 1000));
+    env.block.gas_limit = to_revm_u256(latest.gas_limit);
+    env.block.basefee = to_revm_u256(base_fee);
+    env.block.coinbase = to_revm_address(latest.author.unwrap_or_default());
+    env.block.prevrandao = Some(B256::from(latest.mix_hash.unwrap_or_default().0));
+    env.block.blob_excess_gas_and_price = Some(BlobExcessGasAndPrice::new(0));
+
+    Ok((env, base_fee))
+}
+
+/// Replays a pending or signed transaction. The nonce is left unchecked.
+pub(crate) fn tx_env(tx: &Transaction) -> TxEnv {
+    let (gas_price, gas_priority_fee) = match tx.max_fee_per_gas {
+        Some(max_fee) => (max_fee, tx.max_priority_fee_per_gas),
+        None => (tx.gas_price.unwrap_or_default(), None),
+    };
+
+    TxEnv {
+        caller: to_revm_address(tx.from),
+        gas_limit: tx.gas.low_u64(),
+        gas_price: to_revm_u256(gas_price),
+        gas_priority_fee: gas_priority_fee.map(to_revm_u256),
+        transact_to: match tx.to {
+            Some(to) => TransactTo::Call(to_revm_address(to)),
+            None => TransactTo::create(),
+        },
+        value: to_revm_u256(tx.value),
+        data: tx.input.to_vec().into(),
+        access_list: tx
+            .access_list
+            .iter()
+            .flat_map(|list| &list.0)
+            .map(|item| {
+                let slots = item.storage_keys.iter().map(|key| revm::primitives::U256::from_be_bytes(key.0));
+                (to_revm_address(item.address), slots.collect())
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn leg(from: Address, to: Address, value: U256, data: Vec<u8>, gas_price: U256, tip: U256) -> TxEnv {
+    TxEnv {
+        caller: to_revm_address(from),
+        gas_limit: LEG_GAS_LIMIT,
+        gas_price: to_revm_u256(gas_price),
+        gas_priority_fee: Some(to_revm_u256(tip)),
+        transact_to: TransactTo::Call(to_revm_address(to)),
+        value: to_revm_u256(value),
+        data: data.into(),
+        ..Default::default()
+    }
+}
+
+/// Gas used and output, or `None` if the transaction reverted or is not
+/// valid in the next block (e.g. its fee cap is below the base fee)
+fn execute(evm: &mut EVM<CacheDB<ForkDb>>, tx: TxEnv) -> Result<Option<(u64, Bytes)>, ProviderError> {
+    evm.env.tx = tx;
+    match evm.transact_commit() {
+        Ok(ExecutionResult::Success { gas_used, output, .. }) => Ok(Some((gas_used, output.into_data().to_vec().into()))),
+        Ok(_) => Ok(None),
+        Err(EVMError::Database(e)) => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
+/// ETH back minus ETH in, for comparing frontrun sizes; failures rank last
+fn net_eth(sandwich: &Option<SimulatedSandwich>) -> i128 {
+    match sandwich {
+        Some(sandwich) => sandwich.backrun_wei.low_u128() as i128 - sandwich.frontrun_wei.low_u128() as i128,
+        None => i128::MIN,
+    }
+}
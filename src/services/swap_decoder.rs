@@ -0,0 +1,461 @@
+use crate::contracts::{
+    swap_router02::{
+        ExactInputCall as Router02ExactInputCall, ExactInputSingleCall as Router02ExactInputSingleCall,
+        ExactOutputCall as Router02ExactOutputCall, ExactOutputSingleCall as Router02ExactOutputSingleCall,
+    },
+    uniswap_v3::{ExactInputCall, ExactInputSingleCall, ExactOutputCall, ExactOutputSingleCall},
+    UniswapSwapRouter02Calls, UniswapV2RouterCalls, UniswapV3SwapRouterCalls, UniversalRouterCalls,
+};
+use crate::services::DexRegistry;
+use ethers::abi::{self, AbiDecode, ParamType, Token};
+use ethers::types::{Address, Bytes, Transaction, U256};
+use serde::Serialize;
+
+/// Nested multicalls are unwrapped this deep
+const MAX_MULTICALL_DEPTH: usize = 2;
+
+// Universal Router commands: the low 6 bits of each command byte
+const COMMAND_TYPE_MASK: u8 = 0x3f;
+const V3_SWAP_EXACT_IN: u8 = 0x00;
+const V3_SWAP_EXACT_OUT: u8 = 0x01;
+const V2_SWAP_EXACT_IN: u8 = 0x08;
+const V2_SWAP_EXACT_OUT: u8 = 0x09;
+
+/// Amount meaning "the router's whole balance", set by an earlier command
+const CONTRACT_BALANCE: U256 = U256([0, 0, 0, 1 << 63]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DexProtocol {
+    UniswapV2,
+    UniswapV3,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapKind {
+    ExactIn,
+    ExactOut,
+}
+
+/// One pool on a swap's route
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SwapHop {
+    pub token_in: Address,
+    pub token_out: Address,
+    /// V3 pool fee in hundredths of a basis point; `None` for V2 pairs
+    pub fee: Option<u32>,
+}
+
+/// What a pending router call will swap, decoded from its calldata
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SwapIntent {
+    /// Registered DEX whose pools the swap trades on
+    pub dex: String,
+    pub protocol: DexProtocol,
+    pub kind: SwapKind,
+    /// Pools in the order tokens flow through them
+    pub hops: Vec<SwapHop>,
+    /// Exact input, or the most the sender will pay for `ExactOut`. Zero when
+    /// a Universal Router swap spends what an earlier command left in the router.
+    pub amount_in: U256,
+    /// Exact output, or the least the sender will accept for `ExactIn`
+    pub amount_out: U256,
+    pub recipient: Address,
+    /// Unix seconds, if the call sets one
+    pub deadline: Option<U256>,
+}
+
+impl SwapIntent {
+    pub fn token_in(&self) -> Address {
+        self.hops[0].token_in
+    }
+
+    pub fn token_out(&self) -> Address {
+        self.hops[self.hops.len() - 1].token_out
+    }
+}
+
+/// Where the swaps come from, for resolving router recipient shortcuts
+struct CallContext {
+    sender: Address,
+    router: Address,
+    value: U256,
+    deadline: Option<U256>,
+}
+
+impl CallContext {
+    /// SwapRouter02 and the Universal Router read address(1) as the caller
+    /// and address(2) as the router itself. V2 routers take `to` as it is.
+    fn recipient(&self, recipient: Address) -> Address {
+        if recipient == Address::from_low_u64_be(1) {
+            self.sender
+        } else if recipient == Address::from_low_u64_be(2) {
+            self.router
+        } else {
+            recipient
+        }
+    }
+
+    /// The V3 SwapRouter reads the zero address as itself
+    fn v3_router_recipient(&self, recipient: Address) -> Address {
+        if recipient.is_zero() {
+            self.router
+        } else {
+            recipient
+        }
+    }
+
+    fn with_deadline(&self, deadline: U256) -> Self {
+        Self {
+            deadline: Some(deadline),
+            ..*self
+        }
+    }
+}
+
+/// Swaps in a transaction to a router in `dexes`: a Uniswap V2 (or fork)
+/// router, the V3 SwapRouter, SwapRouter02 or the Universal Router. Calls are
+/// recognised by selector, so registered routers of forks sharing these ABIs
+/// decode too. Each swap is attributed to the DEX at that router with the
+/// matching pool design; swaps no registered DEX serves are left out, as is
+/// everything sent elsewhere.
+pub fn decode_swaps(tx: &Transaction, dexes: &DexRegistry) -> Vec<SwapIntent> {
+    let Some(router) = tx.to.filter(|&to| dexes.is_router(to)) else {
+        return Vec::new();
+    };
+    let context = CallContext {
+        sender: tx.from,
+        router,
+        value: tx.value,
+        deadline: None,
+    };
+
+    let mut swaps = Vec::new();
+    decode_call(&tx.input, &context, 0, &mut swaps);
+    swaps
+        .into_iter()
+        .filter_map(|swap| {
+            let dex = dexes.for_router(router, swap.protocol)?;
+            Some(SwapIntent {
+                dex: dex.name.clone(),
+                ..swap
+            })
+        })
+        .collect()
+}
+
+fn decode_call(input: &[u8], context: &CallContext, depth: usize, swaps: &mut Vec<SwapIntent>) {
+    if let Ok(call) = UniswapV2RouterCalls::decode(input) {
+        swaps.extend(decode_v2_router(call, context));
+    } else if let Ok(call) = UniswapV3SwapRouterCalls::decode(input) {
+        match call {
+            UniswapV3SwapRouterCalls::Multicall(call) => decode_multicall(&call.data, context, depth, swaps),
+            call => swaps.extend(decode_v3_router(call, context)),
+        }
+    } else if let Ok(call) = UniswapSwapRouter02Calls::decode(input) {
+        match call {
+            UniswapSwapRouter02Calls::Multicall(call) => decode_multicall(&call.data, context, depth, swaps),
+            UniswapSwapRouter02Calls::MulticallWithDeadline(call) => {
+                decode_multicall(&call.data, &context.with_deadline(call.deadline), depth, swaps)
+            }
+            UniswapSwapRouter02Calls::MulticallWithPreviousBlockhash(call) => {
+                decode_multicall(&call.data, context, depth, swaps)
+            }
+            call => swaps.extend(decode_router02(call, context)),
+        }
+    } else if let Ok(call) = UniversalRouterCalls::decode(input) {
+        match call {
+            UniversalRouterCalls::Execute(call) => decode_commands(&call.commands, &call.inputs, context, swaps),
+            UniversalRouterCalls::ExecuteWithDeadline(call) => {
+                decode_commands(&call.commands, &call.inputs, &context.with_deadline(call.deadline), swaps)
+            }
+        }
+    }
+}
+
+fn decode_multicall(
+    calls: &[Bytes],
+    context: &CallContext,
+    depth: usize,
+    swaps: &mut Vec<SwapIntent>,
+) {
+    if depth >= MAX_MULTICALL_DEPTH {
+        return;
+    }
+    for call in calls {
+        decode_call(call, context, depth + 1, swaps);
+    }
+}
+
+fn decode_v2_router(call: UniswapV2RouterCalls, context: &CallContext) -> Option<SwapIntent> {
+    use UniswapV2RouterCalls::*;
+
+    let (kind, amount_in, amount_out, path, to, deadline) = match call {
+        SwapExactTokensForTokens(c) => (SwapKind::ExactIn, c.amount_in, c.amount_out_min, c.path, c.to, c.deadline),
+        SwapExactTokensForTokensSupportingFeeOnTransferTokens(c) => {
+            (SwapKind::ExactIn, c.amount_in, c.amount_out_min, c.path, c.to, c.deadline)
+        }
+        SwapExactTokensForETH(c) => (SwapKind::ExactIn, c.amount_in, c.amount_out_min, c.path, c.to, c.deadline),
+        SwapExactTokensForETHSupportingFeeOnTransferTokens(c) => {
+            (SwapKind::ExactIn, c.amount_in, c.amount_out_min, c.path, c.to, c.deadline)
+        }
+        SwapExactETHForTokens(c) => (SwapKind::ExactIn, context.value, c.amount_out_min, c.path, c.to, c.deadline),
+        SwapExactETHForTokensSupportingFeeOnTransferTokens(c) => {
+            (SwapKind::ExactIn, context.value, c.amount_out_min, c.path, c.to, c.deadline)
+        }
+        SwapTokensForExactTokens(c) => (SwapKind::ExactOut, c.amount_in_max, c.amount_out, c.path, c.to, c.deadline),
+        SwapTokensForExactETH(c) => (SwapKind::ExactOut, c.amount_in_max, c.amount_out, c.path, c.to, c.deadline),
+        SwapETHForExactTokens(c) => (SwapKind::ExactOut, context.value, c.amount_out, c.path, c.to, c.deadline),
+    };
+
+    Some(SwapIntent {
+        dex: String::new(),
+        protocol: DexProtocol::UniswapV2,
+        kind,
+        hops: v2_hops(&path)?,
+        amount_in,
+        amount_out,
+        recipient: to,
+        deadline: Some(deadline),
+    })
+}
+
+fn decode_v3_router(call: UniswapV3SwapRouterCalls, context: &CallContext) -> Option<SwapIntent> {
+    let (kind, hops, amount_in, amount_out, recipient, deadline) = match call {
+        UniswapV3SwapRouterCalls::ExactInputSingle(ExactInputSingleCall { params: p }) => (
+            SwapKind::ExactIn,
+            vec![single_hop(p.token_in, p.token_out, p.fee)],
+            p.amount_in,
+            p.amount_out_minimum,
+            p.recipient,
+            p.deadline,
+        ),
+        UniswapV3SwapRouterCalls::ExactInput(ExactInputCall { params: p }) => (
+            SwapKind::ExactIn,
+            v3_hops(&p.path, false)?,
+            p.amount_in,
+            p.amount_out_minimum,
+            p.recipient,
+            p.deadline,
+        ),
+        UniswapV3SwapRouterCalls::ExactOutputSingle(ExactOutputSingleCall { params: p }) => (
+            SwapKind::ExactOut,
+            vec![single_hop(p.token_in, p.token_out, p.fee)],
+            p.amount_in_maximum,
+            p.amount_out,
+            p.recipient,
+            p.deadline,
+        ),
+        UniswapV3SwapRouterCalls::ExactOutput(ExactOutputCall { params: p }) => (
+            SwapKind::ExactOut,
+            v3_hops(&p.path, true)?,
+            p.amount_in_maximum,
+            p.amount_out,
+            p.recipient,
+            p.deadline,
+        ),
+        UniswapV3SwapRouterCalls::Multicall(_) => return None,
+    };
+
+    Some(SwapIntent {
+        dex: String::new(),
+        protocol: DexProtocol::UniswapV3,
+        kind,
+        hops,
+        amount_in,
+        amount_out,
+        recipient: context.v3_router_recipient(recipient),
+        deadline: Some(deadline),
+    })
+}
+
+fn decode_router02(call: UniswapSwapRouter02Calls, context: &CallContext) -> Option<SwapIntent> {
+    use UniswapSwapRouter02Calls::*;
+
+    let (protocol, kind, hops, amount_in, amount_out, recipient) = match call {
+        ExactInputSingle(Router02ExactInputSingleCall { params: p }) => (
+            DexProtocol::UniswapV3,
+            SwapKind::ExactIn,
+            vec![single_hop(p.token_in, p.token_out, p.fee)],
+            p.amount_in,
+            p.amount_out_minimum,
+            p.recipient,
+        ),
+        ExactInput(Router02ExactInputCall { params: p }) => (
+            DexProtocol::UniswapV3,
+            SwapKind::ExactIn,
+            v3_hops(&p.path, false)?,
+            p.amount_in,
+            p.amount_out_minimum,
+            p.recipient,
+        ),
+        ExactOutputSingle(Router02ExactOutputSingleCall { params: p }) => (
+            DexProtocol::UniswapV3,
+            SwapKind::ExactOut,
+            vec![single_hop(p.token_in, p.token_out, p.fee)],
+            p.amount_in_maximum,
+            p.amount_out,
+            p.recipient,
+        ),
+        ExactOutput(Router02ExactOutputCall { params: p }) => (
+            DexProtocol::UniswapV3,
+            SwapKind::ExactOut,
+            v3_hops(&p.path, true)?,
+            p.amount_in_maximum,
+            p.amount_out,
+            p.recipient,
+        ),
+        SwapExactTokensForTokens(c) => (
+            DexProtocol::UniswapV2,
+            SwapKind::ExactIn,
+            v2_hops(&c.path)?,
+            c.amount_in,
+            c.amount_out_min,
+            c.to,
+        ),
+        SwapTokensForExactTokens(c) => (
+            DexProtocol::UniswapV2,
+            SwapKind::ExactOut,
+            v2_hops(&c.path)?,
+            c.amount_in_max,
+            c.amount_out,
+            c.to,
+        ),
+        Multicall(_) | MulticallWithDeadline(_) | MulticallWithPreviousBlockhash(_) => return None,
+    };
+
+    Some(SwapIntent {
+        dex: String::new(),
+        protocol,
+        kind,
+        hops,
+        amount_in,
+        amount_out,
+        recipient: context.recipient(recipient),
+        deadline: context.deadline,
+    })
+}
+
+/// Swap commands of a Universal Router `execute`; other commands (wrapping,
+/// permits, sweeps) are skipped
+fn decode_commands(
+    commands: &[u8],
+    inputs: &[Bytes],
+    context: &CallContext,
+    swaps: &mut Vec<SwapIntent>,
+) {
+    let v3_swap = [ParamType::Address, ParamType::Uint(256), ParamType::Uint(256), ParamType::Bytes, ParamType::Bool];
+    let v2_swap = [
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Array(Box::new(ParamType::Address)),
+        ParamType::Bool,
+    ];
+
+    for (command, input) in commands.iter().zip(inputs) {
+        let command = command & COMMAND_TYPE_MASK;
+        let (protocol, kind) = match command {
+            V3_SWAP_EXACT_IN => (DexProtocol::UniswapV3, SwapKind::ExactIn),
+            V3_SWAP_EXACT_OUT => (DexProtocol::UniswapV3, SwapKind::ExactOut),
+            V2_SWAP_EXACT_IN => (DexProtocol::UniswapV2, SwapKind::ExactIn),
+            V2_SWAP_EXACT_OUT => (DexProtocol::UniswapV2, SwapKind::ExactOut),
+            _ => continue,
+        };
+        let params: &[ParamType] = match protocol {
+            DexProtocol::UniswapV3 => &v3_swap,
+            DexProtocol::UniswapV2 => &v2_swap,
+        };
+        let Ok(tokens) = abi::decode(params, input) else {
+            continue;
+        };
+
+        // (recipient, amount, limit, path, payerIsUser)
+        let [Token::Address(recipient), Token::Uint(amount), Token::Uint(limit), path, _] = &tokens[..] else {
+            continue;
+        };
+        let hops = match path {
+            // V3 exact-output paths run from the output token back to the input
+            Token::Bytes(path) => v3_hops(path, kind == SwapKind::ExactOut),
+            Token::Array(path) => {
+                let path: Option<Vec<Address>> = path.iter().map(|token| token.clone().into_address()).collect();
+                path.and_then(|path| v2_hops(&path))
+            }
+            _ => None,
+        };
+        let Some(hops) = hops else {
+            continue;
+        };
+
+        let (amount_in, amount_out) = match kind {
+            SwapKind::ExactIn if *amount == CONTRACT_BALANCE => (U256::zero(), *limit),
+            SwapKind::ExactIn => (*amount, *limit),
+            SwapKind::ExactOut => (*limit, *amount),
+        };
+
+        swaps.push(SwapIntent {
+            dex: String::new(),
+            protocol,
+            kind,
+            hops,
+            amount_in,
+            amount_out,
+            recipient: context.recipient(*recipient),
+            deadline: context.deadline,
+        });
+    }
+}
+
+fn single_hop(token_in: Address, token_out: Address, fee: u32) -> SwapHop {
+    SwapHop {
+        token_in,
+        token_out,
+        fee: Some(fee),
+    }
+}
+
+fn v2_hops(path: &[Address]) -> Option<Vec<SwapHop>> {
+    if path.len() < 2 {
+        return None;
+    }
+    Some(
+        path.windows(2)
+            .map(|pair| SwapHop {
+                token_in: pair[0],
+                token_out: pair[1],
+                fee: None,
+            })
+            .collect(),
+    )
+}
+
+/// Packed V3 path: token (20 bytes), then fee (3 bytes) and token for each
+/// pool. Exact-output paths are encoded output first, so `reversed` flips them.
+fn v3_hops(path: &[u8], reversed: bool) -> Option<Vec<SwapHop>> {
+    const ADDRESS: usize = 20;
+    const HOP: usize = 3 + ADDRESS;
+
+    let pools = path.len().saturating_sub(ADDRESS) / HOP;
+    if pools == 0 || path.len() != ADDRESS + pools * HOP {
+        return None;
+    }
+
+    let mut hops: Vec<SwapHop> = path[ADDRESS..]
+        .chunks_exact(HOP)
+        .enumerate()
+        .map(|(i, hop)| SwapHop {
+            token_in: Address::from_slice(&path[i * HOP..i * HOP + ADDRESS]),
+            token_out: Address::from_slice(&hop[3..]),
+            fee: Some(u32::from_be_bytes([0, hop[0], hop[1], hop[2]])),
+        })
+        .collect();
+
+    if reversed {
+        hops.reverse();
+        for hop in &mut hops {
+            std::mem::swap(&mut hop.token_in, &mut hop.token_out);
+        }
+    }
+    Some(hops)
+}
@@ -0,0 +1,264 @@
+use crate::models::{InclusionBucket, InclusionStats, MinedTransaction, TxLifecycle, TxStatus};
+use crate::services::{
+    mempool_store::{PendingTransaction, Removal},
+    BlockFollower, EthereumService, MempoolService,
+};
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use ethers::types::{Transaction, H256, U256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Most missed blocks fetched when the follower skipped ahead. Transactions
+/// mined in blocks beyond this are later reported as dropped.
+const MAX_CATCH_UP_BLOCKS: u64 = 256;
+
+/// The gas predictor's priority fee should get a transaction included within
+/// this many blocks
+const INCLUSION_TARGET_BLOCKS: u64 = 2;
+
+/// Mined transactions a priority fee bucket needs before it is trusted
+const MIN_BUCKET_SAMPLES: usize = 20;
+
+/// Mined transactions kept for status lookups and inclusion statistics
+const MAX_MINED: usize = 10_000;
+
+/// Lower bounds of the priority fee buckets, in gwei
+const FEE_BUCKETS_GWEI: [f64; 6] = [0.0, 0.5, 1.0, 2.0, 5.0, 10.0];
+
+#[derive(Debug, Default)]
+struct MinedLog {
+    by_hash: HashMap<H256, MinedTransaction>,
+    /// Oldest first
+    order: VecDeque<H256>,
+}
+
+/// Correlates pending transactions with new blocks to tell whether each was
+/// mined (and how long that took), replaced or dropped
+pub struct TxLifecycleTracker {
+    mempool: Arc<MempoolService>,
+    chain: Arc<EthereumService>,
+    mined: Mutex<MinedLog>,
+}
+
+impl TxLifecycleTracker {
+    pub fn new(mempool: Arc<MempoolService>, chain: Arc<EthereumService>) -> Self {
+        Self {
+            mempool,
+            chain,
+            mined: Mutex::new(MinedLog::default()),
+        }
+    }
+
+    /// Checks every block the follower publishes
+    pub async fn run(&self, follower: Arc<BlockFollower>) {
+        tracing::info!("Tracking pending transaction lifecycle on {}", follower.chain_name());
+
+        let mut updates = follower.subscribe();
+        let mut last_block: Option<u64> = None;
+
+        while updates.changed().await.is_ok() {
+            let Some(head) = updates.borrow_and_update().as_ref().map(|update| update.block_number) else {
+                continue;
+            };
+
+            let start = match last_block {
+                Some(last) if head <= last => continue,
+                Some(last) => {
+                    let oldest = head.saturating_sub(MAX_CATCH_UP_BLOCKS - 1);
+                    if last + 1 < oldest {
+                        tracing::warn!(
+                            "Lifecycle tracking skipped blocks {}-{}; transactions mined there will show as dropped",
+                            last + 1,
+                            oldest - 1
+                        );
+                    }
+                    (last + 1).max(oldest)
+                }
+                None => head,
+            };
+
+            for number in start..=head {
+                if let Err(e) = self.process_block(number).await {
+                    tracing::warn!("Lifecycle tracking failed for block {}: {}", number, e);
+                }
+            }
+            last_block = Some(head);
+
+            let target_secs = INCLUSION_TARGET_BLOCKS * self.chain.chain().block_time_seconds();
+            self.chain
+                .set_observed_priority_fee(self.priority_fee_for_inclusion_within(target_secs));
+        }
+    }
+
+    /// Marks the pending transactions in block `number` as mined
+    pub async fn process_block(&self, number: u64) -> Result<()> {
+        let Some(block) = self.chain.get_block(number).await? else {
+            return Ok(());
+        };
+        let base_fee = block.base_fee_per_gas.unwrap_or_default();
+        let timestamp = block.timestamp.as_u64();
+
+        let mut included = 0;
+        for (position, hash) in block.transactions.iter().enumerate() {
+            let Some(pending) = self.mempool.take_included(*hash) else {
+                continue;
+            };
+            self.record(*hash, mined(&pending, number, position, timestamp, base_fee));
+            included += 1;
+        }
+
+        tracing::debug!(
+            "Block {}: {} of {} transactions were seen pending",
+            number,
+            included,
+            block.transactions.len()
+        );
+        Ok(())
+    }
+
+    fn record(&self, hash: H256, mined: MinedTransaction) {
+        let mut log = self.mined.lock().unwrap();
+        if log.by_hash.insert(hash, mined).is_none() {
+            log.order.push_back(hash);
+        }
+        while log.order.len() > MAX_MINED {
+            if let Some(oldest) = log.order.pop_front() {
+                log.by_hash.remove(&oldest);
+            }
+        }
+    }
+
+    /// `None` if the transaction was never seen, or was forgotten
+    pub fn status(&self, hash: H256) -> Option<TxStatus> {
+        if let Some(mined) = self.mined.lock().unwrap().by_hash.get(&hash) {
+            return Some(TxStatus {
+                hash,
+                lifecycle: TxLifecycle::Mined(mined.clone()),
+            });
+        }
+
+        if let Some(pending) = self.mempool.get_pending(hash) {
+            return Some(TxStatus {
+                hash,
+                lifecycle: TxLifecycle::Pending {
+                    first_seen: pending.first_seen,
+                    age_secs: (Utc::now() - pending.first_seen).num_seconds().max(0) as u64,
+                    max_fee_gwei: to_gwei(max_fee(&pending.tx)),
+                    priority_fee_gwei: to_gwei(max_priority_fee(&pending.tx)),
+                },
+            });
+        }
+
+        let lifecycle = match self.mempool.removal(hash)? {
+            Removal::Replaced(replaced_by) => TxLifecycle::Replaced { replaced_by },
+            Removal::Dropped => TxLifecycle::Dropped,
+            // Included but already evicted from the mined log
+            Removal::Included => return None,
+        };
+        Some(TxStatus { hash, lifecycle })
+    }
+
+    /// Time to inclusion per priority fee bucket over recently mined transactions
+    pub fn inclusion_stats(&self) -> InclusionStats {
+        let log = self.mined.lock().unwrap();
+
+        let buckets = FEE_BUCKETS_GWEI
+            .iter()
+            .enumerate()
+            .map(|(i, &min)| {
+                let max = FEE_BUCKETS_GWEI.get(i + 1).copied();
+                let mut times: Vec<u64> = log
+                    .by_hash
+                    .values()
+                    .filter(|tx| in_bucket(tx.priority_fee_gwei, min, max))
+                    .map(|tx| tx.time_to_inclusion_secs)
+                    .collect();
+                times.sort_unstable();
+
+                InclusionBucket {
+                    min_priority_fee_gwei: min,
+                    max_priority_fee_gwei: max,
+                    transactions: times.len(),
+                    mean_secs: (!times.is_empty())
+                        .then(|| times.iter().sum::<u64>() as f64 / times.len() as f64),
+                    p50_secs: percentile(&times, 50),
+                    p90_secs: percentile(&times, 90),
+                }
+            })
+            .collect();
+
+        InclusionStats {
+            chain: self.chain.chain().name.clone(),
+            sample_size: log.by_hash.len(),
+            buckets,
+        }
+    }
+
+    /// Median priority fee (gwei) paid in the lowest bucket that includes 90%
+    /// of transactions within `secs`, for the gas predictor. Buckets with too
+    /// few mined transactions are skipped.
+    pub fn priority_fee_for_inclusion_within(&self, secs: u64) -> Option<f64> {
+        let bucket = self
+            .inclusion_stats()
+            .buckets
+            .into_iter()
+            .filter(|bucket| bucket.transactions >= MIN_BUCKET_SAMPLES)
+            .find(|bucket| bucket.p90_secs.is_some_and(|p90| p90 <= secs))?;
+
+        let log = self.mined.lock().unwrap();
+        let mut fees: Vec<f64> = log
+            .by_hash
+            .values()
+            .map(|tx| tx.priority_fee_gwei)
+            .filter(|&fee| in_bucket(fee, bucket.min_priority_fee_gwei, bucket.max_priority_fee_gwei))
+            .collect();
+        fees.sort_unstable_by(f64::total_cmp);
+        fees.get(fees.len() / 2).copied()
+    }
+}
+
+fn mined(pending: &PendingTransaction, block_number: u64, position: usize, timestamp: u64, base_fee: U256) -> MinedTransaction {
+    let effective = effective_gas_price(&pending.tx, base_fee);
+    let mined_at = Utc.timestamp_opt(timestamp as i64, 0).single().unwrap_or_else(Utc::now);
+
+    MinedTransaction {
+        block_number,
+        position,
+        first_seen: pending.first_seen,
+        time_to_inclusion_secs: (mined_at - pending.first_seen).num_seconds().max(0) as u64,
+        effective_gas_price_gwei: to_gwei(effective),
+        priority_fee_gwei: to_gwei(effective.saturating_sub(base_fee)),
+    }
+}
+
+/// EIP-1559: base fee plus the tip, capped by the max fee. Legacy: the gas price.
+fn effective_gas_price(tx: &Transaction, base_fee: U256) -> U256 {
+    match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+        (Some(max_fee), Some(tip)) => max_fee.min(base_fee + tip),
+        _ => tx.gas_price.unwrap_or_default(),
+    }
+}
+
+fn max_fee(tx: &Transaction) -> U256 {
+    tx.max_fee_per_gas.or(tx.gas_price).unwrap_or_default()
+}
+
+fn max_priority_fee(tx: &Transaction) -> U256 {
+    tx.max_priority_fee_per_gas.or(tx.gas_price).unwrap_or_default()
+}
+
+fn to_gwei(wei: U256) -> f64 {
+    wei.as_u128() as f64 / 1e9
+}
+
+fn in_bucket(priority_fee_gwei: f64, min: f64, max: Option<f64>) -> bool {
+    priority_fee_gwei >= min && !max.is_some_and(|max| priority_fee_gwei >= max)
+}
+
+fn percentile(sorted: &[u64], pct: usize) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    Some(sorted[(sorted.len() - 1) * pct / 100])
+}
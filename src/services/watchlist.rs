@@ -0,0 +1,340 @@
+use crate::error::QGuardError;
+use crate::models::{CreateWatchlistRequest, WatchRole, WatchedAddress, Watchlist, WatchlistMatch, WebhookDelivery};
+use crate::services::{outbound::public_client, CacheService, MempoolService};
+use anyhow::Result;
+use chrono::Utc;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Transaction};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+const MAX_WATCHLISTS_PER_AGENT: usize = 10;
+const MAX_ADDRESSES_PER_WATCHLIST: usize = 100;
+
+/// Redis hash of every watchlist as JSON, by id
+const WATCHLISTS_KEY: &str = "watchlists";
+/// How often matching picks up watchlists changed by other instances
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Matches buffered per WebSocket subscriber
+const MATCH_BUFFER: usize = 1024;
+
+const WEBHOOK_ATTEMPTS: u32 = 4;
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(1);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_CONCURRENT_WEBHOOKS: usize = 32;
+
+/// Per-agent address watchlists matched against every new pending transaction.
+/// Matches go out over a broadcast channel (for WebSockets) and to each
+/// watchlist's webhook, signed with the server key (EIP-191). Watchlists are
+/// stored in Redis, so they survive restarts and are shared by every instance;
+/// without Redis they live in this process only.
+pub struct WatchlistService {
+    redis: Option<ConnectionManager>,
+    /// Every watchlist, as last loaded from Redis, for matching
+    lists: RwLock<HashMap<Uuid, Watchlist>>,
+    /// Held while creating or deleting, so the per-agent limit holds here
+    writes: Mutex<()>,
+    matches: broadcast::Sender<Arc<WatchlistMatch>>,
+    /// `None` when the key does not parse; webhooks are then refused
+    signer: Option<LocalWallet>,
+    webhook_slots: Arc<Semaphore>,
+}
+
+impl WatchlistService {
+    pub fn new(signing_key: &str, cache: Arc<CacheService>) -> Self {
+        let signer = match signing_key.trim_start_matches("0x").parse::<LocalWallet>() {
+            Ok(signer) => Some(signer),
+            Err(e) => {
+                tracing::warn!("SELLER_PRIVATE_KEY is not a valid key ({}), watchlist webhooks are disabled", e);
+                None
+            }
+        };
+
+        Self {
+            redis: cache.redis(),
+            lists: RwLock::new(HashMap::new()),
+            writes: Mutex::new(()),
+            matches: broadcast::channel(MATCH_BUFFER).0,
+            signer,
+            webhook_slots: Arc::new(Semaphore::new(MAX_CONCURRENT_WEBHOOKS)),
+        }
+    }
+
+    /// Address webhook signatures recover to
+    pub fn signer_address(&self) -> Option<Address> {
+        self.signer.as_ref().map(|signer| signer.address())
+    }
+
+    pub async fn create(&self, agent: Address, request: CreateWatchlistRequest) -> Result<Watchlist, QGuardError> {
+        if request.addresses.is_empty() || request.addresses.len() > MAX_ADDRESSES_PER_WATCHLIST {
+            return Err(QGuardError::InvalidRequest(format!(
+                "A watchlist needs between 1 and {} addresses",
+                MAX_ADDRESSES_PER_WATCHLIST
+            )));
+        }
+        if let Some(url) = &request.webhook_url {
+            if self.signer.is_none() {
+                return Err(QGuardError::ServiceUnavailable(
+                    "Webhooks are disabled: no signing key is configured".to_string(),
+                ));
+            }
+            // Checked again on every delivery, as DNS may change
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|e| QGuardError::InvalidRequest(format!("Invalid webhook URL: {}", e)))?;
+            public_client(&parsed, WEBHOOK_TIMEOUT)
+                .await
+                .map_err(|e| QGuardError::InvalidRequest(format!("Webhook URL rejected: {}", e)))?;
+        }
+
+        let _writing = self.writes.lock().await;
+        self.refresh().await;
+        if self.list_cached(agent).len() >= MAX_WATCHLISTS_PER_AGENT {
+            return Err(QGuardError::InvalidRequest(format!(
+                "At most {} watchlists per agent",
+                MAX_WATCHLISTS_PER_AGENT
+            )));
+        }
+
+        let mut addresses = request.addresses;
+        addresses.sort();
+        addresses.dedup();
+
+        let watchlist = Watchlist {
+            id: Uuid::new_v4(),
+            agent,
+            name: request.name,
+            addresses,
+            webhook_url: request.webhook_url,
+            created_at: Utc::now(),
+        };
+        if let Some(mut redis) = self.redis.clone() {
+            let json = serde_json::to_string(&watchlist).map_err(|e| QGuardError::InternalError(e.to_string()))?;
+            redis
+                .hset::<_, _, _, ()>(WATCHLISTS_KEY, watchlist.id.to_string(), json)
+                .await
+                .map_err(|e| QGuardError::InternalError(format!("Storing watchlist failed: {}", e)))?;
+        }
+        self.lists.write().unwrap().insert(watchlist.id, watchlist.clone());
+
+        tracing::info!("Agent {:?} created watchlist {} ({} addresses)", agent, watchlist.id, watchlist.addresses.len());
+        Ok(watchlist)
+    }
+
+    pub async fn list(&self, agent: Address) -> Vec<Watchlist> {
+        self.refresh().await;
+        self.list_cached(agent)
+    }
+
+    fn list_cached(&self, agent: Address) -> Vec<Watchlist> {
+        let mut lists: Vec<Watchlist> = self
+            .lists
+            .read()
+            .unwrap()
+            .values()
+            .filter(|list| list.agent == agent)
+            .cloned()
+            .collect();
+        lists.sort_by_key(|list| list.created_at);
+        lists
+    }
+
+    /// False if the watchlist does not exist or belongs to another agent
+    pub async fn delete(&self, agent: Address, id: Uuid) -> Result<bool, QGuardError> {
+        let _writing = self.writes.lock().await;
+        self.refresh().await;
+        if !self.lists.read().unwrap().get(&id).is_some_and(|list| list.agent == agent) {
+            return Ok(false);
+        }
+
+        if let Some(mut redis) = self.redis.clone() {
+            redis
+                .hdel::<_, _, ()>(WATCHLISTS_KEY, id.to_string())
+                .await
+                .map_err(|e| QGuardError::InternalError(format!("Deleting watchlist failed: {}", e)))?;
+        }
+        Ok(self.lists.write().unwrap().remove(&id).is_some())
+    }
+
+    /// Reloads every watchlist from Redis, where other instances may have
+    /// changed them. Keeps the current ones when Redis fails.
+    async fn refresh(&self) {
+        let Some(mut redis) = self.redis.clone() else { return };
+        let stored = match redis.hgetall::<_, HashMap<String, String>>(WATCHLISTS_KEY).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                tracing::warn!("Loading watchlists failed: {}", e);
+                return;
+            }
+        };
+
+        let lists = stored
+            .values()
+            .filter_map(|json| serde_json::from_str::<Watchlist>(json).ok())
+            .map(|list| (list.id, list))
+            .collect();
+        *self.lists.write().unwrap() = lists;
+    }
+
+    /// Every match, for all agents
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<WatchlistMatch>> {
+        self.matches.subscribe()
+    }
+
+    /// Matches each new pending transaction until the mempool goes away
+    pub async fn run(&self, mempool: Arc<MempoolService>) {
+        let mut transactions = mempool.subscribe();
+        let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+
+        loop {
+            let received = tokio::select! {
+                received = transactions.recv() => received,
+                _ = refresh.tick() => {
+                    self.refresh().await;
+                    continue;
+                }
+            };
+            let tx = match received {
+                Ok(tx) => tx,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!("Watchlist matching fell behind, skipped {} transactions", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+
+            for watch_match in self.match_transaction(&tx) {
+                let watch_match = Arc::new(watch_match);
+                let webhook_url = self
+                    .lists
+                    .read()
+                    .unwrap()
+                    .get(&watch_match.watchlist_id)
+                    .and_then(|list| list.webhook_url.clone());
+
+                if let Some(url) = webhook_url {
+                    // Waiting for a slot holds matching back rather than piling up tasks
+                    if let Ok(slot) = self.webhook_slots.clone().acquire_owned().await {
+                        self.spawn_webhook(slot, url, watch_match.clone());
+                    }
+                }
+                let _ = self.matches.send(watch_match);
+            }
+        }
+    }
+
+    fn match_transaction(&self, tx: &Transaction) -> Vec<WatchlistMatch> {
+        let mut involved = vec![WatchedAddress {
+            address: tx.from,
+            role: WatchRole::Sender,
+        }];
+        if let Some(to) = tx.to {
+            involved.push(WatchedAddress {
+                address: to,
+                role: WatchRole::Recipient,
+            });
+        }
+        involved.extend(calldata_addresses(&tx.input).map(|address| WatchedAddress {
+            address,
+            role: WatchRole::Calldata,
+        }));
+
+        self.lists
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(|list| {
+                let matches: Vec<WatchedAddress> = involved
+                    .iter()
+                    .filter(|watched| list.addresses.binary_search(&watched.address).is_ok())
+                    .cloned()
+                    .collect();
+
+                (!matches.is_empty()).then(|| WatchlistMatch {
+                    watchlist_id: list.id,
+                    agent: list.agent,
+                    tx_hash: tx.hash,
+                    from: tx.from,
+                    to: tx.to,
+                    value_eth: tx.value.as_u128() as f64 / 1e18,
+                    matches,
+                    detected_at: Utc::now(),
+                })
+            })
+            .collect()
+    }
+
+    /// Delivers in the background, holding `slot` until done
+    fn spawn_webhook(&self, slot: OwnedSemaphorePermit, url: String, watch_match: Arc<WatchlistMatch>) {
+        let Some(signer) = self.signer.clone() else { return };
+
+        tokio::spawn(async move {
+            let _slot = slot;
+            if let Err(e) = deliver_webhook(&signer, &url, &watch_match).await {
+                tracing::warn!("Watchlist {} webhook failed: {}", watch_match.watchlist_id, e);
+            }
+        });
+    }
+}
+
+/// POSTs the match, retrying with exponential backoff on errors, 429 and 5xx.
+/// Retries resend the same signed body, nonce included.
+async fn deliver_webhook(signer: &LocalWallet, url: &str, watch_match: &WatchlistMatch) -> Result<()> {
+    let url = reqwest::Url::parse(url)?;
+    let delivery = WebhookDelivery {
+        nonce: Uuid::new_v4(),
+        timestamp: Utc::now().timestamp(),
+        watch_match: watch_match.clone(),
+    };
+    let body = serde_json::to_vec(&delivery)?;
+    let signature = signer.sign_message(&body).await?;
+
+    let mut delay = WEBHOOK_RETRY_DELAY;
+    for attempt in 1..=WEBHOOK_ATTEMPTS {
+        // Resolved on each attempt; private addresses are refused outright
+        let client = public_client(&url, WEBHOOK_TIMEOUT).await?;
+        let response = client
+            .post(url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-QGuard-Signature", format!("0x{}", signature))
+            .header("X-QGuard-Signer", format!("{:?}", signer.address()))
+            .body(body.clone())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) if response.status().is_redirection() => {
+                anyhow::bail!("redirected with {}, redirects are not followed", response.status());
+            }
+            Ok(response) if response.status().is_client_error() && response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                anyhow::bail!("rejected with {}", response.status());
+            }
+            Ok(response) => tracing::debug!("Webhook attempt {} got {}", attempt, response.status()),
+            Err(e) => tracing::debug!("Webhook attempt {} failed: {}", attempt, e),
+        }
+
+        if attempt < WEBHOOK_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    anyhow::bail!("gave up after {} attempts", WEBHOOK_ATTEMPTS)
+}
+
+/// Addresses passed as ABI-encoded arguments: 32-byte words after the selector
+/// holding a left-padded, non-zero 20-byte value
+fn calldata_addresses(input: &[u8]) -> impl Iterator<Item = Address> + '_ {
+    input
+        .get(4..)
+        .unwrap_or_default()
+        .chunks_exact(32)
+        .filter(|word| word[..12].iter().all(|b| *b == 0) && word[12..].iter().any(|b| *b != 0))
+        .map(|word| Address::from_slice(&word[12..]))
+}
@@ -0,0 +1,599 @@
+//! End-to-end tests of the HTTP API against in-memory chains: no RPC node,
+//! Redis or facilitator is needed.
+
+mod common;
+
+use axum::http::StatusCode;
+use common::*;
+use ethers::abi::AbiEncode;
+use ethers::types::{Address, U256};
+use q_guard::contracts::{SwapExactTokensForTokensCall, UNISWAP_V2_ROUTER};
+use q_guard::models::AgentBehavior;
+use q_guard::services::{FakeChain, ReputationConfig};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Price of the gas endpoints ($0.01)
+const GAS_PRICE: u64 = CENT;
+
+#[tokio::test]
+async fn health_reports_fake_chain_without_redis() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app.get("/health", &[]).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["ethereum_rpc"], true);
+    assert_eq!(body["redis"], false);
+    assert_eq!(body["status"], "degraded");
+    assert_eq!(body["mempool"]["state"], "disabled");
+}
+
+#[tokio::test]
+async fn gas_prediction_requires_payment() {
+    let app = TestApp::new().await;
+
+    let (status, body) = app.get("/api/gas/prediction", &[]).await;
+
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(body["error_code"], "PAYMENT_REQUIRED");
+}
+
+#[tokio::test]
+async fn paid_gas_prediction_is_served_and_settled() {
+    let mut app = TestApp::new().await;
+    let settle = app
+        .facilitator
+        .mock("POST", "/settle")
+        .with_status(200)
+        .create_async()
+        .await;
+
+    let tx = app.pay(PAYER, RECIPIENT, GAS_PRICE);
+    let (status, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["success"], true);
+    let base_fee = body["data"]["base_fee_gwei"].as_f64().unwrap();
+    assert!((base_fee - 20.0).abs() < 1.0, "base fee {}", base_fee);
+    settle.assert_async().await;
+}
+
+#[tokio::test]
+async fn payment_to_another_address_is_rejected() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, Address::repeat_byte(0x01), GAS_PRICE);
+    let (status, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(body["error_code"], "PAYMENT_VERIFICATION_FAILED");
+}
+
+#[tokio::test]
+async fn underpayment_is_rejected() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, GAS_PRICE / 2);
+    let (status, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(body["error_code"], "PAYMENT_VERIFICATION_FAILED");
+    // Anyone can present the payer's transaction, so it is not held against them
+    assert_eq!(app.ledger.behavior(PAYER).await, AgentBehavior::default());
+}
+
+#[tokio::test]
+async fn unknown_payment_transaction_is_rejected() {
+    let app = TestApp::new().await;
+
+    let tx = ethers::types::H256::repeat_byte(0x42);
+    let (status, _) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+}
+
+#[tokio::test]
+async fn low_reputation_agent_is_denied() {
+    let app = TestApp::new().await;
+    let agent = Address::repeat_byte(0x55);
+    // Average feedback of 5 / 100 scores 50
+    app.register_agent(agent, 5, None).await;
+
+    let tx = app.pay(agent, RECIPIENT, GAS_PRICE);
+    let (status, body) = app
+        .get("/api/gas/prediction", &[payment_header(tx), agent_header(agent)])
+        .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "INSUFFICIENT_REPUTATION");
+}
+
+#[tokio::test]
+async fn naming_a_trusted_agent_does_not_lift_the_payer() {
+    let app = TestApp::new().await;
+    let payer = Address::repeat_byte(0x57);
+    let trusted = Address::repeat_byte(0x59);
+    app.register_agent(payer, 5, None).await;
+    app.register_agent(trusted, 100, Some(100)).await;
+
+    let tx = app.pay(payer, RECIPIENT, GAS_PRICE);
+    let (status, body) = app
+        .get("/api/gas/prediction", &[payment_header(tx), agent_header(trusted)])
+        .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "INSUFFICIENT_REPUTATION");
+}
+
+#[tokio::test]
+async fn trusted_agent_is_served() {
+    let app = TestApp::new().await;
+    let agent = Address::repeat_byte(0x56);
+    app.register_agent(agent, 100, Some(100)).await;
+
+    let tx = app.pay(agent, RECIPIENT, GAS_PRICE);
+    let (status, body) = app
+        .get("/api/gas/prediction", &[payment_header(tx), agent_header(agent)])
+        .await;
+
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[tokio::test]
+async fn unregistered_payers_are_refused_when_registration_is_required() {
+    let app = TestApp::with_reputation(ReputationConfig {
+        require_registration: true,
+        ..test_reputation()
+    })
+    .await;
+    let agent = Address::repeat_byte(0x58);
+
+    let tx = app.pay(agent, RECIPIENT, GAS_PRICE);
+    let headers = [payment_header(tx), agent_header(agent)];
+    let (status, body) = app.get("/api/gas/prediction", &headers).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error_code"], "AGENT_NOT_REGISTERED");
+
+    // Naming a registered agent does not help an unregistered payer
+    let registered = Address::repeat_byte(0x59);
+    app.register_agent(registered, 50, None).await;
+    let spoofed = app.pay(PAYER, RECIPIENT, GAS_PRICE);
+    let (status, _) = app
+        .get("/api/gas/prediction", &[payment_header(spoofed), agent_header(registered)])
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The refused payment was not redeemed, so it counts once the payer registers
+    app.register_agent(agent, 50, None).await;
+    let (status, body) = app.get("/api/gas/prediction", &headers).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+}
+
+#[tokio::test]
+async fn replayed_payments_are_rejected() {
+    let app = TestApp::new().await;
+    let agent = Address::repeat_byte(0x5b);
+    app.register_agent(agent, 100, Some(100)).await;
+
+    let tx = app.pay(agent, RECIPIENT, GAS_PRICE);
+    let headers = [payment_header(tx), agent_header(agent)];
+    let (status, body) = app.get("/api/gas/prediction", &headers).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, body) = app.get("/api/gas/prediction", &headers).await;
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(body["error_code"], "PAYMENT_REPLAYED");
+
+    // Whoever replayed it is unknown, so the payer's history is untouched
+    let behavior = app.ledger.behavior(agent).await;
+    assert_eq!(behavior.verified_payments, 1);
+    assert_eq!(behavior.rate_limit_violations, 0);
+}
+
+#[tokio::test]
+async fn failed_payments_count_once_against_their_sender() {
+    let app = TestApp::new().await;
+    let agent = Address::repeat_byte(0x5f);
+    let elsewhere = Address::repeat_byte(0x60);
+
+    let misdirected = app.pay(agent, elsewhere, GAS_PRICE);
+    let short = app.pay(agent, RECIPIENT, GAS_PRICE - 1);
+    for tx in [misdirected, misdirected, short] {
+        let (status, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+        assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+        assert_eq!(body["error_code"], "PAYMENT_VERIFICATION_FAILED");
+    }
+
+    let behavior = app.ledger.behavior(agent).await;
+    assert_eq!(behavior.payment_failures, 2);
+    assert_eq!(behavior.verified_payments, 0);
+}
+
+#[tokio::test]
+async fn replays_do_not_use_up_the_payers_rate_limit() {
+    // Two requests per payer, never refilled
+    let app = TestApp::with_rate_limit(0, 2).await;
+    let agent = Address::repeat_byte(0x5e);
+    app.register_agent(agent, 100, Some(100)).await;
+
+    let tx = app.pay(agent, RECIPIENT, GAS_PRICE);
+    let (status, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    for _ in 0..3 {
+        let (_, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+        assert_eq!(body["error_code"], "PAYMENT_REPLAYED");
+    }
+
+    let tx = app.pay(agent, RECIPIENT, GAS_PRICE);
+    let (status, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(app.ledger.behavior(agent).await.rate_limit_violations, 0);
+
+    // Over the limit, the payment is released rather than used up
+    let tx = app.pay(agent, RECIPIENT, GAS_PRICE);
+    for _ in 0..2 {
+        let (status, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body["error_code"], "RATE_LIMIT_EXCEEDED");
+    }
+    assert_eq!(app.ledger.behavior(agent).await.rate_limit_violations, 2);
+}
+
+#[tokio::test]
+async fn events_are_recorded_against_the_payer() {
+    let app = TestApp::new().await;
+    let payer = Address::repeat_byte(0x5c);
+    let named = Address::repeat_byte(0x5d);
+    app.register_agent(payer, 100, Some(100)).await;
+    app.register_agent(named, 100, Some(100)).await;
+
+    // The header names another agent, but the payment is the payer's
+    let tx = app.pay(payer, RECIPIENT, GAS_PRICE);
+    let (status, body) = app
+        .get("/api/gas/prediction", &[payment_header(tx), agent_header(named)])
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    assert_eq!(app.ledger.behavior(payer).await.verified_payments, 1);
+    assert_eq!(app.ledger.behavior(named).await, AgentBehavior::default());
+}
+
+#[tokio::test]
+async fn agent_profile_reads_the_registration_file() {
+    let app = TestApp::new().await;
+    let agent = Address::repeat_byte(0x59);
+    let agent_id = app.register_agent(agent, 80, Some(60)).await;
+
+    let tx = app.pay(PAYER, RECIPIENT, GAS_PRICE);
+    let (status, body) = app.get(&format!("/api/agents/{:?}", agent), &[payment_header(tx)]).await;
+
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let profile = &body["data"];
+    assert_eq!(profile["agent_id"], json!(agent_id));
+    assert_eq!(profile["registration"]["name"], "test agent");
+    assert_eq!(profile["feedback"]["average"], 80);
+    assert_eq!(profile["validations"]["count"], 3);
+    // 80 * 10 for feedback plus 60 * 5 for validations
+    assert_eq!(profile["reputation"], 1100);
+
+    let tx = app.pay(PAYER, RECIPIENT, GAS_PRICE);
+    let uri = format!("/api/agents/{:?}", Address::repeat_byte(0x5a));
+    let (status, _) = app.get(&uri, &[payment_header(tx)]).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn cost_estimate_prices_each_tier_in_usd() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, GAS_PRICE);
+    let (status, body) = app
+        .post("/api/gas/estimate", &[payment_header(tx)], json!({ "gas_limit": 21000 }))
+        .await;
+
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let estimate = &body["data"];
+    assert_eq!(estimate["gas_limit"], 21000);
+    assert_eq!(estimate["eth_usd_price"].as_f64(), Some(3000.0));
+
+    let tiers = estimate["tiers"].as_array().unwrap();
+    assert_eq!(tiers.len(), 3);
+    for tier in tiers {
+        let eth = tier["expected_cost_eth"].as_f64().unwrap();
+        let usd = tier["expected_cost_usd"].as_f64().unwrap();
+        // At least the base fee: 21000 gas at ~20 gwei
+        assert!(eth > 21_000.0 * 19e-9, "cost {} ETH", eth);
+        assert!((usd - eth * 3000.0).abs() < 1e-6);
+    }
+}
+
+#[tokio::test]
+async fn reverting_transaction_estimate_is_a_bad_request() {
+    let app = TestApp::new().await;
+    app.chain.revert_gas_estimate("insufficient balance");
+
+    let tx = app.pay(PAYER, RECIPIENT, GAS_PRICE);
+    let transaction = json!({ "to": format!("{:?}", Address::repeat_byte(0x77)), "data": "0x" });
+    let (status, body) = app
+        .post("/api/gas/estimate", &[payment_header(tx)], json!({ "transaction": transaction }))
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["error_code"], "INVALID_REQUEST");
+
+    // Rejected before the agent was charged
+    let (_, stats) = app.get("/stats", &[]).await;
+    assert_eq!(stats["total_payments"], 0);
+
+    // The payment was not used up, so it still buys a request
+    let (status, body) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(app.ledger.behavior(PAYER).await.verified_payments, 1);
+}
+
+#[tokio::test]
+async fn oversized_history_ranges_are_bad_requests() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let uri = format!("/api/gas/history/heatmap?days={}", u64::MAX);
+    let (status, body) = app.get(&uri, &[payment_header(tx)]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let uri = format!("/api/gas/history?from_block=1&to_block={}", u64::MAX);
+    let (status, body) = app.get(&uri, &[payment_header(tx)]).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+}
+
+#[tokio::test]
+async fn offline_chain_is_reported_unhealthy() {
+    let chain = Arc::new(FakeChain::new());
+    chain.set_offline(true);
+    let app = TestApp::with_chain(chain).await;
+
+    let (status, body) = app.get("/health", &[]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["ethereum_rpc"], false);
+    assert_eq!(body["status"], "unhealthy");
+
+    let tx = app.pay(PAYER, RECIPIENT, GAS_PRICE);
+    let (status, _) = app.get("/api/gas/prediction", &[payment_header(tx)]).await;
+    assert!(status.is_server_error(), "{}", status);
+}
+
+#[tokio::test]
+async fn prices_reject_invalid_token_addresses() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, GAS_PRICE);
+    let (status, body) = app.get("/api/prices?tokens=0x1234", &[payment_header(tx)]).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["error_code"], "INVALID_REQUEST");
+}
+
+#[tokio::test]
+async fn mev_opportunities_reject_invalid_queries() {
+    let app = TestApp::new().await;
+
+    for query in ["sort=newest", "types=frontrun", "limit=0", "tokens=0x1234"] {
+        let tx = app.pay(PAYER, RECIPIENT, 10 * CENT);
+        let (status, body) = app
+            .get(&format!("/api/mev/opportunities?{}", query), &[payment_header(tx)])
+            .await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", query, body);
+        assert_eq!(body["error_code"], "INVALID_REQUEST");
+    }
+}
+
+#[tokio::test]
+async fn mev_endpoint_is_unavailable_without_mempool() {
+    let app = TestApp::new().await;
+
+    // Reported before any payment is asked for
+    for uri in ["/api/mev/opportunities", "/api/mempool/summary", "/ws/mev"] {
+        let (status, body) = app.get(uri, &[]).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{}", uri);
+        assert_eq!(body["error_code"], "SERVICE_UNAVAILABLE");
+    }
+}
+
+#[tokio::test]
+async fn protection_rejects_transactions_that_are_not_swaps() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let request = json!({
+        "from": format!("{:?}", Address::repeat_byte(0x42)),
+        "to": format!("{:?}", Address::repeat_byte(0x77)),
+        "data": "0xa9059cbb",
+    });
+    let (status, body) = app.post("/api/mev/protect", &[payment_header(tx)], request).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["error_code"], "INVALID_REQUEST");
+}
+
+#[tokio::test]
+async fn protection_rejects_oversized_swap_amounts() {
+    let app = TestApp::new().await;
+    let call = SwapExactTokensForTokensCall {
+        amount_in: U256::MAX,
+        amount_out_min: U256::one(),
+        path: vec![Address::repeat_byte(0x11), Address::repeat_byte(0x22)],
+        to: Address::repeat_byte(0x42),
+        deadline: U256::MAX,
+    };
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let request = json!({
+        "from": format!("{:?}", Address::repeat_byte(0x42)),
+        "to": UNISWAP_V2_ROUTER,
+        "data": format!("0x{}", hex::encode(call.encode())),
+    });
+    let (status, body) = app.post("/api/mev/protect", &[payment_header(tx)], request).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    let (_, stats) = app.get("/stats", &[]).await;
+    assert_eq!(stats["total_payments"], 0);
+}
+
+#[tokio::test]
+async fn protection_rejects_oversized_fees() {
+    let app = TestApp::new().await;
+    let call = SwapExactTokensForTokensCall {
+        amount_in: U256::exp10(18),
+        amount_out_min: U256::one(),
+        path: vec![Address::repeat_byte(0x11), Address::repeat_byte(0x22)],
+        to: Address::repeat_byte(0x42),
+        deadline: U256::MAX,
+    };
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let request = json!({
+        "from": format!("{:?}", Address::repeat_byte(0x42)),
+        "to": UNISWAP_V2_ROUTER,
+        "data": format!("0x{}", hex::encode(call.encode())),
+        "max_fee_per_gas": U256::MAX,
+        "max_priority_fee_per_gas": U256::MAX,
+    });
+    let (status, body) = app.post("/api/mev/protect", &[payment_header(tx)], request).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    let (_, stats) = app.get("/stats", &[]).await;
+    assert_eq!(stats["total_payments"], 0);
+}
+
+#[tokio::test]
+async fn bundle_simulation_rejects_malformed_transactions() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let (status, body) = app
+        .post("/api/mev/bundle/simulate", &[payment_header(tx)], json!({ "txs": ["0x02c0"] }))
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["error_code"], "INVALID_REQUEST");
+}
+
+#[tokio::test]
+async fn bundle_simulation_limits_the_bundle_size() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let txs = vec!["0x02c0"; 17];
+    let (status, body) = app
+        .post("/api/mev/bundle/simulate", &[payment_header(tx)], json!({ "txs": txs }))
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert!(body["error"].as_str().unwrap().contains("limit is 16"), "{}", body);
+    let (_, stats) = app.get("/stats", &[]).await;
+    assert_eq!(stats["total_payments"], 0);
+}
+
+#[tokio::test]
+async fn mev_history_limits_the_block_range() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let (status, body) = app
+        .get("/api/mev/history?from_block=100&to_block=300", &[payment_header(tx)])
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["error_code"], "INVALID_REQUEST");
+}
+
+#[tokio::test]
+async fn mev_history_rejects_unmined_ranges() {
+    let app = TestApp::new().await;
+
+    let tx = app.pay(PAYER, RECIPIENT, 5 * CENT);
+    let (status, body) = app
+        .get("/api/mev/history?from_block=100&to_block=120", &[payment_header(tx)])
+        .await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert_eq!(body["error_code"], "INVALID_REQUEST");
+    let (_, stats) = app.get("/stats", &[]).await;
+    assert_eq!(stats["total_payments"], 0);
+}
+
+#[tokio::test]
+async fn transaction_status_is_unavailable_without_mempool() {
+    let app = TestApp::new().await;
+
+    let uri = format!("/api/mempool/tx/{:?}", ethers::types::H256::repeat_byte(0x42));
+    let (status, _) = app.get(&uri, &[]).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    let (status, _) = app.get("/api/mempool/inclusion", &[]).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn watchlists_belong_to_the_creating_agent() {
+    let app = TestApp::new().await;
+    let owner = Address::repeat_byte(0x56);
+    let other = Address::repeat_byte(0x57);
+    for agent in [owner, other] {
+        app.register_agent(agent, 100, Some(100)).await;
+    }
+
+    let tx = app.pay(owner, RECIPIENT, GAS_PRICE);
+    let (status, body) = app
+        .post(
+            "/api/watchlists",
+            &[payment_header(tx), agent_header(owner)],
+            json!({ "name": "treasury", "addresses": [format!("{:?}", Address::repeat_byte(0x11))] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let id = body["data"]["id"].as_str().unwrap().to_string();
+
+    // Ownership follows the payer, whatever the agent header claims
+    let tx = app.pay(other, RECIPIENT, GAS_PRICE);
+    let (status, body) = app
+        .get("/api/watchlists", &[payment_header(tx), agent_header(owner)])
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"], json!([]));
+
+    let tx = app.pay(owner, RECIPIENT, GAS_PRICE);
+    let (status, body) = app.get("/api/watchlists", &[payment_header(tx)]).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["data"][0]["id"], id.as_str());
+    // The match stream is metered, so a single request's payment does not open it
+    let tx = app.pay(owner, RECIPIENT, GAS_PRICE);
+    let (status, body) = app.get("/ws/watchlists", &[payment_header(tx)]).await;
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(body["error_code"], "PAYMENT_VERIFICATION_FAILED");
+}
+
+#[tokio::test]
+async fn webhooks_to_private_addresses_are_rejected() {
+    let app = TestApp::new().await;
+    let owner = Address::repeat_byte(0x56);
+    app.register_agent(owner, 100, Some(100)).await;
+
+    for url in ["http://127.0.0.1:8080/hook", "http://10.0.0.7/hook", "http://[::1]/hook", "ftp://example.com/hook"] {
+        let tx = app.pay(owner, RECIPIENT, GAS_PRICE);
+        let (status, body) = app
+            .post(
+                "/api/watchlists",
+                &[payment_header(tx)],
+                json!({
+                    "name": "treasury",
+                    "addresses": [format!("{:?}", Address::repeat_byte(0x11))],
+                    "webhook_url": url,
+                }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}: {}", url, body);
+    }
+}
@@ -0,0 +1,150 @@
+use ethers::abi::{AbiEncode, Token};
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Bytes, Log, Transaction, U256, U64};
+use q_guard::contracts::{
+    GetReservesCall, GetReservesReturn, SwapExactETHForTokensCall, SyncFilter, Token0Call, Token0Return, Token1Call,
+    Token1Return, UNISWAP_V2_ROUTER, WETH,
+};
+use q_guard::models::{DexDeployment, DexKind};
+use q_guard::services::{ArbitrageScanner, DexRegistry, FakeChain, PoolSpec};
+use std::sync::Arc;
+
+/// Sorts below WETH, so it is token0 of both pairs
+const TOKEN: Address = Address::repeat_byte(0x11);
+const UNISWAP_PAIR: Address = Address::repeat_byte(0xa1);
+const SUSHI_PAIR: Address = Address::repeat_byte(0xa2);
+
+fn ether(amount: u64) -> u128 {
+    amount as u128 * 10u128.pow(18)
+}
+
+fn weth() -> Address {
+    WETH.parse().unwrap()
+}
+
+/// Sets a pair's reserves as (token, WETH)
+fn script_pair(chain: &FakeChain, pair: Address, token_reserve: u128, weth_reserve: u128) {
+    chain.on_call::<Token0Call>(pair, Token0Return(TOKEN));
+    chain.on_call::<Token1Call>(pair, Token1Return(weth()));
+    chain.on_call::<GetReservesCall>(
+        pair,
+        GetReservesReturn {
+            reserve_0: token_reserve,
+            reserve_1: weth_reserve,
+            block_timestamp_last: 0,
+        },
+    );
+}
+
+/// Two pairs at the same price: 1 WETH = 2000 TOKEN
+async fn scanner() -> (Arc<FakeChain>, ArbitrageScanner) {
+    scanner_on(DexRegistry::mainnet(), "sushiswap").await
+}
+
+/// A Uniswap V2 pair and a pair of `other_venue`, both at 1 WETH = 2000 TOKEN
+async fn scanner_on(dexes: DexRegistry, other_venue: &str) -> (Arc<FakeChain>, ArbitrageScanner) {
+    let chain = Arc::new(FakeChain::new());
+    chain.mine(U256::exp10(9), 15_000_000, 30_000_000, U256::exp10(9));
+    script_pair(&chain, UNISWAP_PAIR, ether(2_000_000), ether(1_000));
+    script_pair(&chain, SUSHI_PAIR, ether(2_000_000), ether(1_000));
+
+    let pools = vec![
+        PoolSpec {
+            venue: "uniswap_v2".to_string(),
+            address: UNISWAP_PAIR,
+        },
+        PoolSpec {
+            venue: other_venue.to_string(),
+            address: SUSHI_PAIR,
+        },
+    ];
+    let scanner = ArbitrageScanner::new(chain.clone(), pools, Arc::new(dexes));
+    scanner.load().await;
+    assert_eq!(scanner.pool_count(), 2);
+    (chain, scanner)
+}
+
+/// Buys TOKEN with `eth` on the Uniswap V2 router
+fn buy_on_uniswap(eth: u128) -> Transaction {
+    buy_through(UNISWAP_V2_ROUTER.parse().unwrap(), eth)
+}
+
+fn buy_through(router: Address, eth: u128) -> Transaction {
+    let call = SwapExactETHForTokensCall {
+        amount_out_min: U256::zero(),
+        path: vec![weth(), TOKEN],
+        to: Address::repeat_byte(0x99),
+        deadline: U256::MAX,
+    };
+    Transaction {
+        to: Some(router),
+        value: eth.into(),
+        input: call.encode().into(),
+        gas_price: Some(U256::exp10(10)),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn large_swap_opens_a_cycle_through_the_other_venue() {
+    let (_chain, scanner) = scanner().await;
+
+    let cycles = scanner.backrun_cycles(&buy_on_uniswap(ether(50)));
+    let best = cycles.first().expect("a profitable cycle");
+
+    // The victim made TOKEN expensive on Uniswap: buy it on Sushi, sell it there
+    assert_eq!(best.pools, vec![SUSHI_PAIR, UNISWAP_PAIR]);
+    assert_eq!(best.tokens, vec![weth(), TOKEN, weth()]);
+    assert!(best.profit_wei() > 0.1e18, "profit {}", best.profit_wei());
+    assert!(best.amount_in_wei > 0.0 && best.amount_in_wei < ether(50) as f64);
+    assert_eq!(best.gas, 21_000 + 2 * 60_000);
+    assert!(!best.uses_v3);
+}
+
+#[tokio::test]
+async fn sync_logs_update_reserves() {
+    let (chain, scanner) = scanner().await;
+
+    // A small swap does not beat two 0.3% fees while both pairs agree
+    assert!(scanner.backrun_cycles(&buy_on_uniswap(ether(1))).is_empty());
+
+    // SushiSwap's price drops 10% in block 1
+    chain.add_log(Log {
+        address: SUSHI_PAIR,
+        topics: vec![SyncFilter::signature()],
+        data: Bytes::from(ethers::abi::encode(&[
+            Token::Uint(ether(2_200_000).into()),
+            Token::Uint(ether(1_000).into()),
+        ])),
+        block_number: Some(U64::from(1)),
+        ..Default::default()
+    });
+    scanner.refresh(1, 1).await.unwrap();
+
+    let cycles = scanner.backrun_cycles(&buy_on_uniswap(ether(1)));
+    assert_eq!(cycles.first().map(|cycle| cycle.pools.clone()), Some(vec![SUSHI_PAIR, UNISWAP_PAIR]));
+}
+
+#[tokio::test]
+async fn registered_dexes_are_scanned_and_decoded() {
+    let router = Address::repeat_byte(0xf0);
+    let mut dexes = DexRegistry::mainnet();
+    dexes.insert(DexDeployment {
+        name: "fork_swap".to_string(),
+        chain: "ethereum".to_string(),
+        kind: DexKind::UniswapV2,
+        routers: vec![router],
+        v2_router: Some(router),
+        factory: Some(Address::repeat_byte(0xf1)),
+        init_code_hash: Some(Default::default()),
+        fee_tiers: vec![2_500],
+    });
+    let (_chain, scanner) = scanner_on(dexes, "fork_swap").await;
+
+    // The fork's swaps move its own pair, not Uniswap's
+    let cycles = scanner.backrun_cycles(&buy_through(router, ether(50)));
+    assert_eq!(cycles.first().map(|cycle| cycle.pools.clone()), Some(vec![UNISWAP_PAIR, SUSHI_PAIR]));
+
+    // Routers outside the registry are not decoded
+    assert!(scanner.backrun_cycles(&buy_through(Address::repeat_byte(0xf2), ether(50))).is_empty());
+}
@@ -0,0 +1,157 @@
+use ethers::abi::AbiDecode;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, Eip1559TransactionRequest, U256};
+use q_guard::contracts::compound_v3::{AbsorbCall, BuyCollateralCall};
+use q_guard::contracts::ApproveCall;
+use q_guard::models::{BundleTransaction, ChainSpec, LendingProtocol};
+use q_guard::services::bundle::liquidation_bundle;
+use q_guard::services::liquidation::CollateralPurchase;
+use q_guard::services::{decode_raw_transaction, BundleSimulator, FakeChain, LiquidationCandidate};
+use std::sync::Arc;
+
+const RECIPIENT: Address = Address::repeat_byte(0x77);
+/// REVERT(0, 0)
+const ALWAYS_REVERTS: &str = "0x60006000fd";
+const REVERTER: Address = Address::repeat_byte(0xde);
+
+fn gwei(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(9)
+}
+
+fn wallet() -> LocalWallet {
+    "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+        .parse::<LocalWallet>()
+        .unwrap()
+        .with_chain_id(1u64)
+}
+
+/// Signed EIP-1559 transaction paying a 2 gwei tip
+fn signed(wallet: &LocalWallet, nonce: u64, to: Address, value: U256, gas: u64) -> Bytes {
+    let tx: TypedTransaction = Eip1559TransactionRequest::new()
+        .from(wallet.address())
+        .to(to)
+        .value(value)
+        .nonce(nonce)
+        .gas(gas)
+        .max_fee_per_gas(gwei(100))
+        .max_priority_fee_per_gas(gwei(2))
+        .chain_id(1u64)
+        .into();
+    let signature = wallet.sign_transaction_sync(&tx).unwrap();
+    tx.rlp_signed(&signature)
+}
+
+/// Latest block at a 1 gwei base fee, half full, so the next one keeps it
+fn chain(wallet: &LocalWallet) -> Arc<FakeChain> {
+    let chain = Arc::new(FakeChain::new());
+    chain.mine(gwei(1), 15_000_000, 30_000_000, gwei(1));
+    chain.set_account(wallet.address(), U256::exp10(18), Bytes::new());
+    chain.set_account(REVERTER, U256::zero(), ALWAYS_REVERTS.parse().unwrap());
+    chain
+}
+
+#[tokio::test]
+async fn raw_transactions_decode_with_their_sender() {
+    let wallet = wallet();
+    let raw = signed(&wallet, 3, RECIPIENT, U256::exp10(17), 21_000);
+
+    let tx = decode_raw_transaction(&raw).unwrap();
+    assert_eq!(tx.from, wallet.address());
+    assert_eq!((tx.to, tx.nonce, tx.value), (Some(RECIPIENT), 3.into(), U256::exp10(17)));
+
+    assert!(decode_raw_transaction(&Bytes::from(vec![0x02, 0xc0])).is_err());
+}
+
+#[tokio::test]
+async fn bundle_reports_each_transaction_and_builder_payment() {
+    let wallet = wallet();
+    let simulator = BundleSimulator::new(chain(&wallet), ChainSpec::ethereum());
+
+    let txs = [
+        signed(&wallet, 0, RECIPIENT, U256::exp10(17), 21_000),
+        signed(&wallet, 1, REVERTER, U256::zero(), 50_000),
+        // Reuses a nonce, so it cannot be included
+        signed(&wallet, 1, RECIPIENT, U256::one(), 21_000),
+    ]
+    .iter()
+    .map(|raw| decode_raw_transaction(raw).unwrap())
+    .collect();
+
+    let simulation = simulator.simulate(txs).await.unwrap();
+    assert_eq!((simulation.state_block, simulation.block_number), (0, 1));
+    assert!(!simulation.success);
+
+    let [transfer, reverted, invalid] = &simulation.results[..] else {
+        panic!("expected 3 results, got {:?}", simulation.results);
+    };
+    assert!(transfer.success);
+    assert_eq!(transfer.gas_used, 21_000);
+    assert_eq!(transfer.gas_price_gwei, 3.0);
+    assert_eq!(transfer.coinbase_diff, (gwei(2) * U256::from(21_000)).to_string());
+
+    assert!(!reverted.success);
+    assert_eq!(reverted.error.as_deref(), Some("Reverted: 0x"));
+    assert!(reverted.gas_used > 21_000);
+
+    assert!(!invalid.success);
+    assert_eq!(invalid.gas_used, 0);
+    assert!(invalid.error.as_deref().unwrap().contains("Nonce"), "{:?}", invalid.error);
+
+    // Builders rank bundles by what they pay per gas: the 2 gwei tip
+    assert_eq!(simulation.total_gas_used, 21_000 + reverted.gas_used);
+    assert_eq!(simulation.bundle_gas_price_gwei, 2.0);
+}
+
+#[test]
+fn comet_liquidation_buys_back_the_absorbed_collateral() {
+    let searcher = Address::repeat_byte(0x42);
+    let comet = Address::repeat_byte(0xc3);
+    let (usdc, weth, wbtc) = (Address::repeat_byte(0x01), Address::repeat_byte(0x02), Address::repeat_byte(0x03));
+    let purchases = vec![
+        CollateralPurchase { asset: weth, base_amount: U256::from(2_000_000_000u64), min_amount: U256::exp10(18) },
+        CollateralPurchase { asset: wbtc, base_amount: U256::from(3_000_000_000u64), min_amount: U256::exp10(7) },
+    ];
+    let candidate = LiquidationCandidate {
+        protocol: LendingProtocol::CompoundV3,
+        market: comet,
+        borrower: Address::repeat_byte(0xb0),
+        health_factor: 0.9,
+        debt_asset: usdc,
+        collateral_asset: wbtc,
+        max_repay: 4_500.0,
+        repay_usd: 4_500.0,
+        bonus: 0.05,
+        profit_usd: 250.0,
+        gas: 550_000,
+        purchases: purchases.clone(),
+    };
+
+    let bundle = liquidation_bundle(&candidate, None, searcher, 2, gwei(10), gwei(1));
+    let txs: Vec<_> = bundle
+        .txs
+        .iter()
+        .map(|tx| match tx {
+            BundleTransaction::Unsigned(tx) => tx,
+            BundleTransaction::Signed { .. } => panic!("unexpected signed transaction"),
+        })
+        .collect();
+    let [absorb, approve, buys @ ..] = &txs[..] else {
+        panic!("expected absorb, approve and purchases, got {:?}", txs);
+    };
+
+    assert_eq!(absorb.to, comet);
+    assert_eq!(AbsorbCall::decode(&absorb.data).unwrap().accounts, vec![candidate.borrower]);
+    // Enough base token for every purchase
+    assert_eq!(approve.to, usdc);
+    let approval = ApproveCall::decode(&approve.data).unwrap();
+    assert_eq!((approval.spender, approval.amount), (comet, U256::from(5_000_000_000u64)));
+
+    assert_eq!(buys.len(), 2);
+    for (buy, purchase) in buys.iter().zip(&purchases) {
+        assert_eq!(buy.to, comet);
+        let call = BuyCollateralCall::decode(&buy.data).unwrap();
+        assert_eq!((call.asset, call.recipient), (purchase.asset, searcher));
+        assert_eq!((call.min_amount, call.base_amount), (purchase.min_amount, purchase.base_amount));
+    }
+}
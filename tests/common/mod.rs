@@ -0,0 +1,294 @@
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use ethers::types::{
+    Address, Bytes, Log, Transaction, TransactionReceipt, H256, I256, U256, U64,
+};
+use q_guard::{
+    app::{build_router, AppServices},
+    config::{Config, Environment},
+    contracts::{DecimalsCall, DecimalsReturn, LatestRoundDataCall, LatestRoundDataReturn},
+    models::{ChainSpec, GasModel},
+    services::*,
+};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tower::Service;
+
+pub const USDC: Address = Address::repeat_byte(0xcc);
+pub const RECIPIENT: Address = Address::repeat_byte(0xee);
+pub const PAYER: Address = Address::repeat_byte(0xaa);
+pub const ETH_USD_FEED: Address = Address::repeat_byte(0xfe);
+
+/// keccak256("Transfer(address,address,uint256)")
+const TRANSFER_TOPIC: &str = "ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+/// One cent of USDC (6 decimals)
+pub const CENT: u64 = 10_000;
+
+/// The full router wired to fake chains: Ethereum for data, Base Sepolia for
+/// payments and another Base Sepolia holding the ERC-8004 registries
+pub struct TestApp {
+    pub router: Router,
+    pub chain: Arc<FakeChain>,
+    pub payments: Arc<FakeChain>,
+    pub facilitator: mockito::ServerGuard,
+    /// ERC-8004 registries on their own fake Base Sepolia
+    pub erc8004: FakeErc8004,
+    /// The app's view of `erc8004`
+    pub registry: Arc<Erc8004Registry>,
+    /// Payment and usage history behind the local reputation score
+    pub ledger: Arc<AgentLedger>,
+    next_tx: AtomicU64,
+}
+
+impl TestApp {
+    /// Twenty blocks at a steady 20 gwei base fee and ETH at $3000
+    pub async fn new() -> Self {
+        Self::with_chain(default_chain()).await
+    }
+
+    /// Like `new`, with its own reputation settings
+    pub async fn with_reputation(reputation: ReputationConfig) -> Self {
+        Self::build(default_chain(), |config| config.reputation = reputation).await
+    }
+
+    /// Like `new`, with its own rate limit (per client IP and per payer)
+    pub async fn with_rate_limit(per_second: u64, burst: u32) -> Self {
+        Self::build(default_chain(), |config| {
+            config.rate_limit_per_second = per_second;
+            config.rate_limit_burst = burst;
+        })
+        .await
+    }
+
+    pub async fn with_chain(chain: Arc<FakeChain>) -> Self {
+        Self::build(chain, |_| {}).await
+    }
+
+    async fn build(chain: Arc<FakeChain>, configure: impl FnOnce(&mut Config)) -> Self {
+        let facilitator = mockito::Server::new_async().await;
+        let payments = Arc::new(FakeChain::new());
+        let erc8004 = FakeErc8004::new(Arc::new(FakeChain::new()), "base-sepolia");
+        let mut config = test_config(&facilitator.url());
+        config.erc8004_registries = vec![erc8004.deployment()];
+        configure(&mut config);
+
+        let cache = Arc::new(CacheService::new(&config.redis_url).await.unwrap());
+        let ethereum = Arc::new(
+            EthereumService::new(ChainSpec::ethereum(), chain.clone(), cache.clone(), GasModel::Ewma).await,
+        );
+        let history = Arc::new(GasHistoryStore::new(cache.clone(), 1000, 7));
+        let chains = Arc::new(ChainRegistry::new(ethereum.clone(), history));
+        let analytics = Arc::new(Analytics::new(cache.clone()));
+        let dexes = Arc::new(DexRegistry::mainnet());
+        let prices = Arc::new(PriceService::new(chain.clone(), cache.clone(), dexes.clone(), ETH_USD_FEED, Vec::new()));
+        let registry = Arc::new(Erc8004Registry::new(erc8004.chain(), cache.clone(), erc8004.deployment()));
+        let ledger = Arc::new(AgentLedger::new(cache.clone()));
+        let reputation = Arc::new(
+            ReputationService::new(cache.clone(), ledger.clone(), Some(registry.clone()), config.reputation.clone())
+                .await,
+        );
+        let arbitrage = Arc::new(ArbitrageScanner::new(chain.clone(), Vec::new(), dexes.clone()));
+        let liquidations = Arc::new(LiquidationMonitor::new(chain.clone(), Vec::new(), 7200, dexes.wrapped_native()));
+        let mev_detector = Arc::new(MEVDetector::new(
+            ethereum.clone(),
+            prices.clone(),
+            dexes.clone(),
+            arbitrage,
+            liquidations,
+        ));
+        let mev_history = Arc::new(MevHistoryService::new(chain.clone(), cache.clone(), dexes));
+        let mev_feed = Arc::new(MevFeed::new(mev_detector.clone()));
+        let watchlists = Arc::new(WatchlistService::new(&config.seller_private_key, cache.clone()));
+
+        let router = build_router(
+            &config,
+            AppServices {
+                cache,
+                ethereum: ethereum.clone(),
+                chains,
+                analytics,
+                reputation,
+                prices,
+                payment_chain: payments.clone(),
+                mempool: None,
+                tx_lifecycle: None,
+                watchlists,
+                mev_detector,
+                mev_feed,
+                mev_history,
+            },
+        )
+        .await
+        .unwrap();
+
+        Self {
+            router,
+            chain,
+            payments,
+            facilitator,
+            erc8004,
+            registry,
+            ledger,
+            next_tx: AtomicU64::new(1),
+        }
+    }
+
+    /// Registers `agent` in the ERC-8004 registries with feedback and,
+    /// optionally, validations averaging the given scores (0-100), and
+    /// indexes the mint
+    pub async fn register_agent(&self, agent: Address, feedback: u8, validation: Option<u8>) -> U256 {
+        let agent_id = self.erc8004.register(agent, r#"data:application/json,{"name":"test agent"}"#);
+        self.erc8004.give_feedback(agent_id, 10, feedback);
+        if let Some(validation) = validation {
+            self.erc8004.validate(agent_id, 3, validation);
+        }
+        self.registry.sync().await.unwrap();
+        agent_id
+    }
+
+    /// Mines a USDC transfer of `amount` (6 decimals) on the payment chain and
+    /// returns its hash for the `X-Payment` header
+    pub fn pay(&self, from: Address, to: Address, amount: u64) -> H256 {
+        let hash = H256::from_low_u64_be(self.next_tx.fetch_add(1, Ordering::SeqCst));
+
+        let tx = Transaction {
+            hash,
+            from,
+            to: Some(USDC),
+            ..Default::default()
+        };
+        let receipt = TransactionReceipt {
+            transaction_hash: hash,
+            status: Some(U64::one()),
+            logs: vec![Log {
+                address: USDC,
+                topics: vec![
+                    TRANSFER_TOPIC.parse().unwrap(),
+                    H256::from(from),
+                    H256::from(to),
+                ],
+                data: Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Uint(amount.into())])),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        self.payments.add_transaction(tx, receipt);
+        hash
+    }
+
+    pub async fn get(&self, uri: &str, headers: &[(&str, String)]) -> (StatusCode, Value) {
+        self.send(request("GET", uri, headers).body(Body::empty()).unwrap()).await
+    }
+
+    pub async fn post(&self, uri: &str, headers: &[(&str, String)], body: Value) -> (StatusCode, Value) {
+        let request = request("POST", uri, headers)
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        self.send(request).await
+    }
+
+    async fn send(&self, request: Request<Body>) -> (StatusCode, Value) {
+        // Router is always ready, so it can be called without `poll_ready`
+        let response = self.router.clone().call(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+}
+
+/// Scores come from the ERC-8004 registries alone, so they do not drift as
+/// tests pay
+pub fn test_reputation() -> ReputationConfig {
+    ReputationConfig {
+        require_registration: false,
+        onchain_weight: 1.0,
+    }
+}
+
+/// Ethereum for `TestApp::new`
+fn default_chain() -> Arc<FakeChain> {
+    let chain = Arc::new(FakeChain::new());
+    for _ in 0..20 {
+        chain.mine(gwei(20), 15_000_000, 30_000_000, gwei(1));
+    }
+    chain.on_call::<DecimalsCall>(ETH_USD_FEED, DecimalsReturn(8));
+    chain.on_call::<LatestRoundDataCall>(
+        ETH_USD_FEED,
+        LatestRoundDataReturn {
+            round_id: 1,
+            answer: I256::from(3000) * I256::exp10(8),
+            started_at: U256::zero(),
+            updated_at: U256::from(chrono::Utc::now().timestamp()),
+            answered_in_round: 1,
+        },
+    );
+    chain
+}
+
+fn request(method: &str, uri: &str, headers: &[(&str, String)]) -> axum::http::request::Builder {
+    headers
+        .iter()
+        .fold(Request::builder().method(method).uri(uri), |builder, (name, value)| {
+            builder.header(*name, value)
+        })
+}
+
+pub fn payment_header(hash: H256) -> (&'static str, String) {
+    ("X-Payment", format!("{:?}", hash))
+}
+
+pub fn agent_header(agent: Address) -> (&'static str, String) {
+    ("X-Agent-Address", format!("{:?}", agent))
+}
+
+pub fn gwei(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(9)
+}
+
+fn test_config(facilitator_url: &str) -> Config {
+    Config {
+        environment: Environment::Development,
+        host: "127.0.0.1".to_string(),
+        port: 0,
+        eth_rpc_urls: vec!["http://localhost:8545".to_string()],
+        eth_ws_url: None,
+        mempool: MempoolConfig::default(),
+        dex_registry: DexRegistry::mainnet(),
+        arbitrage_pools: Vec::new(),
+        lending_markets: Vec::new(),
+        liquidation_lookback_blocks: 7200,
+        rpc_pool: PoolConfig::default(),
+        l2_chains: Vec::new(),
+        gas_model: GasModel::Ewma,
+        eth_usd_feed: ETH_USD_FEED,
+        price_feeds: Vec::new(),
+        base_sepolia_rpc_urls: vec!["http://localhost:8546".to_string()],
+        base_sepolia_chain_id: 84532,
+        usdc_address: USDC,
+        facilitator_url: facilitator_url.to_string(),
+        recipient_address: RECIPIENT,
+        seller_private_key: "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80".to_string(),
+        gas_stream_price: "0.10".to_string(),
+        gas_stream_message_price: 0.001,
+        mev_stream_price: "1.00".to_string(),
+        mev_stream_message_price: 0.01,
+        watchlist_stream_price: "1.00".to_string(),
+        watchlist_stream_message_price: 0.01,
+        erc8004_registries: Vec::new(),
+        erc8004_network: "base-sepolia".to_string(),
+        reputation: test_reputation(),
+        gas_history_max_blocks: 1000,
+        gas_history_retention_days: 7,
+        // Not a Redis URL, so the cache stays in memory without connection retries
+        redis_url: "memory://".to_string(),
+        rate_limit_per_second: 100,
+        rate_limit_burst: 100,
+    }
+}
@@ -0,0 +1,86 @@
+use ethers::types::Address;
+use q_guard::contracts::{OP_STACK_WETH, SUSHISWAP_ROUTER, UNIVERSAL_ROUTER, WETH};
+use q_guard::models::{ChainSpec, DexKind};
+use q_guard::services::{DexProtocol, DexRegistry};
+
+const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+#[test]
+fn mainnet_pools_are_derived_from_init_code_hashes() {
+    let registry = DexRegistry::mainnet();
+    let (usdc, weth) = (address(USDC), address(WETH));
+
+    let v2 = registry.get("uniswap_v2").unwrap();
+    assert_eq!(
+        v2.pool_address(weth, usdc, None),
+        Some(address("0xB4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"))
+    );
+
+    let v3 = registry.get("uniswap_v3").unwrap();
+    assert_eq!(
+        v3.pool_address(usdc, weth, Some(500)),
+        Some(address("0x88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"))
+    );
+    assert_eq!(v3.pool_address(usdc, weth, None), None);
+
+    let sushiswap = registry.get("sushiswap").unwrap();
+    assert_eq!(
+        sushiswap.pool_address(usdc, weth, None),
+        Some(address("0x397FF1542f962076d0BFE58eA045FfA2d347ACa0"))
+    );
+    assert_eq!(sushiswap.v2_router, Some(address(SUSHISWAP_ROUTER)));
+}
+
+#[test]
+fn routers_resolve_to_the_dex_their_swaps_trade_on() {
+    let registry = DexRegistry::mainnet();
+    let universal = address(UNIVERSAL_ROUTER);
+
+    assert_eq!(registry.for_router(universal, DexProtocol::UniswapV2).unwrap().name, "uniswap_v2");
+    assert_eq!(registry.for_router(universal, DexProtocol::UniswapV3).unwrap().name, "uniswap_v3");
+    assert_eq!(
+        registry.for_router(address(SUSHISWAP_ROUTER), DexProtocol::UniswapV2).unwrap().name,
+        "sushiswap"
+    );
+    assert!(registry.for_router(address(SUSHISWAP_ROUTER), DexProtocol::UniswapV3).is_none());
+    assert!(!registry.is_router(Address::repeat_byte(0x42)));
+}
+
+#[test]
+fn registry_file_adds_and_replaces_deployments() {
+    let fork_router = Address::repeat_byte(0xf0);
+    let base_router = Address::repeat_byte(0xba);
+    let json = format!(
+        r#"[
+            {{ "name": "fork_swap", "chain": "ethereum", "kind": "uniswap_v2", "routers": ["{0:?}"], "v2_router": "{0:?}" }},
+            {{ "name": "sushiswap", "chain": "ethereum", "kind": "uniswap_v2", "routers": [], "fee_tiers": [2500] }},
+            {{ "name": "uniswap_v2", "chain": "base", "kind": "uniswap_v2", "routers": ["{1:?}"] }}
+        ]"#,
+        fork_router, base_router
+    );
+    let path = std::env::temp_dir().join(format!("dex-registry-{}.json", std::process::id()));
+    std::fs::write(&path, json).unwrap();
+
+    let registry = DexRegistry::load(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let ethereum = registry.on_chain(&ChainSpec::ethereum());
+    let fork = ethereum.get("fork_swap").unwrap();
+    assert_eq!((fork.kind, fork.v2_router), (DexKind::UniswapV2, Some(fork_router)));
+    assert!(ethereum.is_router(fork_router));
+    assert!(!ethereum.is_router(base_router));
+    assert_eq!(ethereum.get("sushiswap").unwrap().fee_tiers, vec![2500]);
+    assert!(!ethereum.is_router(address(SUSHISWAP_ROUTER)));
+    // The mainnet Uniswap V2 deployment is untouched by Base's
+    assert_eq!(ethereum.get("uniswap_v2").unwrap().routers.len(), 3);
+
+    assert_eq!(ethereum.wrapped_native(), address(WETH));
+
+    let base = registry.on_chain(&ChainSpec::base());
+    assert_eq!(base.deployments().len(), 1);
+    assert_eq!(base.wrapped_native(), address(OP_STACK_WETH));
+}
@@ -0,0 +1,150 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ethers::types::Address;
+use q_guard::services::fake_erc8004::{FAKE_IDENTITY_REGISTRY, FAKE_REPUTATION_REGISTRY};
+use q_guard::services::*;
+use serde_json::json;
+use std::sync::Arc;
+
+const ALICE: Address = Address::repeat_byte(0xa1);
+const BOB: Address = Address::repeat_byte(0xb0);
+
+async fn registries() -> (FakeErc8004, Arc<Erc8004Registry>, Arc<CacheService>) {
+    let fake = FakeErc8004::new(Arc::new(FakeChain::new()), "base-sepolia");
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+    let registry = Arc::new(Erc8004Registry::new(fake.chain(), cache.clone(), fake.deployment()));
+    (fake, registry, cache)
+}
+
+fn ledger(cache: &Arc<CacheService>) -> Arc<AgentLedger> {
+    Arc::new(AgentLedger::new(cache.clone()))
+}
+
+/// Scores from the registries alone
+fn onchain_only(require_registration: bool) -> ReputationConfig {
+    ReputationConfig {
+        require_registration,
+        onchain_weight: 1.0,
+    }
+}
+
+#[test]
+fn deployments_parse_with_optional_validation_and_required_start_block() {
+    let deployment: Erc8004Deployment = format!(
+        "base-sepolia:{:?}:{:?}:{:?}:1200",
+        FAKE_IDENTITY_REGISTRY,
+        FAKE_REPUTATION_REGISTRY,
+        Address::repeat_byte(0x86)
+    )
+    .parse()
+    .unwrap();
+    assert_eq!(deployment.network, "base-sepolia");
+    assert_eq!(deployment.identity, FAKE_IDENTITY_REGISTRY);
+    assert_eq!(deployment.validation, Some(Address::repeat_byte(0x86)));
+    assert_eq!(deployment.from_block, 1200);
+
+    let minimal: Erc8004Deployment = format!("ethereum:{:?}:{:?}::0", FAKE_IDENTITY_REGISTRY, FAKE_REPUTATION_REGISTRY)
+        .parse()
+        .unwrap();
+    assert_eq!(minimal.validation, None);
+    assert_eq!(minimal.from_block, 0);
+
+    assert!("base-sepolia".parse::<Erc8004Deployment>().is_err());
+    let no_start = format!("ethereum:{:?}:{:?}", FAKE_IDENTITY_REGISTRY, FAKE_REPUTATION_REGISTRY);
+    assert!(no_start.parse::<Erc8004Deployment>().is_err());
+    assert!(format!("base-sepolia:0x12:{:?}::0", FAKE_REPUTATION_REGISTRY).parse::<Erc8004Deployment>().is_err());
+}
+
+#[tokio::test]
+async fn agent_ids_are_indexed_from_mints() {
+    let (fake, registry, _) = registries().await;
+    registry.sync().await.unwrap();
+    assert_eq!(registry.agent_id(ALICE), None);
+    assert!(!registry.is_registered(ALICE).await.unwrap());
+
+    let first = fake.register(ALICE, "ipfs://alice");
+    let bob = fake.register(BOB, "ipfs://bob");
+    fake.register(ALICE, "ipfs://alice-2");
+
+    // Lookups only see mints indexed by the last sync; the lowest id wins
+    assert_eq!(registry.agent_id(ALICE), None);
+    registry.sync().await.unwrap();
+    assert_eq!(registry.agent_id(ALICE), Some(first));
+    assert_eq!(registry.agent_id(BOB), Some(bob));
+    assert!(registry.is_registered(BOB).await.unwrap());
+    assert_eq!(registry.token_uri(bob).await.unwrap(), "ipfs://bob");
+}
+
+#[tokio::test]
+async fn registration_files_are_read_from_data_uris() {
+    let (fake, registry, _) = registries().await;
+    let card = json!({
+        "type": "https://eips.ethereum.org/EIPS/eip-8004#registration-v1",
+        "name": "Gas oracle agent",
+        "description": "Buys gas predictions",
+        "endpoints": [{ "name": "A2A", "endpoint": "https://agent.example/.well-known/agent-card.json", "version": "0.3.0" }],
+        "registrations": [{ "agentId": 1, "agentRegistry": format!("eip155:84532:{:?}", FAKE_IDENTITY_REGISTRY) }],
+        "supportedTrust": ["reputation"]
+    });
+    let uri = format!("data:application/json;base64,{}", STANDARD.encode(card.to_string()));
+    let agent_id = fake.register(ALICE, &uri);
+
+    let registration = registry.registration(agent_id).await.unwrap();
+    assert_eq!(registration.name, "Gas oracle agent");
+    assert_eq!(registration.endpoints[0].name, "A2A");
+    assert_eq!(registration.registrations[0].agent_id, 1);
+    assert_eq!(registration.supported_trust, vec!["reputation".to_string()]);
+
+    let broken = fake.register(BOB, "data:application/json,{not json");
+    assert!(registry.registration(broken).await.is_err());
+    let unsupported = fake.register(BOB, "ftp://agent.example/card.json");
+    assert!(registry.registration(unsupported).await.is_err());
+    // HTTP(S) URIs are only fetched from public hosts
+    let private = fake.register(BOB, "http://127.0.0.1:8080/card.json");
+    let error = registry.registration(private).await.unwrap_err();
+    assert!(format!("{:#}", error).contains("non-public address"), "{:#}", error);
+}
+
+#[tokio::test]
+async fn reputation_scores_feedback_and_validations() {
+    let (fake, registry, cache) = registries().await;
+    let reputation =
+        ReputationService::new(cache.clone(), ledger(&cache), Some(registry.clone()), onchain_only(false)).await;
+
+    // Unregistered, and registered without feedback
+    assert_eq!(reputation.get_reputation(BOB).await.unwrap(), 250);
+    fake.register(ALICE, "ipfs://alice");
+    registry.sync().await.unwrap();
+    assert_eq!(reputation.get_reputation(ALICE).await.unwrap(), 250);
+
+    let carol = Address::repeat_byte(0xc0);
+    let carol_id = fake.register(carol, "ipfs://carol");
+    fake.give_feedback(carol_id, 12, 90);
+    fake.validate(carol_id, 2, 40);
+    registry.sync().await.unwrap();
+    assert_eq!(reputation.get_reputation(carol).await.unwrap(), 900 + 200);
+
+    let dave = Address::repeat_byte(0xd0);
+    let dave_id = fake.register(dave, "ipfs://dave");
+    fake.give_feedback(dave_id, 4, 5);
+    registry.sync().await.unwrap();
+    assert_eq!(reputation.get_reputation(dave).await.unwrap(), 50);
+}
+
+#[tokio::test]
+async fn registration_is_only_verified_when_required() {
+    let (fake, registry, cache) = registries().await;
+    let optional =
+        ReputationService::new(cache.clone(), ledger(&cache), Some(registry.clone()), onchain_only(false)).await;
+    let required = ReputationService::new(cache.clone(), ledger(&cache), Some(registry), onchain_only(true)).await;
+
+    assert!(optional.verify_registration(ALICE).await.unwrap());
+    assert!(!required.verify_registration(ALICE).await.unwrap());
+
+    fake.register(ALICE, "ipfs://alice");
+    assert!(required.verify_registration(ALICE).await.unwrap());
+
+    // Without registries there is nothing to check against
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+    let local = ReputationService::new(cache.clone(), ledger(&cache), None, onchain_only(true)).await;
+    assert!(local.verify_registration(BOB).await.unwrap());
+}
@@ -0,0 +1,42 @@
+use ethers::types::{Address, Bytes, H256, U256};
+use q_guard::services::fork_db::to_revm_address;
+use q_guard::services::{FakeChain, ForkDb};
+use revm::db::CacheDB;
+use revm::primitives::{ExecutionResult, TransactTo};
+use revm::EVM;
+use std::sync::Arc;
+
+const CONTRACT: Address = Address::repeat_byte(0xc0);
+
+/// Returns storage slot 0: SLOAD(0), MSTORE(0), RETURN(0, 32)
+const RETURN_SLOT_ZERO: &str = "0x60005460005260206000f3";
+
+fn call_contract(fork: ForkDb) -> U256 {
+    let mut evm = EVM::new();
+    evm.database(CacheDB::new(fork));
+    evm.env.tx.caller = to_revm_address(Address::repeat_byte(0x01));
+    evm.env.tx.transact_to = TransactTo::Call(to_revm_address(CONTRACT));
+    evm.env.tx.gas_limit = 100_000;
+
+    match evm.transact().unwrap().result {
+        ExecutionResult::Success { output, .. } => U256::from_big_endian(output.data()),
+        other => panic!("call failed: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn executes_against_state_fetched_once() {
+    let chain = Arc::new(FakeChain::new());
+    chain.set_account(CONTRACT, U256::zero(), RETURN_SLOT_ZERO.parse::<Bytes>().unwrap());
+    chain.set_storage(CONTRACT, H256::zero(), H256::from_low_u64_be(42));
+
+    let fork = ForkDb::new(chain.clone(), 0);
+    let first = fork.clone();
+    let value = tokio::task::spawn_blocking(move || call_contract(first)).await.unwrap();
+    assert_eq!(value, U256::from(42));
+
+    // Clones share what the first execution fetched
+    chain.set_offline(true);
+    let value = tokio::task::spawn_blocking(move || call_contract(fork)).await.unwrap();
+    assert_eq!(value, U256::from(42));
+}
@@ -0,0 +1,110 @@
+use ethers::types::{FeeHistory, U256};
+use q_guard::models::BlockFeeRecord;
+use q_guard::services::gas_history::{aggregate, fee_records};
+use q_guard::services::{CacheService, GasHistoryStore};
+use std::sync::Arc;
+
+/// Start of an hour bucket
+const HOUR_START: u64 = 472_222 * 3600;
+
+fn record(number: u64, timestamp: u64, base_fee_gwei: f64) -> BlockFeeRecord {
+    BlockFeeRecord {
+        number,
+        timestamp,
+        base_fee_gwei,
+        gas_used_ratio: 0.5,
+        priority_fee_p10_gwei: 0.5,
+        priority_fee_p50_gwei: 1.0,
+        priority_fee_p90_gwei: 2.0,
+    }
+}
+
+fn numbers(records: &[BlockFeeRecord]) -> Vec<u64> {
+    records.iter().map(|r| r.number).collect()
+}
+
+#[tokio::test]
+async fn flushed_buckets_survive_a_restart() {
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+
+    let before = GasHistoryStore::new(cache.clone(), 1000, 7);
+    before
+        .record("ethereum", (1..=3).map(|n| record(n, HOUR_START + n * 12, 20.0)).collect())
+        .await;
+    before.flush().await;
+
+    // A new process writes to the same hour without losing what is stored
+    let after = GasHistoryStore::new(cache, 1000, 7);
+    after.record("ethereum", vec![record(4, HOUR_START + 48, 21.0)]).await;
+    after.flush().await;
+
+    let records = after.records_between("ethereum", HOUR_START, HOUR_START + 3600).await;
+    assert_eq!(numbers(&records), vec![1, 2, 3, 4]);
+}
+
+#[tokio::test]
+async fn blocks_dropped_from_memory_are_served_from_buckets() {
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+    let store = GasHistoryStore::new(cache, 2, 7);
+
+    store.record("base", vec![record(1, HOUR_START, 10.0)]).await;
+    store.flush().await;
+    store
+        .record("base", vec![record(2, HOUR_START + 2, 11.0), record(3, HOUR_START + 4, 12.0)])
+        .await;
+    store.flush().await;
+
+    let records = store.records_between("base", HOUR_START, HOUR_START + 3600).await;
+    assert_eq!(numbers(&records), vec![1, 2, 3]);
+    assert!(store.records_between("ethereum", HOUR_START, HOUR_START + 3600).await.is_empty());
+}
+
+#[test]
+fn aggregate_buckets_fees_by_time() {
+    let records: Vec<BlockFeeRecord> = (0..10)
+        .map(|n| record(n, HOUR_START + n * 12, 10.0 + n as f64))
+        .collect();
+
+    let history = aggregate("ethereum", &records, HOUR_START, HOUR_START + 120, 60);
+    assert_eq!(history.blocks, 10);
+    assert_eq!(history.buckets.len(), 2);
+
+    let first = &history.buckets[0];
+    assert_eq!((first.first_block, first.last_block, first.blocks), (0, 4, 5));
+    assert_eq!(first.base_fee_gwei.min, 10.0);
+    assert_eq!(first.base_fee_gwei.median, 12.0);
+    assert_eq!(first.base_fee_gwei.max, 14.0);
+    assert_eq!(first.priority_fee_gwei.median, 1.0);
+    assert_eq!(history.buckets[1].first_block, 5);
+}
+
+#[test]
+fn aggregate_tolerates_records_before_the_range() {
+    let records = vec![record(1, HOUR_START - 30, 10.0), record(2, HOUR_START + 30, 20.0)];
+
+    let history = aggregate("ethereum", &records, HOUR_START, HOUR_START + 60, 60);
+    assert_eq!(history.buckets.len(), 1);
+    assert_eq!(history.buckets[0].blocks, 2);
+}
+
+#[test]
+fn fee_records_read_fee_history_responses() {
+    let gwei = |n: u64| U256::from(n) * U256::exp10(9);
+    let history = FeeHistory {
+        oldest_block: U256::from(100),
+        // One extra entry for the block after the newest
+        base_fee_per_gas: vec![gwei(20), gwei(22), gwei(24)],
+        gas_used_ratio: vec![0.9, 0.4],
+        reward: vec![vec![gwei(1), gwei(2), gwei(3)], vec![gwei(1), gwei(1), gwei(5)]],
+    };
+
+    let records = fee_records(&history, |number| number * 12);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].number, 100);
+    assert_eq!(records[0].timestamp, 1200);
+    assert_eq!(records[0].base_fee_gwei, 20.0);
+    assert_eq!(records[0].priority_fee_p90_gwei, 3.0);
+    assert_eq!(records[1].base_fee_gwei, 22.0);
+    assert_eq!(records[1].gas_used_ratio, 0.4);
+    assert_eq!(records[1].priority_fee_p50_gwei, 1.0);
+}
@@ -0,0 +1,110 @@
+use q_guard::models::GasModel;
+use q_guard::services::gas_predictor::{
+    next_base_fee_gwei, percentile, BlockSample, GasPredictor, KalmanPredictor,
+};
+
+const GAS_LIMIT: u64 = 30_000_000;
+
+fn block(number: u64, base_fee_gwei: f64, gas_used: u64) -> BlockSample {
+    BlockSample {
+        number,
+        timestamp: 1_700_000_000 + number * 12,
+        base_fee_gwei,
+        gas_used,
+        gas_limit: GAS_LIMIT,
+    }
+}
+
+/// `base_fees` at half-full blocks, oldest first
+fn history(base_fees: &[f64]) -> Vec<BlockSample> {
+    base_fees
+        .iter()
+        .enumerate()
+        .map(|(i, base_fee)| block(i as u64, *base_fee, GAS_LIMIT / 2))
+        .collect()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+}
+
+#[test]
+fn next_base_fee_follows_eip1559() {
+    // Full blocks raise the base fee by 12.5%, empty ones lower it by 12.5%
+    assert_close(next_base_fee_gwei(&block(1, 100.0, GAS_LIMIT)), 112.5);
+    assert_close(next_base_fee_gwei(&block(1, 100.0, GAS_LIMIT / 2)), 100.0);
+    assert_close(next_base_fee_gwei(&block(1, 100.0, 0)), 87.5);
+    assert_close(next_base_fee_gwei(&block(1, 100.0, GAS_LIMIT * 3 / 4)), 106.25);
+
+    let no_limit = BlockSample {
+        gas_limit: 0,
+        ..block(1, 100.0, 0)
+    };
+    assert_close(next_base_fee_gwei(&no_limit), 100.0);
+}
+
+#[test]
+fn eip1559_model_predicts_from_the_latest_block() {
+    let mut blocks = history(&[10.0, 20.0, 40.0]);
+    blocks.push(block(3, 80.0, GAS_LIMIT));
+
+    let estimate = GasModel::Eip1559.predictor().predict(&blocks).unwrap();
+    assert_close(estimate.base_fee_gwei, 90.0);
+    // Two more full blocks of headroom plus the priority fee
+    assert_close(estimate.max_fee_gwei, 90.0 * 1.125 * 1.125 + 2.0);
+}
+
+#[test]
+fn percentiles_use_the_nearest_rank() {
+    let values = [5.0, 1.0, 4.0, 2.0, 3.0];
+    assert_eq!(percentile(&values, 0.0), Some(1.0));
+    assert_eq!(percentile(&values, 0.5), Some(3.0));
+    assert_eq!(percentile(&values, 0.75), Some(4.0));
+    assert_eq!(percentile(&values, 1.0), Some(5.0));
+    // Out of range percentiles are clamped
+    assert_eq!(percentile(&values, 2.0), Some(5.0));
+    assert_eq!(percentile(&[], 0.5), None);
+}
+
+#[test]
+fn percentile_model_ignores_short_spikes() {
+    let blocks = history(&[20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 500.0]);
+
+    let estimate = GasModel::Percentile.predictor().predict(&blocks).unwrap();
+    assert_close(estimate.base_fee_gwei, 20.0);
+}
+
+#[test]
+fn kalman_filter_tracks_a_steady_base_fee() {
+    let blocks = history(&[30.0; 20]);
+
+    let estimate = GasModel::Kalman.predictor().predict(&blocks).unwrap();
+    assert_close(estimate.base_fee_gwei, 30.0);
+    assert!(estimate.max_fee_gwei > estimate.base_fee_gwei + 2.0);
+    assert!(estimate.confidence > 0.5 && estimate.confidence <= 1.0);
+}
+
+#[test]
+fn kalman_filter_moves_part_way_towards_a_new_level() {
+    let mut base_fees = vec![10.0; 20];
+    base_fees.push(20.0);
+
+    let estimate = KalmanPredictor::default().predict(&history(&base_fees)).unwrap();
+    assert!(
+        estimate.base_fee_gwei > 10.0 && estimate.base_fee_gwei < 20.0,
+        "{}",
+        estimate.base_fee_gwei
+    );
+
+    // Zero base fees carry no information
+    assert!(KalmanPredictor::default().predict(&history(&[0.0, 0.0])).is_err());
+}
+
+#[test]
+fn every_model_rejects_an_empty_history() {
+    for model in [GasModel::Ewma, GasModel::Eip1559, GasModel::Percentile, GasModel::Kalman] {
+        let predictor = model.predictor();
+        assert_eq!(predictor.model(), model);
+        assert!(predictor.predict(&[]).is_err(), "{:?}", model);
+    }
+}
@@ -0,0 +1,159 @@
+use ethers::abi::{self, ParamType, Token};
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Bytes, Log, Transaction, H256, U256, U64};
+use q_guard::contracts::compound_v3::{
+    BaseScaleCall, BaseScaleReturn, BaseTokenCall, BaseTokenPriceFeedCall, BaseTokenPriceFeedReturn, BaseTokenReturn,
+    BorrowBalanceOfCall, BorrowBalanceOfReturn, CollateralBalanceOfCall, CollateralBalanceOfReturn, GetAssetInfoCall,
+    GetAssetInfoReturn, GetPriceCall, GetPriceReturn, NumAssetsCall, NumAssetsReturn, StoreFrontPriceFactorCall,
+    StoreFrontPriceFactorReturn, SupplyFilter, WithdrawFilter,
+};
+use q_guard::contracts::{AggregatorCall, AggregatorReturn, DecimalsCall, DecimalsReturn};
+use q_guard::models::{ChainSpec, LendingProtocol};
+use q_guard::services::{FakeChain, LendingMarket, LiquidationMonitor};
+use std::sync::Arc;
+
+const COMET: Address = Address::repeat_byte(0xc3);
+const USDC: Address = Address::repeat_byte(0xb0);
+const WBTC: Address = Address::repeat_byte(0xb1);
+const USDC_FEED: Address = Address::repeat_byte(0xf0);
+const WBTC_FEED: Address = Address::repeat_byte(0xf1);
+/// Receives the reports behind `WBTC_FEED`
+const WBTC_AGGREGATOR: Address = Address::repeat_byte(0xa9);
+const BORROWER: Address = Address::repeat_byte(0x42);
+
+fn e18(amount: f64) -> u64 {
+    (amount * 1e18) as u64
+}
+
+fn dollars(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(8)
+}
+
+/// Log of `event` with the borrower as both indexed addresses
+fn event_log(topic: H256, block: u64) -> Log {
+    Log {
+        address: COMET,
+        topics: vec![topic, H256::from(BORROWER), H256::from(BORROWER)],
+        data: Bytes::from(abi::encode(&[Token::Uint(U256::one())])),
+        block_number: Some(U64::from(block)),
+        ..Default::default()
+    }
+}
+
+/// A USDC market with WBTC collateral at $20,000, liquidated below 85%
+/// collateral value. The borrower owes $16,000 against 1 WBTC: health 1.0625.
+async fn monitor() -> (Arc<FakeChain>, LiquidationMonitor) {
+    let chain = Arc::new(FakeChain::new());
+    chain.mine(U256::exp10(9), 15_000_000, 30_000_000, U256::exp10(9));
+
+    chain.on_call::<BaseTokenCall>(COMET, BaseTokenReturn(USDC));
+    chain.on_call::<BaseTokenPriceFeedCall>(COMET, BaseTokenPriceFeedReturn(USDC_FEED));
+    chain.on_call::<BaseScaleCall>(COMET, BaseScaleReturn(U256::exp10(6)));
+    chain.on_call::<StoreFrontPriceFactorCall>(COMET, StoreFrontPriceFactorReturn(e18(0.6).into()));
+    chain.on_call::<NumAssetsCall>(COMET, NumAssetsReturn(1));
+    chain.on_call::<GetAssetInfoCall>(
+        COMET,
+        GetAssetInfoReturn {
+            offset: 0,
+            asset: WBTC,
+            price_feed: WBTC_FEED,
+            scale: 100_000_000,
+            borrow_collateral_factor: e18(0.8),
+            liquidate_collateral_factor: e18(0.85),
+            liquidation_factor: e18(0.9),
+            supply_cap: u128::MAX,
+        },
+    );
+    chain.on_exact_call(COMET, GetPriceCall { price_feed: USDC_FEED }, GetPriceReturn(dollars(1)));
+    chain.on_exact_call(COMET, GetPriceCall { price_feed: WBTC_FEED }, GetPriceReturn(dollars(20_000)));
+    chain.on_call::<AggregatorCall>(WBTC_FEED, AggregatorReturn(WBTC_AGGREGATOR));
+    chain.on_call::<DecimalsCall>(WBTC_FEED, DecimalsReturn(8));
+
+    chain.add_log(event_log(WithdrawFilter::signature(), 0));
+    chain.on_exact_call(
+        COMET,
+        BorrowBalanceOfCall { account: BORROWER },
+        BorrowBalanceOfReturn(U256::from(16_000) * U256::exp10(6)),
+    );
+    chain.on_call::<CollateralBalanceOfCall>(COMET, CollateralBalanceOfReturn(100_000_000));
+
+    let market = LendingMarket {
+        protocol: LendingProtocol::CompoundV3,
+        address: COMET,
+    };
+    let monitor = LiquidationMonitor::new(chain.clone(), vec![market], 100, ChainSpec::ethereum().wrapped_native);
+    assert!(monitor.load(0).await);
+    (chain, monitor)
+}
+
+/// OCR1 `transmit` of a report whose median observation is `median`
+fn transmit(median: U256) -> Transaction {
+    let observations = [median - 1, median, median + 1].map(Token::Int).to_vec();
+    let report = abi::encode(&[
+        Token::FixedBytes(vec![0; 32]),
+        Token::FixedBytes(vec![0; 32]),
+        Token::Array(observations),
+    ]);
+
+    let bytes32_array = ParamType::Array(Box::new(ParamType::FixedBytes(32)));
+    let selector = abi::short_signature(
+        "transmit",
+        &[ParamType::Bytes, bytes32_array.clone(), bytes32_array, ParamType::FixedBytes(32)],
+    );
+    let args = abi::encode(&[
+        Token::Bytes(report),
+        Token::Array(Vec::new()),
+        Token::Array(Vec::new()),
+        Token::FixedBytes(vec![0; 32]),
+    ]);
+
+    Transaction {
+        to: Some(WBTC_AGGREGATOR),
+        input: [selector.as_slice(), &args].concat().into(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn pending_price_drop_makes_borrower_liquidatable() {
+    let (_chain, monitor) = monitor().await;
+    assert_eq!(monitor.position_count(), 1);
+    assert!(monitor.open_liquidations(3000.0).is_empty());
+
+    // A 5% drop still leaves the position healthy
+    assert!(monitor.after_price_update(&transmit(dollars(19_000)), 3000.0).is_empty());
+
+    let candidates = monitor.after_price_update(&transmit(dollars(18_000)), 3000.0);
+    let candidate = candidates.first().expect("liquidatable after the report");
+    assert_eq!(candidate.borrower, BORROWER);
+    assert_eq!((candidate.debt_asset, candidate.collateral_asset), (USDC, WBTC));
+    assert!((candidate.health_factor - 0.85 * 18_000.0 / 16_000.0).abs() < 1e-9);
+    // Absorb repays the whole debt; the collateral sells at 60% of the 10% liquidation discount
+    assert!((candidate.max_repay - 16_000.0).abs() < 1e-6);
+    assert!((candidate.bonus - 0.06).abs() < 1e-9);
+    // 99% of the collateral is bought back, paying 94% of its price
+    assert!((candidate.profit_usd - 0.99 * 18_000.0 * 0.06).abs() < 1e-6);
+    let [purchase] = &candidate.purchases[..] else {
+        panic!("expected one purchase, got {:?}", candidate.purchases);
+    };
+    assert_eq!(purchase.asset, WBTC);
+    assert!(purchase.base_amount.as_u128().abs_diff(16_750_800_000) <= 1);
+    assert!(purchase.min_amount.as_u128().abs_diff(98_505_000) <= 1);
+
+    // Reports for other aggregators are ignored
+    let mut other = transmit(dollars(18_000));
+    other.to = Some(Address::repeat_byte(0x01));
+    assert!(monitor.after_price_update(&other, 3000.0).is_empty());
+}
+
+#[tokio::test]
+async fn repaid_positions_stop_being_tracked() {
+    let (chain, monitor) = monitor().await;
+
+    chain.mine(U256::exp10(9), 15_000_000, 30_000_000, U256::exp10(9));
+    chain.add_log(event_log(SupplyFilter::signature(), 1));
+    chain.on_exact_call(COMET, BorrowBalanceOfCall { account: BORROWER }, BorrowBalanceOfReturn(U256::zero()));
+
+    monitor.process_blocks(1, 1).await.unwrap();
+    assert_eq!(monitor.position_count(), 0);
+}
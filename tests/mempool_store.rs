@@ -0,0 +1,84 @@
+use ethers::types::{Address, Transaction, H256, U256};
+use q_guard::services::{
+    mempool_store::{InsertOutcome, Removal},
+    MempoolStore,
+};
+use std::time::Duration;
+
+fn tx(hash: u64, from: u8, nonce: u64, max_fee_gwei: u64) -> Transaction {
+    Transaction {
+        hash: H256::from_low_u64_be(hash),
+        from: Address::repeat_byte(from),
+        nonce: nonce.into(),
+        max_fee_per_gas: Some(U256::from(max_fee_gwei) * U256::exp10(9)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn duplicates_are_ignored() {
+    let store = MempoolStore::new(10, Duration::from_secs(60));
+
+    assert_eq!(store.insert(tx(1, 0xaa, 0, 20)), InsertOutcome::New);
+    assert_eq!(store.insert(tx(1, 0xaa, 0, 20)), InsertOutcome::Duplicate);
+    assert_eq!(store.len(), 1);
+}
+
+#[test]
+fn higher_fee_replaces_same_sender_and_nonce() {
+    let store = MempoolStore::new(10, Duration::from_secs(60));
+    store.insert(tx(1, 0xaa, 7, 20));
+
+    assert_eq!(store.insert(tx(2, 0xaa, 7, 20)), InsertOutcome::Underpriced);
+    assert_eq!(store.insert(tx(3, 0xaa, 7, 30)), InsertOutcome::Replaced(H256::from_low_u64_be(1)));
+
+    assert!(store.get(H256::from_low_u64_be(1)).is_none());
+    assert_eq!(store.replaced_by(H256::from_low_u64_be(1)), Some(H256::from_low_u64_be(3)));
+    assert_eq!(store.get(H256::from_low_u64_be(3)).unwrap().replaces, Some(H256::from_low_u64_be(1)));
+    assert_eq!(store.replacements(), 1);
+    assert_eq!(store.len(), 1);
+}
+
+#[test]
+fn capacity_evicts_oldest_and_lists_newest_first() {
+    let store = MempoolStore::new(2, Duration::from_secs(60));
+    for i in 1..=3 {
+        store.insert(tx(i, i as u8, 0, 20));
+    }
+
+    let hashes: Vec<_> = store.transactions().iter().map(|tx| tx.hash).collect();
+    assert_eq!(hashes, vec![H256::from_low_u64_be(3), H256::from_low_u64_be(2)]);
+}
+
+#[test]
+fn expired_transactions_are_dropped() {
+    let store = MempoolStore::new(10, Duration::ZERO);
+    store.insert(tx(1, 0xaa, 0, 20));
+
+    assert!(store.transactions().is_empty());
+}
+
+#[test]
+fn removals_are_remembered_up_to_capacity() {
+    let store = MempoolStore::new(2, Duration::from_secs(60));
+    for i in 1..=5 {
+        store.insert(tx(i, i as u8, 0, 20));
+    }
+
+    // 1, 2 and 3 were dropped; only the two latest removals are remembered
+    assert_eq!(store.removal(H256::from_low_u64_be(1)), None);
+    assert_eq!(store.removal(H256::from_low_u64_be(2)), Some(Removal::Dropped));
+    assert_eq!(store.removal(H256::from_low_u64_be(3)), Some(Removal::Dropped));
+}
+
+#[test]
+fn replaced_transaction_can_still_be_included() {
+    let store = MempoolStore::new(10, Duration::from_secs(60));
+    store.insert(tx(1, 0xaa, 7, 20));
+    store.insert(tx(2, 0xaa, 7, 30));
+
+    assert_eq!(store.removal(H256::from_low_u64_be(1)), Some(Removal::Replaced(H256::from_low_u64_be(2))));
+    assert!(store.take_included(H256::from_low_u64_be(1)).is_some());
+    assert_eq!(store.removal(H256::from_low_u64_be(1)), Some(Removal::Included));
+    assert!(store.take_included(H256::from_low_u64_be(1)).is_none());
+}
@@ -0,0 +1,100 @@
+use ethers::abi::AbiEncode;
+use ethers::contract::EthCall;
+use ethers::types::{Address, Transaction, H256, U256};
+use q_guard::contracts::{SwapExactETHForTokensCall, UNISWAP_V2_ROUTER};
+use q_guard::services::mempool_analytics::{summarize, SummaryFilter};
+use q_guard::services::DexRegistry;
+
+const WETH: Address = Address::repeat_byte(0xee);
+const USDC: Address = Address::repeat_byte(0xcc);
+
+fn tx(hash: u64, from: u8, nonce: u64) -> Transaction {
+    Transaction {
+        hash: H256::from_low_u64_be(hash),
+        from: Address::repeat_byte(from),
+        nonce: nonce.into(),
+        gas: 21_000.into(),
+        max_priority_fee_per_gas: Some(U256::from(1_500_000_000u64)),
+        ..Default::default()
+    }
+}
+
+fn router() -> Address {
+    UNISWAP_V2_ROUTER.parse().unwrap()
+}
+
+fn swap(hash: u64, from: u8, value_eth: u64) -> Transaction {
+    let call = SwapExactETHForTokensCall {
+        amount_out_min: U256::zero(),
+        path: vec![WETH, USDC],
+        to: Address::repeat_byte(from),
+        deadline: U256::MAX,
+    };
+    Transaction {
+        to: Some(router()),
+        input: call.encode().into(),
+        value: U256::from(value_eth) * U256::exp10(18),
+        ..tx(hash, from, 0)
+    }
+}
+
+#[test]
+fn summarizes_swaps_senders_and_nonce_gaps() {
+    let txs = vec![swap(1, 0x01, 2), swap(2, 0x02, 3), tx(3, 0x03, 5), tx(4, 0x03, 7), tx(5, 0x03, 8)];
+
+    let summary = summarize(&txs, &SummaryFilter::default(), &DexRegistry::mainnet());
+
+    assert_eq!(summary.pending_transactions, 5);
+    assert_eq!(summary.fee_histogram[1].transactions, 5);
+
+    assert_eq!(summary.top_senders[0].address, Address::repeat_byte(0x03));
+    assert_eq!(summary.top_senders[0].transactions, 3);
+    assert_eq!(summary.top_contracts[0].address, router());
+
+    assert_eq!(summary.nonce_gaps.len(), 1);
+    assert_eq!(summary.nonce_gaps[0].missing, vec![6]);
+    assert_eq!(summary.nonce_gaps[0].blocked_transactions, 2);
+
+    let pair = &summary.dex_swaps[0];
+    assert_eq!((pair.token_in, pair.token_out, pair.swaps), (WETH, USDC, 2));
+    assert_eq!(pair.value_eth, 5.0);
+}
+
+#[test]
+fn swaps_to_unregistered_contracts_are_not_counted() {
+    let lookalike = Transaction {
+        to: Some(Address::repeat_byte(0x7a)),
+        ..swap(1, 0x01, 2)
+    };
+
+    let summary = summarize(&[lookalike], &SummaryFilter::default(), &DexRegistry::mainnet());
+
+    assert_eq!(summary.pending_transactions, 1);
+    assert!(summary.dex_swaps.is_empty());
+}
+
+#[test]
+fn filters_by_selector() {
+    let txs = vec![swap(1, 0x01, 2), tx(2, 0x02, 0)];
+    let filter = SummaryFilter {
+        to: None,
+        selector: Some(SwapExactETHForTokensCall::selector()),
+    };
+
+    let summary = summarize(&txs, &filter, &DexRegistry::mainnet());
+
+    assert_eq!(summary.pending_transactions, 1);
+    assert_eq!(summary.dex_swaps.len(), 1);
+}
+
+#[test]
+fn large_nonce_gaps_list_only_the_first_missing_nonces() {
+    let txs = vec![tx(1, 0x01, 3), tx(2, 0x01, 5), tx(3, 0x01, u64::MAX / 2)];
+
+    let summary = summarize(&txs, &SummaryFilter::default(), &DexRegistry::mainnet());
+
+    let gap = &summary.nonce_gaps[0];
+    assert_eq!((gap.lowest_nonce, gap.highest_nonce), (3, u64::MAX / 2));
+    assert_eq!(gap.missing, vec![4, 6, 7, 8, 9, 10, 11, 12, 13, 14]);
+    assert_eq!(gap.blocked_transactions, 2);
+}
@@ -0,0 +1,215 @@
+use chrono::Utc;
+use ethers::types::{Address, Transaction, H256, U256};
+use q_guard::models::{
+    BundleTransaction, ChainSpec, ExecutionDetails, GasModel, MEVOpportunity, MEVType, MevFilter, MevOpportunityQuery,
+    MevSort,
+};
+use q_guard::services::*;
+use std::sync::Arc;
+
+const TOKEN: Address = Address::repeat_byte(0x11);
+const OTHER_TOKEN: Address = Address::repeat_byte(0x22);
+const POOL: Address = Address::repeat_byte(0xa1);
+
+async fn feed() -> MevFeed {
+    let chain = Arc::new(FakeChain::new());
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+    let ethereum =
+        Arc::new(EthereumService::new(ChainSpec::ethereum(), chain.clone(), cache.clone(), GasModel::Ewma).await);
+    let dexes = Arc::new(DexRegistry::mainnet());
+    let prices = Arc::new(PriceService::new(chain.clone(), cache, dexes.clone(), Address::zero(), Vec::new()));
+    let arbitrage = Arc::new(ArbitrageScanner::new(chain.clone(), Vec::new(), dexes.clone()));
+    let liquidations = Arc::new(LiquidationMonitor::new(chain, Vec::new(), 7200, dexes.wrapped_native()));
+
+    MevFeed::new(Arc::new(MEVDetector::new(ethereum, prices, dexes, arbitrage, liquidations)))
+}
+
+fn opportunity(kind: MEVType, net_profit_usd: f64, token: Address, expires_in_blocks: u64) -> MEVOpportunity {
+    MEVOpportunity {
+        opportunity_type: kind,
+        profit_usd: net_profit_usd + 10.0,
+        gas_cost_usd: 10.0,
+        net_profit_usd,
+        confidence: 0.7,
+        target_transaction: format!("{:?}", H256::repeat_byte(0x01)),
+        suggested_gas_price: 20.0,
+        execution_details: ExecutionDetails {
+            target_pool: POOL,
+            token_in: Address::repeat_byte(0xee),
+            token_out: token,
+            amount_in: "1".to_string(),
+            expected_profit: "0.1".to_string(),
+            route: Vec::new(),
+            liquidation: None,
+        },
+        expires_in_blocks,
+        detected_at: Utc::now(),
+        bundle: None,
+    }
+}
+
+fn pending_tx() -> Arc<Transaction> {
+    Arc::new(Transaction {
+        hash: H256::repeat_byte(0x01),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn opportunities_expire_after_their_blocks_are_mined() {
+    let feed = feed().await;
+    feed.advance(100);
+
+    feed.publish(
+        pending_tx(),
+        vec![
+            (opportunity(MEVType::Arbitrage, 50.0, TOKEN, 1), None),
+            (opportunity(MEVType::Arbitrage, 80.0, TOKEN, 3), None),
+        ],
+    );
+    assert_eq!(feed.live().len(), 2);
+    assert_eq!(feed.live()[0].expires_at_block, 101);
+
+    feed.advance(101);
+    assert_eq!(feed.live().len(), 1);
+    assert_eq!(feed.opportunities(None)[0].net_profit_usd, 80.0);
+
+    // Heads never move backwards
+    feed.advance(99);
+    assert_eq!(feed.head(), 101);
+
+    feed.advance(103);
+    assert!(feed.live().is_empty());
+}
+
+#[tokio::test]
+async fn subscribers_receive_new_opportunities() {
+    let feed = feed().await;
+    let mut opportunities = feed.subscribe();
+
+    feed.publish(pending_tx(), vec![(opportunity(MEVType::Arbitrage, 50.0, TOKEN, 1), None)]);
+
+    let live = opportunities.recv().await.unwrap();
+    assert_eq!(live.opportunity.opportunity_type, MEVType::Arbitrage);
+    assert_eq!(live.expires_at_block, 1);
+}
+
+#[tokio::test]
+async fn bundles_are_built_for_each_searcher_from_the_stored_simulation() {
+    let feed = feed().await;
+    feed.advance(100);
+    let sandwich = SimulatedSandwich {
+        block: 100,
+        pair: POOL,
+        router: Address::repeat_byte(0xf0),
+        wrapped_native: ChainSpec::ethereum().wrapped_native,
+        token: TOKEN,
+        token_amount: U256::exp10(21),
+        frontrun_wei: U256::exp10(18),
+        backrun_wei: U256::exp10(18) * 11 / 10,
+        frontrun_gas: 120_000,
+        backrun_gas: 110_000,
+        frontrun_gas_price: U256::exp10(10),
+        backrun_gas_price: U256::exp10(9),
+    };
+
+    feed.publish(
+        pending_tx(),
+        vec![
+            (opportunity(MEVType::Sandwich, 50.0, TOKEN, 1), Some(BundlePlan::Sandwich(sandwich))),
+            (opportunity(MEVType::Arbitrage, 20.0, TOKEN, 1), None),
+        ],
+    );
+
+    assert!(feed.opportunities(None).iter().all(|opportunity| opportunity.bundle.is_none()));
+    for searcher in [Address::repeat_byte(0x5e), Address::repeat_byte(0x5f)] {
+        let opportunities = feed.opportunities(Some(searcher));
+        let bundle = opportunities[0].bundle.as_ref().unwrap();
+        assert_eq!(bundle.block_number, "0x65");
+        assert_eq!(bundle.txs.len(), 4);
+        let BundleTransaction::Unsigned(frontrun) = &bundle.txs[0] else { panic!("frontrun is signed") };
+        assert_eq!(frontrun.from, searcher);
+        assert!(opportunities[1].bundle.is_none());
+    }
+}
+
+#[test]
+fn filters_match_type_profit_token_and_pool() {
+    let sandwich = opportunity(MEVType::Sandwich, 120.0, TOKEN, 1);
+
+    assert!(MevFilter::default().matches(&sandwich));
+    assert!(MevFilter {
+        types: vec![MEVType::Sandwich, MEVType::Liquidation],
+        min_net_profit_usd: 100.0,
+        tokens: vec![OTHER_TOKEN, TOKEN],
+        pools: vec![POOL],
+    }
+    .matches(&sandwich));
+
+    let arbitrage_only = MevFilter {
+        types: vec![MEVType::Arbitrage],
+        ..Default::default()
+    };
+    assert!(!arbitrage_only.matches(&sandwich));
+    let min_profit = MevFilter {
+        min_net_profit_usd: 150.0,
+        ..Default::default()
+    };
+    assert!(!min_profit.matches(&sandwich));
+    let other_token = MevFilter {
+        tokens: vec![OTHER_TOKEN],
+        ..Default::default()
+    };
+    assert!(!other_token.matches(&sandwich));
+    let other_pool = MevFilter {
+        pools: vec![Address::repeat_byte(0xa2)],
+        ..Default::default()
+    };
+    assert!(!other_pool.matches(&sandwich));
+
+    assert_eq!("backrun".parse::<MEVType>().unwrap(), MEVType::BackRun);
+    assert!("frontrun".parse::<MEVType>().is_err());
+}
+
+#[test]
+fn queries_sort_and_page_matching_opportunities() {
+    let mut confident = opportunity(MEVType::Liquidation, 40.0, TOKEN, 1);
+    confident.confidence = 0.9;
+    let opportunities = vec![
+        opportunity(MEVType::Arbitrage, 20.0, TOKEN, 2),
+        opportunity(MEVType::Sandwich, 60.0, TOKEN, 1),
+        confident,
+        opportunity(MEVType::Arbitrage, 5.0, TOKEN, 1),
+    ];
+    let mut query = MevOpportunityQuery {
+        filter: MevFilter {
+            min_net_profit_usd: 10.0,
+            ..Default::default()
+        },
+        sort: MevSort::NetProfit,
+        limit: 2,
+        offset: 0,
+    };
+
+    let page = query.apply(opportunities.clone());
+    assert_eq!(page.total, 3);
+    assert!(page.has_more);
+    let profits: Vec<f64> = page.opportunities.iter().map(|o| o.net_profit_usd).collect();
+    assert_eq!(profits, vec![60.0, 40.0]);
+
+    query.offset = 2;
+    let page = query.apply(opportunities.clone());
+    assert_eq!(page.opportunities.len(), 1);
+    assert!(!page.has_more);
+
+    query.offset = 0;
+    query.sort = MevSort::Confidence;
+    assert_eq!(query.apply(opportunities.clone()).opportunities[0].opportunity_type, MEVType::Liquidation);
+
+    query.sort = MevSort::Expiry;
+    query.limit = 3;
+    assert_eq!(query.apply(opportunities).opportunities[2].expires_in_blocks, 2);
+
+    assert_eq!("expiry".parse::<MevSort>().unwrap(), MevSort::Expiry);
+    assert!("newest".parse::<MevSort>().is_err());
+}
@@ -0,0 +1,222 @@
+use ethers::abi::{self, Token};
+use ethers::contract::EthEvent;
+use ethers::types::{Address, Bytes, Log, Transaction, TransactionReceipt, H256, U256, U64};
+use q_guard::contracts::aave_v3::LiquidationCallFilter;
+use q_guard::contracts::{SwapFilter, Token0Call, Token0Return, Token1Call, Token1Return, WETH};
+use q_guard::models::LendingProtocol;
+use q_guard::services::{CacheService, DexRegistry, FakeChain, MevHistoryService};
+use std::sync::Arc;
+
+/// Sorts below WETH, so it is token0 of both pairs
+const TOKEN: Address = Address::repeat_byte(0x11);
+const PAIR: Address = Address::repeat_byte(0xa1);
+const OTHER_PAIR: Address = Address::repeat_byte(0xa2);
+const SEARCHER: Address = Address::repeat_byte(0x5e);
+const VICTIM: Address = Address::repeat_byte(0x42);
+const ARBITRAGEUR: Address = Address::repeat_byte(0xab);
+const LIQUIDATOR: Address = Address::repeat_byte(0x1d);
+const AAVE_POOL: Address = Address::repeat_byte(0xaa);
+const BUILDER: Address = Address::repeat_byte(0xb1);
+const PROPOSER: Address = Address::repeat_byte(0xb2);
+
+/// Every transaction uses 100k gas at 2 gwei, 1 gwei above the base fee
+const GAS_USED: u64 = 100_000;
+const GAS_COST_ETH: f64 = 100_000.0 * 2e-9;
+
+fn ether(amount: f64) -> U256 {
+    U256::from((amount * 1e18) as u128)
+}
+
+fn weth() -> Address {
+    WETH.parse().unwrap()
+}
+
+/// V2 `Swap` on a (TOKEN, WETH) pair; positive `weth_in` buys TOKEN
+fn swap_log(pair: Address, weth_in: f64, token_out: f64, token_in: f64, weth_out: f64) -> Log {
+    Log {
+        address: pair,
+        topics: vec![SwapFilter::signature(), H256::zero(), H256::zero()],
+        data: Bytes::from(abi::encode(&[
+            Token::Uint(ether(token_in)),
+            Token::Uint(ether(weth_in)),
+            Token::Uint(ether(token_out)),
+            Token::Uint(ether(weth_out)),
+        ])),
+        ..Default::default()
+    }
+}
+
+fn liquidation_log() -> Log {
+    Log {
+        address: AAVE_POOL,
+        topics: vec![
+            LiquidationCallFilter::signature(),
+            H256::from(weth()),
+            H256::from(TOKEN),
+            H256::from(VICTIM),
+        ],
+        data: Bytes::from(abi::encode(&[
+            Token::Uint(U256::from(1_000)),
+            Token::Uint(U256::from(2_000)),
+            Token::Address(LIQUIDATOR),
+            Token::Bool(false),
+        ])),
+        ..Default::default()
+    }
+}
+
+fn mine_transaction(chain: &FakeChain, index: u64, from: Address, logs: Vec<Log>) {
+    let hash = H256::from_low_u64_be(0x1000 + index);
+    let tx = Transaction {
+        hash,
+        from,
+        block_number: Some(U64::zero()),
+        transaction_index: Some(index.into()),
+        ..Default::default()
+    };
+    let receipt = TransactionReceipt {
+        transaction_hash: hash,
+        gas_used: Some(GAS_USED.into()),
+        effective_gas_price: Some(U256::from(2_000_000_000u64)),
+        logs,
+        ..Default::default()
+    };
+    chain.add_transaction(tx, receipt);
+}
+
+fn hash(index: u64) -> H256 {
+    H256::from_low_u64_be(0x1000 + index)
+}
+
+/// Block 0: a sandwich around a victim's buy, an arbitrage across both pairs
+/// and an Aave liquidation
+async fn history() -> MevHistoryService {
+    let chain = Arc::new(FakeChain::new());
+    chain.mine(U256::exp10(9), 15_000_000, 30_000_000, U256::exp10(9));
+    for pair in [PAIR, OTHER_PAIR] {
+        chain.on_call::<Token0Call>(pair, Token0Return(TOKEN));
+        chain.on_call::<Token1Call>(pair, Token1Return(weth()));
+    }
+
+    mine_transaction(&chain, 0, SEARCHER, vec![swap_log(PAIR, 1.0, 1_900.0, 0.0, 0.0)]);
+    mine_transaction(&chain, 1, VICTIM, vec![swap_log(PAIR, 10.0, 18_000.0, 0.0, 0.0)]);
+    mine_transaction(&chain, 2, SEARCHER, vec![swap_log(PAIR, 0.0, 0.0, 1_900.0, 1.1)]);
+    mine_transaction(
+        &chain,
+        3,
+        ARBITRAGEUR,
+        vec![
+            swap_log(OTHER_PAIR, 2.0, 4_000.0, 0.0, 0.0),
+            swap_log(PAIR, 0.0, 0.0, 4_000.0, 2.05),
+        ],
+    );
+    mine_transaction(&chain, 4, LIQUIDATOR, vec![liquidation_log()]);
+
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+    MevHistoryService::new(chain, cache, Arc::new(DexRegistry::mainnet()))
+}
+
+#[tokio::test]
+async fn block_report_finds_each_kind_of_mev() {
+    let history = history().await;
+    assert!(history.block_report(1).await.unwrap().is_none());
+
+    let report = history.block_report(0).await.unwrap().expect("block 0 is mined");
+    assert!((report.priority_fees_eth - 5.0 * 100_000.0 * 1e-9).abs() < 1e-12);
+
+    let [sandwich] = &report.sandwiches[..] else {
+        panic!("expected one sandwich, got {:?}", report.sandwiches);
+    };
+    assert_eq!((sandwich.pool, sandwich.searcher, sandwich.token), (PAIR, SEARCHER, weth()));
+    assert_eq!((sandwich.frontrun, sandwich.backrun), (hash(0), hash(2)));
+    assert_eq!(sandwich.victims, vec![hash(1)]);
+    assert!((sandwich.profit_eth.unwrap() - 0.1).abs() < 1e-9);
+
+    let [arbitrage] = &report.arbitrages[..] else {
+        panic!("expected one arbitrage, got {:?}", report.arbitrages);
+    };
+    assert_eq!(arbitrage.pools, vec![OTHER_PAIR, PAIR]);
+    assert!((arbitrage.profit_eth.unwrap() - 0.05).abs() < 1e-9);
+
+    let [liquidation] = &report.liquidations[..] else {
+        panic!("expected one liquidation, got {:?}", report.liquidations);
+    };
+    assert_eq!(liquidation.protocol, LendingProtocol::AaveV3);
+    assert_eq!((liquidation.liquidator, liquidation.borrower), (LIQUIDATOR, VICTIM));
+    assert_eq!(liquidation.collateral_seized.as_deref(), Some("2000"));
+
+    // The sandwich's two legs are paid for out of its profit
+    let searcher = report.searchers.iter().find(|s| s.searcher == SEARCHER).unwrap();
+    assert_eq!(searcher.sandwiches, 1);
+    assert!((searcher.net_profit_eth - (0.1 - 2.0 * GAS_COST_ETH)).abs() < 1e-9);
+    assert_eq!(report.searchers[0].searcher, SEARCHER);
+    assert!(!report.searchers.iter().any(|s| s.searcher == VICTIM));
+
+    // The seized collateral is not priced, so the liquidation is only counted
+    let liquidator = report.searchers.iter().find(|s| s.searcher == LIQUIDATOR).unwrap();
+    assert_eq!(liquidator.liquidations, 1);
+    assert_eq!((liquidator.gross_profit_eth, liquidator.gas_cost_eth, liquidator.net_profit_eth), (0.0, 0.0, 0.0));
+}
+
+#[tokio::test]
+async fn range_report_totals_searchers_and_builders() {
+    let history = history().await;
+
+    assert!(history.range_report(1, 10).await.unwrap().is_none());
+
+    let report = history.range_report(0, 10).await.unwrap().expect("block 0 is mined");
+    assert_eq!((report.from_block, report.to_block), (0, 0));
+    assert_eq!((report.sandwiches, report.arbitrages, report.liquidations), (1, 1, 1));
+    assert_eq!(report.searchers.len(), 3);
+
+    let [builder] = &report.builders[..] else {
+        panic!("expected one builder, got {:?}", report.builders);
+    };
+    assert_eq!(builder.blocks, 1);
+    assert!((builder.net_profit_eth - report.blocks[0].priority_fees_eth).abs() < 1e-12);
+}
+
+#[tokio::test]
+async fn builder_revenue_includes_direct_transfers() {
+    let chain = Arc::new(FakeChain::new());
+    chain.mine(U256::exp10(9), 15_000_000, 30_000_000, U256::exp10(9));
+    chain.set_fee_recipient(BUILDER);
+    chain.mine(U256::exp10(9), 15_000_000, 30_000_000, U256::exp10(9));
+
+    // Paying no tip, so the builder only earns what searchers sent it
+    // directly: its balance grows by 0.1 ETH after paying the proposer 0.4
+    let payment = Transaction {
+        hash: hash(10),
+        from: BUILDER,
+        to: Some(PROPOSER),
+        value: ether(0.4),
+        block_number: Some(U64::one()),
+        transaction_index: Some(U64::zero()),
+        ..Default::default()
+    };
+    let receipt = TransactionReceipt {
+        transaction_hash: hash(10),
+        gas_used: Some(U256::from(21_000)),
+        effective_gas_price: Some(U256::exp10(9)),
+        ..Default::default()
+    };
+    chain.add_transaction(payment, receipt);
+    chain.set_balance_at(BUILDER, 0, ether(10.0));
+    chain.set_balance_at(BUILDER, 1, ether(10.1));
+
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+    let history = MevHistoryService::new(chain, cache, Arc::new(DexRegistry::mainnet()));
+    let report = history.range_report(1, 1).await.unwrap().expect("block 1 is mined");
+
+    let block = &report.blocks[0];
+    assert_eq!(block.builder, BUILDER);
+    assert_eq!(block.priority_fees_eth, 0.0);
+    assert!((block.proposer_payment_eth - 0.4).abs() < 1e-9);
+    assert!((block.builder_revenue_eth - 0.5).abs() < 1e-9);
+
+    let [builder] = &report.builders[..] else {
+        panic!("expected one builder, got {:?}", report.builders);
+    };
+    assert!((builder.revenue_eth - 0.5).abs() < 1e-9);
+    assert!((builder.net_profit_eth - 0.1).abs() < 1e-9);
+}
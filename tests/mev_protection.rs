@@ -0,0 +1,41 @@
+use q_guard::services::mev_protection::{
+    amount_out, max_sandwich_profit, safe_slippage_bps, sandwich_profit, slippage_bps,
+};
+
+/// 1,000 ETH against 2,000,000 tokens, in whole units
+const RESERVE_IN: f64 = 1_000.0;
+const RESERVE_OUT: f64 = 2_000_000.0;
+const AMOUNT_IN: f64 = 10.0;
+
+fn min_out(bps: f64) -> f64 {
+    amount_out(AMOUNT_IN, RESERVE_IN, RESERVE_OUT) * (1.0 - bps / 1e4)
+}
+
+#[test]
+fn exact_minimum_leaves_nothing_to_extract() {
+    assert!(max_sandwich_profit(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, min_out(0.0)) < 1e-9);
+    assert!(slippage_bps(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, min_out(0.0)).abs() < 1e-9);
+    // Any frontrun pushes the swap below its minimum
+    assert!(sandwich_profit(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, min_out(0.0), 0.1).is_none());
+}
+
+#[test]
+fn wider_slippage_lets_a_sandwich_take_more() {
+    let at_1_percent = max_sandwich_profit(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, min_out(100.0));
+    let at_5_percent = max_sandwich_profit(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, min_out(500.0));
+
+    assert!(at_1_percent > 0.0);
+    assert!(at_5_percent > at_1_percent);
+    assert!((slippage_bps(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, min_out(500.0)) - 500.0).abs() < 1e-6);
+}
+
+#[test]
+fn safe_slippage_leaves_only_the_searchers_gas() {
+    let cost = 0.01;
+    let safe = safe_slippage_bps(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, cost);
+
+    // The frontrun's own 0.3% fee protects some tolerance
+    assert!(safe > 0.0 && safe < 500.0, "safe {} bps", safe);
+    assert!(max_sandwich_profit(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, min_out(safe)) <= cost);
+    assert!(max_sandwich_profit(RESERVE_IN, RESERVE_OUT, AMOUNT_IN, min_out(safe + 5.0)) > cost);
+}
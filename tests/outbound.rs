@@ -0,0 +1,39 @@
+use q_guard::services::outbound::{is_public, public_client};
+use reqwest::Url;
+use std::net::IpAddr;
+use std::time::Duration;
+
+fn ip(ip: &str) -> IpAddr {
+    ip.parse().unwrap()
+}
+
+#[test]
+fn only_public_addresses_are_allowed() {
+    for public in ["1.1.1.1", "93.184.216.34", "2606:4700:4700::1111"] {
+        assert!(is_public(ip(public)), "{}", public);
+    }
+    for private in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "255.255.255.255",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(!is_public(ip(private)), "{}", private);
+    }
+}
+
+#[tokio::test]
+async fn clients_are_not_built_for_private_hosts() {
+    for url in ["http://localhost:3000/", "http://127.0.0.1/", "http://[fe80::1]/", "file:///etc/passwd"] {
+        let url = Url::parse(url).unwrap();
+        assert!(public_client(&url, Duration::from_secs(1)).await.is_err(), "{}", url);
+    }
+}
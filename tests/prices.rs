@@ -0,0 +1,122 @@
+use ethers::types::{Address, I256, U256};
+use q_guard::contracts::uniswap_v3::{LiquidityCall, LiquidityReturn, ObserveCall, ObserveReturn};
+use q_guard::contracts::{
+    DecimalsCall, DecimalsReturn, LatestRoundDataCall, LatestRoundDataReturn, SymbolCall, SymbolReturn, USDC, WETH,
+};
+use q_guard::models::PriceSource;
+use q_guard::services::{CacheService, DexRegistry, FakeChain, PriceFeed, PriceService};
+use std::sync::Arc;
+
+const ETH_USD_FEED: Address = Address::repeat_byte(0xfe);
+const LINK_USD_FEED: Address = Address::repeat_byte(0xf1);
+/// Priced from a Chainlink feed
+const LINK: Address = Address::repeat_byte(0x22);
+/// Sorts below WETH, so it is token0 of its pool
+const TOKEN: Address = Address::repeat_byte(0x11);
+/// Has no feed and no pool
+const UNLISTED: Address = Address::repeat_byte(0x33);
+/// Has no `decimals`, so it cannot be priced at all
+const NOT_A_TOKEN: Address = Address::repeat_byte(0x44);
+
+/// Tick at which one TOKEN is worth 0.001 WETH (both 18 decimals)
+const TOKEN_TICK: i64 = -69_082;
+
+fn weth() -> Address {
+    WETH.parse().unwrap()
+}
+
+/// TOKEN/WETH pool of the registered Uniswap V3 deployment at `fee`
+fn pool(fee: u32) -> Address {
+    let dexes = DexRegistry::mainnet();
+    dexes.get("uniswap_v3").unwrap().pool_address(TOKEN, weth(), Some(fee)).unwrap()
+}
+
+fn script_feed(chain: &FakeChain, feed: Address, price: i64) {
+    chain.on_call::<DecimalsCall>(feed, DecimalsReturn(8));
+    chain.on_call::<LatestRoundDataCall>(
+        feed,
+        LatestRoundDataReturn {
+            round_id: 1,
+            answer: I256::from(price) * I256::exp10(8),
+            started_at: U256::zero(),
+            updated_at: U256::from(chrono::Utc::now().timestamp()),
+            answered_in_round: 1,
+        },
+    );
+}
+
+fn script_token(chain: &FakeChain, token: Address, symbol: &str, decimals: u8) {
+    chain.on_call::<DecimalsCall>(token, DecimalsReturn(decimals));
+    chain.on_call::<SymbolCall>(token, SymbolReturn(symbol.to_string()));
+}
+
+/// ETH at $3000, LINK at $15 from its feed, and TOKEN in two WETH pools; no
+/// other pool is deployed
+async fn service() -> PriceService {
+    let chain = Arc::new(FakeChain::new());
+    script_feed(&chain, ETH_USD_FEED, 3000);
+    script_feed(&chain, LINK_USD_FEED, 15);
+    script_token(&chain, weth(), "WETH", 18);
+    script_token(&chain, USDC.parse().unwrap(), "USDC", 6);
+    script_token(&chain, LINK, "LINK", 18);
+    script_token(&chain, TOKEN, "TKN", 18);
+    script_token(&chain, UNLISTED, "NOPE", 18);
+
+    for (fee, liquidity) in [(3_000, 1_000_000u128), (10_000, 1_000)] {
+        chain.on_call::<LiquidityCall>(pool(fee), LiquidityReturn(liquidity));
+    }
+    chain.on_call::<ObserveCall>(pool(3_000), ObserveReturn(vec![0, TOKEN_TICK * 1800], vec![U256::zero(); 2]));
+
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+    let feeds = vec![PriceFeed {
+        token: LINK,
+        feed: LINK_USD_FEED,
+    }];
+    PriceService::new(chain, cache, Arc::new(DexRegistry::mainnet()), ETH_USD_FEED, feeds)
+}
+
+#[tokio::test]
+async fn weth_and_feed_tokens_are_priced_by_chainlink() {
+    let prices = service().await;
+
+    let weth_price = prices.token_price(weth()).await.unwrap().unwrap();
+    assert_eq!(weth_price.price_usd, 3000.0);
+    assert_eq!(weth_price.source, PriceSource::Chainlink);
+    assert_eq!(weth_price.oracle, ETH_USD_FEED);
+
+    let link = prices.token_price(LINK).await.unwrap().unwrap();
+    assert_eq!(link.price_usd, 15.0);
+    assert_eq!(link.symbol.as_deref(), Some("LINK"));
+    assert_eq!(link.oracle, LINK_USD_FEED);
+}
+
+#[tokio::test]
+async fn other_tokens_use_the_twap_of_their_most_liquid_pool() {
+    let prices = service().await;
+
+    let price = prices.token_price(TOKEN).await.unwrap().unwrap();
+
+    assert_eq!(price.source, PriceSource::UniswapV3Twap);
+    assert_eq!(price.oracle, pool(3_000));
+    assert_eq!(price.quote_token, Some(weth()));
+    assert!((price.price_usd - 3.0).abs() < 0.01, "{}", price.price_usd);
+}
+
+#[tokio::test]
+async fn tokens_without_feed_or_pool_are_unavailable() {
+    let prices = service().await;
+
+    let result = prices.token_prices(&[LINK, UNLISTED, NOT_A_TOKEN]).await.unwrap();
+
+    assert_eq!(result.prices.len(), 1);
+    assert_eq!(result.unavailable, vec![UNLISTED, NOT_A_TOKEN]);
+}
+
+#[test]
+fn price_feeds_parse_from_config() {
+    let feed: PriceFeed = format!("{:?}:{:?}", LINK, LINK_USD_FEED).parse().unwrap();
+    assert_eq!(feed.token, LINK);
+    assert_eq!(feed.feed, LINK_USD_FEED);
+
+    assert!("0x22".parse::<PriceFeed>().is_err());
+}
@@ -0,0 +1,110 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, routing::post, Json, Router};
+use ethers::providers::Middleware;
+use q_guard::services::{PoolConfig, ProviderPool};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A JSON-RPC endpoint answering every request with block 0x10
+#[derive(Clone)]
+struct FakeRpc {
+    delay: Duration,
+    healthy: bool,
+    hits: Arc<AtomicUsize>,
+}
+
+impl FakeRpc {
+    fn hits(&self) -> usize {
+        self.hits.load(Ordering::SeqCst)
+    }
+}
+
+async fn answer(State(rpc): State<FakeRpc>, Json(request): Json<Value>) -> impl IntoResponse {
+    rpc.hits.fetch_add(1, Ordering::SeqCst);
+    tokio::time::sleep(rpc.delay).await;
+    if !rpc.healthy {
+        return (StatusCode::BAD_GATEWAY, "upstream down").into_response();
+    }
+    Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": "0x10" })).into_response()
+}
+
+/// Serves a fake endpoint on a local port and returns its URL
+async fn serve(delay: Duration, healthy: bool) -> (String, FakeRpc) {
+    let rpc = FakeRpc {
+        delay,
+        healthy,
+        hits: Arc::new(AtomicUsize::new(0)),
+    };
+    let router = Router::new().route("/", post(answer)).with_state(rpc.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    (url, rpc)
+}
+
+fn config(hedge_delay: Option<Duration>, failure_threshold: u32) -> PoolConfig {
+    PoolConfig {
+        hedge_delay,
+        failure_threshold,
+        cooldown: Duration::from_secs(60),
+        quorum: 1,
+    }
+}
+
+#[tokio::test]
+async fn failing_providers_are_failed_over_and_ranked_last() {
+    let (down_url, down) = serve(Duration::ZERO, false).await;
+    let (up_url, up) = serve(Duration::ZERO, true).await;
+    let provider = ProviderPool::connect("test", &[down_url, up_url], config(None, 3)).unwrap();
+
+    for _ in 0..3 {
+        assert_eq!(provider.get_block_number().await.unwrap().as_u64(), 16);
+    }
+
+    // Tried once in the first request, then ranked behind the working provider
+    assert_eq!(down.hits(), 1);
+    assert_eq!(up.hits(), 3);
+    let metrics = provider.as_ref().metrics();
+    assert_eq!(metrics[0].errors, 1);
+    assert!(metrics[0].available);
+    assert_eq!(metrics[1].errors, 0);
+    assert!(metrics[1].latency_ms.is_some());
+}
+
+#[tokio::test]
+async fn repeated_failures_open_the_circuit() {
+    let (down_url, _) = serve(Duration::ZERO, false).await;
+    let provider = ProviderPool::connect("test", &[down_url], config(None, 2)).unwrap();
+
+    for _ in 0..2 {
+        assert!(provider.get_block_number().await.is_err());
+    }
+
+    let metrics = provider.as_ref().metrics();
+    assert!(!metrics[0].available);
+    assert_eq!(metrics[0].consecutive_failures, 2);
+}
+
+#[tokio::test]
+async fn slow_providers_losing_a_hedge_are_penalised() {
+    let (slow_url, slow) = serve(Duration::from_millis(500), true).await;
+    let (fast_url, fast) = serve(Duration::ZERO, true).await;
+    let provider =
+        ProviderPool::connect("test", &[slow_url, fast_url], config(Some(Duration::from_millis(50)), 3)).unwrap();
+
+    // Neither is measured, so the slow one goes first and the request is hedged
+    assert_eq!(provider.get_block_number().await.unwrap().as_u64(), 16);
+    assert_eq!((slow.hits(), fast.hits()), (1, 1));
+
+    let metrics = provider.as_ref().metrics();
+    let slow_latency = metrics[0].latency_ms.expect("cancelled request was not recorded");
+    assert!(slow_latency >= 50.0, "{}", slow_latency);
+    assert!(slow_latency > metrics[1].latency_ms.unwrap());
+
+    // From then on the fast provider is asked first
+    for _ in 0..3 {
+        provider.get_block_number().await.unwrap();
+    }
+    assert_eq!((slow.hits(), fast.hits()), (1, 4));
+}
@@ -0,0 +1,148 @@
+use chrono::{Duration, Utc};
+use ethers::types::{Address, H256};
+use q_guard::middleware::RateLimiter;
+use q_guard::models::AgentBehavior;
+use q_guard::services::reputation::local_score;
+use q_guard::services::*;
+use std::sync::Arc;
+
+const ALICE: Address = Address::repeat_byte(0xa1);
+const BOB: Address = Address::repeat_byte(0xb0);
+
+async fn cache() -> Arc<CacheService> {
+    Arc::new(CacheService::new("memory://").await.unwrap())
+}
+
+#[test]
+fn local_score_rewards_payments_and_tenure() {
+    let now = Utc::now();
+    assert_eq!(local_score(&AgentBehavior::default(), now), 250);
+
+    let paying = AgentBehavior {
+        verified_payments: 10,
+        first_seen: Some(now - Duration::days(30)),
+        ..Default::default()
+    };
+    assert_eq!(local_score(&paying, now), 250 + 50 + 60);
+
+    // Both bonuses are capped
+    let veteran = AgentBehavior {
+        verified_payments: 1000,
+        first_seen: Some(now - Duration::days(1000)),
+        ..Default::default()
+    };
+    assert_eq!(local_score(&veteran, now), 250 + 750 + 250);
+}
+
+#[test]
+fn local_score_penalizes_misbehavior() {
+    let now = Utc::now();
+    let careless = AgentBehavior {
+        rate_limit_violations: 1,
+        ..Default::default()
+    };
+    assert_eq!(local_score(&careless, now), 250 - 10);
+
+    let failing = AgentBehavior {
+        payment_failures: 2,
+        rate_limit_violations: 1,
+        ..Default::default()
+    };
+    assert_eq!(local_score(&failing, now), 250 - 50 - 10);
+
+    let abusive = AgentBehavior {
+        rate_limit_violations: 30,
+        ..Default::default()
+    };
+    assert_eq!(local_score(&abusive, now), 0);
+}
+
+#[tokio::test]
+async fn ledger_records_events() {
+    let ledger = AgentLedger::new(cache().await);
+    assert_eq!(ledger.behavior(ALICE).await, AgentBehavior::default());
+
+    ledger.record(ALICE, AgentEvent::PaymentVerified { amount_usd: 0.01 }).await;
+    ledger.record(ALICE, AgentEvent::PaymentVerified { amount_usd: 0.05 }).await;
+    ledger.record(ALICE, AgentEvent::PaymentFailed).await;
+    ledger.record(ALICE, AgentEvent::RateLimited).await;
+
+    let behavior = ledger.behavior(ALICE).await;
+    assert_eq!(behavior.verified_payments, 2);
+    assert!((behavior.paid_usd - 0.06).abs() < 1e-9);
+    assert_eq!(behavior.payment_failures, 1);
+    assert_eq!(behavior.rate_limit_violations, 1);
+    assert!(behavior.first_seen.is_some());
+    assert!(behavior.last_seen >= behavior.first_seen);
+    assert_eq!(ledger.behavior(BOB).await, AgentBehavior::default());
+}
+
+#[tokio::test]
+async fn payments_are_redeemed_once() {
+    let ledger = AgentLedger::new(cache().await);
+    let tx = H256::repeat_byte(0x42);
+
+    assert!(ledger.redeem_payment(tx).await);
+    assert!(!ledger.redeem_payment(tx).await);
+    assert!(ledger.redeem_payment(H256::repeat_byte(0x43)).await);
+
+    // A released payment can be redeemed again
+    ledger.release_payment(tx).await;
+    assert!(ledger.redeem_payment(tx).await);
+    assert!(!ledger.redeem_payment(tx).await);
+}
+
+#[tokio::test]
+async fn local_score_is_used_without_registries() {
+    let cache = cache().await;
+    let ledger = Arc::new(AgentLedger::new(cache.clone()));
+    let reputation = ReputationService::new(cache, ledger.clone(), None, ReputationConfig::default()).await;
+
+    assert_eq!(reputation.get_reputation(ALICE).await.unwrap(), 250);
+    for _ in 0..4 {
+        ledger.record(ALICE, AgentEvent::PaymentVerified { amount_usd: 0.01 }).await;
+    }
+    assert_eq!(reputation.get_reputation(ALICE).await.unwrap(), 270);
+
+    for _ in 0..16 {
+        ledger.record(BOB, AgentEvent::RateLimited).await;
+    }
+    assert_eq!(reputation.get_reputation(BOB).await.unwrap(), 90);
+    assert!(!reputation.verify_access(Some(BOB), 100).await.unwrap());
+}
+
+#[tokio::test]
+async fn local_score_is_blended_with_registries() {
+    let fake = FakeErc8004::new(Arc::new(FakeChain::new()), "base-sepolia");
+    let cache = cache().await;
+    let registry = Arc::new(Erc8004Registry::new(fake.chain(), cache.clone(), fake.deployment()));
+    let ledger = Arc::new(AgentLedger::new(cache.clone()));
+    let config = ReputationConfig {
+        require_registration: false,
+        onchain_weight: 0.75,
+    };
+    let reputation = ReputationService::new(cache, ledger.clone(), Some(registry.clone()), config).await;
+
+    // 1000 on-chain, 250 locally
+    let agent_id = fake.register(ALICE, "ipfs://alice");
+    fake.give_feedback(agent_id, 20, 100);
+    registry.sync().await.unwrap();
+    assert_eq!(reputation.get_reputation(ALICE).await.unwrap(), 750 + 63);
+
+    // Local history moves the score while the on-chain one stays cached
+    for _ in 0..10 {
+        ledger.record(ALICE, AgentEvent::PaymentVerified { amount_usd: 0.01 }).await;
+    }
+    assert_eq!(reputation.get_reputation(ALICE).await.unwrap(), 750 + 75);
+}
+
+#[test]
+fn rate_limiter_buckets_are_per_key() {
+    let limiter = RateLimiter::new(1, 3);
+
+    for _ in 0..3 {
+        assert!(limiter.try_acquire(ALICE));
+    }
+    assert!(!limiter.try_acquire(ALICE));
+    assert!(limiter.try_acquire(BOB));
+}
@@ -0,0 +1,162 @@
+use ethers::abi::AbiEncode;
+use ethers::types::{Address, Bytes, Transaction, H256, U256};
+use q_guard::contracts::{SwapExactETHForTokensCall, WETH};
+use q_guard::models::{ChainSpec, DexDeployment, DexKind};
+use q_guard::services::*;
+use std::sync::Arc;
+
+const ROUTER: Address = Address::repeat_byte(0x70);
+const FACTORY: Address = Address::repeat_byte(0xfa);
+const VICTIM: Address = Address::repeat_byte(0x11);
+
+/// A V2 router and pair in one contract, which is also the token it trades
+/// against ETH. Reserves are storage slots 0 (ETH) and 1 (token); balances are
+/// keyed by address. Runtime code assembled from:
+///
+/// - `swapExactETHForTokens(min, path, to, deadline)`: credits `to` with
+///   `getAmountOut(msg.value, reserveEth, reserveToken)`, reverting below `min`
+/// - `swapExactTokensForETH(amountIn, min, path, to, deadline)`: debits the
+///   caller and sends `to` the ETH out, reverting below `min`
+/// - `approve(spender, amount)`: returns true
+///
+/// Both swaps return `[amountIn, amountOut]` and charge the 0.3% V2 fee.
+const PAIR_ROUTER_CODE: &str = "0x60003560e01c80637ff36ab51461003757806318cbafe5146100bd578063095ea7b31461002c575b600080fd5b600160005260206000f35b34608052600435610120526044356101005260005460a05260015460c05260a0516103e8026080516103e502016080516103e50260c051020460e0526101205160e051106100275760a0516080510160005560e05160c05103600155610100515460e0510161010051556020600052600260205260805160405260e05160605260806000f35b600435608052602435610120526064356101005260805133541061002757608051335403335560015460a05260005460c05260a0516103e8026080516103e502016080516103e50260c051020460e0526101205160e051106100275760a0516080510160015560e05160c05103600055600060006000600060e051610100515af115610027576020600052600260205260805160405260e05160605260806000f3";
+
+const RESERVE_ETH: u64 = 100;
+const RESERVE_TOKEN: u64 = 200_000;
+const VICTIM_ETH: u64 = 10;
+/// Search precision of the simulator
+const PRECISION_WEI: u64 = 1_000_000_000_000_000;
+
+fn eth(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(18)
+}
+
+fn gwei(amount: u64) -> U256 {
+    U256::from(amount) * U256::exp10(9)
+}
+
+/// Uniswap V2 `getAmountOut`
+fn amount_out(amount_in: U256, reserve_in: U256, reserve_out: U256) -> U256 {
+    let with_fee = amount_in * 997;
+    with_fee * reserve_out / (reserve_in * 1000 + with_fee)
+}
+
+/// What the victim receives after a frontrun of `frontrun` wei, and the
+/// tokens the frontrun bought
+fn victim_out(frontrun: U256) -> (U256, U256) {
+    let bought = amount_out(frontrun, eth(RESERVE_ETH), eth(RESERVE_TOKEN));
+    let received = amount_out(eth(VICTIM_ETH), eth(RESERVE_ETH) + frontrun, eth(RESERVE_TOKEN) - bought);
+    (received, bought)
+}
+
+fn dexes() -> DexRegistry {
+    DexRegistry::new(vec![DexDeployment {
+        name: "test_v2".to_string(),
+        chain: "ethereum".to_string(),
+        kind: DexKind::UniswapV2,
+        routers: vec![ROUTER],
+        v2_router: Some(ROUTER),
+        factory: Some(FACTORY),
+        init_code_hash: Some(H256::repeat_byte(0xc0)),
+        fee_tiers: vec![3_000],
+    }])
+}
+
+/// The WETH / token pair derived from the factory
+fn pair() -> Address {
+    let dexes = dexes();
+    dexes.get("test_v2").unwrap().pool_address(WETH.parse().unwrap(), ROUTER, None).unwrap()
+}
+
+fn simulator() -> SandwichSimulator {
+    let chain = Arc::new(FakeChain::new());
+    chain.mine(gwei(10), 15_000_000, 30_000_000, gwei(1));
+    chain.set_account(ROUTER, eth(1000), PAIR_ROUTER_CODE.parse::<Bytes>().unwrap());
+    chain.set_storage(ROUTER, H256::zero(), H256::from_uint(&eth(RESERVE_ETH)));
+    chain.set_storage(ROUTER, H256::from_low_u64_be(1), H256::from_uint(&eth(RESERVE_TOKEN)));
+    chain.set_account(VICTIM, eth(100), Bytes::new());
+
+    SandwichSimulator::new(chain, ChainSpec::ethereum(), Arc::new(dexes()))
+}
+
+/// Buys the token with 10 ETH, accepting 1% less than the quoted output
+fn victim() -> Transaction {
+    let quoted = amount_out(eth(VICTIM_ETH), eth(RESERVE_ETH), eth(RESERVE_TOKEN));
+    let call = SwapExactETHForTokensCall {
+        amount_out_min: quoted * 99 / 100,
+        path: vec![WETH.parse().unwrap(), ROUTER],
+        to: VICTIM,
+        deadline: U256::MAX,
+    };
+    Transaction {
+        hash: H256::repeat_byte(0x01),
+        from: VICTIM,
+        to: Some(ROUTER),
+        value: eth(VICTIM_ETH),
+        gas: 300_000.into(),
+        max_fee_per_gas: Some(gwei(100)),
+        max_priority_fee_per_gas: Some(gwei(2)),
+        input: call.encode().into(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn frontrun_is_sized_to_the_victims_slippage_bound() {
+    let victim = victim();
+    let swap = decode_swaps(&victim, &dexes()).remove(0);
+    let min_out = amount_out(eth(VICTIM_ETH), eth(RESERVE_ETH), eth(RESERVE_TOKEN)) * 99 / 100;
+
+    let sandwich = simulator().simulate(&victim, &swap).await.unwrap().expect("no sandwich found");
+    assert_eq!((sandwich.block, sandwich.pair, sandwich.router, sandwich.token), (0, pair(), ROUTER, ROUTER));
+
+    // The victim's swap still goes through, but would not with a frontrun
+    // one search step larger
+    let (received, bought) = victim_out(sandwich.frontrun_wei);
+    assert!(received >= min_out);
+    assert!(victim_out(sandwich.frontrun_wei + 2 * PRECISION_WEI).0 < min_out);
+    assert_eq!(sandwich.token_amount, bought);
+
+    // The backrun sells into the pool the victim left behind
+    let reserve_eth = eth(RESERVE_ETH) + sandwich.frontrun_wei + eth(VICTIM_ETH);
+    let reserve_token = eth(RESERVE_TOKEN) - bought - received;
+    assert_eq!(sandwich.backrun_wei, amount_out(bought, reserve_token, reserve_eth));
+    assert_eq!(sandwich.profit_wei(), sandwich.backrun_wei - sandwich.frontrun_wei);
+    assert!(sandwich.profit_wei() > eth(1) / 10, "{}", sandwich.profit_wei());
+}
+
+#[tokio::test]
+async fn gas_is_costed_at_the_bid_over_the_victim() {
+    let victim = victim();
+    let swap = decode_swaps(&victim, &dexes()).remove(0);
+
+    let sandwich = simulator().simulate(&victim, &swap).await.unwrap().unwrap();
+
+    // Base fee 10 gwei, the victim's 2 gwei tip plus 1 gwei for the frontrun
+    assert_eq!(sandwich.frontrun_gas_price, gwei(13));
+    assert_eq!(sandwich.backrun_gas_price, gwei(10));
+    assert!(sandwich.frontrun_gas > 21_000 && sandwich.backrun_gas > 21_000);
+    assert_eq!(
+        sandwich.gas_cost_wei(),
+        gwei(13) * sandwich.frontrun_gas + gwei(10) * sandwich.backrun_gas
+    );
+    assert!(sandwich.gas_cost_wei() < sandwich.profit_wei());
+}
+
+#[tokio::test]
+async fn only_swaps_on_registered_v2_dexes_are_simulated() {
+    let unknown = Transaction {
+        to: Some(Address::repeat_byte(0x99)),
+        ..victim()
+    };
+    assert!(decode_swaps(&unknown, &dexes()).is_empty());
+
+    // Attributed to a DEX the simulator's registry does not have
+    let victim = victim();
+    let swap = SwapIntent {
+        dex: "other_v2".to_string(),
+        ..decode_swaps(&victim, &dexes()).remove(0)
+    };
+    assert!(simulator().simulate(&victim, &swap).await.unwrap().is_none());
+}
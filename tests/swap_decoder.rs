@@ -0,0 +1,203 @@
+use ethers::abi::AbiEncode;
+use ethers::types::{Address, Bytes, Transaction, U256};
+use q_guard::contracts::{SwapExactETHForTokensCall, SUSHISWAP_ROUTER};
+use q_guard::services::{decode_swaps, DexProtocol, DexRegistry, SwapHop, SwapIntent, SwapKind};
+use serde_json::Value;
+
+/// Router calldata in mainnet format, keyed by name. Each entry has `from`,
+/// `to`, `value` and `input`, and `tx_hash` when it was taken from mainnet
+/// with `scripts/fetch_swap_fixtures.sh`.
+const FIXTURES: &str = include_str!("fixtures/swap_calldata.json");
+
+const SENDER: &str = "0x3d1e6a3e8ab4a1e8b1e7a2d1d0b7c2b9e0f4a5c6";
+const SWAP_ROUTER_02: &str = "0x68b3465833fb72A70ecDF485E0e4C7bD8665Fc45";
+const UNIVERSAL_ROUTER: &str = "0x3fC91A3afd70395Cd496C647d5a6CC9D4B2b7FAD";
+const WETH: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+const USDT: &str = "0xdAC17F958D2ee523a2206206994597C13D831ec7";
+const DAI: &str = "0x6B175474E89094C44Da98b954EedeAC495271d0F";
+const PEPE: &str = "0x6982508145454Ce325dDbE47a25d4ec3d2311933";
+const DEADLINE: u64 = 1_700_000_000;
+
+fn decode(name: &str) -> Vec<SwapIntent> {
+    let fixtures: Value = serde_json::from_str(FIXTURES).unwrap();
+    let fixture = &fixtures[name];
+    let field = |key: &str| fixture[key].as_str().unwrap_or_else(|| panic!("{} has no {}", name, key));
+
+    let tx = Transaction {
+        from: field("from").parse().unwrap(),
+        to: Some(field("to").parse().unwrap()),
+        value: U256::from_str_radix(field("value"), 16).unwrap(),
+        input: field("input").parse::<Bytes>().unwrap(),
+        ..Default::default()
+    };
+    decode_swaps(&tx, &DexRegistry::mainnet())
+}
+
+fn address(address: &str) -> Address {
+    address.parse().unwrap()
+}
+
+fn hop(token_in: &str, token_out: &str, fee: Option<u32>) -> SwapHop {
+    SwapHop {
+        token_in: address(token_in),
+        token_out: address(token_out),
+        fee,
+    }
+}
+
+fn units(amount: u64, decimals: usize) -> U256 {
+    U256::from(amount) * U256::exp10(decimals)
+}
+
+#[test]
+fn decodes_v2_router_swaps() {
+    let swaps = decode("v2_swap_exact_eth_for_tokens");
+    assert_eq!(
+        swaps,
+        vec![SwapIntent {
+            dex: "uniswap_v2".to_string(),
+            protocol: DexProtocol::UniswapV2,
+            kind: SwapKind::ExactIn,
+            hops: vec![hop(WETH, PEPE, None)],
+            amount_in: units(5, 17),
+            amount_out: units(41_000_000_000, 18),
+            recipient: address(SENDER),
+            deadline: Some(DEADLINE.into()),
+        }]
+    );
+
+    let swaps = decode("v2_swap_exact_tokens_for_tokens_multi_hop");
+    assert_eq!(swaps[0].hops, vec![hop(USDC, WETH, None), hop(WETH, DAI, None)]);
+    assert_eq!((swaps[0].amount_in, swaps[0].amount_out), (units(2500, 6), units(2480, 18)));
+
+    let swaps = decode("v2_swap_tokens_for_exact_eth");
+    assert_eq!(swaps[0].kind, SwapKind::ExactOut);
+    assert_eq!((swaps[0].amount_in, swaps[0].amount_out), (units(3100, 6), units(1, 18)));
+    assert_eq!((swaps[0].token_in(), swaps[0].token_out()), (address(USDT), address(WETH)));
+}
+
+#[test]
+fn decodes_v3_swap_router_swaps() {
+    let swaps = decode("v3_exact_input_single");
+    assert_eq!(
+        swaps,
+        vec![SwapIntent {
+            dex: "uniswap_v3".to_string(),
+            protocol: DexProtocol::UniswapV3,
+            kind: SwapKind::ExactIn,
+            hops: vec![hop(WETH, USDC, Some(500))],
+            amount_in: units(2, 18),
+            amount_out: units(5900, 6),
+            recipient: address(SENDER),
+            deadline: Some(DEADLINE.into()),
+        }]
+    );
+
+    // Exact-output paths are encoded from the output token back
+    let swaps = decode("v3_exact_output_multi_hop");
+    assert_eq!(swaps[0].kind, SwapKind::ExactOut);
+    assert_eq!(swaps[0].hops, vec![hop(WETH, USDC, Some(500)), hop(USDC, DAI, Some(100))]);
+    assert_eq!(swaps[0].amount_out, units(1000, 18));
+    assert_eq!(swaps[0].amount_in, units(1, 18) / 3);
+}
+
+#[test]
+fn decodes_swap_router_02_multicalls() {
+    // The unwrapWETH9 call that follows the swap is not a swap
+    let swaps = decode("router02_multicall_exact_input_single_unwrap");
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].hops, vec![hop(USDC, WETH, Some(500))]);
+    assert_eq!(swaps[0].recipient, address(SWAP_ROUTER_02));
+    assert_eq!(swaps[0].deadline, Some(DEADLINE.into()));
+
+    let swaps = decode("router02_multicall_v2_swap");
+    assert_eq!(swaps[0].protocol, DexProtocol::UniswapV2);
+    assert_eq!(swaps[0].hops, vec![hop(WETH, USDT, None)]);
+    assert_eq!(swaps[0].recipient, address(SENDER));
+    assert_eq!(swaps[0].deadline, None);
+}
+
+#[test]
+fn decodes_universal_router_commands() {
+    // WRAP_ETH is skipped
+    let swaps = decode("universal_router_wrap_and_v3_swap");
+    assert_eq!(
+        swaps,
+        vec![SwapIntent {
+            dex: "uniswap_v3".to_string(),
+            protocol: DexProtocol::UniswapV3,
+            kind: SwapKind::ExactIn,
+            hops: vec![hop(WETH, USDC, Some(3000))],
+            amount_in: units(1, 18),
+            amount_out: units(2950, 6),
+            recipient: address(SENDER),
+            deadline: Some(DEADLINE.into()),
+        }]
+    );
+
+    let swaps = decode("universal_router_split_v3_then_v2");
+    assert_eq!(swaps.len(), 2);
+    assert_eq!(swaps[0].recipient, address(UNIVERSAL_ROUTER));
+    assert_eq!(swaps[1].protocol, DexProtocol::UniswapV2);
+    assert_eq!(swaps[1].dex, "uniswap_v2");
+    assert_eq!(swaps[1].hops, vec![hop(WETH, PEPE, None)]);
+    assert_eq!(swaps[1].amount_in, U256::zero(), "spends the router's balance");
+    assert_eq!(swaps[1].deadline, None);
+
+    let swaps = decode("universal_router_v3_exact_out");
+    assert_eq!(swaps[0].kind, SwapKind::ExactOut);
+    assert_eq!(swaps[0].hops, vec![hop(USDC, WETH, Some(500))]);
+    assert_eq!((swaps[0].amount_in, swaps[0].amount_out), (units(3100, 6), units(1, 18)));
+}
+
+#[test]
+fn ignores_calls_that_are_not_swaps() {
+    assert!(decode("erc20_transfer").is_empty());
+}
+
+#[test]
+fn only_registered_routers_are_decoded() {
+    let call = SwapExactETHForTokensCall {
+        amount_out_min: U256::one(),
+        path: vec![address(WETH), address(PEPE)],
+        to: address(SENDER),
+        deadline: DEADLINE.into(),
+    };
+    let tx = Transaction {
+        from: address(SENDER),
+        to: Some(Address::repeat_byte(0x42)),
+        value: units(1, 18),
+        input: call.encode().into(),
+        ..Default::default()
+    };
+    assert!(decode_swaps(&tx, &DexRegistry::mainnet()).is_empty());
+
+    // The same calldata sent to SushiSwap's router trades on SushiSwap pools
+    let tx = Transaction { to: Some(address(SUSHISWAP_ROUTER)), ..tx };
+    let swaps = decode_swaps(&tx, &DexRegistry::mainnet());
+    assert_eq!(swaps.len(), 1);
+    assert_eq!(swaps[0].dex, "sushiswap");
+}
+
+#[test]
+fn v2_routers_take_the_recipient_as_given() {
+    // address(2) means the router only on SwapRouter02 and the Universal Router
+    for to in [Address::zero(), Address::from_low_u64_be(2)] {
+        let call = SwapExactETHForTokensCall {
+            amount_out_min: U256::one(),
+            path: vec![address(WETH), address(PEPE)],
+            to,
+            deadline: DEADLINE.into(),
+        };
+        let tx = Transaction {
+            from: address(SENDER),
+            to: Some(address("0x7a250d5630B4cF539739dF2C5dAcb4c659F2488D")),
+            value: units(1, 18),
+            input: call.encode().into(),
+            ..Default::default()
+        };
+        let swaps = decode_swaps(&tx, &DexRegistry::mainnet());
+        assert_eq!(swaps[0].recipient, to);
+    }
+}
@@ -0,0 +1,169 @@
+use ethers::types::{Address, Transaction, TransactionReceipt, H256, U256};
+use q_guard::models::{ChainSpec, GasModel, TxLifecycle};
+use q_guard::services::gas_predictor::BlockSample;
+use q_guard::services::*;
+use std::sync::Arc;
+use std::time::Duration;
+
+const GWEI: u64 = 1_000_000_000;
+
+struct Tracking {
+    chain: Arc<FakeChain>,
+    mempool: Arc<MempoolService>,
+    ethereum: Arc<EthereumService>,
+    tracker: TxLifecycleTracker,
+}
+
+async fn tracking(config: MempoolConfig) -> Tracking {
+    let chain = Arc::new(FakeChain::new());
+    let cache = Arc::new(CacheService::new("memory://").await.unwrap());
+    let ethereum =
+        Arc::new(EthereumService::new(ChainSpec::ethereum(), chain.clone(), cache, GasModel::Ewma).await);
+    let mempool = Arc::new(MempoolService::new("ws://unused", config));
+    let tracker = TxLifecycleTracker::new(mempool.clone(), ethereum.clone());
+
+    Tracking {
+        chain,
+        mempool,
+        ethereum,
+        tracker,
+    }
+}
+
+/// An EIP-1559 transaction tipping `tip_tenths_gwei` tenths of a gwei over the base fee
+fn tx(hash: u64, from: u8, nonce: u64, tip_tenths_gwei: u64) -> Transaction {
+    Transaction {
+        hash: H256::from_low_u64_be(hash),
+        from: Address::repeat_byte(from),
+        nonce: nonce.into(),
+        gas: 21_000.into(),
+        max_fee_per_gas: Some(U256::from(200 * GWEI)),
+        max_priority_fee_per_gas: Some(U256::from(tip_tenths_gwei * GWEI / 10)),
+        ..Default::default()
+    }
+}
+
+impl Tracking {
+    /// Mines a block at a 20 gwei base fee containing `txs`
+    fn mine(&self, txs: &[Transaction]) -> u64 {
+        let number = self.chain.mine(U256::from(20 * GWEI), 15_000_000, 30_000_000, U256::from(GWEI));
+        for tx in txs {
+            let mined = Transaction {
+                block_number: Some(number.into()),
+                ..tx.clone()
+            };
+            self.chain.add_transaction(mined, TransactionReceipt::default());
+        }
+        number
+    }
+}
+
+#[tokio::test]
+async fn mined_transactions_are_reported_with_their_block() {
+    let t = tracking(MempoolConfig::default()).await;
+    let seen = tx(1, 0x01, 0, 30);
+    let unseen = tx(2, 0x02, 0, 30);
+    t.mempool.insert(seen.clone());
+
+    let number = t.mine(&[unseen.clone(), seen.clone()]);
+    t.tracker.process_block(number).await.unwrap();
+
+    let Some(TxLifecycle::Mined(mined)) = t.tracker.status(seen.hash).map(|s| s.lifecycle) else {
+        panic!("transaction was not reported as mined");
+    };
+    assert_eq!((mined.block_number, mined.position), (number, 1));
+    assert_eq!(mined.effective_gas_price_gwei, 23.0);
+    assert_eq!(mined.priority_fee_gwei, 3.0);
+    assert!(t.mempool.get_pending(seen.hash).is_none());
+
+    // Only transactions seen pending are tracked
+    assert!(t.tracker.status(unseen.hash).is_none());
+}
+
+#[tokio::test]
+async fn replaced_and_dropped_transactions_are_reported() {
+    let t = tracking(MempoolConfig {
+        capacity: 2,
+        ..Default::default()
+    })
+    .await;
+    let original = tx(1, 0x01, 7, 10);
+    let replacement = Transaction {
+        max_fee_per_gas: Some(U256::from(300 * GWEI)),
+        ..tx(2, 0x01, 7, 20)
+    };
+    t.mempool.insert(original.clone());
+    t.mempool.insert(replacement.clone());
+
+    let status = t.tracker.status(original.hash).unwrap();
+    assert!(matches!(status.lifecycle, TxLifecycle::Replaced { replaced_by } if replaced_by == replacement.hash));
+    assert!(matches!(
+        t.tracker.status(replacement.hash).unwrap().lifecycle,
+        TxLifecycle::Pending { .. }
+    ));
+
+    // Two newer transactions evict the replacement
+    t.mempool.insert(tx(3, 0x02, 0, 10));
+    t.mempool.insert(tx(4, 0x03, 0, 10));
+    assert!(matches!(
+        t.tracker.status(replacement.hash).unwrap().lifecycle,
+        TxLifecycle::Dropped
+    ));
+}
+
+#[tokio::test]
+async fn inclusion_stats_bucket_by_priority_fee() {
+    let t = tracking(MempoolConfig {
+        ttl: Duration::from_secs(3600),
+        ..Default::default()
+    })
+    .await;
+
+    // 0.3 gwei lands in the first bucket, 3 gwei in the 2-5 gwei one
+    let txs: Vec<Transaction> = (0..25u64)
+        .map(|i| tx(i + 1, 0x10, i, if i < 5 { 3 } else { 30 }))
+        .collect();
+    for tx in &txs {
+        t.mempool.insert(tx.clone());
+    }
+    let number = t.mine(&txs);
+    t.tracker.process_block(number).await.unwrap();
+
+    let stats = t.tracker.inclusion_stats();
+    assert_eq!(stats.sample_size, 25);
+    assert_eq!(stats.buckets[0].transactions, 5);
+    assert_eq!(stats.buckets[3].min_priority_fee_gwei, 2.0);
+    assert_eq!(stats.buckets[3].transactions, 20);
+    assert_eq!(stats.buckets[3].p90_secs, Some(0));
+
+    // The first bucket is too small to trust, so the fee paid in the 2 gwei one is used
+    let priority_fee = t.tracker.priority_fee_for_inclusion_within(24);
+    assert_eq!(priority_fee, Some(3.0));
+}
+
+#[tokio::test]
+async fn observed_priority_fee_feeds_gas_predictions() {
+    let t = tracking(MempoolConfig::default()).await;
+    let history: Vec<BlockSample> = (0..20)
+        .map(|number| BlockSample {
+            number,
+            timestamp: 1_700_000_000 + number * 12,
+            base_fee_gwei: 20.0,
+            gas_used: 15_000_000,
+            gas_limit: 30_000_000,
+        })
+        .collect();
+
+    let default = t.ethereum.build_prediction(GasModel::Eip1559, &history, None).unwrap();
+    assert_eq!(default.priority_fee_gwei, 2.0);
+
+    t.ethereum.set_observed_priority_fee(Some(0.5));
+    let observed = t.ethereum.build_prediction(GasModel::Eip1559, &history, None).unwrap();
+    assert_eq!(observed.priority_fee_gwei, 0.5);
+    assert!((default.max_fee_gwei - observed.max_fee_gwei - 1.5).abs() < 1e-9);
+
+    // Untipped transactions landing quickly do not advertise a zero tip
+    t.ethereum.set_observed_priority_fee(Some(0.0));
+    let floored = t.ethereum.build_prediction(GasModel::Eip1559, &history, None).unwrap();
+    assert_eq!(floored.priority_fee_gwei, 0.1);
+}